@@ -404,6 +404,14 @@ char*           dc_get_blobdir               (const dc_context_t* context);
  * - `mdns_enabled` = 0=do not send or request read receipts,
  *                    1=send and request read receipts
  *                    default=send and request read receipts, only send but not request if `bot` is set
+ * - `typing_indicators_enabled` = 1=send and show typing indicators (default),
+ *                    0=do not send typing indicators and do not show the ones of others,
+ *                    see dc_set_typing()
+ * - `request_dsn`  = 1=request delivery status notifications from the SMTP server
+ *                    for each recipient of sent messages, shown in dc_get_msg_info(),
+ *                    if the server supports it,
+ *                    0=only process failure reports sent by the servers anyway (default).
+ *                    Requesting reports increases traffic as a report is received for each recipient.
  * - `bcc_self`     = 0=do not send a copy of outgoing messages to self,
  *                    1=send a copy of outgoing messages to self (default).
  *                    Sending messages to self is needed for a proper multi-account setup,
@@ -421,6 +429,15 @@ char*           dc_get_blobdir               (const dc_context_t* context);
  *                    The library uses the `media_quality` setting to use different defaults
  *                    for recoding images sent with type #DC_MSG_IMAGE.
  *                    If needed, recoding other file types is up to the UI.
+ * - `low_bandwidth` = DC_LOW_BANDWIDTH_OFF (0) = do not save bandwidth (default),
+ *                    DC_LOW_BANDWIDTH_ON (1) = always save bandwidth,
+ *                    DC_LOW_BANDWIDTH_AUTO (2) = save bandwidth while the measured download throughput is low.
+ *                    While bandwidth is saved, messages larger than 32 KiB are downloaded after smaller ones
+ *                    and large attachments are only downloaded on request,
+ *                    outgoing images are recoded as with DC_MEDIA_QUALITY_WORSE,
+ *                    webxdc realtime channels are not used,
+ *                    read receipts are only sent along with other messages
+ *                    and servers without IMAP IDLE are polled every 5 minutes.
  * - `bot`          = Set to "1" if this is a bot.
  *                    Prevents adding the "Device messages" and "Saved messages" chats,
  *                    adds Auto-Submitted header to outgoing messages,
@@ -478,11 +495,46 @@ char*           dc_get_blobdir               (const dc_context_t* context);
  * - `webxdc_realtime_enabled` = Whether the realtime APIs should be enabled.
  *                               0 = WebXDC realtime API is disabled and behaves as noop.
  *                               1 = WebXDC realtime API is enabled (default).
+ * - `webxdc_realtime_reliable` = Whether realtime messages should be buffered and replayed
+ *                               to peers that missed them, e.g. because they joined late.
+ *                               0 = Realtime messages are ephemeral (default).
+ *                               1 = Recent realtime messages are buffered and replayed.
+ * - `webxdc_catalog_bot` = Fingerprint of the key of a bot sending webxdc apps.
+ *                          Apps sent by this key-contact are added to the webxdc catalog,
+ *                          apps sent from the same address with another key are ignored.
+ * - `webxdc_catalog_url` = URL of a JSON index of webxdc apps added to the webxdc catalog
+ *                          when it is updated.
  * - `who_can_call_me` = Who can cause call notifications.
  *                       0 = Everybody (except explicitly blocked contacts),
  *                       1 = Contacts (default, does not include contact requests),
  *                       2 = Nobody (calls never result in a notification).
+ * - `call_schedule` = JSON object with time-based rules for incoming calls,
+ *                     synced across devices. Unset or empty (default) allows calls at any time.
+ *                     `working_hours` and `quiet_hours` are lists of
+ *                     `{"weekday": 0-6 (0 = Monday), "start_minute": 0-1440, "end_minute": 0-1440}`,
+ *                     a period ending before it starts continues on the next day.
+ *                     If `working_hours` is not empty, calls are only possible during these periods.
+ *                     Calls during `quiet_hours` are not possible.
+ *                     `exceptions` is a list of addresses that can call at any time.
+ *                     `utc_offset_minutes` is the offset from UTC of the time zone the periods are in,
+ *                     UIs should set it so all devices agree; if unset, each device uses its local time.
+ *                     Calls that are not possible are declined
+ *                     and the caller gets the reply #DC_STR_UNAVAILABLE_FOR_CALLS in 1:1 chats.
  * - `force_encryption` = 1 (default) to force encryption, 0 to allow unencrypted messages.
+ * - `key_profile` = Profile of the OpenPGP key generated on configuration.
+ *                    0 = Classic (default), Ed25519 signing key and X25519 encryption subkey.
+ *                    1 = Hybrid, additionally adds post-quantum ML-KEM-768+X25519 encryption subkey,
+ *                        which is used if all recipients of a message support it.
+ *                    Changes affect keys generated in the future only.
+ * - `wkd_lookup` = 1 to allow looking up certificates of new contacts
+ *                    in the Web Key Directory (WKD) of their domain
+ *                    with the `lookup_wkd_contact` JSON-RPC method,
+ *                    so the first message to them is encrypted,
+ *                    0 = do not look up certificates (default).
+ * - `sign_unencrypted` = 1 to sign messages that are sent unencrypted
+ *                    as OpenPGP/MIME `multipart/signed`,
+ *                    so recipients using e.g. GnuPG can verify them,
+ *                    0 = send unencrypted messages unsigned (default).
  *
  * Also, there are configs that are only needed
  * if you want to use the deprecated dc_configure() API, such as:
@@ -502,7 +554,28 @@ char*           dc_get_blobdir               (const dc_context_t* context);
  * - `send_security`= SMTP-socket, one of @ref DC_SOCKET, defaults to #DC_SOCKET_AUTO
  * - `server_flags` = IMAP-/SMTP-flags as a combination of @ref DC_LP flags, guessed if left out
  * - `proxy_enabled` = Proxy enabled. Disabled by default.
- * - `proxy_url` = Proxy URL. May contain multiple URLs separated by newline, but only the first one is used.
+ * - `proxy_url` = Proxy URL. May contain multiple URLs separated by newline.
+ *                    The first one is tried first unless another proxy worked last,
+ *                    the other proxies are used if connecting through it fails,
+ *                    see #DC_EVENT_ACTIVE_PROXY_CHANGED.
+ * - `tor_mode` = 1 to route all IMAP, SMTP and HTTP connections through Tor,
+ *                    using the SOCKS5 proxies from `proxy_url`
+ *                    or the local Tor daemon at `socks5://127.0.0.1:9050` if there are none.
+ *                    Direct connections are refused while enabled,
+ *                    realtime channels only connect to the iroh relay through Tor.
+ *                    Needed to use `.onion` servers.
+ *                    0 = do not use Tor (default).
+ * - `doh_mode` = how to use DNS-over-HTTPS for resolving hostnames:
+ *                    0 = use the system resolver only (default),
+ *                    1 = use the endpoints from `doh_urls`, fall back to the system resolver if all of them fail,
+ *                    2 = use the endpoints from `doh_urls` only.
+ * - `doh_urls` = DNS-over-HTTPS endpoint URLs separated by newlines, tried in the given order.
+ *                    Defaults to `https://1.1.1.1/dns-query` and `https://9.9.9.9/dns-query`.
+ * - `strict_certificate_pinning` = 1 to refuse TLS connections
+ *                    if the server key differs from the key seen last time,
+ *                    the connectivity view then shows both key hashes.
+ *                    0 = accept and remember changed keys if the certificate is valid (default).
+ *                    Keys pinned manually are always enforced.
  * - `imap_certificate_checks` = how to check IMAP and SMTP certificates, one of the @ref DC_CERTCK flags, defaults to #DC_CERTCK_AUTO (0)
 
  * If you want to retrieve a value, use dc_get_config().
@@ -1068,6 +1141,12 @@ void            dc_send_edit_request         (dc_context_t* context, uint32_t ms
  *     - `document`: optional document name. shown eg. in title bar.
  *     - `summary`: optional summary. shown beside app icon.
  *     - `notify`: optional array of other users `selfAddr` to be notified e.g. by a sound about `info` or `summary`.
+ *     - `snapshot`: optional, set to `true` if the update contains the whole state of the app.
+ *       Previous updates are deleted then.
+ *       Only the sender of the webxdc instance can send snapshot updates.
+ *       Each instance has a storage quota for status updates,
+ *       if it is exceeded, only snapshot updates are accepted.
+ *       Snapshot updates count against the quota as well.
  * @param descr Deprecated, set to NULL
  * @return 1=success, 0=error
  */
@@ -1107,6 +1186,57 @@ int dc_send_webxdc_status_update (dc_context_t* context, uint32_t msg_id, const
 char* dc_get_webxdc_status_updates (dc_context_t* context, uint32_t msg_id, uint32_t serial);
 
 
+/**
+ * Replace the app of a webxdc instance, e.g. to update it to a new version.
+ * Only the app sender can do this, see dc_msg_get_webxdc_info().
+ *
+ * The new .xdc is sent to the chat and replaces the app for all members,
+ * status updates of the instance are preserved.
+ * #DC_EVENT_WEBXDC_INSTANCE_UPDATED is emitted when the app was replaced.
+ *
+ * @memberof dc_context_t
+ * @param context The context object.
+ * @param msg_id The ID of the webxdc instance.
+ * @param file The new .xdc file.
+ * @return 1=success, 0=error
+ */
+int dc_replace_webxdc (dc_context_t* context, uint32_t msg_id, const char* file);
+
+
+/**
+ * Reset the state of a webxdc instance on this device.
+ * All status updates, the summary and the document name are deleted,
+ * other chat members are not affected.
+ *
+ * This can be offered to the user if an app is broken
+ * or uses too much storage.
+ * #DC_EVENT_WEBXDC_INSTANCE_UPDATED is emitted, UI should reload the webxdc if it is open.
+ *
+ * @memberof dc_context_t
+ * @param context The context object.
+ * @param msg_id The ID of the webxdc instance.
+ * @return 1=success, 0=error
+ */
+int dc_reset_webxdc_state (dc_context_t* context, uint32_t msg_id);
+
+/**
+ * Grant or revoke a permission requested by a webxdc instance.
+ * The requested and granted permissions are returned by dc_msg_get_webxdc_info().
+ *
+ * Apps request permissions using the `permissions` list in their manifest.toml;
+ * known permissions are `realtime`, `location`, `contacts-picker`, `internet` and `large-updates`.
+ * `internet` can only be granted to encrypted apps in "Saved Messages".
+ *
+ * @memberof dc_context_t
+ * @param context The context object.
+ * @param msg_id The ID of the webxdc instance.
+ * @param permission The permission to grant or revoke, e.g. `realtime`.
+ * @param granted 1=grant the permission, 0=revoke it.
+ * @return 1=success, 0=error
+ */
+int dc_set_webxdc_permission (dc_context_t* context, uint32_t msg_id, const char* permission, int granted);
+
+
 /**
  * Set Webxdc file as integration.
  * see dc_init_webxdc_integration() for more details about Webxdc integrations.
@@ -1216,13 +1346,25 @@ uint32_t        dc_init_webxdc_integration    (dc_context_t* context, uint32_t c
  * In the UI, the sorted chatlist is used as an overview about calls as well as messages.
  * To place a call with a contact that has no chat yet, use dc_create_chat_by_contact_id() first.
  *
+ * Group calls:
+ *
+ * - calls placed in groups ring on all members' devices;
+ *   members join using dc_accept_incoming_call(), also after ringing stopped,
+ *   and all members receive #DC_EVENT_CALL_PARTICIPANT_JOINED
+ *
+ * - participants leave using dc_end_call(),
+ *   all members receive #DC_EVENT_CALL_PARTICIPANT_LEFT;
+ *   members who did not join yet decline the call for themselves only
+ *
+ * - the call ends with #DC_EVENT_CALL_ENDED when the last participant leaves
+ *
  * UI will usually allow only one call at the same time,
  * this has to be tracked by UI across profile, the core does not track this.
  *
  * @memberof dc_context_t
  * @param context The context object.
  * @param chat_id The chat to place a call for.
- *     This needs to be a one-to-one chat.
+ *     This needs to be a one-to-one chat or a group.
  * @param place_call_info any data that other devices receive
  *     in #DC_EVENT_INCOMING_CALL.
  * @param has_video Whether the call has video initially.
@@ -1243,6 +1385,9 @@ uint32_t        dc_place_outgoing_call       (dc_context_t* context, uint32_t ch
  * If the call is already accepted or ended, nothing happens.
  * If the chat is a contact request, it is accepted implicitly.
  *
+ * For group calls, this joins the call.
+ * Other members receive #DC_EVENT_CALL_PARTICIPANT_JOINED.
+ *
  * @memberof dc_context_t
  * @param context The context object.
  * @param msg_id The ID of the call to accept.
@@ -1271,6 +1416,9 @@ uint32_t        dc_place_outgoing_call       (dc_context_t* context, uint32_t ch
   *
   * If the call is already ended, nothing happens.
   *
+  * For group calls, this leaves the call or declines it if it was not joined yet.
+  * The group call ends for everyone when the last participant leaves.
+  *
   * @memberof dc_context_t
   * @param context The context object.
   * @param msg_id the ID of the call.
@@ -1616,6 +1764,43 @@ dc_array_t*     dc_get_chat_media            (dc_context_t* context, uint32_t ch
 void            dc_set_chat_visibility       (dc_context_t* context, uint32_t chat_id, int visibility);
 
 
+/**
+ * Join the realtime channel of a chat to exchange typing indicators.
+ *
+ * UIs should call this function when the chat is opened,
+ * before the user starts typing, see dc_set_typing().
+ * The channel is advertised to the chat members with a hidden message
+ * only once, afterwards it is rejoined without sending a message.
+ * Nothing is done in chats where no typing indicators are sent.
+ *
+ * @memberof dc_context_t
+ * @param context The context object as returned from dc_context_new().
+ * @param chat_id The ID of the opened chat.
+ */
+void            dc_join_typing               (dc_context_t* context, uint32_t chat_id);
+
+
+/**
+ * Notify the members of a chat that the user started or stopped typing.
+ *
+ * Typing indicators are sent over realtime channels, never by email,
+ * so they only reach members that are online.
+ * They are only sent after dc_join_typing() was called for the chat.
+ * They are rate-limited, so it is fine to call this function on each keystroke.
+ * Nothing is sent if the dc_set_config()-option `typing_indicators_enabled` is unset,
+ * in unencrypted chats, contact requests and chats with many members.
+ *
+ * Other members receive #DC_EVENT_CONTACT_TYPING.
+ *
+ * @memberof dc_context_t
+ * @param context The context object as returned from dc_context_new().
+ * @param chat_id The ID of the chat the user is typing in.
+ * @param typing 1=the user types into the message field,
+ *     0=the message was sent or the field was cleared.
+ */
+void            dc_set_typing                (dc_context_t* context, uint32_t chat_id, int typing);
+
+
 /**
  * Delete a chat.
  *
@@ -4210,6 +4395,12 @@ char*             dc_msg_get_webxdc_blob      (const dc_msg_t* msg, const char*
  * - internet_access:
  *   true if the Webxdc should get internet access;
  *   this is the case i.e. for experimental maps integration.
+ * - permissions: list of permissions requested by the app in the manifest,
+ *   e.g. `["realtime", "large-updates"]`.
+ *   Apps without `permissions` in the manifest request `realtime` and `large-updates`,
+ *   map integrations additionally `location` and `internet`.
+ * - granted_permissions: list of permissions granted to the app,
+ *   see dc_set_webxdc_permission().
  * - self_addr: address to be used for `window.webxdc.selfAddr` in JS land.
  * - is_app_sender: Define if the local user is the one who initially shared the webxdc application in the chat.
  * - is_broadcast: Define if the app runs in a broadcasting context.
@@ -6317,6 +6508,10 @@ void dc_event_unref(dc_event_t* event);
  * State changed from @ref DC_STATE_OUT_PENDING, @ref DC_STATE_OUT_DELIVERED or @ref DC_STATE_OUT_MDN_RCVD
  * to @ref DC_STATE_OUT_FAILED.
  *
+ * The event is also emitted without changing the state
+ * if the message could not be sent to some of the recipients,
+ * see dc_msg_get_error() for details.
+ *
  * @param data1 (int) chat_id
  * @param data2 (int) msg_id
  */
@@ -6508,6 +6703,34 @@ void dc_event_unref(dc_event_t* event);
 
 #define DC_EVENT_WEBXDC_INSTANCE_DELETED          2121
 
+/**
+ * A newer version of a webxdc app used in chats is available in the webxdc catalog,
+ * see `webxdc_catalog_bot` and `webxdc_catalog_url` config options.
+ *
+ * @param data1 0
+ * @param data2 (char*) ID of the app in the webxdc catalog.
+ */
+#define DC_EVENT_WEBXDC_APP_UPDATE_AVAILABLE      2122
+
+/**
+ * The app of a webxdc instance was replaced, e.g. by a new version,
+ * see dc_replace_webxdc(), or its state was reset, see dc_reset_webxdc_state().
+ * UI should reload the webxdc if it is open.
+ *
+ * @param data1 (int) msg_id of the webxdc instance
+ */
+#define DC_EVENT_WEBXDC_INSTANCE_UPDATED          2123
+
+/**
+ * Received status updates of a webxdc instance were dropped
+ * because they exceed the storage quota of the instance.
+ * The instance sender can compact the updates by sending a snapshot update,
+ * see dc_send_webxdc_status_update().
+ *
+ * @param data1 (int) msg_id of the webxdc instance
+ */
+#define DC_EVENT_WEBXDC_STATUS_UPDATES_DROPPED    2124
+
 /**
  * Data received over an ephemeral peer channel.
  *
@@ -6529,6 +6752,41 @@ void dc_event_unref(dc_event_t* event);
 
 #define DC_EVENT_WEBXDC_REALTIME_ADVERTISEMENT    2151
 
+/**
+ * Data received over the realtime channel of a chat.
+ *
+ * @param data1 (int) chat_id
+ * @param data2 (int) + (char*) binary data.
+ *     length is returned as integer with dc_event_get_data2_int()
+ *     and binary data is returned as dc_event_get_data2_str().
+ *     Binary data must be passed to dc_str_unref() afterwards.
+ */
+
+#define DC_EVENT_CHAT_REALTIME_DATA               2152
+
+/**
+ * Advertisement for the realtime channel of a chat received.
+ * @param data1 (int) chat_id
+ * @param data2 0
+ */
+
+#define DC_EVENT_CHAT_REALTIME_ADVERTISEMENT      2153
+
+/**
+ * A contact started or stopped typing in a chat, see dc_set_typing().
+ *
+ * The contact should be shown as not typing anymore
+ * if no new event is received within 10 seconds.
+ *
+ * @param data1 (int) chat_id
+ * @param data2 (int) contact_id of the typing contact.
+ *     dc_event_get_data2_str() returns "1" if the contact is typing
+ *     and "0" if the contact stopped typing.
+ *     The string must be passed to dc_str_unref() afterwards.
+ */
+
+#define DC_EVENT_CONTACT_TYPING                   2154
+
 /**
  * Tells that the Background fetch was completed (or timed out).
  *
@@ -6634,10 +6892,34 @@ void dc_event_unref(dc_event_t* event);
  *
  * UI usually only takes action in case call UI was opened before, otherwise the event should be ignored.
  *
+ * For group calls, the event is sent when the last participant left the call
+ * and when the incoming call was declined on another device
+ * while other participants stay in the call.
+ *
  * @param data1 (int) msg_id ID of the message referring to the call
  */
 #define DC_EVENT_CALL_ENDED                               2580
 
+/**
+ * A member joined a group call using dc_accept_incoming_call()
+ * on this or another device.
+ *
+ * Current participants can be listed using the JSON-RPC API.
+ *
+ * @param data1 (int) msg_id ID of the message referring to the call
+ * @param data2 (int) contact_id ID of the contact who joined the call
+ */
+#define DC_EVENT_CALL_PARTICIPANT_JOINED                  2590
+
+/**
+ * A participant left a group call using dc_end_call()
+ * on this or another device.
+ *
+ * @param data1 (int) msg_id ID of the message referring to the call
+ * @param data2 (int) contact_id ID of the contact who left the call
+ */
+#define DC_EVENT_CALL_PARTICIPANT_LEFT                    2591
+
 /**
  * Transport relay added/deleted or default has changed.
  * UI should update the list.
@@ -6649,6 +6931,17 @@ void dc_event_unref(dc_event_t* event);
 #define DC_EVENT_TRANSPORTS_MODIFIED           2600
 
 
+/**
+ * Connections are made through another proxy now,
+ * because the previously used proxy failed or proxy configuration changed.
+ * UI may update the proxy settings and the connectivity view.
+ *
+ * @param data1 0
+ * @param data2 (char*) Host and port of the proxy in use.
+ */
+#define DC_EVENT_ACTIVE_PROXY_CHANGED          2610
+
+
 /**
  * @}
  */
@@ -6665,6 +6958,14 @@ void dc_event_unref(dc_event_t* event);
 #define DC_MEDIA_QUALITY_WORSE    1
 
 
+/*
+ * Values for dc_get|set_config("low_bandwidth")
+ */
+#define DC_LOW_BANDWIDTH_OFF  0
+#define DC_LOW_BANDWIDTH_ON   1
+#define DC_LOW_BANDWIDTH_AUTO 2
+
+
 /**
  * @defgroup DC_PROVIDER_STATUS DC_PROVIDER_STATUS
  *
@@ -7478,6 +7779,16 @@ void dc_event_unref(dc_event_t* event);
 /// Used when creating text for the "Encryption Info" dialogs.
 #define DC_STR_MESSAGES_ARE_E2EE 242
 
+/// "Connected through %1$s."
+///
+/// Used in the connectivity view, `%1$s` is replaced by host and port of the proxy in use.
+#define DC_STR_PROXY_ACTIVE 243
+
+/// "I am not available for calls right now."
+///
+/// Sent as a reply when an incoming call is declined because of `call_schedule`.
+#define DC_STR_UNAVAILABLE_FOR_CALLS 244
+
 /**
  * @}
  */
@@ -32,7 +32,7 @@
 use deltachat::message::MsgId;
 use deltachat::qr_code_generator::{create_qr_svg, generate_backup_qr, get_securejoin_qr_svg};
 use deltachat::stock_str::StockMessage;
-use deltachat::webxdc::StatusUpdateSerial;
+use deltachat::webxdc::{StatusUpdateSerial, WebxdcPermission};
 use deltachat::*;
 use deltachat::{accounts::Accounts, log::LogExt};
 use deltachat_jsonrpc::api::CommandApi;
@@ -543,8 +543,14 @@ fn spawn_configure(ctx: Context) {
         EventType::ConfigSynced { .. } => 2111,
         EventType::WebxdcStatusUpdate { .. } => 2120,
         EventType::WebxdcInstanceDeleted { .. } => 2121,
+        EventType::WebxdcAppUpdateAvailable { .. } => 2122,
+        EventType::WebxdcInstanceUpdated { .. } => 2123,
+        EventType::WebxdcStatusUpdatesDropped { .. } => 2124,
         EventType::WebxdcRealtimeData { .. } => 2150,
         EventType::WebxdcRealtimeAdvertisementReceived { .. } => 2151,
+        EventType::ChatRealtimeData { .. } => 2152,
+        EventType::ChatRealtimeAdvertisementReceived { .. } => 2153,
+        EventType::ContactTyping { .. } => 2154,
         EventType::AccountsBackgroundFetchDone => 2200,
         EventType::ChatlistChanged => 2300,
         EventType::ChatlistItemChanged { .. } => 2301,
@@ -555,7 +561,10 @@ fn spawn_configure(ctx: Context) {
         EventType::IncomingCallAccepted { .. } => 2560,
         EventType::OutgoingCallAccepted { .. } => 2570,
         EventType::CallEnded { .. } => 2580,
+        EventType::CallParticipantJoined { .. } => 2590,
+        EventType::CallParticipantLeft { .. } => 2591,
         EventType::TransportsModified => 2600,
+        EventType::ActiveProxyChanged { .. } => 2610,
         #[allow(unreachable_patterns)]
         #[cfg(test)]
         _ => unreachable!("This is just to silence a rust_analyzer false-positive"),
@@ -591,7 +600,9 @@ fn spawn_configure(ctx: Context) {
         | EventType::ChatlistChanged
         | EventType::AccountsChanged
         | EventType::AccountsItemChanged
-        | EventType::TransportsModified => 0,
+        | EventType::TransportsModified
+        | EventType::ActiveProxyChanged { .. }
+        | EventType::WebxdcAppUpdateAvailable { .. } => 0,
         EventType::IncomingReaction { contact_id, .. }
         | EventType::IncomingWebxdcNotify { contact_id, .. } => contact_id.to_u32() as libc::c_int,
         EventType::MsgsChanged { chat_id, .. }
@@ -621,13 +632,20 @@ fn spawn_configure(ctx: Context) {
         | EventType::WebxdcStatusUpdate { msg_id, .. }
         | EventType::WebxdcRealtimeAdvertisementReceived { msg_id }
         | EventType::WebxdcInstanceDeleted { msg_id, .. }
+        | EventType::WebxdcInstanceUpdated { msg_id }
+        | EventType::WebxdcStatusUpdatesDropped { msg_id }
         | EventType::IncomingCall { msg_id, .. }
         | EventType::IncomingCallAccepted { msg_id, .. }
         | EventType::OutgoingCallAccepted { msg_id, .. }
-        | EventType::CallEnded { msg_id, .. } => msg_id.to_u32() as libc::c_int,
+        | EventType::CallEnded { msg_id, .. }
+        | EventType::CallParticipantJoined { msg_id, .. }
+        | EventType::CallParticipantLeft { msg_id, .. } => msg_id.to_u32() as libc::c_int,
         EventType::ChatlistItemChanged { chat_id } => {
             chat_id.unwrap_or_default().to_u32() as libc::c_int
         }
+        EventType::ChatRealtimeData { chat_id, .. }
+        | EventType::ChatRealtimeAdvertisementReceived { chat_id }
+        | EventType::ContactTyping { chat_id, .. } => chat_id.to_u32() as libc::c_int,
         EventType::EventChannelOverflow { n } => *n as libc::c_int,
         #[allow(unreachable_patterns)]
         #[cfg(test)]
@@ -665,6 +683,8 @@ fn spawn_configure(ctx: Context) {
         | EventType::MsgsNoticed(_)
         | EventType::ConnectivityChanged
         | EventType::WebxdcInstanceDeleted { .. }
+        | EventType::WebxdcInstanceUpdated { .. }
+        | EventType::WebxdcStatusUpdatesDropped { .. }
         | EventType::IncomingMsgBunch
         | EventType::SelfavatarChanged
         | EventType::AccountsBackgroundFetchDone
@@ -676,10 +696,13 @@ fn spawn_configure(ctx: Context) {
         | EventType::ChatModified(_)
         | EventType::ChatDeleted { .. }
         | EventType::WebxdcRealtimeAdvertisementReceived { .. }
+        | EventType::ChatRealtimeAdvertisementReceived { .. }
         | EventType::OutgoingCallAccepted { .. }
         | EventType::CallEnded { .. }
         | EventType::EventChannelOverflow { .. }
-        | EventType::TransportsModified => 0,
+        | EventType::TransportsModified
+        | EventType::ActiveProxyChanged { .. }
+        | EventType::WebxdcAppUpdateAvailable { .. } => 0,
         EventType::MsgsChanged { msg_id, .. }
         | EventType::ReactionsChanged { msg_id, .. }
         | EventType::IncomingReaction { msg_id, .. }
@@ -696,11 +719,16 @@ fn spawn_configure(ctx: Context) {
             status_update_serial,
             ..
         } => status_update_serial.to_u32() as libc::c_int,
-        EventType::WebxdcRealtimeData { data, .. } => data.len() as libc::c_int,
+        EventType::WebxdcRealtimeData { data, .. } | EventType::ChatRealtimeData { data, .. } => {
+            data.len() as libc::c_int
+        }
         EventType::IncomingCall { has_video, .. } => *has_video as libc::c_int,
         EventType::IncomingCallAccepted {
             from_this_device, ..
         } => *from_this_device as libc::c_int,
+        EventType::CallParticipantJoined { contact_id, .. }
+        | EventType::CallParticipantLeft { contact_id, .. }
+        | EventType::ContactTyping { contact_id, .. } => contact_id.to_u32() as libc::c_int,
 
         #[allow(unreachable_patterns)]
         #[cfg(test)]
@@ -772,6 +800,8 @@ fn spawn_configure(ctx: Context) {
         | EventType::SelfavatarChanged
         | EventType::WebxdcStatusUpdate { .. }
         | EventType::WebxdcInstanceDeleted { .. }
+        | EventType::WebxdcInstanceUpdated { .. }
+        | EventType::WebxdcStatusUpdatesDropped { .. }
         | EventType::AccountsBackgroundFetchDone
         | EventType::ChatEphemeralTimerModified { .. }
         | EventType::ChatDeleted { .. }
@@ -782,6 +812,7 @@ fn spawn_configure(ctx: Context) {
         | EventType::AccountsItemChanged
         | EventType::IncomingCallAccepted { .. }
         | EventType::WebxdcRealtimeAdvertisementReceived { .. }
+        | EventType::ChatRealtimeAdvertisementReceived { .. }
         | EventType::TransportsModified => ptr::null_mut(),
         EventType::IncomingCall {
             place_call_info, ..
@@ -795,7 +826,10 @@ fn spawn_configure(ctx: Context) {
             let data2 = accept_call_info.to_c_string().unwrap_or_default();
             data2.into_raw()
         }
-        EventType::CallEnded { .. } | EventType::EventChannelOverflow { .. } => ptr::null_mut(),
+        EventType::CallEnded { .. }
+        | EventType::CallParticipantJoined { .. }
+        | EventType::CallParticipantLeft { .. }
+        | EventType::EventChannelOverflow { .. } => ptr::null_mut(),
         EventType::ConfigureProgress { comment, .. } => {
             if let Some(comment) = comment {
                 comment.to_c_string().unwrap_or_default().into_raw()
@@ -811,7 +845,19 @@ fn spawn_configure(ctx: Context) {
             let data2 = key.to_string().to_c_string().unwrap_or_default();
             data2.into_raw()
         }
-        EventType::WebxdcRealtimeData { data, .. } => {
+        EventType::ActiveProxyChanged { proxy } => {
+            let data2 = proxy.to_c_string().unwrap_or_default();
+            data2.into_raw()
+        }
+        EventType::WebxdcAppUpdateAvailable { app_id } => {
+            let data2 = app_id.to_c_string().unwrap_or_default();
+            data2.into_raw()
+        }
+        EventType::ContactTyping { typing, .. } => {
+            let data2 = if *typing { "1" } else { "0" };
+            data2.to_c_string().unwrap_or_default().into_raw()
+        }
+        EventType::WebxdcRealtimeData { data, .. } | EventType::ChatRealtimeData { data, .. } => {
             let ptr = libc::malloc(data.len());
             libc::memcpy(ptr, data.as_ptr() as *mut libc::c_void, data.len());
             ptr as *mut libc::c_char
@@ -1138,6 +1184,67 @@ fn spawn_configure(ctx: Context) {
     .strdup()
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn dc_replace_webxdc(
+    context: *mut dc_context_t,
+    msg_id: u32,
+    file: *const libc::c_char,
+) -> libc::c_int {
+    if context.is_null() || file.is_null() {
+        eprintln!("ignoring careless call to dc_replace_webxdc()");
+        return 0;
+    }
+    let ctx = &*context;
+
+    block_on(ctx.replace_webxdc(MsgId::new(msg_id), &to_string_lossy(file)))
+        .context("Failed to replace webxdc")
+        .log_err(ctx)
+        .is_ok() as libc::c_int
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn dc_reset_webxdc_state(
+    context: *mut dc_context_t,
+    msg_id: u32,
+) -> libc::c_int {
+    if context.is_null() {
+        eprintln!("ignoring careless call to dc_reset_webxdc_state()");
+        return 0;
+    }
+    let ctx = &*context;
+
+    block_on(ctx.reset_webxdc_state(MsgId::new(msg_id)))
+        .context("Failed to reset webxdc state")
+        .log_err(ctx)
+        .is_ok() as libc::c_int
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn dc_set_webxdc_permission(
+    context: *mut dc_context_t,
+    msg_id: u32,
+    permission: *const libc::c_char,
+    granted: libc::c_int,
+) -> libc::c_int {
+    if context.is_null() || permission.is_null() {
+        eprintln!("ignoring careless call to dc_set_webxdc_permission()");
+        return 0;
+    }
+    let ctx = &*context;
+
+    let Ok(permission) = to_string_lossy(permission)
+        .parse::<WebxdcPermission>()
+        .context("Unknown webxdc permission")
+        .log_err(ctx)
+    else {
+        return 0;
+    };
+    block_on(ctx.set_webxdc_permission(MsgId::new(msg_id), permission, granted != 0))
+        .context("Failed to set webxdc permission")
+        .log_err(ctx)
+        .is_ok() as libc::c_int
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn dc_set_webxdc_integration(
     context: *mut dc_context_t,
@@ -1605,6 +1712,44 @@ fn from_prim<S, T>(s: S) -> Option<T>
     })
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn dc_join_typing(context: *mut dc_context_t, chat_id: u32) {
+    if context.is_null() {
+        eprintln!("ignoring careless call to dc_join_typing()");
+        return;
+    }
+    let ctx = &*context;
+
+    block_on(async move {
+        deltachat::peer_channels::join_typing(ctx, ChatId::new(chat_id))
+            .await
+            .context("Failed to join typing")
+            .log_err(ctx)
+            .unwrap_or(())
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn dc_set_typing(
+    context: *mut dc_context_t,
+    chat_id: u32,
+    typing: libc::c_int,
+) {
+    if context.is_null() {
+        eprintln!("ignoring careless call to dc_set_typing()");
+        return;
+    }
+    let ctx = &*context;
+
+    block_on(async move {
+        deltachat::peer_channels::set_typing(ctx, ChatId::new(chat_id), typing != 0)
+            .await
+            .context("Failed to set typing")
+            .log_err(ctx)
+            .unwrap_or(())
+    })
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn dc_delete_chat(context: *mut dc_context_t, chat_id: u32) {
     if context.is_null() {
@@ -8,7 +8,9 @@
 use anyhow::{anyhow, bail, ensure, Context, Result};
 pub use deltachat::accounts::Accounts;
 use deltachat::blob::BlobObject;
-use deltachat::calls::ice_servers;
+use deltachat::calls::{
+    active_call, clear_call_history, get_call_history, get_missed_calls_count, ice_servers,
+};
 use deltachat::chat::{
     self, add_contact_to_chat, forward_msgs, forward_msgs_2ctx, get_chat_media, get_chat_msgs,
     get_chat_msgs_ex, markfresh_chat, marknoticed_all_chats, marknoticed_chat,
@@ -23,11 +25,14 @@
 use deltachat::imex;
 use deltachat::location;
 use deltachat::message::{
-    self, delete_msgs_ex, get_existing_msg_ids, get_msg_read_receipt_count, get_msg_read_receipts,
-    markseen_msgs, Message, MessageState, MsgId, Viewtype,
+    self, delete_msgs_ex, get_existing_msg_ids, get_msg_delivery_report,
+    get_msg_read_receipt_count, get_msg_read_receipts, markseen_msgs, Message, MessageState, MsgId,
+    Viewtype,
 };
 use deltachat::peer_channels::{
-    leave_webxdc_realtime, send_webxdc_realtime_advertisement, send_webxdc_realtime_data,
+    join_typing, leave_chat_realtime, leave_webxdc_realtime, send_chat_realtime_advertisement,
+    send_chat_realtime_data, send_webxdc_realtime_advertisement, send_webxdc_realtime_data,
+    set_typing,
 };
 use deltachat::provider::get_provider_info;
 use deltachat::qr::{self, Qr};
@@ -35,8 +40,10 @@
 use deltachat::reaction::{get_msg_reactions, send_reaction};
 use deltachat::securejoin;
 use deltachat::stock_str::StockMessage;
-use deltachat::storage_usage::{get_blobdir_storage_usage, get_storage_usage};
-use deltachat::webxdc::StatusUpdateSerial;
+use deltachat::storage_usage::{
+    get_blobdir_storage_usage, get_storage_usage, get_webxdc_storage_usage,
+};
+use deltachat::webxdc::{StatusUpdateSerial, WebxdcPermission};
 use deltachat::EventEmitter;
 use sanitize_filename::is_sanitized;
 use tokio::fs;
@@ -48,18 +55,21 @@
 
 use num_traits::FromPrimitive;
 use types::account::Account;
-use types::calls::JsonrpcCallInfo;
+use types::calls::{JsonrpcCallHistoryFilter, JsonrpcCallHistoryItem, JsonrpcCallInfo};
 use types::chat::FullChat;
 use types::contact::{ContactObject, VcardContact};
+use types::diagnostics::NetworkDiagnostics;
 use types::events::Event;
 use types::http::HttpResponse;
-use types::message::{MessageData, MessageObject, MessageReadReceipt};
+use types::message::{MessageData, MessageDeliveryStatus, MessageObject, MessageReadReceipt};
 use types::notify_state::JsonrpcNotifyState;
 use types::provider_info::ProviderInfo;
 use types::reactions::JsonrpcReactions;
-use types::webxdc::WebxdcMessageInfo;
+use types::tls::PinnedCertificate;
+use types::traffic::TrafficStatsEntry;
+use types::webxdc::{WebxdcCatalogEntry, WebxdcMessageInfo, WebxdcStorageUsage};
 
-use self::types::message::{MessageInfo, MessageLoadResult};
+use self::types::message::{MessageCryptoInfo, MessageInfo, MessageLoadResult};
 use self::types::{
     chat::{BasicChat, JsonrpcChatVisibility, MuteDuration},
     location::JsonrpcLocation,
@@ -1553,6 +1563,18 @@ async fn get_message_info_object(
         MessageInfo::from_msg_id(&ctx, MsgId::new(message_id)).await
     }
 
+    /// Returns cryptographic details of a single message:
+    /// encryption type, signer fingerprints, signature verification result,
+    /// header protection and gossiped keys.
+    async fn get_message_crypto_info(
+        &self,
+        account_id: u32,
+        message_id: u32,
+    ) -> Result<MessageCryptoInfo> {
+        let ctx = self.get_context(account_id).await?;
+        MessageCryptoInfo::from_msg_id(&ctx, MsgId::new(message_id)).await
+    }
+
     /// Returns count of read receipts on message.
     ///
     /// This view count is meant as a feedback measure for the channel owner only.
@@ -1583,6 +1605,29 @@ async fn get_message_read_receipts(
         Ok(receipts)
     }
 
+    /// Returns per-recipient delivery status of an outgoing message
+    /// reported by the servers in Delivery Status Notifications.
+    ///
+    /// Recipients for which no report was received are not listed.
+    async fn get_message_delivery_report(
+        &self,
+        account_id: u32,
+        message_id: u32,
+    ) -> Result<Vec<MessageDeliveryStatus>> {
+        let ctx = self.get_context(account_id).await?;
+        let report = get_msg_delivery_report(&ctx, MsgId::new(message_id))
+            .await?
+            .into_iter()
+            .map(|status| MessageDeliveryStatus {
+                addr: status.addr,
+                status: status.status.into(),
+                reason: status.reason,
+                timestamp: status.timestamp,
+            })
+            .collect();
+        Ok(report)
+    }
+
     /// Asks the core to start downloading a message fully.
     /// This function is typically called when the user hits the "Download" button
     /// that is shown by the UI in case `download_state` is `'Available'` or `'Failure'`
@@ -1884,6 +1929,41 @@ async fn import_vcard_contents(&self, account_id: u32, vcard: String) -> Result<
             .collect())
     }
 
+    /// Imports an ASCII-armored OpenPGP certificate for the given address,
+    /// e.g. a certificate of a contact using another OpenPGP client.
+    ///
+    /// Returns the id of the key-contact the certificate is attached to.
+    async fn import_openpgp_certificate(
+        &self,
+        account_id: u32,
+        addr: String,
+        armored_key: String,
+    ) -> Result<u32> {
+        let ctx = self.get_context(account_id).await?;
+        let contact_id =
+            deltachat::contact::import_openpgp_certificate(&ctx, &addr, &armored_key).await?;
+        Ok(contact_id.to_u32())
+    }
+
+    /// Looks up the OpenPGP certificate for the address
+    /// in the Web Key Directory (WKD) of its domain.
+    ///
+    /// May be called after `create_contact()` with the same name and address,
+    /// creating the contact itself does not access the network.
+    ///
+    /// Returns the id of the key-contact with the given name if a certificate is found,
+    /// `null` if none is found or WKD lookup is disabled with the `wkd_lookup` option.
+    async fn lookup_wkd_contact(
+        &self,
+        account_id: u32,
+        name: String,
+        addr: String,
+    ) -> Result<Option<u32>> {
+        let ctx = self.get_context(account_id).await?;
+        let contact_id = deltachat::wkd::lookup_contact(&ctx, &name, &addr).await?;
+        Ok(contact_id.map(|id| id.to_u32()))
+    }
+
     /// Returns a vCard containing contacts with the given ids.
     async fn make_vcard(&self, account_id: u32, contacts: Vec<u32>) -> Result<String> {
         let ctx = self.get_context(account_id).await?;
@@ -2111,6 +2191,64 @@ async fn get_connectivity_html(&self, account_id: u32) -> Result<String> {
         ctx.get_connectivity_html().await
     }
 
+    /// Tests connectivity of all configured transports and proxies
+    /// and returns a report with the outcome and duration of each step,
+    /// e.g. to attach it to a support request.
+    ///
+    /// This may take up to a few minutes.
+    async fn run_network_diagnostics(&self, account_id: u32) -> Result<NetworkDiagnostics> {
+        let ctx = self.get_context(account_id).await?;
+        Ok(ctx.run_network_diagnostics().await?.into())
+    }
+
+    /// Returns TLS certificate keys remembered after successful connections
+    /// or pinned manually.
+    async fn get_pinned_certificates(&self, account_id: u32) -> Result<Vec<PinnedCertificate>> {
+        let ctx = self.get_context(account_id).await?;
+        Ok(ctx
+            .get_pinned_certificates()
+            .await?
+            .into_iter()
+            .map(Into::into)
+            .collect())
+    }
+
+    /// Pins TLS certificate key for the host.
+    ///
+    /// `spki_hash` is a base64 of SHA-256 hash of the certificate Subject Public Key Info,
+    /// e.g. scanned from a QR code.
+    /// Connections to the host fail if the server presents another key.
+    async fn pin_certificate(
+        &self,
+        account_id: u32,
+        host: String,
+        spki_hash: String,
+    ) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        ctx.pin_certificate(&host, &spki_hash).await
+    }
+
+    /// Removes the pinned TLS certificate key of the host.
+    async fn unpin_certificate(&self, account_id: u32, host: String) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        ctx.unpin_certificate(&host).await
+    }
+
+    /// Returns daily traffic statistics of the last 90 days
+    /// per transport and protocol, newest day first.
+    ///
+    /// Traffic not related to a transport, e.g. HTTP requests
+    /// and realtime channels, has transport ID 0.
+    async fn get_traffic_stats(&self, account_id: u32) -> Result<Vec<TrafficStatsEntry>> {
+        let ctx = self.get_context(account_id).await?;
+        Ok(ctx
+            .get_traffic_stats()
+            .await?
+            .into_iter()
+            .map(Into::into)
+            .collect())
+    }
+
     // ---------------------------------------------
     //                  locations
     // ---------------------------------------------
@@ -2235,6 +2373,57 @@ async fn leave_webxdc_realtime(&self, account_id: u32, instance_message_id: u32)
         leave_webxdc_realtime(&ctx, MsgId::new(instance_message_id)).await
     }
 
+    /// Sends realtime data to the peers of the chat with the given id.
+    ///
+    /// Data is received as `ChatRealtimeData` event by the peers that joined the channel.
+    async fn send_chat_realtime_data(
+        &self,
+        account_id: u32,
+        chat_id: u32,
+        data: Vec<u8>,
+    ) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        send_chat_realtime_data(&ctx, ChatId::new(chat_id), data).await
+    }
+
+    /// Joins the realtime channel of the chat with the given id
+    /// and advertises it to the chat members.
+    async fn send_chat_realtime_advertisement(&self, account_id: u32, chat_id: u32) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        if let Some(fut) = send_chat_realtime_advertisement(&ctx, ChatId::new(chat_id)).await? {
+            tokio::spawn(fut);
+        }
+        Ok(())
+    }
+
+    /// Leaves the realtime channel of the chat with the given id.
+    async fn leave_chat_realtime(&self, account_id: u32, chat_id: u32) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        leave_chat_realtime(&ctx, ChatId::new(chat_id)).await
+    }
+
+    /// Joins the realtime channel of the chat to exchange typing indicators.
+    ///
+    /// Call this when the chat is opened, before the user starts typing.
+    /// The channel is advertised to the chat members only once,
+    /// afterwards it is rejoined without sending a message.
+    async fn join_typing(&self, account_id: u32, chat_id: u32) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        join_typing(&ctx, ChatId::new(chat_id)).await
+    }
+
+    /// Notifies the members of the chat that the user started or stopped typing.
+    ///
+    /// Call this with `typing` set when the user types into the message field
+    /// and unset when the message is sent or the field is cleared.
+    /// Typing indicators are rate-limited, so it is fine to call this on each keystroke.
+    /// They are only sent after `join_typing` was called for the chat.
+    /// Other members receive `ContactTyping` events.
+    async fn set_typing(&self, account_id: u32, chat_id: u32, typing: bool) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        set_typing(&ctx, ChatId::new(chat_id), typing).await
+    }
+
     async fn get_webxdc_status_updates(
         &self,
         account_id: u32,
@@ -2284,6 +2473,61 @@ async fn get_webxdc_blob(
         Ok(general_purpose::STANDARD_NO_PAD.encode(blob))
     }
 
+    /// Replaces the app of the webxdc instance by the .xdc file at `file_path`,
+    /// e.g. to update it to a new version.
+    ///
+    /// Only the app sender can do this, status updates are preserved.
+    async fn replace_webxdc(
+        &self,
+        account_id: u32,
+        instance_msg_id: u32,
+        file_path: String,
+    ) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        ctx.replace_webxdc(MsgId::new(instance_msg_id), &file_path)
+            .await
+    }
+
+    /// Deletes the state of the webxdc instance on this device,
+    /// i.e. all status updates, the summary and the document name.
+    ///
+    /// Other chat members are not affected.
+    /// `WebxdcInstanceUpdated` is emitted so that the app can be reloaded.
+    async fn reset_webxdc_state(&self, account_id: u32, instance_msg_id: u32) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        ctx.reset_webxdc_state(MsgId::new(instance_msg_id)).await
+    }
+
+    /// Returns the storage used by the webxdc instance.
+    async fn get_webxdc_storage_usage(
+        &self,
+        account_id: u32,
+        instance_msg_id: u32,
+    ) -> Result<WebxdcStorageUsage> {
+        let ctx = self.get_context(account_id).await?;
+        let usage = get_webxdc_storage_usage(&ctx, MsgId::new(instance_msg_id)).await?;
+        Ok(usage.into())
+    }
+
+    /// Grants or revokes a permission requested by the webxdc instance,
+    /// eg. "realtime", "location", "contacts-picker", "internet" or "large-updates".
+    ///
+    /// "internet" can only be granted to encrypted apps in "Saved Messages".
+    async fn set_webxdc_permission(
+        &self,
+        account_id: u32,
+        instance_msg_id: u32,
+        permission: String,
+        granted: bool,
+    ) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        let permission = permission
+            .parse::<WebxdcPermission>()
+            .with_context(|| format!("Unknown webxdc permission {permission:?}"))?;
+        ctx.set_webxdc_permission(MsgId::new(instance_msg_id), permission, granted)
+            .await
+    }
+
     /// Sets Webxdc file as integration.
     /// `file` is the .xdc to use as Webxdc integration.
     async fn set_webxdc_integration(&self, account_id: u32, file_path: String) -> Result<()> {
@@ -2307,6 +2551,57 @@ async fn init_webxdc_integration(
             .map(|msg_id| msg_id.to_u32()))
     }
 
+    /// Returns apps from the webxdc catalog, sorted by name.
+    ///
+    /// If `query` is given, only apps with `query` in the name or description are returned.
+    async fn get_webxdc_catalog(
+        &self,
+        account_id: u32,
+        query: Option<String>,
+    ) -> Result<Vec<WebxdcCatalogEntry>> {
+        let ctx = self.get_context(account_id).await?;
+        Ok(ctx
+            .get_webxdc_catalog(query.as_deref())
+            .await?
+            .into_iter()
+            .map(Into::into)
+            .collect())
+    }
+
+    /// Updates the webxdc catalog from the catalog bot and the catalog URL.
+    ///
+    /// Emits `WebxdcAppUpdateAvailable` for apps used in chats
+    /// if the catalog contains a newer version.
+    async fn update_webxdc_catalog(&self, account_id: u32) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        ctx.update_webxdc_catalog().await
+    }
+
+    /// Sets the key-contact whose webxdc apps are added to the webxdc catalog.
+    ///
+    /// `null` unsets the catalog bot.
+    async fn set_webxdc_catalog_bot(&self, account_id: u32, contact_id: Option<u32>) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        ctx.set_webxdc_catalog_bot(contact_id.map(ContactId::new))
+            .await
+    }
+
+    /// Sends the app `app_id` from the webxdc catalog to the chat `chat_id`.
+    ///
+    /// Returns the ID of the sent message.
+    async fn install_webxdc_from_catalog(
+        &self,
+        account_id: u32,
+        chat_id: u32,
+        app_id: String,
+    ) -> Result<u32> {
+        let ctx = self.get_context(account_id).await?;
+        let msg_id = ctx
+            .install_webxdc_from_catalog(ChatId::new(chat_id), &app_id)
+            .await?;
+        Ok(msg_id.to_u32())
+    }
+
     /// Starts an outgoing call.
     async fn place_outgoing_call(
         &self,
@@ -2349,6 +2644,44 @@ async fn call_info(&self, account_id: u32, msg_id: u32) -> Result<JsonrpcCallInf
         Ok(call_info)
     }
 
+    /// Returns the ID of the group call going on in the chat, if any.
+    ///
+    /// The call can be joined with `accept_incoming_call`.
+    async fn get_active_call(&self, account_id: u32, chat_id: u32) -> Result<Option<u32>> {
+        let ctx = self.get_context(account_id).await?;
+        let call_id = active_call(&ctx, ChatId::new(chat_id)).await?;
+        Ok(call_id.map(|msg_id| msg_id.to_u32()))
+    }
+
+    /// Returns calls across all chats, newest first.
+    async fn get_call_history(
+        &self,
+        account_id: u32,
+        filter: JsonrpcCallHistoryFilter,
+    ) -> Result<Vec<JsonrpcCallHistoryItem>> {
+        let ctx = self.get_context(account_id).await?;
+        let history = get_call_history(&ctx, filter.into())
+            .await?
+            .into_iter()
+            .map(Into::into)
+            .collect();
+        Ok(history)
+    }
+
+    /// Returns the number of missed calls not seen yet.
+    async fn get_missed_calls_count(&self, account_id: u32) -> Result<usize> {
+        let ctx = self.get_context(account_id).await?;
+        get_missed_calls_count(&ctx).await
+    }
+
+    /// Clears the call history on all devices.
+    ///
+    /// The call messages stay in the chats.
+    async fn clear_call_history(&self, account_id: u32) -> Result<()> {
+        let ctx = self.get_context(account_id).await?;
+        clear_call_history(&ctx).await
+    }
+
     /// Returns JSON with ICE servers, to be used for WebRTC video calls.
     async fn ice_servers(&self, account_id: u32) -> Result<String> {
         let ctx = self.get_context(account_id).await?;
@@ -1,9 +1,11 @@
 use anyhow::{Context as _, Result};
 
-use deltachat::calls::{call_state, CallState};
+use deltachat::calls::{
+    call_participants, call_state, CallHistoryFilter, CallHistoryItem, CallState,
+};
 use deltachat::context::Context;
 use deltachat::message::MsgId;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use typescript_type_def::TypeDef;
 
 #[derive(Serialize, TypeDef, schemars::JsonSchema)]
@@ -22,6 +24,14 @@ pub struct JsonrpcCallInfo {
     ///
     /// For example, if the call is accepted, active, canceled, declined etc.
     pub state: JsonrpcCallState,
+
+    /// True if the call is placed in a group chat.
+    pub is_group: bool,
+
+    /// IDs of the contacts currently in the group call.
+    ///
+    /// Empty for 1:1 calls.
+    pub participants: Vec<u32>,
 }
 
 impl JsonrpcCallInfo {
@@ -32,11 +42,18 @@ pub async fn from_msg_id(context: &Context, msg_id: MsgId) -> Result<JsonrpcCall
         let sdp_offer = call_info.place_call_info.clone();
         let has_video = call_info.has_video_initially();
         let state = JsonrpcCallState::from_msg_id(context, msg_id).await?;
+        let participants = call_participants(context, msg_id)
+            .await?
+            .into_iter()
+            .map(|contact_id| contact_id.to_u32())
+            .collect();
 
         Ok(JsonrpcCallInfo {
             sdp_offer,
             has_video,
             state,
+            is_group: call_info.is_group,
+            participants,
         })
     }
 }
@@ -77,21 +94,100 @@ pub enum JsonrpcCallState {
     /// on the receiver side canceled calls
     /// usually result in missed calls.
     Canceled,
-}
 
-impl JsonrpcCallState {
-    pub async fn from_msg_id(context: &Context, msg_id: MsgId) -> Result<JsonrpcCallState> {
-        let call_state = call_state(context, msg_id).await?;
+    /// Group call that is going on without us
+    /// because we declined or left it
+    /// or did not join it while it was ringing.
+    ///
+    /// The call can still be joined with `accept_incoming_call`.
+    InProgress,
+}
 
-        let jsonrpc_call_state = match call_state {
+impl From<CallState> for JsonrpcCallState {
+    fn from(call_state: CallState) -> Self {
+        match call_state {
             CallState::Alerting => JsonrpcCallState::Alerting,
             CallState::Active => JsonrpcCallState::Active,
             CallState::Completed { duration } => JsonrpcCallState::Completed { duration },
             CallState::Missed => JsonrpcCallState::Missed,
             CallState::Declined => JsonrpcCallState::Declined,
             CallState::Canceled => JsonrpcCallState::Canceled,
-        };
+            CallState::InProgress => JsonrpcCallState::InProgress,
+        }
+    }
+}
+
+impl JsonrpcCallState {
+    pub async fn from_msg_id(context: &Context, msg_id: MsgId) -> Result<JsonrpcCallState> {
+        let call_state = call_state(context, msg_id).await?;
+        Ok(call_state.into())
+    }
+}
+
+#[derive(Deserialize, TypeDef, schemars::JsonSchema)]
+#[serde(rename = "CallHistoryFilter")]
+pub enum JsonrpcCallHistoryFilter {
+    /// All calls.
+    All,
+
+    /// Incoming calls, including missed and declined ones.
+    Incoming,
+
+    /// Outgoing calls.
+    Outgoing,
+
+    /// Missed incoming calls.
+    Missed,
+
+    /// Calls declined by us or by the callee.
+    Declined,
+}
+
+impl From<JsonrpcCallHistoryFilter> for CallHistoryFilter {
+    fn from(filter: JsonrpcCallHistoryFilter) -> Self {
+        match filter {
+            JsonrpcCallHistoryFilter::All => CallHistoryFilter::All,
+            JsonrpcCallHistoryFilter::Incoming => CallHistoryFilter::Incoming,
+            JsonrpcCallHistoryFilter::Outgoing => CallHistoryFilter::Outgoing,
+            JsonrpcCallHistoryFilter::Missed => CallHistoryFilter::Missed,
+            JsonrpcCallHistoryFilter::Declined => CallHistoryFilter::Declined,
+        }
+    }
+}
+
+#[derive(Serialize, TypeDef, schemars::JsonSchema)]
+#[serde(rename = "CallHistoryItem", rename_all = "camelCase")]
+pub struct JsonrpcCallHistoryItem {
+    /// ID of the message referring to the call.
+    pub msg_id: u32,
+
+    /// ID of the chat which the message belongs to.
+    pub chat_id: u32,
+
+    pub timestamp: i64,
+
+    pub is_incoming: bool,
+
+    /// True if the call is started as a video call.
+    pub has_video: bool,
+
+    /// True if the call is placed in a group chat.
+    pub is_group: bool,
+
+    /// Call state, including the duration of completed calls.
+    pub state: JsonrpcCallState,
+}
 
-        Ok(jsonrpc_call_state)
+impl From<CallHistoryItem> for JsonrpcCallHistoryItem {
+    fn from(item: CallHistoryItem) -> Self {
+        JsonrpcCallHistoryItem {
+            msg_id: item.msg_id.to_u32(),
+            chat_id: item.chat_id.to_u32(),
+            timestamp: item.timestamp,
+            is_incoming: item.is_incoming,
+            has_video: item.has_video,
+            is_group: item.is_group,
+            state: item.state.into(),
+        }
     }
 }
@@ -0,0 +1,188 @@
+use deltachat::diagnostics::{
+    DiagnosticsStep as CoreDiagnosticsStep, DnsReport as CoreDnsReport,
+    ImapCapabilitiesReport as CoreImapCapabilitiesReport,
+    NetworkDiagnostics as CoreNetworkDiagnostics, ProxyReport as CoreProxyReport,
+    ServerReport as CoreServerReport, TransportReport as CoreTransportReport,
+};
+use serde::Serialize;
+use typescript_type_def::TypeDef;
+
+#[derive(Serialize, TypeDef, schemars::JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DiagnosticsStep {
+    /// True if the step succeeded.
+    ok: bool,
+
+    /// Time the step took in milliseconds.
+    duration_ms: u64,
+
+    /// Error message if the step failed.
+    error: Option<String>,
+}
+
+impl From<CoreDiagnosticsStep> for DiagnosticsStep {
+    fn from(step: CoreDiagnosticsStep) -> Self {
+        DiagnosticsStep {
+            ok: step.ok,
+            duration_ms: step.duration_ms,
+            error: step.error,
+        }
+    }
+}
+
+#[derive(Serialize, TypeDef, schemars::JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DnsReport {
+    /// Outcome of the resolution.
+    step: DiagnosticsStep,
+
+    /// Resolved IP addresses.
+    addrs: Vec<String>,
+}
+
+impl From<CoreDnsReport> for DnsReport {
+    fn from(dns: CoreDnsReport) -> Self {
+        DnsReport {
+            step: dns.step.into(),
+            addrs: dns.addrs,
+        }
+    }
+}
+
+#[derive(Serialize, TypeDef, schemars::JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ImapCapabilitiesReport {
+    idle: bool,
+    r#move: bool,
+    quota: bool,
+    condstore: bool,
+    metadata: bool,
+    compress: bool,
+    notify: bool,
+    push: bool,
+    chatmail: bool,
+}
+
+impl From<CoreImapCapabilitiesReport> for ImapCapabilitiesReport {
+    fn from(capabilities: CoreImapCapabilitiesReport) -> Self {
+        ImapCapabilitiesReport {
+            idle: capabilities.idle,
+            r#move: capabilities.r#move,
+            quota: capabilities.quota,
+            condstore: capabilities.condstore,
+            metadata: capabilities.metadata,
+            compress: capabilities.compress,
+            notify: capabilities.notify,
+            push: capabilities.push,
+            chatmail: capabilities.chatmail,
+        }
+    }
+}
+
+#[derive(Serialize, TypeDef, schemars::JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ServerReport {
+    host: String,
+    port: u16,
+
+    /// `tls`, `starttls` or `plain`.
+    security: String,
+
+    /// Resolution of the hostname, `null` in Tor mode.
+    dns: Option<DnsReport>,
+
+    /// IP addresses from the persistent DNS cache.
+    dns_cache: Vec<String>,
+
+    /// TCP connection, TLS or STARTTLS handshake and reading the greeting.
+    connect: DiagnosticsStep,
+
+    /// Authentication, `null` if connection failed.
+    login: Option<DiagnosticsStep>,
+
+    /// IMAP capabilities, only for IMAP servers after successful login.
+    capabilities: Option<ImapCapabilitiesReport>,
+}
+
+impl From<CoreServerReport> for ServerReport {
+    fn from(server: CoreServerReport) -> Self {
+        ServerReport {
+            host: server.host,
+            port: server.port,
+            security: server.security,
+            dns: server.dns.map(Into::into),
+            dns_cache: server.dns_cache,
+            connect: server.connect.into(),
+            login: server.login.map(Into::into),
+            capabilities: server.capabilities.map(Into::into),
+        }
+    }
+}
+
+#[derive(Serialize, TypeDef, schemars::JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TransportReport {
+    addr: String,
+    imap: Vec<ServerReport>,
+    smtp: Vec<ServerReport>,
+}
+
+impl From<CoreTransportReport> for TransportReport {
+    fn from(transport: CoreTransportReport) -> Self {
+        TransportReport {
+            addr: transport.addr,
+            imap: transport.imap.into_iter().map(Into::into).collect(),
+            smtp: transport.smtp.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+#[derive(Serialize, TypeDef, schemars::JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ProxyReport {
+    /// Proxy host and port, without credentials.
+    proxy: String,
+
+    /// TCP connection to the proxy.
+    connect: DiagnosticsStep,
+}
+
+impl From<CoreProxyReport> for ProxyReport {
+    fn from(proxy: CoreProxyReport) -> Self {
+        ProxyReport {
+            proxy: proxy.proxy,
+            connect: proxy.connect.into(),
+        }
+    }
+}
+
+#[derive(Serialize, TypeDef, schemars::JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct NetworkDiagnostics {
+    core_version: String,
+
+    /// Time of the diagnostics as a unix timestamp.
+    timestamp: i64,
+
+    tor_mode: bool,
+
+    /// Configured DNS-over-HTTPS mode.
+    doh_mode: i32,
+
+    proxies: Vec<ProxyReport>,
+
+    transports: Vec<TransportReport>,
+}
+
+impl From<CoreNetworkDiagnostics> for NetworkDiagnostics {
+    fn from(report: CoreNetworkDiagnostics) -> Self {
+        NetworkDiagnostics {
+            core_version: report.core_version.to_string(),
+            timestamp: report.timestamp,
+            tor_mode: report.tor_mode,
+            doh_mode: report.doh_mode,
+            proxies: report.proxies.into_iter().map(Into::into).collect(),
+            transports: report.transports.into_iter().map(Into::into).collect(),
+        }
+    }
+}
@@ -183,6 +183,9 @@ pub enum EventType {
 
     /// A single message could not be sent. State changed from DC_STATE_OUT_PENDING or DC_STATE_OUT_DELIVERED to
     /// DC_STATE_OUT_FAILED, see `Message.state`.
+    ///
+    /// Also emitted without changing the state if the message
+    /// could not be sent to some of the recipients, see `Message.error`.
     #[serde(rename_all = "camelCase")]
     MsgFailed {
         /// ID of the chat which the message belongs to.
@@ -376,6 +379,39 @@ pub enum EventType {
         msg_id: u32,
     },
 
+    /// Data received over the realtime channel of a chat.
+    #[serde(rename_all = "camelCase")]
+    ChatRealtimeData {
+        /// Chat ID.
+        chat_id: u32,
+
+        /// Realtime data.
+        data: Vec<u8>,
+    },
+
+    /// Advertisement for the realtime channel of a chat received.
+    #[serde(rename_all = "camelCase")]
+    ChatRealtimeAdvertisementReceived {
+        /// Chat ID.
+        chat_id: u32,
+    },
+
+    /// A contact started or stopped typing in a chat.
+    ///
+    /// The contact should be shown as not typing anymore
+    /// if no new event is received within 10 seconds.
+    #[serde(rename_all = "camelCase")]
+    ContactTyping {
+        /// Chat ID.
+        chat_id: u32,
+
+        /// ID of the typing contact.
+        contact_id: u32,
+
+        /// True if the contact is typing, false if the contact stopped typing.
+        typing: bool,
+    },
+
     /// Inform that a message containing a webxdc instance has been deleted
     #[serde(rename_all = "camelCase")]
     WebxdcInstanceDeleted {
@@ -383,6 +419,33 @@ pub enum EventType {
         msg_id: u32,
     },
 
+    /// The app of a webxdc instance was replaced, e.g. by a new version,
+    /// or its state was reset.
+    ///
+    /// UI should reload the webxdc if it is open.
+    #[serde(rename_all = "camelCase")]
+    WebxdcInstanceUpdated {
+        /// ID of the webxdc instance.
+        msg_id: u32,
+    },
+
+    /// Received status updates of a webxdc instance were dropped
+    /// because they exceed the storage quota of the instance.
+    ///
+    /// The instance sender can compact the updates by sending a snapshot update.
+    #[serde(rename_all = "camelCase")]
+    WebxdcStatusUpdatesDropped {
+        /// ID of the webxdc instance.
+        msg_id: u32,
+    },
+
+    /// A newer version of a webxdc app used in chats is available in the webxdc catalog.
+    #[serde(rename_all = "camelCase")]
+    WebxdcAppUpdateAvailable {
+        /// ID of the app in the webxdc catalog.
+        app_id: String,
+    },
+
     /// Tells that the Background fetch was completed (or timed out).
     /// This event acts as a marker, when you reach this event you can be sure
     /// that all events emitted during the background fetch were processed.
@@ -456,6 +519,10 @@ pub enum EventType {
     },
 
     /// Call ended.
+    ///
+    /// For group calls, this is emitted when the last participant left
+    /// and when the incoming call was declined on another device
+    /// while other participants stay in the call.
     CallEnded {
         /// ID of the info message referring to the call.
         msg_id: u32,
@@ -463,6 +530,28 @@ pub enum EventType {
         chat_id: u32,
     },
 
+    /// A member joined the group call.
+    CallParticipantJoined {
+        /// ID of the info message referring to the call.
+        msg_id: u32,
+        /// ID of the chat which the message belongs to.
+        chat_id: u32,
+        /// ID of the contact who joined the call.
+        contact_id: u32,
+        /// User-defined info passed to accept_incoming_call()
+        join_call_info: String,
+    },
+
+    /// A participant left the group call.
+    CallParticipantLeft {
+        /// ID of the info message referring to the call.
+        msg_id: u32,
+        /// ID of the chat which the message belongs to.
+        chat_id: u32,
+        /// ID of the contact who left the call.
+        contact_id: u32,
+    },
+
     /// One or more transports has changed.
     ///
     /// UI should update the list.
@@ -471,6 +560,14 @@ pub enum EventType {
     /// synchronization messages arrives,
     /// but not when the UI modifies the transport list by itself.
     TransportsModified,
+
+    /// Connections are made through another proxy now,
+    /// because the previously used proxy failed
+    /// or proxy configuration changed.
+    ActiveProxyChanged {
+        /// Host and port of the proxy in use.
+        proxy: String,
+    },
 }
 
 impl From<CoreEventType> for EventType {
@@ -614,9 +711,36 @@ fn from(event: CoreEventType) -> Self {
                     msg_id: msg_id.to_u32(),
                 }
             }
+            CoreEventType::ChatRealtimeData { chat_id, data } => ChatRealtimeData {
+                chat_id: chat_id.to_u32(),
+                data,
+            },
+            CoreEventType::ChatRealtimeAdvertisementReceived { chat_id } => {
+                ChatRealtimeAdvertisementReceived {
+                    chat_id: chat_id.to_u32(),
+                }
+            }
+            CoreEventType::ContactTyping {
+                chat_id,
+                contact_id,
+                typing,
+            } => ContactTyping {
+                chat_id: chat_id.to_u32(),
+                contact_id: contact_id.to_u32(),
+                typing,
+            },
             CoreEventType::WebxdcInstanceDeleted { msg_id } => WebxdcInstanceDeleted {
                 msg_id: msg_id.to_u32(),
             },
+            CoreEventType::WebxdcInstanceUpdated { msg_id } => WebxdcInstanceUpdated {
+                msg_id: msg_id.to_u32(),
+            },
+            CoreEventType::WebxdcStatusUpdatesDropped { msg_id } => WebxdcStatusUpdatesDropped {
+                msg_id: msg_id.to_u32(),
+            },
+            CoreEventType::WebxdcAppUpdateAvailable { app_id } => {
+                WebxdcAppUpdateAvailable { app_id }
+            }
             CoreEventType::AccountsBackgroundFetchDone => AccountsBackgroundFetchDone,
             CoreEventType::ChatlistItemChanged { chat_id } => ChatlistItemChanged {
                 chat_id: chat_id.map(|id| id.to_u32()),
@@ -658,7 +782,28 @@ fn from(event: CoreEventType) -> Self {
                 msg_id: msg_id.to_u32(),
                 chat_id: chat_id.to_u32(),
             },
+            CoreEventType::CallParticipantJoined {
+                msg_id,
+                chat_id,
+                contact_id,
+                join_call_info,
+            } => CallParticipantJoined {
+                msg_id: msg_id.to_u32(),
+                chat_id: chat_id.to_u32(),
+                contact_id: contact_id.to_u32(),
+                join_call_info,
+            },
+            CoreEventType::CallParticipantLeft {
+                msg_id,
+                chat_id,
+                contact_id,
+            } => CallParticipantLeft {
+                msg_id: msg_id.to_u32(),
+                chat_id: chat_id.to_u32(),
+                contact_id: contact_id.to_u32(),
+            },
             CoreEventType::TransportsModified => TransportsModified,
+            CoreEventType::ActiveProxyChanged { proxy } => ActiveProxyChanged { proxy },
 
             #[allow(unreachable_patterns)]
             #[cfg(test)]
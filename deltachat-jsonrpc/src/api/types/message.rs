@@ -8,6 +8,7 @@
 use deltachat::contact::Contact;
 use deltachat::context::Context;
 use deltachat::download;
+use deltachat::message::DeliveryStatus;
 use deltachat::message::Message;
 use deltachat::message::MsgId;
 use deltachat::message::Viewtype;
@@ -668,6 +669,41 @@ pub struct MessageReadReceipt {
     pub timestamp: i64,
 }
 
+#[derive(Serialize, TypeDef, schemars::JsonSchema)]
+#[serde(rename = "DeliveryStatus")]
+pub enum JsonrpcDeliveryStatus {
+    /// Message was delivered to the recipient.
+    Delivered,
+
+    /// Delivery is delayed, the server keeps trying.
+    Delayed,
+
+    /// Delivery has failed.
+    Failed,
+}
+
+impl From<DeliveryStatus> for JsonrpcDeliveryStatus {
+    fn from(status: DeliveryStatus) -> Self {
+        match status {
+            DeliveryStatus::Delivered => Self::Delivered,
+            DeliveryStatus::Delayed => Self::Delayed,
+            DeliveryStatus::Failed => Self::Failed,
+        }
+    }
+}
+
+#[derive(Serialize, TypeDef, schemars::JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MessageDeliveryStatus {
+    /// Address of the recipient.
+    pub addr: String,
+    pub status: JsonrpcDeliveryStatus,
+    /// Diagnostic code returned by the server, if any.
+    pub reason: Option<String>,
+    /// Timestamp of receiving the delivery report.
+    pub timestamp: i64,
+}
+
 #[derive(Serialize, TypeDef, schemars::JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct MessageInfo {
@@ -705,6 +741,57 @@ pub async fn from_msg_id(context: &Context, msg_id: MsgId) -> Result<Self> {
     }
 }
 
+#[derive(Serialize, TypeDef, schemars::JsonSchema)]
+pub enum EncryptionType {
+    /// The message is not encrypted.
+    None,
+    /// The message is encrypted to the public keys of the recipients.
+    PublicKey,
+    /// The message is encrypted with a shared secret.
+    SharedSecret,
+}
+
+impl From<deltachat::message::EncryptionType> for EncryptionType {
+    fn from(value: deltachat::message::EncryptionType) -> Self {
+        match value {
+            deltachat::message::EncryptionType::None => EncryptionType::None,
+            deltachat::message::EncryptionType::PublicKey => EncryptionType::PublicKey,
+            deltachat::message::EncryptionType::SharedSecret => EncryptionType::SharedSecret,
+        }
+    }
+}
+
+#[derive(Serialize, TypeDef, schemars::JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MessageCryptoInfo {
+    encryption: EncryptionType,
+    /// Hex fingerprints of the keys that made valid signatures over the message.
+    signer_fingerprints: Vec<String>,
+    /// True if the message is encrypted and has a valid signature.
+    signature_valid: bool,
+    /// True if the message was signed with the key of the sender contact.
+    signer_matches_from: bool,
+    /// True if the message headers were protected.
+    header_protection: bool,
+    /// Addresses of the recipients whose keys were gossiped in the message.
+    gossip_addrs: Vec<String>,
+}
+
+impl MessageCryptoInfo {
+    pub async fn from_msg_id(context: &Context, msg_id: MsgId) -> Result<Self> {
+        let message = Message::load_from_db(context, msg_id).await?;
+        let info = message.get_crypto_info(context).await?;
+        Ok(Self {
+            encryption: info.encryption.into(),
+            signer_fingerprints: info.signer_fingerprints.iter().map(|fp| fp.hex()).collect(),
+            signature_valid: info.signature_valid,
+            signer_matches_from: info.signer_matches_from,
+            header_protection: info.header_protection,
+            gossip_addrs: info.gossip_addrs,
+        })
+    }
+}
+
 #[derive(
     Debug, PartialEq, Eq, Copy, Clone, Serialize, Deserialize, TypeDef, schemars::JsonSchema,
 )]
@@ -3,6 +3,7 @@
 pub mod chat;
 pub mod chat_list;
 pub mod contact;
+pub mod diagnostics;
 pub mod events;
 pub mod http;
 pub mod location;
@@ -12,6 +13,8 @@
 pub mod provider_info;
 pub mod qr;
 pub mod reactions;
+pub mod tls;
+pub mod traffic;
 pub mod webxdc;
 
 pub fn color_int_to_hex_string(color: u32) -> String {
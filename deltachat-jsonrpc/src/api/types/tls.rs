@@ -0,0 +1,31 @@
+use deltachat::net::SpkiPin;
+use serde::Serialize;
+use typescript_type_def::TypeDef;
+
+#[derive(Serialize, TypeDef, schemars::JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PinnedCertificate {
+    /// Hostname the certificate key is pinned for.
+    host: String,
+
+    /// Base64 of SHA-256 hash of the certificate Subject Public Key Info.
+    spki_hash: String,
+
+    /// Timestamp of the last time the key was seen or pinned manually.
+    timestamp: i64,
+
+    /// True if the key was pinned manually
+    /// rather than remembered after successful connection.
+    manual: bool,
+}
+
+impl From<SpkiPin> for PinnedCertificate {
+    fn from(pin: SpkiPin) -> Self {
+        PinnedCertificate {
+            host: pin.host,
+            spki_hash: pin.spki_hash,
+            timestamp: pin.timestamp,
+            manual: pin.manual,
+        }
+    }
+}
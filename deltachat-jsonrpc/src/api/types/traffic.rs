@@ -0,0 +1,86 @@
+use deltachat::net::{TrafficProtocol, TrafficStats};
+use serde::Serialize;
+use typescript_type_def::TypeDef;
+
+#[derive(Serialize, TypeDef, schemars::JsonSchema)]
+#[serde(rename = "TrafficProtocol")]
+pub enum JsonrpcTrafficProtocol {
+    /// IMAP connections.
+    Imap,
+
+    /// SMTP connections.
+    Smtp,
+
+    /// HTTP(S) requests.
+    Http,
+
+    /// Realtime channels.
+    Iroh,
+}
+
+impl From<TrafficProtocol> for JsonrpcTrafficProtocol {
+    fn from(protocol: TrafficProtocol) -> Self {
+        match protocol {
+            TrafficProtocol::Imap => Self::Imap,
+            TrafficProtocol::Smtp => Self::Smtp,
+            TrafficProtocol::Http => Self::Http,
+            TrafficProtocol::Iroh => Self::Iroh,
+        }
+    }
+}
+
+#[derive(Serialize, TypeDef, schemars::JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TrafficStatsEntry {
+    /// UTC day in `YYYY-MM-DD` format.
+    day: String,
+
+    /// Transport ID, 0 for traffic not related to a transport.
+    transport_id: u32,
+
+    /// Address of the transport if it still exists.
+    addr: Option<String>,
+
+    protocol: JsonrpcTrafficProtocol,
+
+    bytes_sent: u64,
+
+    bytes_received: u64,
+
+    connection_attempts: u64,
+
+    connection_failures: u64,
+
+    /// Number of IMAP IDLE commands.
+    idle_count: u64,
+
+    /// Average duration of IMAP IDLE in milliseconds,
+    /// `null` if there was no IDLE.
+    average_idle_duration_ms: Option<u64>,
+
+    /// Number of IMAP fetches.
+    fetch_count: u64,
+
+    /// Average duration of IMAP fetches in milliseconds,
+    /// `null` if there was no fetch.
+    average_fetch_duration_ms: Option<u64>,
+}
+
+impl From<TrafficStats> for TrafficStatsEntry {
+    fn from(stats: TrafficStats) -> Self {
+        TrafficStatsEntry {
+            day: stats.day,
+            transport_id: stats.transport_id,
+            addr: stats.addr,
+            protocol: stats.protocol.into(),
+            bytes_sent: stats.bytes_sent,
+            bytes_received: stats.bytes_received,
+            connection_attempts: stats.connection_attempts,
+            connection_failures: stats.connection_failures,
+            idle_count: stats.idle_count,
+            average_idle_duration_ms: stats.idle_duration_ms.checked_div(stats.idle_count),
+            fetch_count: stats.fetch_count,
+            average_fetch_duration_ms: stats.fetch_duration_ms.checked_div(stats.fetch_count),
+        }
+    }
+}
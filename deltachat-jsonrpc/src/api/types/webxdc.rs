@@ -1,7 +1,8 @@
 use deltachat::{
     context::Context,
     message::{Message, MsgId},
-    webxdc::WebxdcInfo,
+    storage_usage::WebxdcStorageUsage as CoreWebxdcStorageUsage,
+    webxdc::{WebxdcCatalogEntry as CoreWebxdcCatalogEntry, WebxdcInfo},
 };
 use serde::Serialize;
 use typescript_type_def::TypeDef;
@@ -35,6 +36,10 @@ pub struct WebxdcMessageInfo {
     source_code_url: Option<String>,
     /// True if full internet access should be granted to the app.
     internet_access: bool,
+    /// Permissions requested by the app, eg. "realtime" or "location".
+    permissions: Vec<String>,
+    /// Permissions granted to the app by the user.
+    granted_permissions: Vec<String>,
     /// Address to be used for `window.webxdc.selfAddr` in JS land.
     self_addr: String,
     /// Define if the local user is the one who initially shared the webxdc application in the chat.
@@ -63,6 +68,8 @@ pub async fn get_for_message(
             source_code_url,
             request_integration: _,
             internet_access,
+            permissions,
+            granted_permissions,
             self_addr,
             is_app_sender,
             is_broadcast,
@@ -77,6 +84,11 @@ pub async fn get_for_message(
             summary: maybe_empty_string_to_option(summary),
             source_code_url: maybe_empty_string_to_option(source_code_url),
             internet_access,
+            permissions: permissions.iter().map(ToString::to_string).collect(),
+            granted_permissions: granted_permissions
+                .iter()
+                .map(ToString::to_string)
+                .collect(),
             self_addr,
             is_app_sender,
             is_broadcast,
@@ -85,3 +97,61 @@ pub async fn get_for_message(
         })
     }
 }
+
+#[derive(Serialize, TypeDef, schemars::JsonSchema)]
+#[serde(rename = "WebxdcCatalogEntry", rename_all = "camelCase")]
+pub struct WebxdcCatalogEntry {
+    /// Unique ID of the app, `app_id` from the manifest.
+    app_id: String,
+    /// Name of the app.
+    name: String,
+    /// Description of the app.
+    description: Option<String>,
+    /// Version of the app.
+    version: Option<String>,
+    /// URL where the source code of the app can be found.
+    source_code_url: Option<String>,
+    /// Absolute path of the app icon.
+    icon: Option<String>,
+    /// Timestamp of the last update of the entry.
+    timestamp: i64,
+}
+
+impl From<CoreWebxdcCatalogEntry> for WebxdcCatalogEntry {
+    fn from(entry: CoreWebxdcCatalogEntry) -> Self {
+        Self {
+            app_id: entry.app_id,
+            name: entry.name,
+            description: maybe_empty_string_to_option(entry.description),
+            version: maybe_empty_string_to_option(entry.version),
+            source_code_url: maybe_empty_string_to_option(entry.source_code_url),
+            icon: entry.icon.map(|path| path.to_string_lossy().into_owned()),
+            timestamp: entry.timestamp,
+        }
+    }
+}
+
+#[derive(Serialize, TypeDef, schemars::JsonSchema)]
+#[serde(rename = "WebxdcStorageUsage", rename_all = "camelCase")]
+pub struct WebxdcStorageUsage {
+    /// Total size of the status updates in bytes.
+    status_updates_size: u64,
+    /// Number of status updates.
+    status_updates_count: u64,
+    /// Maximum size of the status updates in bytes.
+    /// Only snapshot updates are accepted when the quota is exceeded.
+    status_updates_quota: u64,
+    /// Size of the .xdc file in bytes.
+    xdc_size: u64,
+}
+
+impl From<CoreWebxdcStorageUsage> for WebxdcStorageUsage {
+    fn from(usage: CoreWebxdcStorageUsage) -> Self {
+        Self {
+            status_updates_size: usage.status_updates_size,
+            status_updates_count: usage.status_updates_count,
+            status_updates_quota: usage.status_updates_quota,
+            xdc_size: usage.xdc_size,
+        }
+    }
+}
@@ -69,6 +69,9 @@ class EventType(str, Enum):
     SELFAVATAR_CHANGED = "SelfavatarChanged"
     WEBXDC_STATUS_UPDATE = "WebxdcStatusUpdate"
     WEBXDC_INSTANCE_DELETED = "WebxdcInstanceDeleted"
+    WEBXDC_INSTANCE_UPDATED = "WebxdcInstanceUpdated"
+    WEBXDC_STATUS_UPDATES_DROPPED = "WebxdcStatusUpdatesDropped"
+    WEBXDC_APP_UPDATE_AVAILABLE = "WebxdcAppUpdateAvailable"
     CHATLIST_CHANGED = "ChatlistChanged"
     CHATLIST_ITEM_CHANGED = "ChatlistItemChanged"
     ACCOUNTS_CHANGED = "AccountsChanged"
@@ -77,10 +80,16 @@ class EventType(str, Enum):
     INCOMING_CALL_ACCEPTED = "IncomingCallAccepted"
     OUTGOING_CALL_ACCEPTED = "OutgoingCallAccepted"
     CALL_ENDED = "CallEnded"
+    CALL_PARTICIPANT_JOINED = "CallParticipantJoined"
+    CALL_PARTICIPANT_LEFT = "CallParticipantLeft"
     CONFIG_SYNCED = "ConfigSynced"
     WEBXDC_REALTIME_DATA = "WebxdcRealtimeData"
     WEBXDC_REALTIME_ADVERTISEMENT_RECEIVED = "WebxdcRealtimeAdvertisementReceived"
+    CHAT_REALTIME_DATA = "ChatRealtimeData"
+    CHAT_REALTIME_ADVERTISEMENT_RECEIVED = "ChatRealtimeAdvertisementReceived"
+    CONTACT_TYPING = "ContactTyping"
     TRANSPORTS_MODIFIED = "TransportsModified"
+    ACTIVE_PROXY_CHANGED = "ActiveProxyChanged"
 
 
 class ChatId(IntEnum):
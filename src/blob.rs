@@ -12,11 +12,9 @@
 use image::ImageReader;
 use image::{DynamicImage, GenericImage, GenericImageView, ImageFormat, Pixel, Rgba};
 use image::{codecs::jpeg::JpegEncoder, metadata::Orientation};
-use num_traits::FromPrimitive;
 use tokio::{fs, task};
 use tokio_stream::wrappers::ReadDirStream;
 
-use crate::config::Config;
 use crate::constants::{self, MediaQuality};
 use crate::context::Context;
 use crate::events::EventType;
@@ -256,18 +254,13 @@ pub(crate) fn store_from_base64(context: &Context, data: &str) -> Result<Option<
 
     /// Recode image to avatar size.
     pub async fn recode_to_avatar_size(&mut self, context: &Context) -> Result<()> {
-        let (max_wh, max_bytes) =
-            match MediaQuality::from_i32(context.get_config_int(Config::MediaQuality).await?)
-                .unwrap_or_default()
-            {
-                MediaQuality::Balanced => (
-                    constants::BALANCED_AVATAR_SIZE,
-                    constants::BALANCED_AVATAR_BYTES,
-                ),
-                MediaQuality::Worse => {
-                    (constants::WORSE_AVATAR_SIZE, constants::WORSE_AVATAR_BYTES)
-                }
-            };
+        let (max_wh, max_bytes) = match context.get_media_quality().await? {
+            MediaQuality::Balanced => (
+                constants::BALANCED_AVATAR_SIZE,
+                constants::BALANCED_AVATAR_BYTES,
+            ),
+            MediaQuality::Worse => (constants::WORSE_AVATAR_SIZE, constants::WORSE_AVATAR_BYTES),
+        };
 
         let viewtype = &mut Viewtype::Image;
         let is_avatar = true;
@@ -290,16 +283,13 @@ pub async fn check_or_recode_image(
         name: Option<String>,
         viewtype: &mut Viewtype,
     ) -> Result<String> {
-        let (max_wh, max_bytes) =
-            match MediaQuality::from_i32(context.get_config_int(Config::MediaQuality).await?)
-                .unwrap_or_default()
-            {
-                MediaQuality::Balanced => (
-                    constants::BALANCED_IMAGE_SIZE,
-                    constants::BALANCED_IMAGE_BYTES,
-                ),
-                MediaQuality::Worse => (constants::WORSE_IMAGE_SIZE, constants::WORSE_IMAGE_BYTES),
-            };
+        let (max_wh, max_bytes) = match context.get_media_quality().await? {
+            MediaQuality::Balanced => (
+                constants::BALANCED_IMAGE_SIZE,
+                constants::BALANCED_IMAGE_BYTES,
+            ),
+            MediaQuality::Worse => (constants::WORSE_IMAGE_SIZE, constants::WORSE_IMAGE_BYTES),
+        };
         let is_avatar = false;
         self.check_or_recode_to_size(context, name, viewtype, max_wh, max_bytes, is_avatar)
     }
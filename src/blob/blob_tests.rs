@@ -1,6 +1,7 @@
 use std::time::Duration;
 
 use super::*;
+use crate::config::Config;
 use crate::message::{Message, Viewtype};
 use crate::param::Param;
 use crate::sql;
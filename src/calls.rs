@@ -2,25 +2,33 @@
 //!
 //! Internally, calls are bound a user-visible message initializing the call.
 //! This means, the "Call ID" is a "Message ID" - similar to Webxdc IDs.
+//!
+//! Calls in groups can be joined and left by all the members.
+//! Joining uses the same "call accepted" message as accepting 1:1 calls
+//! and leaving uses the same "call ended" message as ending 1:1 calls.
+//! The group call ends when the last participant leaves.
 use crate::chat::ChatIdBlocked;
 use crate::chat::{Chat, ChatId, send_msg};
 use crate::config::Config;
-use crate::constants::{Blocked, Chattype};
-use crate::contact::ContactId;
+use crate::constants::{Blocked, Chattype, DC_CHAT_ID_LAST_SPECIAL};
+use crate::contact::{Contact, ContactId};
 use crate::context::{Context, WeakContext};
 use crate::events::EventType;
 use crate::headerdef::HeaderDef;
-use crate::log::warn;
-use crate::message::{Message, MsgId, Viewtype, markseen_msgs};
+use crate::log::{LogExt, warn};
+use crate::message::{Message, MessageState, MsgId, Viewtype, markseen_msgs};
 use crate::mimeparser::{MimeMessage, SystemMessage};
 use crate::net::dns::lookup_host_with_cache;
 use crate::param::Param;
 use crate::stock_str;
+use crate::sync::SyncData;
 use crate::tools::{normalize_text, time};
 use anyhow::{Context as _, Result, ensure};
+use chrono::{Datelike, FixedOffset, Local, Timelike, Utc};
+use deltachat_contact_tools::addr_cmp;
 use deltachat_derive::{FromSql, ToSql};
 use num_traits::FromPrimitive;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use std::str::FromStr;
 use std::time::Duration;
 use tokio::task;
@@ -53,6 +61,13 @@
 /// immediately after ringing started.
 const CALL_CANCELED_TIMESTAMP: Param = Param::Arg2;
 
+/// Set if incoming group call was declined by us
+/// without joining it.
+///
+/// Unlike 1:1 calls, declining a group call
+/// does not end it for other participants.
+const CALL_DECLINED_TIMESTAMP: Param = Param::Arg3;
+
 /// Information about the status of a call.
 #[derive(Debug, Default)]
 pub struct CallInfo {
@@ -65,6 +80,9 @@ pub struct CallInfo {
     /// Message referring to the call.
     /// Data are persisted along with the message using Param::Arg*
     pub msg: Message,
+
+    /// True if the call is placed in a group chat.
+    pub is_group: bool,
 }
 
 impl CallInfo {
@@ -173,6 +191,39 @@ pub fn is_ended(&self) -> bool {
         self.msg.param.exists(CALL_ENDED_TIMESTAMP)
     }
 
+    async fn mark_as_declined(&mut self, context: &Context) -> Result<()> {
+        self.msg.param.set_i64(CALL_DECLINED_TIMESTAMP, time());
+        self.msg.update_param(context).await?;
+        Ok(())
+    }
+
+    /// Returns true if the incoming group call was declined
+    /// without joining it.
+    pub fn is_declined(&self) -> bool {
+        self.msg.param.exists(CALL_DECLINED_TIMESTAMP)
+    }
+
+    /// Updates the message text according to the state of the ended call.
+    async fn update_text_ended(&self, context: &Context) -> Result<()> {
+        match call_state(context, self.msg.id).await? {
+            CallState::Completed { .. } => self.update_text_duration(context).await?,
+            CallState::Missed => {
+                self.update_text(context, &stock_str::missed_call(context))
+                    .await?
+            }
+            CallState::Declined => {
+                self.update_text(context, &stock_str::declined_call(context))
+                    .await?
+            }
+            CallState::Canceled => {
+                self.update_text(context, &stock_str::canceled_call(context))
+                    .await?
+            }
+            CallState::Alerting | CallState::Active | CallState::InProgress => {}
+        }
+        Ok(())
+    }
+
     /// Returns call duration in seconds.
     #[expect(clippy::arithmetic_side_effects)]
     pub fn duration_seconds(&self) -> i64 {
@@ -200,10 +251,16 @@ pub async fn place_outgoing_call(
     ) -> Result<MsgId> {
         let chat = Chat::load_from_db(self, chat_id).await?;
         ensure!(
-            chat.typ == Chattype::Single,
-            "Can only place calls in 1:1 chats"
+            matches!(chat.typ, Chattype::Single | Chattype::Group),
+            "Can only place calls in 1:1 chats and groups"
         );
         ensure!(!chat.is_self_talk(), "Cannot call self");
+        if chat.typ == Chattype::Group {
+            ensure!(
+                chat.is_self_in_chat(self).await?,
+                "Cannot place a call in a group we are not a member of"
+            );
+        }
 
         let outgoing_call_str = stock_str::outgoing_call(self, has_video_initially);
         let mut call = Message {
@@ -215,6 +272,9 @@ pub async fn place_outgoing_call(
         call.param
             .set_int(Param::WebrtcHasVideoInitially, has_video_initially.into());
         call.id = send_msg(self, chat_id, &mut call).await?;
+        if chat.typ == Chattype::Group {
+            add_call_participant(self, call.id, ContactId::SELF, time()).await?;
+        }
 
         let wait = RINGING_SECONDS;
         let context = self.get_weak_context();
@@ -228,6 +288,9 @@ pub async fn place_outgoing_call(
     }
 
     /// Accept an incoming call.
+    ///
+    /// For group calls this joins the call,
+    /// also if it was placed by us and left before.
     pub async fn accept_incoming_call(
         &self,
         call_id: MsgId,
@@ -236,13 +299,23 @@ pub async fn accept_incoming_call(
         let mut call: CallInfo = self.load_call_by_id(call_id).await?.with_context(|| {
             format!("accept_incoming_call is called with {call_id} which does not refer to a call")
         })?;
-        ensure!(call.is_incoming());
-        if call.is_accepted() || call.is_ended() {
-            info!(self, "Call already accepted/ended");
-            return Ok(());
+        if call.is_group {
+            if call.is_ended() || is_call_participant(self, call_id, ContactId::SELF).await? {
+                info!(self, "Group call already joined/ended");
+                return Ok(());
+            }
+            add_call_participant(self, call_id, ContactId::SELF, time()).await?;
+        } else {
+            ensure!(call.is_incoming());
+            if call.is_accepted() || call.is_ended() {
+                info!(self, "Call already accepted/ended");
+                return Ok(());
+            }
         }
 
-        call.mark_as_accepted(self).await?;
+        if !call.is_accepted() {
+            call.mark_as_accepted(self).await?;
+        }
         let chat = Chat::load_from_db(self, call.msg.chat_id).await?;
         if chat.is_contact_request() {
             chat.id.accept(self).await?;
@@ -271,7 +344,18 @@ pub async fn accept_incoming_call(
     }
 
     /// Cancel, decline or hangup an incoming or outgoing call.
+    ///
+    /// For group calls this leaves the call
+    /// or declines it if it was not joined yet.
+    /// The group call ends when the last participant leaves.
     pub async fn end_call(&self, call_id: MsgId) -> Result<()> {
+        self.end_call_ex(call_id, None).await
+    }
+
+    /// Ends the call like [`Self::end_call`].
+    ///
+    /// If `ended_rfc724_mid` is set, it is used as the Message-ID of the `CallEnded` message.
+    async fn end_call_ex(&self, call_id: MsgId, ended_rfc724_mid: Option<String>) -> Result<()> {
         let mut call: CallInfo = self.load_call_by_id(call_id).await?.with_context(|| {
             format!("end_call is called with {call_id} which does not refer to a call")
         })?;
@@ -280,7 +364,21 @@ pub async fn end_call(&self, call_id: MsgId) -> Result<()> {
             return Ok(());
         }
 
-        if !call.is_accepted() {
+        if call.is_group {
+            if remove_call_participant(self, call_id, ContactId::SELF, time()).await? {
+                self.emit_event(EventType::CallParticipantLeft {
+                    msg_id: call_id,
+                    chat_id: call.msg.chat_id,
+                    contact_id: ContactId::SELF,
+                });
+            } else if call.is_incoming() && !call.is_declined() {
+                call.mark_as_declined(self).await?;
+                markseen_msgs(self, vec![call_id]).await?;
+            } else {
+                info!(self, "Group call already left");
+                return Ok(());
+            }
+        } else if !call.is_accepted() {
             if call.is_incoming() {
                 call.mark_as_ended(self).await?;
                 markseen_msgs(self, vec![call_id]).await?;
@@ -303,14 +401,51 @@ pub async fn end_call(&self, call_id: MsgId) -> Result<()> {
         };
         msg.param.set_cmd(SystemMessage::CallEnded);
         msg.hidden = true;
+        if let Some(rfc724_mid) = ended_rfc724_mid {
+            msg.rfc724_mid = rfc724_mid;
+        }
         msg.set_quote(self, Some(&call.msg)).await?;
         msg.id = send_msg(self, call.msg.chat_id, &mut msg).await?;
 
+        if call.is_group {
+            self.end_group_call_if_empty(&mut call).await?;
+        } else {
+            self.emit_event(EventType::CallEnded {
+                msg_id: call.msg.id,
+                chat_id: call.msg.chat_id,
+            });
+        }
+        self.emit_msgs_changed(call.msg.chat_id, call_id);
+        Ok(())
+    }
+
+    /// Ends the group call if all participants have left it.
+    async fn end_group_call_if_empty(&self, call: &mut CallInfo) -> Result<()> {
+        if !call_participants(self, call.msg.id).await?.is_empty() {
+            return Ok(());
+        }
+        call.mark_as_ended(self).await?;
+        call.update_text_ended(self).await?;
         self.emit_event(EventType::CallEnded {
             msg_id: call.msg.id,
             chat_id: call.msg.chat_id,
         });
-        self.emit_msgs_changed(call.msg.chat_id, call_id);
+        Ok(())
+    }
+
+    /// Tells the caller of a call declined because of the call schedule
+    /// that we are not available.
+    async fn send_unavailable_reply(&self, call: &CallInfo) -> Result<()> {
+        let chat = Chat::load_from_db(self, call.msg.chat_id).await?;
+        if chat.typ != Chattype::Single || chat.blocked != Blocked::Not {
+            return Ok(());
+        }
+        let mut msg = Message::new_text(stock_str::unavailable_for_calls(self));
+        // All our devices decline the call and send the reply,
+        // the same Message-ID makes sure that the caller sees it only once.
+        msg.rfc724_mid = format!("unavailable.{}", call.msg.rfc724_mid);
+        msg.set_quote(self, Some(&call.msg)).await?;
+        send_msg(self, call.msg.chat_id, &mut msg).await?;
         Ok(())
     }
 
@@ -328,6 +463,14 @@ async fn emit_end_call_if_unaccepted(
             );
             return Ok(());
         };
+        if call.is_group {
+            // Group calls go on as long as anybody is in them,
+            // so only leave an unanswered group call placed by us.
+            if !call.is_incoming() && !call.is_accepted() && !call.is_ended() {
+                context.end_call(call_id).await?;
+            }
+            return Ok(());
+        }
         if !call.is_accepted() && !call.is_ended() {
             if call.is_incoming() {
                 call.mark_as_canceled(&context).await?;
@@ -358,6 +501,9 @@ pub(crate) async fn handle_call_msg(
                 warn!(self, "{call_id} does not refer to a call message");
                 return Ok(());
             };
+            if call.is_group {
+                add_call_participant(self, call_id, from_id, mime_message.timestamp_sent).await?;
+            }
 
             if call.is_incoming() {
                 if call.is_stale() {
@@ -370,6 +516,12 @@ pub(crate) async fn handle_call_msg(
                     call.update_text(self, &incoming_call_str).await?;
                     self.emit_msgs_changed(call.msg.chat_id, call_id); // ringing calls are not additionally notified
                     let can_call_me = match who_can_call_me(self).await? {
+                        // Group calls ring if the group is accepted,
+                        // there is no 1:1 chat to check.
+                        WhoCanCallMe::Contacts | WhoCanCallMe::Everybody if call.is_group => {
+                            Chat::load_from_db(self, call.msg.chat_id).await?.blocked
+                                == Blocked::Not
+                        }
                         WhoCanCallMe::Contacts => ChatIdBlocked::lookup_by_contact(self, from_id)
                             .await?
                             .is_some_and(|chat_id_blocked| {
@@ -390,6 +542,20 @@ pub(crate) async fn handle_call_msg(
                             .is_none_or(|chat_id_blocked| chat_id_blocked.blocked != Blocked::Yes),
                         WhoCanCallMe::Nobody => false,
                     };
+                    if can_call_me
+                        && !is_available_for_call(self, from_id)
+                            .await
+                            .log_err(self)
+                            .unwrap_or(true)
+                    {
+                        info!(self, "Declining {call_id} because of the call schedule");
+                        // All our devices may decline the call,
+                        // the same Message-ID makes sure that the caller processes it only once.
+                        self.end_call_ex(call_id, Some(format!("ended.{}", call.msg.rfc724_mid)))
+                            .await?;
+                        self.send_unavailable_reply(&call).await?;
+                        return Ok(());
+                    }
                     if can_call_me {
                         self.emit_event(EventType::IncomingCall {
                             msg_id: call.msg.id,
@@ -419,6 +585,12 @@ pub(crate) async fn handle_call_msg(
                         return Ok(());
                     };
 
+                    if call.is_group {
+                        return self
+                            .handle_group_call_joined(&mut call, mime_message, from_id)
+                            .await;
+                    }
+
                     if call.is_ended() || call.is_accepted() {
                         info!(self, "CallAccepted received for accepted/ended call");
                         return Ok(());
@@ -449,6 +621,12 @@ pub(crate) async fn handle_call_msg(
                         return Ok(());
                     };
 
+                    if call.is_group {
+                        return self
+                            .handle_group_call_left(&mut call, mime_message, from_id)
+                            .await;
+                    }
+
                     if call.is_ended() {
                         // may happen eg. if a a message is missed
                         info!(self, "CallEnded received for ended call");
@@ -495,13 +673,95 @@ pub(crate) async fn handle_call_msg(
         Ok(())
     }
 
+    /// Handles a member joining the group call.
+    async fn handle_group_call_joined(
+        &self,
+        call: &mut CallInfo,
+        mime_message: &MimeMessage,
+        from_id: ContactId,
+    ) -> Result<()> {
+        if call.is_ended() {
+            info!(self, "CallAccepted received for ended group call");
+            return Ok(());
+        }
+
+        add_call_participant(self, call.msg.id, from_id, mime_message.timestamp_sent).await?;
+        if !call.is_accepted() && from_id != call.msg.from_id {
+            call.mark_as_accepted(self).await?;
+        }
+        self.emit_msgs_changed(call.msg.chat_id, call.msg.id);
+        if from_id == ContactId::SELF {
+            // Joined on another device, stop ringing.
+            self.emit_event(EventType::IncomingCallAccepted {
+                msg_id: call.msg.id,
+                chat_id: call.msg.chat_id,
+                from_this_device: false,
+            });
+        }
+        let join_call_info = mime_message
+            .get_header(HeaderDef::ChatWebrtcAccepted)
+            .unwrap_or_default();
+        self.emit_event(EventType::CallParticipantJoined {
+            msg_id: call.msg.id,
+            chat_id: call.msg.chat_id,
+            contact_id: from_id,
+            join_call_info: join_call_info.to_string(),
+        });
+        Ok(())
+    }
+
+    /// Handles a member leaving or declining the group call.
+    async fn handle_group_call_left(
+        &self,
+        call: &mut CallInfo,
+        mime_message: &MimeMessage,
+        from_id: ContactId,
+    ) -> Result<()> {
+        if call.is_ended() {
+            info!(self, "CallEnded received for ended group call");
+            return Ok(());
+        }
+
+        if remove_call_participant(self, call.msg.id, from_id, mime_message.timestamp_sent).await? {
+            self.emit_event(EventType::CallParticipantLeft {
+                msg_id: call.msg.id,
+                chat_id: call.msg.chat_id,
+                contact_id: from_id,
+            });
+            self.end_group_call_if_empty(call).await?;
+        } else if from_id == ContactId::SELF && call.is_incoming() && !call.is_declined() {
+            // Declined on another device, stop ringing.
+            call.mark_as_declined(self).await?;
+            self.emit_event(EventType::CallEnded {
+                msg_id: call.msg.id,
+                chat_id: call.msg.chat_id,
+            });
+        } else {
+            // Members who did not join may decline the call.
+            return Ok(());
+        }
+        self.emit_msgs_changed(call.msg.chat_id, call.msg.id);
+        Ok(())
+    }
+
     /// Loads information about the call given its ID.
     ///
     /// If the message referred to by ID is
     /// not a call message, returns `None`.
     pub async fn load_call_by_id(&self, call_id: MsgId) -> Result<Option<CallInfo>> {
         let call = Message::load_from_db(self, call_id).await?;
-        Ok(self.load_call_by_message(call))
+        let Some(mut call_info) = self.load_call_by_message(call) else {
+            return Ok(None);
+        };
+        let chat_type: Option<Chattype> = self
+            .sql
+            .query_get_value(
+                "SELECT type FROM chats WHERE id=?",
+                (call_info.msg.chat_id,),
+            )
+            .await?;
+        call_info.is_group = chat_type == Some(Chattype::Group);
+        Ok(Some(call_info))
     }
 
     // Loads information about the call given the `Message`.
@@ -527,10 +787,120 @@ fn load_call_by_message(&self, call: Message) -> Option<CallInfo> {
                 .unwrap_or_default()
                 .to_string(),
             msg: call,
+            is_group: false,
         })
     }
 }
 
+/// Records that the contact joined the group call.
+async fn add_call_participant(
+    context: &Context,
+    call_id: MsgId,
+    contact_id: ContactId,
+    timestamp: i64,
+) -> Result<()> {
+    // Join reported before the last leave
+    // is an outdated message arriving late.
+    context
+        .sql
+        .execute(
+            "INSERT INTO call_participants (msg_id, contact_id, joined_timestamp)
+             VALUES (?, ?, ?)
+             ON CONFLICT(msg_id, contact_id) DO UPDATE
+             SET joined_timestamp=excluded.joined_timestamp, left_timestamp=0
+             WHERE excluded.joined_timestamp>=call_participants.left_timestamp",
+            (call_id, contact_id, timestamp),
+        )
+        .await?;
+    Ok(())
+}
+
+/// Records that the contact left the group call.
+///
+/// Returns false if the contact was not in the call.
+async fn remove_call_participant(
+    context: &Context,
+    call_id: MsgId,
+    contact_id: ContactId,
+    timestamp: i64,
+) -> Result<bool> {
+    let updated = context
+        .sql
+        .execute(
+            "UPDATE call_participants SET left_timestamp=?
+             WHERE msg_id=? AND contact_id=? AND left_timestamp=0",
+            (timestamp.max(1), call_id, contact_id),
+        )
+        .await?;
+    Ok(updated > 0)
+}
+
+/// Returns true if the contact is currently in the group call.
+async fn is_call_participant(
+    context: &Context,
+    call_id: MsgId,
+    contact_id: ContactId,
+) -> Result<bool> {
+    context
+        .sql
+        .exists(
+            "SELECT COUNT(*) FROM call_participants
+             WHERE msg_id=? AND contact_id=? AND left_timestamp=0",
+            (call_id, contact_id),
+        )
+        .await
+}
+
+/// Returns contacts currently in the group call
+/// in the order of joining.
+///
+/// For 1:1 calls the list is always empty.
+pub async fn call_participants(context: &Context, call_id: MsgId) -> Result<Vec<ContactId>> {
+    context
+        .sql
+        .query_map_vec(
+            "SELECT contact_id FROM call_participants
+             WHERE msg_id=? AND left_timestamp=0
+             ORDER BY joined_timestamp, contact_id",
+            (call_id,),
+            |row| {
+                let contact_id: ContactId = row.get(0)?;
+                Ok(contact_id)
+            },
+        )
+        .await
+}
+
+/// Returns the group call going on in the chat, if any.
+///
+/// UI can use it to show that a call is in progress
+/// and offer to join it with [`Context::accept_incoming_call`].
+pub async fn active_call(context: &Context, chat_id: ChatId) -> Result<Option<MsgId>> {
+    let call_ids = context
+        .sql
+        .query_map_vec(
+            "SELECT DISTINCT call_participants.msg_id
+             FROM call_participants
+             INNER JOIN msgs ON msgs.id=call_participants.msg_id
+             WHERE msgs.chat_id=? AND call_participants.left_timestamp=0
+             ORDER BY msgs.timestamp DESC",
+            (chat_id,),
+            |row| {
+                let msg_id: MsgId = row.get(0)?;
+                Ok(msg_id)
+            },
+        )
+        .await?;
+    for call_id in call_ids {
+        if let Some(call) = context.load_call_by_id(call_id).await?
+            && !call.is_ended()
+        {
+            return Ok(Some(call_id));
+        }
+    }
+    Ok(None)
+}
+
 /// State of the call for display in the message bubble.
 #[derive(Debug, PartialEq, Eq)]
 pub enum CallState {
@@ -567,6 +937,14 @@ pub enum CallState {
     /// on the receiver side canceled calls
     /// usually result in missed calls.
     Canceled,
+
+    /// Group call that is going on without us
+    /// because we declined or left it
+    /// or did not join it while it was ringing.
+    ///
+    /// The call can still be joined
+    /// with [`Context::accept_incoming_call`].
+    InProgress,
 }
 
 /// Returns call state given the message ID.
@@ -577,7 +955,33 @@ pub async fn call_state(context: &Context, msg_id: MsgId) -> Result<CallState> {
         .load_call_by_id(msg_id)
         .await?
         .with_context(|| format!("{msg_id} is not a call message"))?;
-    let state = if call.is_incoming() {
+    call_info_state(context, &call).await
+}
+
+/// Returns state of the already loaded call.
+async fn call_info_state(context: &Context, call: &CallInfo) -> Result<CallState> {
+    let self_left_timestamp = if call.is_group {
+        context
+            .sql
+            .query_get_value(
+                "SELECT left_timestamp FROM call_participants WHERE msg_id=? AND contact_id=?",
+                (call.msg.id, ContactId::SELF),
+            )
+            .await?
+    } else {
+        None
+    };
+    Ok(call_info_state_ex(call, self_left_timestamp))
+}
+
+/// Returns state of the already loaded call.
+///
+/// `self_left_timestamp` is the `left_timestamp` of our row in `call_participants`,
+/// [`None`] if we never joined the group call.
+fn call_info_state_ex(call: &CallInfo, self_left_timestamp: Option<i64>) -> CallState {
+    if call.is_group {
+        group_call_state(call, self_left_timestamp)
+    } else if call.is_incoming() {
         if call.is_accepted() {
             if call.is_ended() {
                 CallState::Completed {
@@ -611,8 +1015,239 @@ pub async fn call_state(context: &Context, msg_id: MsgId) -> Result<CallState> {
         CallState::Declined
     } else {
         CallState::Alerting
-    };
-    Ok(state)
+    }
+}
+
+/// Returns state of the group call from our point of view.
+fn group_call_state(call: &CallInfo, self_left_timestamp: Option<i64>) -> CallState {
+    if call.is_ended() {
+        let participated = self_left_timestamp.is_some();
+        if !call.is_incoming() && !call.is_accepted() {
+            // Nobody joined our call.
+            CallState::Canceled
+        } else if participated {
+            CallState::Completed {
+                duration: call.duration_seconds(),
+            }
+        } else if call.is_declined() {
+            CallState::Declined
+        } else {
+            CallState::Missed
+        }
+    } else if self_left_timestamp == Some(0) {
+        if call.is_accepted() {
+            CallState::Active
+        } else {
+            // Waiting for others to join our call.
+            CallState::Alerting
+        }
+    } else if call.is_incoming()
+        && self_left_timestamp.is_none()
+        && !call.is_declined()
+        && !call.is_stale()
+    {
+        // Ring only if we have not been in the call yet.
+        CallState::Alerting
+    } else {
+        CallState::InProgress
+    }
+}
+
+/// Filter for [`get_call_history`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum CallHistoryFilter {
+    /// All calls.
+    #[default]
+    All,
+
+    /// Incoming calls, including missed and declined ones.
+    Incoming,
+
+    /// Outgoing calls.
+    Outgoing,
+
+    /// Missed incoming calls.
+    Missed,
+
+    /// Calls declined by us or by the callee.
+    Declined,
+}
+
+/// Call history entry.
+#[derive(Debug)]
+pub struct CallHistoryItem {
+    /// ID of the message referring to the call.
+    pub msg_id: MsgId,
+
+    /// ID of the chat which the message belongs to.
+    pub chat_id: ChatId,
+
+    /// Timestamp of the call message.
+    pub timestamp: i64,
+
+    /// True if the call is an incoming call.
+    pub is_incoming: bool,
+
+    /// True if the call is started as a video call.
+    pub has_video: bool,
+
+    /// True if the call is placed in a group chat.
+    pub is_group: bool,
+
+    /// Call state including the duration of completed calls.
+    pub state: CallState,
+}
+
+/// Returns calls across all chats, newest first.
+///
+/// Calls older than the last [`clear_call_history`] are not returned.
+pub async fn get_call_history(
+    context: &Context,
+    filter: CallHistoryFilter,
+) -> Result<Vec<CallHistoryItem>> {
+    let calls = load_calls(context, false).await?;
+
+    let mut history = Vec::new();
+    for (call, self_left_timestamp) in calls {
+        let state = call_info_state_ex(&call, self_left_timestamp);
+        let matches = match filter {
+            CallHistoryFilter::All => true,
+            CallHistoryFilter::Incoming => call.is_incoming(),
+            CallHistoryFilter::Outgoing => !call.is_incoming(),
+            CallHistoryFilter::Missed => state == CallState::Missed,
+            CallHistoryFilter::Declined => state == CallState::Declined,
+        };
+        if matches {
+            history.push(CallHistoryItem {
+                msg_id: call.msg.id,
+                chat_id: call.msg.chat_id,
+                timestamp: call.msg.timestamp_sort,
+                is_incoming: call.is_incoming(),
+                has_video: call.has_video_initially(),
+                is_group: call.is_group,
+                state,
+            });
+        }
+    }
+    Ok(history)
+}
+
+/// Returns the number of missed calls not seen yet,
+/// e.g. to show a badge in the chat list.
+///
+/// Missed calls in blocked chats
+/// and calls older than the last [`clear_call_history`] are not counted.
+pub async fn get_missed_calls_count(context: &Context) -> Result<usize> {
+    let count = load_calls(context, true)
+        .await?
+        .iter()
+        .filter(|(call, self_left_timestamp)| {
+            call_info_state_ex(call, *self_left_timestamp) == CallState::Missed
+        })
+        .count();
+    Ok(count)
+}
+
+/// Loads calls newer than the last [`clear_call_history`]
+/// together with the `left_timestamp` of our participation in group calls.
+///
+/// If `fresh_only` is set, only unseen calls in chats that are not blocked are loaded.
+async fn load_calls(context: &Context, fresh_only: bool) -> Result<Vec<(CallInfo, Option<i64>)>> {
+    let cleared_timestamp = context
+        .get_config_i64(Config::CallHistoryClearedTimestamp)
+        .await?;
+    let calls = context
+        .sql
+        .query_map_vec(
+            "SELECT m.id, m.chat_id, m.from_id, m.timestamp, m.timestamp_sent, m.param,
+                    c.type, p.left_timestamp
+             FROM msgs m
+             INNER JOIN chats c ON c.id=m.chat_id
+             LEFT JOIN call_participants p ON p.msg_id=m.id AND p.contact_id=?
+             WHERE m.type=? AND m.chat_id>? AND m.timestamp>?
+               AND (?=0 OR (m.state=? AND c.blocked!=?))
+             ORDER BY m.timestamp DESC, m.id DESC",
+            (
+                ContactId::SELF,
+                Viewtype::Call,
+                DC_CHAT_ID_LAST_SPECIAL,
+                cleared_timestamp,
+                fresh_only,
+                MessageState::InFresh,
+                Blocked::Yes,
+            ),
+            |row| {
+                let msg = Message {
+                    id: row.get(0)?,
+                    chat_id: row.get(1)?,
+                    from_id: row.get(2)?,
+                    timestamp_sort: row.get(3)?,
+                    timestamp_sent: row.get(4)?,
+                    param: row.get::<_, String>(5)?.parse().unwrap_or_default(),
+                    viewtype: Viewtype::Call,
+                    ..Default::default()
+                };
+                let chat_type: Chattype = row.get(6)?;
+                let self_left_timestamp: Option<i64> = row.get(7)?;
+                let call = CallInfo {
+                    msg,
+                    is_group: chat_type == Chattype::Group,
+                    ..Default::default()
+                };
+                Ok((call, self_left_timestamp))
+            },
+        )
+        .await?;
+    Ok(calls)
+}
+
+/// Clears the call history on all devices.
+///
+/// The call messages stay in the chats,
+/// but are not returned by [`get_call_history`]
+/// and not counted by [`get_missed_calls_count`] anymore.
+pub async fn clear_call_history(context: &Context) -> Result<()> {
+    // Message timestamps may be slightly in the future
+    // because of timestamp smearing.
+    let last_call_timestamp: i64 = context
+        .sql
+        .query_get_value(
+            "SELECT MAX(timestamp) FROM msgs WHERE type=?",
+            (Viewtype::Call,),
+        )
+        .await?
+        .unwrap_or_default();
+    let timestamp = std::cmp::max(time(), last_call_timestamp);
+    context
+        .set_config_internal(
+            Config::CallHistoryClearedTimestamp,
+            Some(&timestamp.to_string()),
+        )
+        .await?;
+    context
+        .add_sync_item(SyncData::ClearCallHistory { timestamp })
+        .await?;
+    context.scheduler.interrupt_smtp().await;
+    context.emit_msgs_changed_without_ids();
+    Ok(())
+}
+
+impl Context {
+    /// Clears the call history as requested by another device.
+    pub(crate) async fn sync_clear_call_history(&self, timestamp: i64) -> Result<()> {
+        let cleared_timestamp = self
+            .get_config_i64(Config::CallHistoryClearedTimestamp)
+            .await?;
+        if timestamp > cleared_timestamp {
+            self.set_config_internal(
+                Config::CallHistoryClearedTimestamp,
+                Some(&timestamp.to_string()),
+            )
+            .await?;
+            self.emit_msgs_changed_without_ids();
+        }
+        Ok(())
+    }
 }
 
 /// ICE server for JSON serialization.
@@ -797,6 +1432,132 @@ pub enum WhoCanCallMe {
     Nobody = 2,
 }
 
+/// Time-based rules for incoming calls.
+///
+/// Stored as JSON in [`Config::CallSchedule`] and synced across devices.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CallSchedule {
+    /// Periods during which calls are possible.
+    ///
+    /// If empty, calls are possible at any time outside of `quiet_hours`.
+    pub working_hours: Vec<WeeklyPeriod>,
+
+    /// Periods during which calls are not possible.
+    pub quiet_hours: Vec<WeeklyPeriod>,
+
+    /// Addresses of contacts who can call at any time.
+    pub exceptions: Vec<String>,
+
+    /// Offset from UTC in minutes of the time zone the periods are in.
+    ///
+    /// If unset, each device uses its local time zone,
+    /// so devices in different time zones may disagree.
+    pub utc_offset_minutes: Option<i32>,
+}
+
+/// Period of time on a day of the week.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WeeklyPeriod {
+    /// Day of the week, 0 is Monday and 6 is Sunday.
+    pub weekday: u8,
+
+    /// Start of the period in minutes since midnight.
+    pub start_minute: u16,
+
+    /// End of the period in minutes since midnight, exclusive.
+    ///
+    /// If it is not after `start_minute`, the period ends on the next day.
+    pub end_minute: u16,
+}
+
+impl WeeklyPeriod {
+    fn contains(&self, weekday: u8, minute: u16) -> bool {
+        if self.start_minute < self.end_minute {
+            self.weekday == weekday && self.start_minute <= minute && minute < self.end_minute
+        } else {
+            let next_weekday = if self.weekday >= 6 {
+                0
+            } else {
+                self.weekday.saturating_add(1)
+            };
+            (self.weekday == weekday && self.start_minute <= minute)
+                || (next_weekday == weekday && minute < self.end_minute)
+        }
+    }
+}
+
+impl CallSchedule {
+    /// Parses the schedule from JSON and checks that all periods are valid.
+    pub fn from_json(json: &str) -> Result<Self> {
+        let schedule: Self = serde_json::from_str(json).context("Invalid call schedule")?;
+        for period in schedule.working_hours.iter().chain(&schedule.quiet_hours) {
+            ensure!(period.weekday <= 6, "Invalid weekday {}", period.weekday);
+            ensure!(
+                period.start_minute <= 1440 && period.end_minute <= 1440,
+                "Invalid period {}-{}",
+                period.start_minute,
+                period.end_minute
+            );
+        }
+        if let Some(offset) = schedule.utc_offset_minutes {
+            ensure!(
+                offset.abs() < 1440,
+                "Invalid offset from UTC {offset} minutes"
+            );
+        }
+        Ok(schedule)
+    }
+
+    /// Returns whether `addr` can call on `weekday` (0 is Monday)
+    /// at `minute` since midnight.
+    pub fn is_available(&self, addr: &str, weekday: u8, minute: u16) -> bool {
+        if self
+            .exceptions
+            .iter()
+            .any(|exception| addr_cmp(exception, addr))
+        {
+            return true;
+        }
+        if self
+            .quiet_hours
+            .iter()
+            .any(|period| period.contains(weekday, minute))
+        {
+            return false;
+        }
+        self.working_hours.is_empty()
+            || self
+                .working_hours
+                .iter()
+                .any(|period| period.contains(weekday, minute))
+    }
+}
+
+/// Returns whether the call schedule allows `contact_id` to call now.
+async fn is_available_for_call(context: &Context, contact_id: ContactId) -> Result<bool> {
+    let Some(json) = context
+        .get_config(Config::CallSchedule)
+        .await?
+        .filter(|json| !json.is_empty())
+    else {
+        return Ok(true);
+    };
+    let schedule = CallSchedule::from_json(&json)?;
+    let contact = Contact::get_by_id(context, contact_id).await?;
+    let now = match schedule.utc_offset_minutes {
+        Some(offset) => {
+            let offset = FixedOffset::east_opt(offset.saturating_mul(60))
+                .context("Invalid offset from UTC")?;
+            Utc::now().with_timezone(&offset)
+        }
+        None => Local::now().fixed_offset(),
+    };
+    let weekday = u8::try_from(now.weekday().num_days_from_monday())?;
+    let minute = u16::try_from(now.num_seconds_from_midnight() / 60)?;
+    Ok(schedule.is_available(contact.get_addr(), weekday, minute))
+}
+
 /// Returns currently configuration of the "who can call me" option.
 async fn who_can_call_me(context: &Context) -> Result<WhoCanCallMe> {
     let who_can_call_me =
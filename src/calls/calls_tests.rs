@@ -682,3 +682,428 @@ async fn test_end_text_call() -> Result<()> {
 
     Ok(())
 }
+
+/// Tests joining and leaving a group call.
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_group_call() -> Result<()> {
+    let mut tcm = TestContextManager::new();
+    let alice = &tcm.alice().await;
+    let bob = &tcm.bob().await;
+    let fiona = &tcm.fiona().await;
+
+    let alice_chat_id = alice
+        .create_group_with_members("Stand-up", &[bob, fiona])
+        .await;
+    let sent = alice.send_text(alice_chat_id, "Hi").await;
+    bob.recv_msg(&sent).await.chat_id.accept(bob).await?;
+    fiona.recv_msg(&sent).await.chat_id.accept(fiona).await?;
+
+    // Alice places a call in the group and joins it.
+    let alice_call_id = alice
+        .place_outgoing_call(alice_chat_id, PLACE_INFO.to_string(), false)
+        .await?;
+    let sent_call = alice.pop_sent_msg().await;
+    assert_eq!(
+        call_participants(alice, alice_call_id).await?,
+        vec![ContactId::SELF]
+    );
+    assert_eq!(call_state(alice, alice_call_id).await?, CallState::Alerting);
+    assert_eq!(
+        active_call(alice, alice_chat_id).await?,
+        Some(alice_call_id)
+    );
+
+    let bob_call = bob.recv_msg(&sent_call).await;
+    let fiona_call = fiona.recv_msg(&sent_call).await;
+    for (t, call) in [(bob, &bob_call), (fiona, &fiona_call)] {
+        t.evtracker
+            .get_matching(|evt| matches!(evt, EventType::IncomingCall { .. }))
+            .await;
+        let call_info = t.load_call_by_id(call.id).await?.unwrap();
+        assert!(call_info.is_group);
+        assert_eq!(call_state(t, call.id).await?, CallState::Alerting);
+        assert_eq!(call_participants(t, call.id).await?.len(), 1);
+    }
+
+    // Bob joins.
+    bob.accept_incoming_call(bob_call.id, ACCEPT_INFO.to_string())
+        .await?;
+    let sent_join = bob.pop_sent_msg().await;
+    assert_eq!(call_state(bob, bob_call.id).await?, CallState::Active);
+    alice.recv_msg_trash(&sent_join).await;
+    let alice_bob_id = alice.add_or_lookup_contact_id(bob).await;
+    alice
+        .evtracker
+        .get_matching(|evt| {
+            matches!(
+                evt,
+                EventType::CallParticipantJoined { contact_id, join_call_info, .. }
+                    if *contact_id == alice_bob_id && join_call_info == ACCEPT_INFO
+            )
+        })
+        .await;
+    assert_eq!(call_state(alice, alice_call_id).await?, CallState::Active);
+    assert_eq!(
+        call_participants(alice, alice_call_id).await?,
+        vec![ContactId::SELF, alice_bob_id]
+    );
+    fiona.recv_msg_trash(&sent_join).await;
+
+    // Fiona declines, the call goes on without her.
+    fiona.end_call(fiona_call.id).await?;
+    let sent_decline = fiona.pop_sent_msg().await;
+    assert_eq!(
+        call_state(fiona, fiona_call.id).await?,
+        CallState::InProgress
+    );
+    assert_eq!(
+        active_call(fiona, fiona_call.chat_id).await?,
+        Some(fiona_call.id)
+    );
+    alice.recv_msg_trash(&sent_decline).await;
+    assert_eq!(call_participants(alice, alice_call_id).await?.len(), 2);
+
+    // Alice leaves, Bob stays in the call.
+    alice.end_call(alice_call_id).await?;
+    let sent_leave = alice.pop_sent_msg().await;
+    assert_eq!(
+        call_state(alice, alice_call_id).await?,
+        CallState::InProgress
+    );
+    bob.recv_msg_trash(&sent_leave).await;
+    bob.evtracker
+        .get_matching(|evt| matches!(evt, EventType::CallParticipantLeft { .. }))
+        .await;
+    assert_eq!(call_state(bob, bob_call.id).await?, CallState::Active);
+    fiona.recv_msg_trash(&sent_leave).await;
+
+    // Fiona joins later.
+    fiona
+        .accept_incoming_call(fiona_call.id, ACCEPT_INFO.to_string())
+        .await?;
+    let sent_fiona_join = fiona.pop_sent_msg().await;
+    assert_eq!(call_state(fiona, fiona_call.id).await?, CallState::Active);
+    alice.recv_msg_trash(&sent_fiona_join).await;
+    bob.recv_msg_trash(&sent_fiona_join).await;
+    assert_eq!(call_participants(bob, bob_call.id).await?.len(), 2);
+
+    // The call ends when the last participant leaves.
+    bob.end_call(bob_call.id).await?;
+    let sent_bob_leave = bob.pop_sent_msg().await;
+    assert_eq!(call_state(bob, bob_call.id).await?, CallState::InProgress);
+    fiona.recv_msg_trash(&sent_bob_leave).await;
+    fiona.end_call(fiona_call.id).await?;
+    let sent_fiona_leave = fiona.pop_sent_msg().await;
+    assert!(matches!(
+        call_state(fiona, fiona_call.id).await?,
+        CallState::Completed { .. }
+    ));
+
+    alice.recv_msg_trash(&sent_bob_leave).await;
+    alice.recv_msg_trash(&sent_fiona_leave).await;
+    alice
+        .evtracker
+        .get_matching(|evt| matches!(evt, EventType::CallEnded { .. }))
+        .await;
+    assert!(matches!(
+        call_state(alice, alice_call_id).await?,
+        CallState::Completed { .. }
+    ));
+    assert_eq!(active_call(alice, alice_chat_id).await?, None);
+    Ok(())
+}
+
+/// Tests that an unanswered group call is left when it stops ringing.
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_group_call_timeout() -> Result<()> {
+    let mut tcm = TestContextManager::new();
+    let alice = &tcm.alice().await;
+    let bob = &tcm.bob().await;
+
+    let alice_chat_id = alice.create_group_with_members("Stand-up", &[bob]).await;
+    let sent = alice.send_text(alice_chat_id, "Hi").await;
+    bob.recv_msg(&sent).await.chat_id.accept(bob).await?;
+
+    let alice_call_id = alice
+        .place_outgoing_call(alice_chat_id, PLACE_INFO.to_string(), false)
+        .await?;
+    let bob_call = bob.recv_msg(&alice.pop_sent_msg().await).await;
+
+    // Bob's device stops ringing, the call goes on and Bob can still join.
+    Context::emit_end_call_if_unaccepted(bob.get_weak_context(), 0, bob_call.id).await?;
+    assert!(!bob.load_call_by_id(bob_call.id).await?.unwrap().is_ended());
+    assert_eq!(call_participants(bob, bob_call.id).await?.len(), 1);
+    assert_eq!(active_call(bob, bob_call.chat_id).await?, Some(bob_call.id));
+
+    // Nobody joined, so Alice leaves the call and it ends.
+    alice.evtracker.clear_events();
+    Context::emit_end_call_if_unaccepted(alice.get_weak_context(), 0, alice_call_id).await?;
+    assert!(call_participants(alice, alice_call_id).await?.is_empty());
+    assert_eq!(call_state(alice, alice_call_id).await?, CallState::Canceled);
+    assert_eq!(active_call(alice, alice_chat_id).await?, None);
+    alice
+        .evtracker
+        .get_matching(|evt| matches!(evt, EventType::CallEnded { .. }))
+        .await;
+
+    bob.recv_msg_trash(&alice.pop_sent_msg().await).await;
+    assert!(call_participants(bob, bob_call.id).await?.is_empty());
+    assert_eq!(call_state(bob, bob_call.id).await?, CallState::Missed);
+    assert_eq!(active_call(bob, bob_call.chat_id).await?, None);
+    Ok(())
+}
+
+/// Tests listing, counting and clearing calls.
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_call_history() -> Result<()> {
+    let CallSetup {
+        alice,
+        alice2,
+        alice_call,
+        bob,
+        bob_call,
+        ..
+    } = setup_call().await?;
+
+    // Alice cancels the call, Bob misses it.
+    alice.end_call(alice_call.id).await?;
+    let sent = alice.pop_sent_msg().await;
+    bob.recv_msg_trash(&sent).await;
+    assert_eq!(call_state(&bob, bob_call.id).await?, CallState::Missed);
+
+    let history = get_call_history(&bob, CallHistoryFilter::All).await?;
+    assert_eq!(history.len(), 1);
+    assert_eq!(history[0].msg_id, bob_call.id);
+    assert!(history[0].is_incoming);
+    assert!(history[0].has_video);
+    assert_eq!(history[0].state, CallState::Missed);
+    assert_eq!(
+        get_call_history(&bob, CallHistoryFilter::Missed)
+            .await?
+            .len(),
+        1
+    );
+    assert!(
+        get_call_history(&bob, CallHistoryFilter::Outgoing)
+            .await?
+            .is_empty()
+    );
+    assert_eq!(get_missed_calls_count(&bob).await?, 1);
+
+    // Seen missed calls are not counted, but stay in the history.
+    markseen_msgs(&bob, vec![bob_call.id]).await?;
+    assert_eq!(get_missed_calls_count(&bob).await?, 0);
+    assert_eq!(
+        get_call_history(&bob, CallHistoryFilter::Missed)
+            .await?
+            .len(),
+        1
+    );
+
+    let history = get_call_history(&alice, CallHistoryFilter::Outgoing).await?;
+    assert_eq!(history.len(), 1);
+    assert!(!history[0].is_incoming);
+    assert_eq!(history[0].state, CallState::Canceled);
+    assert!(
+        get_call_history(&alice, CallHistoryFilter::Incoming)
+            .await?
+            .is_empty()
+    );
+
+    // Clearing the call history is synchronized,
+    // the call message stays in the chat.
+    clear_call_history(&alice).await?;
+    assert!(
+        get_call_history(&alice, CallHistoryFilter::All)
+            .await?
+            .is_empty()
+    );
+    assert_eq!(
+        get_call_history(&alice2, CallHistoryFilter::All)
+            .await?
+            .len(),
+        1
+    );
+    test_utils::sync(&alice, &alice2).await;
+    assert!(
+        get_call_history(&alice2, CallHistoryFilter::All)
+            .await?
+            .is_empty()
+    );
+    assert_eq!(
+        Message::load_from_db(&alice, alice_call.id).await?.viewtype,
+        Viewtype::Call
+    );
+    Ok(())
+}
+
+/// Tests listing declined calls and group calls in the call history.
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_call_history_declined() -> Result<()> {
+    let CallSetup {
+        alice,
+        alice_call,
+        bob,
+        bob_call,
+        ..
+    } = setup_call().await?;
+
+    // Bob declines the call.
+    bob.end_call(bob_call.id).await?;
+    alice.recv_msg_trash(&bob.pop_sent_msg().await).await;
+    for (t, call) in [(&alice, &alice_call), (&bob, &bob_call)] {
+        let history = get_call_history(t, CallHistoryFilter::Declined).await?;
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].msg_id, call.id);
+        assert_eq!(history[0].state, CallState::Declined);
+        assert!(
+            get_call_history(t, CallHistoryFilter::Missed)
+                .await?
+                .is_empty()
+        );
+    }
+
+    // Group calls joined by us are listed with the state of the call.
+    let fiona = &TestContext::new_fiona().await;
+    let alice_chat_id = alice.create_group_with_members("Stand-up", &[fiona]).await;
+    let alice_group_call_id = alice
+        .place_outgoing_call(alice_chat_id, PLACE_INFO.to_string(), false)
+        .await?;
+    let history = get_call_history(&alice, CallHistoryFilter::Outgoing).await?;
+    assert_eq!(history.len(), 2);
+    assert_eq!(history[0].msg_id, alice_group_call_id);
+    assert!(history[0].is_group);
+    assert_eq!(history[0].state, CallState::Alerting);
+    assert!(!history[1].is_group);
+    Ok(())
+}
+
+#[test]
+fn test_call_schedule_is_available() -> Result<()> {
+    // Mondays 9:00-17:00 with a lunch break, Bob can call any time.
+    let schedule = CallSchedule::from_json(
+        r#"{
+            "working_hours": [{"weekday": 0, "start_minute": 540, "end_minute": 1020}],
+            "quiet_hours": [{"weekday": 0, "start_minute": 720, "end_minute": 780}],
+            "exceptions": ["Bob@Example.net"]
+        }"#,
+    )?;
+    assert!(schedule.is_available("alice@example.org", 0, 540));
+    assert!(!schedule.is_available("alice@example.org", 0, 539));
+    assert!(!schedule.is_available("alice@example.org", 0, 720));
+    assert!(schedule.is_available("alice@example.org", 0, 780));
+    assert!(!schedule.is_available("alice@example.org", 0, 1020));
+    assert!(!schedule.is_available("alice@example.org", 1, 600));
+    assert!(schedule.is_available("bob@example.net", 1, 0));
+
+    // Quiet hours from Sunday 22:00 to Monday 7:00.
+    let schedule = CallSchedule::from_json(
+        r#"{"quiet_hours": [{"weekday": 6, "start_minute": 1320, "end_minute": 420}]}"#,
+    )?;
+    assert!(schedule.is_available("alice@example.org", 6, 1319));
+    assert!(!schedule.is_available("alice@example.org", 6, 1320));
+    assert!(!schedule.is_available("alice@example.org", 0, 0));
+    assert!(!schedule.is_available("alice@example.org", 0, 419));
+    assert!(schedule.is_available("alice@example.org", 0, 420));
+    assert!(schedule.is_available("alice@example.org", 5, 0));
+
+    assert!(CallSchedule::from_json("{}")?.is_available("alice@example.org", 3, 100));
+    assert!(
+        CallSchedule::from_json(
+            r#"{"quiet_hours": [{"weekday": 7, "start_minute": 0, "end_minute": 60}]}"#
+        )
+        .is_err()
+    );
+    assert!(
+        CallSchedule::from_json(
+            r#"{"quiet_hours": [{"weekday": 1, "start_minute": 0, "end_minute": 1441}]}"#
+        )
+        .is_err()
+    );
+    assert_eq!(
+        CallSchedule::from_json(r#"{"utc_offset_minutes": -300}"#)?.utc_offset_minutes,
+        Some(-300)
+    );
+    assert!(CallSchedule::from_json(r#"{"utc_offset_minutes": 1440}"#).is_err());
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_call_schedule_declines_call() -> Result<()> {
+    let mut tcm = TestContextManager::new();
+    let alice = tcm.alice().await;
+    let bob = tcm.bob().await;
+    let bob2 = tcm.bob().await;
+    for t in [&bob, &bob2] {
+        t.set_config_bool(Config::SyncMsgs, true).await?;
+    }
+    assert!(
+        bob.set_config(Config::CallSchedule, Some("not a schedule"))
+            .await
+            .is_err()
+    );
+
+    // Bob does not want to be called at all and syncs this to his other device.
+    let schedule = CallSchedule {
+        quiet_hours: (0..7)
+            .map(|weekday| WeeklyPeriod {
+                weekday,
+                start_minute: 0,
+                end_minute: 1440,
+            })
+            .collect(),
+        utc_offset_minutes: Some(60),
+        ..Default::default()
+    };
+    bob.set_config(
+        Config::CallSchedule,
+        Some(&serde_json::to_string(&schedule)?),
+    )
+    .await?;
+    test_utils::sync(&bob, &bob2).await;
+    let schedule_json = bob2.get_config(Config::CallSchedule).await?.unwrap();
+    assert_eq!(CallSchedule::from_json(&schedule_json)?, schedule);
+
+    let alice_chat = alice.create_chat(&bob).await;
+    bob.create_chat(&alice).await;
+    bob2.create_chat(&alice).await;
+    alice
+        .place_outgoing_call(alice_chat.id, PLACE_INFO.to_string(), false)
+        .await?;
+    let sent1 = alice.pop_sent_msg().await;
+    let alice_call = alice.get_last_msg_in(alice_chat.id).await;
+
+    // Both devices of Bob decline the call and reply with the same messages.
+    let mut replies = Vec::new();
+    for t in [&bob, &bob2] {
+        let call = t.recv_msg(&sent1).await;
+        assert_eq!(call_state(t, call.id).await?, CallState::Declined);
+        assert_text(t, call.id, "Declined call").await?;
+        let reply = t.pop_sent_msg().await;
+        let ended = t.pop_sent_msg().await;
+        let reply_msg = t.get_last_msg().await;
+        assert_eq!(reply_msg.text, "I am not available for calls right now.");
+        replies.push((reply_msg.rfc724_mid, reply, ended));
+    }
+    assert_eq!(replies[0].0, replies[1].0);
+    assert_eq!(
+        replies[0].2.load_from_db().await.rfc724_mid,
+        replies[1].2.load_from_db().await.rfc724_mid
+    );
+
+    let (_, reply, ended) = &replies[0];
+    alice.recv_msg_trash(ended).await;
+    assert_eq!(
+        call_state(&alice, alice_call.id).await?,
+        CallState::Declined
+    );
+    let msg = alice.recv_msg(reply).await;
+    assert_eq!(msg.text, "I am not available for calls right now.");
+    assert_eq!(msg.quoted_message(&alice).await?.unwrap().id, alice_call.id);
+
+    // The messages from the second device are not processed again.
+    let (_, reply, ended) = &replies[1];
+    assert!(alice.recv_msg_opt(reply).await.is_none());
+    assert!(alice.recv_msg_opt(ended).await.is_none());
+    Ok(())
+}
@@ -38,10 +38,11 @@
 use crate::location;
 use crate::log::{LogExt, warn};
 use crate::logged_debug_assert;
-use crate::message::{self, Message, MessageState, MsgId, Viewtype};
+use crate::message::{self, EncryptionType, Message, MessageState, MsgId, Viewtype};
 use crate::mimefactory::{MimeFactory, RenderedEmail};
 use crate::mimeparser::SystemMessage;
 use crate::param::{Param, Params};
+use crate::peer_channels::rotate_chat_realtime_topic;
 use crate::pgp::addresses_from_public_key;
 use crate::receive_imf::ReceivedMsg;
 use crate::smtp::{self, send_msg_to_smtp};
@@ -669,6 +670,26 @@ pub(crate) async fn delete_ex(self, context: &Context, sync: sync::Sync) -> Resu
                     "DELETE FROM msgs_mdns WHERE msg_id IN (SELECT id FROM msgs WHERE chat_id=?)",
                     (self,),
                 )?;
+                transaction.execute(
+                    "DELETE FROM msgs_delivery WHERE msg_id IN (SELECT id FROM msgs WHERE chat_id=?)",
+                    (self,),
+                )?;
+                transaction.execute(
+                    "DELETE FROM call_participants WHERE msg_id IN (SELECT id FROM msgs WHERE chat_id=?)",
+                    (self,),
+                )?;
+                transaction.execute(
+                    "DELETE FROM webxdc_permissions WHERE msg_id IN (SELECT id FROM msgs WHERE chat_id=?)",
+                    (self,),
+                )?;
+                transaction.execute(
+                    "DELETE FROM webxdc_instance_apps WHERE msg_id IN (SELECT id FROM msgs WHERE chat_id=?)",
+                    (self,),
+                )?;
+                transaction.execute(
+                    "DELETE FROM webxdc_status_updates_size WHERE msg_id IN (SELECT id FROM msgs WHERE chat_id=?)",
+                    (self,),
+                )?;
                 // If you change which information is preserved here, also change `MsgId::trash()`
                 // and other places it references.
                 transaction.execute(
@@ -678,6 +699,11 @@ pub(crate) async fn delete_ex(self, context: &Context, sync: sync::Sync) -> Resu
                     ",
                     (DC_CHAT_ID_TRASH, self),
                 )?;
+                transaction.execute("DELETE FROM chat_realtime_peers WHERE chat_id=?", (self,))?;
+                transaction.execute(
+                    "DELETE FROM chat_realtime_retired_topics WHERE chat_id=?",
+                    (self,),
+                )?;
                 transaction.execute("DELETE FROM chats_contacts WHERE chat_id=?", (self,))?;
                 transaction.execute("DELETE FROM chats WHERE id=?", (self,))?;
                 Ok(())
@@ -2759,6 +2785,7 @@ async fn render_mime_message_and_pre_message(
 ) -> Result<(Option<RenderedEmail>, RenderedEmail)> {
     let needs_pre_message = msg.viewtype.has_file()
         && mimefactory.will_be_encrypted() // unencrypted is likely email, we don't want to spam by sending multiple messages
+        && !msg.param.exists(Param::WebxdcReplaceFor) // replacements are not shown, so nobody would download the post-message
         && msg
             .get_filebytes(context)
             .await?
@@ -2970,6 +2997,11 @@ pub(crate) async fn create_send_msg_jobs(context: &Context, msg: &mut Message) -
             ),
         )
         .await?;
+    if rendered_msg.crypto_info.encryption != EncryptionType::None
+        || rendered_msg.crypto_info.signature_valid
+    {
+        rendered_msg.crypto_info.save(context, msg.id).await?;
+    }
 
     let chunk_size = context.get_max_smtp_rcpt_to().await?;
     let trans_fn = |t: &mut rusqlite::Transaction| {
@@ -4189,6 +4221,10 @@ pub async fn remove_contact_from_chat(
             sync = Sync;
         }
     }
+    rotate_chat_realtime_topic(context, chat_id)
+        .await
+        .log_err(context)
+        .ok();
     context.emit_event(EventType::ChatModified(chat_id));
     if sync.into() {
         chat.sync_contacts(context).await.log_err(context).ok();
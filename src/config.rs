@@ -7,16 +7,20 @@
 use anyhow::{Context as _, Result, bail, ensure};
 use base64::Engine as _;
 use deltachat_contact_tools::{addr_cmp, sanitize_single_line};
+use num_traits::FromPrimitive;
 use serde::{Deserialize, Serialize};
 use strum::{EnumProperty, IntoEnumIterator};
 use strum_macros::{AsRefStr, Display, EnumIter, EnumString};
 use tokio::fs;
 
 use crate::blob::BlobObject;
+use crate::calls::CallSchedule;
+use crate::constants::{LowBandwidthMode, MediaQuality};
 use crate::context::Context;
 use crate::events::EventType;
 use crate::log::LogExt;
 use crate::mimefactory::RECOMMENDED_FILE_SIZE;
+use crate::net::proxy::forget_active_proxy;
 use crate::provider::Provider;
 use crate::sync::{self, Sync::*, SyncData};
 use crate::tools::{get_abs_path, time};
@@ -137,9 +141,47 @@ pub enum Config {
     /// Supported URLs schemes are `http://` (HTTP), `https://` (HTTPS),
     /// `socks5://` (SOCKS5) and `ss://` (Shadowsocks).
     ///
-    /// May contain multiple URLs separated by newline, in which case the first one is used.
+    /// May contain multiple URLs separated by newline.
+    /// The first one is tried first unless another proxy worked last,
+    /// other proxies are used if connection through it fails.
     ProxyUrl,
 
+    /// Route all connections through Tor.
+    ///
+    /// If enabled, IMAP, SMTP and HTTP connections are made
+    /// through the SOCKS5 proxies from `ProxyUrl`,
+    /// or through the local Tor daemon if there are none,
+    /// so that hostnames are resolved by Tor.
+    /// Direct connections are refused,
+    /// realtime channels only connect to the iroh relay through Tor.
+    /// This is needed to connect to `.onion` servers.
+    #[strum(props(default = "0"))]
+    TorMode,
+
+    /// Use of DNS-over-HTTPS, one of the `DohMode` enum values.
+    ///
+    /// Has no effect in Tor mode, where hostnames are resolved by Tor.
+    #[strum(props(default = "0"))] // also change DohMode.default() on changes
+    DohMode,
+
+    /// DNS-over-HTTPS endpoint URLs, separated by newlines.
+    ///
+    /// Endpoints are tried in the given order.
+    /// Hostnames of the endpoints themselves
+    /// are resolved with the system resolver,
+    /// so it is best to use endpoints with IP addresses.
+    #[strum(props(default = "https://1.1.1.1/dns-query\nhttps://9.9.9.9/dns-query"))]
+    DohUrls,
+
+    /// Refuse TLS connections if the server key
+    /// does not match the key seen last time.
+    ///
+    /// By default a changed key is accepted and remembered
+    /// if the certificate is valid.
+    /// Manually pinned keys are always enforced.
+    #[strum(props(default = "0"))]
+    StrictCertificatePinning,
+
     /// True if SOCKS5 is enabled.
     ///
     /// Can be used to disable SOCKS5 without erasing SOCKS5 configuration.
@@ -190,10 +232,36 @@ pub enum Config {
     #[strum(props(default = "1"))]
     MdnsEnabled,
 
+    /// True if typing indicators should be sent and shown
+    /// and realtime channels of chats advertised for them,
+    /// see [`crate::peer_channels::set_typing`].
+    #[strum(props(default = "1"))]
+    TypingIndicatorsEnabled,
+
+    /// True if Delivery Status Notifications should be requested
+    /// for sent messages from the SMTP server.
+    ///
+    /// Notifications are only requested if the server advertises DSN extension.
+    ///
+    /// Failure reports are processed regardless of this setting
+    /// as servers send them by default.
+    #[strum(props(default = "0"))]
+    RequestDsn,
+
     /// Quality of the media files to send.
     #[strum(props(default = "0"))] // also change MediaQuality.default() on changes
     MediaQuality,
 
+    /// Low-bandwidth mode, one of the `LowBandwidthMode` enum values.
+    ///
+    /// While bandwidth is saved, large messages are not downloaded automatically,
+    /// outgoing media is sent in worse quality,
+    /// webxdc realtime channels are not used,
+    /// read receipts are only sent along with other messages
+    /// and IMAP servers without IDLE are polled less often.
+    #[strum(props(default = "0"))] // also change LowBandwidthMode.default() on changes
+    LowBandwidth,
+
     /// Timer in seconds after which the message is deleted from the
     /// device.
     ///
@@ -362,6 +430,11 @@ pub enum Config {
     /// Timestamp of the last `CantDecryptOutgoingMsgs` notification.
     LastCantDecryptOutgoingMsgs,
 
+    /// Timestamp of the last time the call history was cleared.
+    ///
+    /// Older calls are not listed in the call history.
+    CallHistoryClearedTimestamp,
+
     /// Whether to avoid using IMAP IDLE even if the server supports it.
     ///
     /// This is a developer option for testing "fake idle".
@@ -411,6 +484,27 @@ pub enum Config {
     #[strum(props(default = "172800"))]
     GossipPeriod,
 
+    /// Profile of the key generated when the profile is configured.
+    ///
+    /// The options are from the `KeyProfile` enum.
+    /// Changes only affect keys generated in the future.
+    #[strum(props(default = "0"))] // also change KeyProfile.default() on changes
+    KeyProfile,
+
+    /// Look up OpenPGP certificates of new contacts
+    /// in the Web Key Directory of their domain.
+    ///
+    /// Disabled by default because the lookup reveals
+    /// to the domain of the contact that we are going to write to them.
+    #[strum(props(default = "0"))]
+    WkdLookup,
+
+    /// Sign unencrypted outgoing messages with OpenPGP/MIME `multipart/signed`,
+    /// so that recipients without end-to-end encryption
+    /// can still verify who sent the message.
+    #[strum(props(default = "0"))]
+    SignUnencrypted,
+
     /// Row ID of the key in the `keypairs` table
     /// used for signatures, encryption to self and included in `Autocrypt` header.
     KeyId,
@@ -439,6 +533,17 @@ pub enum Config {
     #[strum(props(default = "1"))]
     WebxdcRealtimeEnabled,
 
+    /// Buffer recent realtime messages and replay them to peers that missed them,
+    /// see [`crate::peer_channels`].
+    WebxdcRealtimeReliable,
+
+    /// Fingerprint of the key of the bot whose webxdc apps are added to the webxdc catalog,
+    /// set with [`Context::set_webxdc_catalog_bot`].
+    WebxdcCatalogBot,
+
+    /// URL of the JSON index of webxdc apps added to the webxdc catalog.
+    WebxdcCatalogUrl,
+
     /// Last device token stored on the chatmail server.
     ///
     /// If it has not changed, we do not store
@@ -460,6 +565,10 @@ pub enum Config {
     /// Return an error from `receive_imf_inner()`. For tests.
     SimulateReceiveImfError,
 
+    /// Disable iroh relays, peers can only connect using direct addresses then.
+    /// For tests with local iroh endpoints.
+    IrohRelayDisabled,
+
     /// Enable composing emails with Header Protection as defined in
     /// <https://www.rfc-editor.org/rfc/rfc9788.html> "Header Protection for Cryptographically
     /// Protected Email".
@@ -472,6 +581,12 @@ pub enum Config {
     #[strum(props(default = "1"))]
     WhoCanCallMe,
 
+    /// Call availability schedule as JSON, see [`crate::calls::CallSchedule`].
+    ///
+    /// Incoming calls outside of the schedule are declined automatically.
+    /// Unset or empty means that calls are possible at any time.
+    CallSchedule,
+
     /// Experimental option denoting that the current profile is shared between multiple team members.
     /// For now, the only effect of this option is that seen flags are not synchronized.
     TeamProfile,
@@ -499,9 +614,11 @@ pub(crate) fn is_synced(&self) -> bool {
             self,
             Self::Displayname
                 | Self::MdnsEnabled
+                | Self::TypingIndicatorsEnabled
                 | Self::Selfavatar
                 | Self::Selfstatus
-                | Self::ForceEncryption,
+                | Self::ForceEncryption
+                | Self::CallSchedule,
         )
     }
 
@@ -634,6 +751,32 @@ pub(crate) async fn should_send_mdns(&self) -> Result<bool> {
         self.get_config_bool(Config::MdnsEnabled).await
     }
 
+    /// Returns whether bandwidth should be saved now.
+    ///
+    /// In [`LowBandwidthMode::Auto`] this depends on the last measured download throughput.
+    pub async fn is_low_bandwidth(&self) -> Result<bool> {
+        let mode = LowBandwidthMode::from_i32(self.get_config_int(Config::LowBandwidth).await?)
+            .unwrap_or_default();
+        Ok(match mode {
+            LowBandwidthMode::Off => false,
+            LowBandwidthMode::On => true,
+            LowBandwidthMode::Auto => self.traffic_metrics.is_throughput_low(),
+        })
+    }
+
+    /// Returns the quality of the media files to send.
+    ///
+    /// Media is always sent in worse quality while bandwidth is saved.
+    pub(crate) async fn get_media_quality(&self) -> Result<MediaQuality> {
+        if self.is_low_bandwidth().await? {
+            return Ok(MediaQuality::Worse);
+        }
+        Ok(
+            MediaQuality::from_i32(self.get_config_int(Config::MediaQuality).await?)
+                .unwrap_or_default(),
+        )
+    }
+
     /// Gets the configured provider.
     ///
     /// The provider is determined by the current primary transport.
@@ -676,8 +819,12 @@ fn check_config(key: Config, value: Option<&str>) -> Result<()> {
         match key {
             Config::Socks5Enabled
             | Config::ProxyEnabled
+            | Config::TorMode
+            | Config::StrictCertificatePinning
             | Config::BccSelf
             | Config::MdnsEnabled
+            | Config::TypingIndicatorsEnabled
+            | Config::RequestDsn
             | Config::Configured
             | Config::Bot
             | Config::NotifyAboutWrongPw
@@ -688,6 +835,11 @@ fn check_config(key: Config, value: Option<&str>) -> Result<()> {
                     "Boolean value must be either 0 or 1"
                 );
             }
+            Config::CallSchedule => {
+                if let Some(value) = value.filter(|v| !v.is_empty()) {
+                    CallSchedule::from_json(value)?;
+                }
+            }
             _ => (),
         }
         Ok(())
@@ -759,6 +911,17 @@ pub(crate) async fn set_config_ex(
                 }
                 self.emit_event(EventType::SelfavatarChanged);
             }
+            Config::ProxyUrl => {
+                self.sql.set_raw_config(key.as_ref(), value).await?;
+                // The user may have put another proxy first,
+                // so do not prefer the proxy that worked last.
+                forget_active_proxy(self).await?;
+            }
+            Config::LowBandwidth => {
+                self.sql.set_raw_config(key.as_ref(), value).await?;
+                // Send MDNs deferred while bandwidth was saved.
+                self.scheduler.interrupt_smtp().await;
+            }
             Config::DeleteDeviceAfter => {
                 let ret = self.sql.set_raw_config(key.as_ref(), value).await;
                 // Interrupt ephemeral loop to delete old messages immediately.
@@ -841,6 +1004,11 @@ pub(crate) async fn set_config_ex(
                     self.sql.uncache_raw_config("configured_addr").await;
                 }
             }
+            Config::WebxdcIntegration => {
+                self.sql.set_raw_config(key.as_ref(), value).await?;
+                // Integrations get additional permissions.
+                self.webxdc_permissions_cache.write().clear();
+            }
             _ => {
                 self.sql.set_raw_config(key.as_ref(), value).await?;
             }
@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 use num_traits::FromPrimitive;
 
 use super::*;
@@ -142,6 +144,39 @@ async fn test_mdns_default_behaviour() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_low_bandwidth() -> Result<()> {
+    use crate::download::{LOW_BANDWIDTH_DOWNLOAD_LIMIT, get_download_limit};
+
+    let t = &TestContext::new_alice().await;
+    assert!(!t.is_low_bandwidth().await?);
+    assert_eq!(t.get_media_quality().await?, MediaQuality::Balanced);
+    assert_eq!(get_download_limit(t).await?, None);
+
+    t.set_config(Config::DownloadLimit, Some("1000000")).await?;
+    t.set_config(Config::LowBandwidth, Some("1")).await?;
+    assert!(t.is_low_bandwidth().await?);
+    assert_eq!(t.get_media_quality().await?, MediaQuality::Worse);
+    assert_eq!(
+        get_download_limit(t).await?,
+        Some(LOW_BANDWIDTH_DOWNLOAD_LIMIT)
+    );
+
+    t.set_config(Config::DownloadLimit, Some("1000")).await?;
+    assert_eq!(get_download_limit(t).await?, Some(1000));
+
+    // In automatic mode, bandwidth is saved while the throughput is low.
+    t.set_config(Config::LowBandwidth, Some("2")).await?;
+    assert!(!t.is_low_bandwidth().await?);
+    t.traffic_metrics
+        .record_throughput(100_000, Duration::from_secs(10));
+    assert!(t.is_low_bandwidth().await?);
+    t.traffic_metrics
+        .record_throughput(10_000_000, Duration::from_secs(10));
+    assert!(!t.is_low_bandwidth().await?);
+    Ok(())
+}
+
 const SAVED_MESSAGES_DEDUPLICATED_FILE: &str = "969142cb84015bc135767bc2370934a.png";
 
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
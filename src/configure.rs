@@ -31,6 +31,7 @@
 pub use crate::login_param::EnteredLoginParam;
 use crate::login_param::{EnteredCertificateChecks, TransportListEntry};
 use crate::message::Message;
+use crate::net::dns::is_onion_host;
 use crate::net::proxy::ProxyConfig;
 use crate::oauth2::get_oauth2_addr;
 use crate::provider::{Protocol, Provider, Socket, UsernamePattern};
@@ -579,6 +580,18 @@ async fn configure(ctx: &Context, param: &EnteredLoginParam) -> Result<Option<&'
     progress!(ctx, 1);
 
     let configured_param = get_configured_param(ctx, param).await?;
+    if !ctx.get_config_bool(Config::TorMode).await?
+        && let Some(server) = configured_param
+            .imap
+            .iter()
+            .chain(configured_param.smtp.iter())
+            .find(|server| is_onion_host(&server.connection.host))
+    {
+        bail!(
+            "{} is an onion service, enable Tor mode to use it",
+            server.connection.host
+        );
+    }
     let proxy_config = ProxyConfig::load(ctx).await?;
     let strict_tls = configured_param.strict_tls(proxy_config.is_some());
 
@@ -822,4 +835,23 @@ async fn test_get_configured_param() -> Result<()> {
         assert_eq!(configured_param.smtp_user, "");
         Ok(())
     }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_onion_server_requires_tor_mode() -> Result<()> {
+        let t = &TestContext::new().await;
+        let entered_param = EnteredLoginParam {
+            addr: "alice@example.org".to_string(),
+            imap: EnteredImapLoginParam {
+                server: "imap.exampleexampleexample.onion".to_string(),
+                port: 993,
+                security: Socket::Ssl,
+                password: "foobar".to_string(),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let err = configure(t, &entered_param).await.unwrap_err();
+        assert!(err.to_string().contains("enable Tor mode"));
+        Ok(())
+    }
 }
@@ -46,6 +46,67 @@ pub enum MediaQuality {
     Worse = 1,
 }
 
+/// Profile of the OpenPGP key generated for a new profile.
+#[derive(
+    Debug, Default, Display, Clone, Copy, PartialEq, Eq, FromPrimitive, ToPrimitive, FromSql, ToSql,
+)]
+#[repr(u8)]
+pub enum KeyProfile {
+    /// Ed25519 signing key with X25519 encryption subkey.
+    #[default] // also change Config.KeyProfile props(default) on changes
+    Classic = 0,
+
+    /// Ed25519 signing key with X25519 encryption subkey
+    /// and additional ML-KEM-768+X25519 post-quantum hybrid encryption subkey.
+    Hybrid = 1,
+}
+
+/// Use of DNS-over-HTTPS for resolving hostnames.
+#[derive(
+    Debug,
+    Default,
+    Display,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Hash,
+    FromPrimitive,
+    ToPrimitive,
+    FromSql,
+    ToSql,
+)]
+#[repr(u8)]
+pub enum DohMode {
+    /// Use the system resolver only.
+    #[default] // also change Config.DohMode props(default) on changes
+    Off = 0,
+
+    /// Use DNS-over-HTTPS endpoints,
+    /// fall back to the system resolver if all of them fail.
+    Preferred = 1,
+
+    /// Use DNS-over-HTTPS endpoints only.
+    Required = 2,
+}
+
+/// Low-bandwidth mode for slow or metered connections.
+#[derive(
+    Debug, Default, Display, Clone, Copy, PartialEq, Eq, FromPrimitive, ToPrimitive, FromSql, ToSql,
+)]
+#[repr(u8)]
+pub enum LowBandwidthMode {
+    /// Never save bandwidth.
+    #[default] // also change Config.LowBandwidth props(default) on changes
+    Off = 0,
+
+    /// Always save bandwidth.
+    On = 1,
+
+    /// Save bandwidth when the measured download throughput is low.
+    Auto = 2,
+}
+
 pub const DC_HANDSHAKE_CONTINUE_NORMAL_PROCESSING: i32 = 0x01;
 pub const DC_HANDSHAKE_STOP_NORMAL_PROCESSING: i32 = 0x02;
 pub const DC_HANDSHAKE_ADD_DELETE_JOB: i32 = 0x04;
@@ -267,4 +328,36 @@ fn test_mediaquality_values() {
         assert_eq!(MediaQuality::Balanced, MediaQuality::from_i32(0).unwrap());
         assert_eq!(MediaQuality::Worse, MediaQuality::from_i32(1).unwrap());
     }
+
+    #[test]
+    fn test_keyprofile_values() {
+        // values may be written to disk and must not change
+        assert_eq!(KeyProfile::Classic, KeyProfile::default());
+        assert_eq!(KeyProfile::Classic, KeyProfile::from_i32(0).unwrap());
+        assert_eq!(KeyProfile::Hybrid, KeyProfile::from_i32(1).unwrap());
+    }
+
+    #[test]
+    fn test_dohmode_values() {
+        // values may be written to disk and must not change
+        assert_eq!(DohMode::Off, DohMode::default());
+        assert_eq!(DohMode::Off, DohMode::from_i32(0).unwrap());
+        assert_eq!(DohMode::Preferred, DohMode::from_i32(1).unwrap());
+        assert_eq!(DohMode::Required, DohMode::from_i32(2).unwrap());
+    }
+
+    #[test]
+    fn test_lowbandwidthmode_values() {
+        // values may be written to disk and must not change
+        assert_eq!(LowBandwidthMode::Off, LowBandwidthMode::default());
+        assert_eq!(
+            LowBandwidthMode::Off,
+            LowBandwidthMode::from_i32(0).unwrap()
+        );
+        assert_eq!(LowBandwidthMode::On, LowBandwidthMode::from_i32(1).unwrap());
+        assert_eq!(
+            LowBandwidthMode::Auto,
+            LowBandwidthMode::from_i32(2).unwrap()
+        );
+    }
 }
@@ -11,7 +11,7 @@
 use base64::Engine as _;
 pub use deltachat_contact_tools::may_be_valid_addr;
 use deltachat_contact_tools::{
-    self as contact_tools, ContactAddress, VcardContact, addr_normalize, sanitize_name,
+    self as contact_tools, ContactAddress, VcardContact, addr_cmp, addr_normalize, sanitize_name,
     sanitize_name_and_addr,
 };
 use deltachat_derive::{FromSql, ToSql};
@@ -35,7 +35,7 @@
 use crate::message::MessageState;
 use crate::mimeparser::AvatarAction;
 use crate::param::{Param, Params};
-use crate::pgp::{addresses_from_public_key, merge_openpgp_certificates};
+use crate::pgp::{addresses_from_public_key, merge_openpgp_certificates, user_id_addresses};
 use crate::sync::{self, Sync::*};
 use crate::tools::{SystemTime, duration_to_str, get_abs_path, normalize_text, time, to_lowercase};
 use crate::{chat, chatlist_events, ensure_and_debug_assert_ne, stock_str};
@@ -376,6 +376,67 @@ pub(crate) async fn import_public_key(
     Ok(())
 }
 
+/// Imports an ASCII-armored OpenPGP certificate for the given address.
+///
+/// This allows to encrypt to contacts using other OpenPGP clients
+/// without waiting for an Autocrypt header from them,
+/// e.g. if the certificate was published on the contact's website.
+///
+/// If the certificate has User IDs containing email addresses,
+/// one of them must match `addr`.
+///
+/// Returns the ID of the key-contact identified by the certificate fingerprint.
+///
+/// May result in a `#DC_EVENT_CONTACTS_CHANGED` event.
+pub async fn import_openpgp_certificate(
+    context: &Context,
+    addr: &str,
+    armored_key: &str,
+) -> Result<ContactId> {
+    let addr = ContactAddress::new(addr).context("Invalid address")?;
+    let public_key = SignedPublicKey::from_asc(armored_key).context("Cannot parse certificate")?;
+    let user_id_addrs = user_id_addresses(&public_key);
+    ensure!(
+        user_id_addrs.is_empty() || user_id_addrs.iter().any(|a| addr_cmp(a, &addr)),
+        "Certificate User IDs do not contain {addr}"
+    );
+    let id =
+        import_certificate_contact(context, &addr, &public_key, Origin::ManuallyCreated).await?;
+
+    let mut contact = Contact::get_by_id(context, id).await?;
+    if !contact.param.exists(Param::KeyImportedManually) {
+        contact.param.set_int(Param::KeyImportedManually, 1);
+        contact.update_param(context).await?;
+        context.emit_event(EventType::ContactsChanged(Some(id)));
+    }
+    Ok(id)
+}
+
+/// Imports the certificate into the public key store
+/// and returns the key-contact identified by its fingerprint with the given address.
+///
+/// The caller is responsible for checking that the certificate belongs to `addr`.
+pub(crate) async fn import_certificate_contact(
+    context: &Context,
+    addr: &ContactAddress,
+    public_key: &SignedPublicKey,
+    origin: Origin,
+) -> Result<ContactId> {
+    import_public_key(context, public_key)
+        .await
+        .context("Failed to import certificate")?;
+    let fingerprint = public_key.dc_fingerprint().hex();
+
+    let (id, modified) = Contact::add_or_lookup_ex(context, "", addr, &fingerprint, origin)
+        .await
+        .context("Contact::add_or_lookup() failed")?;
+    ensure!(id != ContactId::SELF, "Cannot import own certificate");
+    if modified != Modifier::None {
+        context.emit_event(EventType::ContactsChanged(Some(id)));
+    }
+    Ok(id)
+}
+
 /// Imports contacts from the given vCard.
 ///
 /// Returns the ids of successfully processed contacts in the order they appear in `vcard`,
@@ -1449,6 +1510,10 @@ pub async fn get_encrinfo(context: &Context, contact_id: ContactId) -> Result<St
             }
         }
 
+        if contact.param.exists(Param::KeyImportedManually) {
+            ret += "\n\nKey was imported manually.";
+        }
+
         Ok(ret)
     }
 
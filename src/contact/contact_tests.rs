@@ -1288,6 +1288,55 @@ async fn test_make_n_import_vcard() -> Result<()> {
     Ok(())
 }
 
+/// Tests importing an ASCII-armored OpenPGP certificate
+/// for an email address matching its user IDs.
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_import_openpgp_certificate() -> Result<()> {
+    let mut tcm = TestContextManager::new();
+    let alice = &tcm.alice().await;
+    let bob = &tcm.bob().await;
+    let bob_certificate = test_utils::bob_keypair().to_public_key().to_asc(None);
+
+    // User IDs of the certificate must match the address.
+    assert!(
+        import_openpgp_certificate(alice, "notbob@example.net", &bob_certificate)
+            .await
+            .is_err()
+    );
+    assert!(
+        import_openpgp_certificate(alice, "bob@example.net", "not a certificate")
+            .await
+            .is_err()
+    );
+
+    let contact_id = import_openpgp_certificate(alice, "bob@example.net", &bob_certificate).await?;
+    let contact = Contact::get_by_id(alice, contact_id).await?;
+    assert!(contact.is_key_contact());
+    assert_eq!(contact.get_addr(), "bob@example.net");
+    assert_eq!(contact.origin, Origin::ManuallyCreated);
+    assert!(contact.e2ee_avail(alice).await?);
+    assert_eq!(contact_id, alice.add_or_lookup_contact_id(bob).await);
+
+    let encrinfo = Contact::get_encrinfo(alice, contact_id).await?;
+    assert!(encrinfo.starts_with("Messages are end-to-end encrypted."));
+    assert!(encrinfo.ends_with("\n\nKey was imported manually."));
+
+    // Importing again is a no-op.
+    assert_eq!(
+        import_openpgp_certificate(alice, "bob@example.net", &bob_certificate).await?,
+        contact_id
+    );
+
+    // Messages sent to the imported contact can be decrypted.
+    let chat_id = ChatId::create_for_contact(alice, contact_id).await?;
+    let sent = alice.send_text(chat_id, "Hello!").await;
+    let msg = bob.recv_msg(&sent).await;
+    assert_eq!(msg.get_text(), "Hello!");
+    assert!(msg.get_showpadlock());
+
+    Ok(())
+}
+
 /// Tests importing a vCard with the same email address,
 /// but a new key.
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
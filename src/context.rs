@@ -20,11 +20,12 @@
 use crate::contact::{Contact, ContactId};
 use crate::debug_logging::DebugLogging;
 use crate::events::{Event, EventEmitter, EventType, Events};
-use crate::imap::{Imap, ServerMetadata};
+use crate::imap::{FolderMeaning, Imap, ServerMetadata};
 use crate::log::warn;
 use crate::logged_debug_assert;
 use crate::message::{self, MessageState, MsgId};
 use crate::net::tls::{SpkiHashStore, TlsSessionStore};
+use crate::net::traffic::TrafficMetrics;
 use crate::peer_channels::Iroh;
 use crate::push::PushSubscriber;
 use crate::quota::QuotaInfo;
@@ -34,6 +35,7 @@
 use crate::timesmearing::SmearedTimestamp;
 use crate::tools::{self, duration_to_str, time, time_elapsed};
 use crate::transport::ConfiguredLoginParam;
+use crate::webxdc::WebxdcPermission;
 use crate::{chatlist_events, stats};
 
 pub use crate::scheduler::connectivity::Connectivity;
@@ -314,6 +316,9 @@ pub struct InnerContext {
     /// even after they expire.
     pub(crate) spki_hash_store: SpkiHashStore,
 
+    /// Traffic counters not yet stored in the database.
+    pub(crate) traffic_metrics: TrafficMetrics,
+
     /// Iroh for realtime peer channels.
     pub(crate) iroh: Arc<RwLock<Option<Iroh>>>,
 
@@ -333,6 +338,14 @@ pub struct InnerContext {
     /// see [`Context::get_connectivity()`].
     pub(crate) connectivities: parking_lot::Mutex<Vec<ConnectivityStore>>,
 
+    /// Permissions granted to webxdc instances together with the app file they were computed for,
+    /// so that the manifest is not parsed on every realtime message or status update.
+    pub(crate) webxdc_permissions_cache:
+        parking_lot::RwLock<HashMap<MsgId, (String, Vec<WebxdcPermission>)>>,
+
+    /// Web Key Directory URL used instead of the ones derived from the address.
+    pub(crate) wkd_url_hook: parking_lot::Mutex<Option<String>>,
+
     #[expect(clippy::type_complexity)]
     /// Transforms the root of the cryptographic payload before encryption.
     pub(crate) pre_encrypt_mime_hook: parking_lot::Mutex<
@@ -518,10 +531,13 @@ pub(crate) fn with_blobdir(
             push_subscribed: AtomicBool::new(false),
             tls_session_store: TlsSessionStore::new(),
             spki_hash_store: SpkiHashStore::new(),
+            traffic_metrics: TrafficMetrics::default(),
             iroh: Arc::new(RwLock::new(None)),
             self_fingerprint: OnceLock::new(),
             self_public_key: Mutex::new(None),
             connectivities: parking_lot::Mutex::new(Vec::new()),
+            webxdc_permissions_cache: parking_lot::RwLock::new(HashMap::new()),
+            wkd_url_hook: None.into(),
             pre_encrypt_mime_hook: None.into(),
         };
 
@@ -632,7 +648,7 @@ pub async fn background_fetch(&self) -> Result<()> {
             // Fetch IMAP folders.
             let folder = connection.folder.clone();
             connection
-                .fetch_move_delete(self, &mut session, &folder)
+                .fetch_move_delete(self, &mut session, &folder, FolderMeaning::Inbox)
                 .await?;
 
             // Update quota (to send warning if full) - but only check it once in a while.
@@ -868,6 +884,7 @@ pub async fn get_info(&self) -> Result<BTreeMap<&'static str, String>> {
             .await?
             .unwrap_or_else(|| "unknown".to_string());
         let mdns_enabled = self.get_config_int(Config::MdnsEnabled).await?;
+        let request_dsn = self.get_config_bool(Config::RequestDsn).await?;
         let bcc_self = self.get_config_int(Config::BccSelf).await?;
         let sync_msgs = self.get_config_int(Config::SyncMsgs).await?;
         let disable_idle = self.get_config_bool(Config::DisableIdle).await?;
@@ -905,6 +922,27 @@ pub async fn get_info(&self) -> Result<BTreeMap<&'static str, String>> {
                 .unwrap_or_else(|| "<unset>".to_string()),
         );
         res.insert("proxy_enabled", proxy_enabled.to_string());
+        res.insert(
+            "tor_mode",
+            self.get_config_bool(Config::TorMode).await?.to_string(),
+        );
+        res.insert(
+            "doh_mode",
+            self.get_config_int(Config::DohMode).await?.to_string(),
+        );
+        res.insert(
+            "doh_urls",
+            self.get_config(Config::DohUrls)
+                .await?
+                .unwrap_or_default()
+                .replace('\n', " "),
+        );
+        res.insert(
+            "strict_certificate_pinning",
+            self.get_config_bool(Config::StrictCertificatePinning)
+                .await?
+                .to_string(),
+        );
         res.insert("used_transport_settings", all_transports);
 
         if let Some(server_id) = &*self.server_id.read().await {
@@ -943,6 +981,12 @@ pub async fn get_info(&self) -> Result<BTreeMap<&'static str, String>> {
             "who_can_call_me",
             self.get_config_int(Config::WhoCanCallMe).await?.to_string(),
         );
+        res.insert(
+            "call_schedule",
+            self.get_config(Config::CallSchedule)
+                .await?
+                .unwrap_or_else(|| "<unset>".to_string()),
+        );
         res.insert(
             "download_limit",
             self.get_config_int(Config::DownloadLimit)
@@ -950,15 +994,38 @@ pub async fn get_info(&self) -> Result<BTreeMap<&'static str, String>> {
                 .to_string(),
         );
         res.insert("mdns_enabled", mdns_enabled.to_string());
+        res.insert("request_dsn", request_dsn.to_string());
         res.insert("bcc_self", bcc_self.to_string());
         res.insert("sync_msgs", sync_msgs.to_string());
         res.insert("disable_idle", disable_idle.to_string());
         res.insert("private_key_count", prv_key_cnt.to_string());
         res.insert("public_key_count", pub_key_cnt.to_string());
+        res.insert(
+            "key_profile",
+            self.get_config_int(Config::KeyProfile).await?.to_string(),
+        );
+        res.insert(
+            "wkd_lookup",
+            self.get_config_bool(Config::WkdLookup).await?.to_string(),
+        );
+        res.insert(
+            "sign_unencrypted",
+            self.get_config_bool(Config::SignUnencrypted)
+                .await?
+                .to_string(),
+        );
         res.insert(
             "media_quality",
             self.get_config_int(Config::MediaQuality).await?.to_string(),
         );
+        res.insert(
+            "low_bandwidth",
+            self.get_config_int(Config::LowBandwidth).await?.to_string(),
+        );
+        res.insert(
+            "low_bandwidth_active",
+            self.is_low_bandwidth().await?.to_string(),
+        );
         res.insert(
             "delete_device_after",
             self.get_config_int(Config::DeleteDeviceAfter)
@@ -977,6 +1044,12 @@ pub async fn get_info(&self) -> Result<BTreeMap<&'static str, String>> {
                 .await?
                 .to_string(),
         );
+        res.insert(
+            "call_history_cleared_timestamp",
+            self.get_config_i64(Config::CallHistoryClearedTimestamp)
+                .await?
+                .to_string(),
+        );
         res.insert(
             "debug_logging",
             self.get_config_int(Config::DebugLogging).await?.to_string(),
@@ -995,6 +1068,30 @@ pub async fn get_info(&self) -> Result<BTreeMap<&'static str, String>> {
                 .await?
                 .to_string(),
         );
+        res.insert(
+            "typing_indicators_enabled",
+            self.get_config_bool(Config::TypingIndicatorsEnabled)
+                .await?
+                .to_string(),
+        );
+        res.insert(
+            "webxdc_realtime_reliable",
+            self.get_config_bool(Config::WebxdcRealtimeReliable)
+                .await?
+                .to_string(),
+        );
+        res.insert(
+            "webxdc_catalog_bot",
+            self.get_config(Config::WebxdcCatalogBot)
+                .await?
+                .unwrap_or_else(|| "<unset>".to_string()),
+        );
+        res.insert(
+            "webxdc_catalog_url",
+            self.get_config(Config::WebxdcCatalogUrl)
+                .await?
+                .unwrap_or_else(|| "<unset>".to_string()),
+        );
         res.insert(
             "donation_request_next_check",
             self.get_config_i64(Config::DonationRequestNextCheck)
@@ -300,6 +300,7 @@ async fn test_get_info_completeness() {
         "stats_last_update",
         "stats_last_old_contact_id",
         "simulate_receive_imf_error", // only used in tests
+        "iroh_relay_disabled",        // only used in tests
     ];
     let t = TestContext::new().await;
     let info = t.get_info().await.unwrap();
@@ -64,6 +64,7 @@ pub async fn debug_logging_loop(context: &Context, events: Receiver<DebugEventLo
                     document: None,
                     uid: None,
                     notify: None,
+                    snapshot: None,
                 },
                 time,
             )
@@ -335,6 +335,20 @@ fn get_autocrypt_mime<'a, 'b>(mail: &'a ParsedMail<'b>) -> Option<&'a ParsedMail
     }
 }
 
+/// Returns the signature of Multipart/Signed message part, as defined in RFC 1847.
+///
+/// Returns None if the message is not Multipart/Signed or doesn't contain necessary parts.
+pub(crate) fn get_detached_signature(mail: &ParsedMail<'_>) -> Option<Vec<u8>> {
+    if mail.ctype.mimetype != "multipart/signed" {
+        return None;
+    }
+    if let [_first_part, second_part] = &mail.subparts[..] {
+        second_part.get_body_raw().ok()
+    } else {
+        None
+    }
+}
+
 /// Validates signatures of Multipart/Signed message part, as defined in RFC 1847.
 ///
 /// Returns the signed part and the set of key
@@ -0,0 +1,479 @@
+//! # Network diagnostics.
+//!
+//! Runs the same steps as a normal connection for every configured transport
+//! and every IMAP and SMTP server candidate
+//! and reports the outcome and duration of each step.
+//! The report is meant to be attached to support requests,
+//! so it contains addresses and hostnames, but no usernames or passwords.
+
+use std::future::Future;
+
+use anyhow::{Context as _, Result, format_err};
+use futures::future::join_all;
+use serde::Serialize;
+use tokio::time::timeout;
+
+use crate::config::Config;
+use crate::constants::DC_VERSION_STR;
+use crate::context::Context;
+use crate::imap::OAuth2;
+use crate::imap::capabilities::Capabilities;
+use crate::imap::client::{Client, determine_capabilities};
+use crate::net::connect_tcp;
+use crate::net::dns::{lookup_cache, lookup_host_with_cache};
+use crate::net::proxy::ProxyConfig;
+use crate::oauth2::get_oauth2_access_token;
+use crate::smtp::connect as smtp_connect;
+use crate::tools::{self, time, time_elapsed};
+use crate::transport::{ConfiguredLoginParam, ConfiguredServerLoginParam};
+
+/// Outcome of a single diagnostics step.
+#[derive(Debug, Clone, Serialize)]
+pub struct DiagnosticsStep {
+    /// True if the step succeeded.
+    pub ok: bool,
+
+    /// Time the step took in milliseconds.
+    pub duration_ms: u64,
+
+    /// Error message if the step failed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Runs a single step, measuring its duration.
+///
+/// Returns the step outcome and the result if it succeeded.
+async fn run_step<T>(fut: impl Future<Output = Result<T>>) -> (DiagnosticsStep, Option<T>) {
+    let start = tools::Time::now();
+    let res = timeout(crate::net::TIMEOUT, fut)
+        .await
+        .unwrap_or_else(|_| Err(format_err!("Timeout")));
+    let duration_ms = time_elapsed(&start)
+        .as_millis()
+        .try_into()
+        .unwrap_or(u64::MAX);
+    match res {
+        Ok(res) => (
+            DiagnosticsStep {
+                ok: true,
+                duration_ms,
+                error: None,
+            },
+            Some(res),
+        ),
+        Err(err) => (
+            DiagnosticsStep {
+                ok: false,
+                duration_ms,
+                error: Some(format!("{err:#}")),
+            },
+            None,
+        ),
+    }
+}
+
+/// Result of resolving the hostname.
+#[derive(Debug, Clone, Serialize)]
+pub struct DnsReport {
+    /// Outcome of the resolution.
+    #[serde(flatten)]
+    pub step: DiagnosticsStep,
+
+    /// Resolved IP addresses.
+    pub addrs: Vec<String>,
+}
+
+/// IMAP capabilities relevant for troubleshooting.
+#[derive(Debug, Clone, Serialize)]
+pub struct ImapCapabilitiesReport {
+    /// IDLE is supported.
+    pub idle: bool,
+
+    /// MOVE is supported.
+    pub r#move: bool,
+
+    /// QUOTA is supported.
+    pub quota: bool,
+
+    /// CONDSTORE is supported.
+    pub condstore: bool,
+
+    /// METADATA is supported.
+    pub metadata: bool,
+
+    /// COMPRESS=DEFLATE is supported.
+    pub compress: bool,
+
+    /// NOTIFY is supported.
+    pub notify: bool,
+
+    /// XDELTAPUSH is supported.
+    pub push: bool,
+
+    /// The server is a chatmail server.
+    pub chatmail: bool,
+}
+
+impl From<&Capabilities> for ImapCapabilitiesReport {
+    fn from(capabilities: &Capabilities) -> Self {
+        Self {
+            idle: capabilities.can_idle,
+            r#move: capabilities.can_move,
+            quota: capabilities.can_check_quota,
+            condstore: capabilities.can_condstore,
+            metadata: capabilities.can_metadata,
+            compress: capabilities.can_compress,
+            notify: capabilities.can_notify,
+            push: capabilities.can_push,
+            chatmail: capabilities.is_chatmail,
+        }
+    }
+}
+
+/// Diagnostics of a single IMAP or SMTP server candidate.
+#[derive(Debug, Clone, Serialize)]
+pub struct ServerReport {
+    /// Server hostname.
+    pub host: String,
+
+    /// Server port.
+    pub port: u16,
+
+    /// `tls`, `starttls` or `plain`.
+    pub security: String,
+
+    /// Resolution with the configured resolvers,
+    /// skipped in Tor mode to avoid leaking the hostname.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dns: Option<DnsReport>,
+
+    /// IP addresses from the persistent DNS cache.
+    pub dns_cache: Vec<String>,
+
+    /// TCP connection, TLS or STARTTLS handshake and reading the greeting.
+    pub connect: DiagnosticsStep,
+
+    /// Authentication, skipped if connection failed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub login: Option<DiagnosticsStep>,
+
+    /// IMAP capabilities, only for IMAP servers after successful login.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub capabilities: Option<ImapCapabilitiesReport>,
+}
+
+/// Diagnostics of a single transport.
+#[derive(Debug, Clone, Serialize)]
+pub struct TransportReport {
+    /// Email address of the transport.
+    pub addr: String,
+
+    /// IMAP server candidates.
+    pub imap: Vec<ServerReport>,
+
+    /// SMTP server candidates.
+    pub smtp: Vec<ServerReport>,
+}
+
+/// Reachability of a configured proxy.
+#[derive(Debug, Clone, Serialize)]
+pub struct ProxyReport {
+    /// Proxy host and port, without credentials.
+    pub proxy: String,
+
+    /// TCP connection to the proxy.
+    pub connect: DiagnosticsStep,
+}
+
+/// Network diagnostics report returned by [`Context::run_network_diagnostics`].
+#[derive(Debug, Clone, Serialize)]
+pub struct NetworkDiagnostics {
+    /// Version of the core library.
+    pub core_version: &'static str,
+
+    /// Time of the diagnostics as a unix timestamp.
+    pub timestamp: i64,
+
+    /// True if Tor mode is enabled.
+    pub tor_mode: bool,
+
+    /// Configured DNS-over-HTTPS mode, see [`Config::DohMode`].
+    pub doh_mode: i32,
+
+    /// Configured proxies.
+    pub proxies: Vec<ProxyReport>,
+
+    /// Configured transports.
+    pub transports: Vec<TransportReport>,
+}
+
+/// Starts a report for the server, resolving its hostname.
+async fn new_server_report(
+    context: &Context,
+    server: &ConfiguredServerLoginParam,
+    alpn: &str,
+    tor_mode: bool,
+) -> Result<ServerReport> {
+    let host = &server.connection.host;
+    let port = server.connection.port;
+    let dns = if tor_mode {
+        None
+    } else {
+        let load_cache = false;
+        let (step, addrs) = run_step(async {
+            let addrs = lookup_host_with_cache(context, host, port, alpn, load_cache).await?;
+            Ok(addrs.iter().map(|addr| addr.ip().to_string()).collect())
+        })
+        .await;
+        Some(DnsReport {
+            step,
+            addrs: addrs.unwrap_or_default(),
+        })
+    };
+    let dns_cache = lookup_cache(context, host, port, alpn, time())
+        .await?
+        .into_iter()
+        .map(|addr| addr.ip().to_string())
+        .collect();
+    Ok(ServerReport {
+        host: host.clone(),
+        port,
+        security: server.connection.security.to_string(),
+        dns,
+        dns_cache,
+        connect: DiagnosticsStep {
+            ok: false,
+            duration_ms: 0,
+            error: None,
+        },
+        login: None,
+        capabilities: None,
+    })
+}
+
+async fn diagnose_imap(
+    context: &Context,
+    param: &ConfiguredLoginParam,
+    server: &ConfiguredServerLoginParam,
+    proxy_config: &Option<ProxyConfig>,
+    tor_mode: bool,
+) -> Result<ServerReport> {
+    let mut report = new_server_report(context, server, "imap", tor_mode).await?;
+    let strict_tls = param.strict_tls(proxy_config.is_some());
+    let (step, client) = run_step(Client::connect(
+        context,
+        proxy_config.clone(),
+        strict_tls,
+        &server.connection,
+    ))
+    .await;
+    report.connect = step;
+    let Some(client) = client else {
+        return Ok(report);
+    };
+
+    let (step, capabilities) = run_step(async {
+        let mut session = if param.oauth2 {
+            let token = get_oauth2_access_token(context, &param.addr, &param.imap_password, true)
+                .await?
+                .context("IMAP could not get OAUTH token")?;
+            let auth = OAuth2 {
+                user: server.user.clone(),
+                access_token: token,
+            };
+            client.authenticate("XOAUTH2", auth).await?
+        } else {
+            client.login(&server.user, &param.imap_password).await?
+        };
+        let capabilities = determine_capabilities(&mut session).await?;
+        session.logout().await.ok();
+        Ok(capabilities)
+    })
+    .await;
+    report.login = Some(step);
+    report.capabilities = capabilities.as_ref().map(Into::into);
+    Ok(report)
+}
+
+async fn diagnose_smtp(
+    context: &Context,
+    param: &ConfiguredLoginParam,
+    server: &ConfiguredServerLoginParam,
+    proxy_config: &Option<ProxyConfig>,
+    tor_mode: bool,
+) -> Result<ServerReport> {
+    let mut report = new_server_report(context, server, "smtp", tor_mode).await?;
+    let strict_tls = param.strict_tls(proxy_config.is_some());
+    let (step, transport) = run_step(smtp_connect::connect(
+        context,
+        proxy_config,
+        strict_tls,
+        server.connection.clone(),
+    ))
+    .await;
+    report.connect = step;
+    let Some((mut transport, _extensions)) = transport else {
+        return Ok(report);
+    };
+
+    let (step, _) = run_step(smtp_connect::authenticate(
+        context,
+        &mut transport,
+        param.oauth2,
+        &param.addr,
+        &server.user,
+        &param.smtp_password,
+    ))
+    .await;
+    report.login = Some(step);
+    transport.quit().await.ok();
+    Ok(report)
+}
+
+async fn diagnose_transport(
+    context: &Context,
+    param: &ConfiguredLoginParam,
+    proxy_config: &Option<ProxyConfig>,
+    tor_mode: bool,
+) -> Result<TransportReport> {
+    let imap_futures = param
+        .imap
+        .iter()
+        .map(|server| diagnose_imap(context, param, server, proxy_config, tor_mode));
+    let smtp_futures = param
+        .smtp
+        .iter()
+        .map(|server| diagnose_smtp(context, param, server, proxy_config, tor_mode));
+    let (imap, smtp) = tokio::join!(join_all(imap_futures), join_all(smtp_futures));
+    Ok(TransportReport {
+        addr: param.addr.clone(),
+        imap: imap.into_iter().collect::<Result<_>>()?,
+        smtp: smtp.into_iter().collect::<Result<_>>()?,
+    })
+}
+
+async fn diagnose_proxy(context: &Context, proxy_config: &ProxyConfig) -> ProxyReport {
+    let (host, port) = proxy_config.host_port();
+    let load_dns_cache = false;
+    let (connect, _) = run_step(connect_tcp(context, &host, port, load_dns_cache)).await;
+    ProxyReport {
+        proxy: format!("{host}:{port}"),
+        connect,
+    }
+}
+
+impl Context {
+    /// Tests connectivity of all configured transports
+    /// and returns a report with the outcome and duration of each step.
+    ///
+    /// For each IMAP and SMTP server candidate the hostname is resolved,
+    /// connection is established, login is attempted
+    /// and IMAP capabilities such as IDLE support are determined.
+    /// Configured proxies are tested for reachability.
+    ///
+    /// The report contains addresses and hostnames,
+    /// but no usernames or passwords.
+    pub async fn run_network_diagnostics(&self) -> Result<NetworkDiagnostics> {
+        let tor_mode = self.get_config_bool(Config::TorMode).await?;
+        let proxies = ProxyConfig::load_all(self).await?;
+        let proxy_config = proxies.first().cloned();
+
+        let (proxies, transports) = tokio::join!(
+            join_all(
+                proxies
+                    .iter()
+                    .map(|proxy_config| diagnose_proxy(self, proxy_config))
+            ),
+            async {
+                let mut transports = Vec::new();
+                for (_transport_id, param) in ConfiguredLoginParam::load_all(self).await? {
+                    transports
+                        .push(diagnose_transport(self, &param, &proxy_config, tor_mode).await?);
+                }
+                anyhow::Ok(transports)
+            }
+        );
+
+        Ok(NetworkDiagnostics {
+            core_version: DC_VERSION_STR,
+            timestamp: time(),
+            tor_mode,
+            doh_mode: self.get_config_int(Config::DohMode).await?,
+            proxies,
+            transports: transports?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::login_param::EnteredLoginParam;
+    use crate::test_utils::TestContext;
+    use crate::transport::{ConfiguredCertificateChecks, ConnectionCandidate, ConnectionSecurity};
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_network_diagnostics() -> Result<()> {
+        let t = &TestContext::new().await;
+        let param = ConfiguredLoginParam {
+            addr: "alice@example.org".to_string(),
+            imap: vec![ConfiguredServerLoginParam {
+                connection: ConnectionCandidate {
+                    host: "imap.example.org".to_string(),
+                    port: 993,
+                    security: ConnectionSecurity::Tls,
+                },
+                user: "alice".to_string(),
+            }],
+            imap_folder: None,
+            imap_user: "".to_string(),
+            imap_password: "imap-secret".to_string(),
+            smtp: vec![ConfiguredServerLoginParam {
+                connection: ConnectionCandidate {
+                    host: "smtp.example.org".to_string(),
+                    port: 587,
+                    security: ConnectionSecurity::Starttls,
+                },
+                user: "alice".to_string(),
+            }],
+            smtp_user: "".to_string(),
+            smtp_password: "smtp-secret".to_string(),
+            provider: None,
+            certificate_checks: ConfiguredCertificateChecks::Strict,
+            oauth2: false,
+        };
+        param
+            .save_to_transports_table(t, &EnteredLoginParam::default(), time())
+            .await?;
+
+        // Use Tor mode with unreachable proxy
+        // so the test does not make DNS requests.
+        t.set_config(Config::ProxyUrl, Some("socks5://127.0.0.1:1"))
+            .await?;
+        t.set_config_bool(Config::TorMode, true).await?;
+
+        let report = t.run_network_diagnostics().await?;
+        assert!(!serde_json::to_string(&report)?.contains("secret"));
+
+        assert_eq!(report.tor_mode, true);
+        assert_eq!(report.proxies.len(), 1);
+        assert_eq!(report.proxies[0].proxy, "127.0.0.1:1");
+        assert_eq!(report.proxies[0].connect.ok, false);
+
+        assert_eq!(report.transports.len(), 1);
+        let transport = &report.transports[0];
+        assert_eq!(transport.addr, "alice@example.org");
+        let imap = &transport.imap[0];
+        assert_eq!(imap.host, "imap.example.org");
+        assert_eq!(imap.security, "tls");
+        assert!(imap.dns.is_none());
+        assert_eq!(imap.connect.ok, false);
+        assert!(imap.connect.error.is_some());
+        assert!(imap.login.is_none());
+        let smtp = &transport.smtp[0];
+        assert_eq!(smtp.port, 587);
+        assert_eq!(smtp.connect.ok, false);
+        Ok(())
+    }
+}
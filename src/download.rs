@@ -6,6 +6,7 @@
 use deltachat_derive::{FromSql, ToSql};
 use serde::{Deserialize, Serialize};
 
+use crate::config::Config;
 use crate::context::Context;
 use crate::imap::session::Session;
 use crate::log::warn;
@@ -25,6 +26,12 @@
 /// Max size for pre messages. A warning is emitted when this is exceeded.
 pub(crate) const PRE_MSG_SIZE_WARNING_THRESHOLD: usize = 150_000;
 
+/// Max size of messages downloaded automatically while bandwidth is saved.
+///
+/// Larger messages are downloaded after all the small messages,
+/// larger Post-Messages are only downloaded on request.
+pub(crate) const LOW_BANDWIDTH_DOWNLOAD_LIMIT: u32 = 32_768;
+
 /// Download state of the message.
 #[derive(
     Debug,
@@ -271,6 +278,23 @@ pub(crate) async fn msg_is_downloaded_for(context: &Context, rfc724_mid: &str) -
         .is_some())
 }
 
+/// Returns the max size of messages downloaded automatically,
+/// `None` if there is no limit.
+///
+/// This is the `DownloadLimit` config, lowered to [`LOW_BANDWIDTH_DOWNLOAD_LIMIT`]
+/// while bandwidth is saved.
+pub(crate) async fn get_download_limit(context: &Context) -> Result<Option<u32>> {
+    let download_limit: Option<u32> = context
+        .get_config_parsed(Config::DownloadLimit)
+        .await?
+        .filter(|&l| 0 < l);
+    if context.is_low_bandwidth().await? {
+        let download_limit = download_limit.unwrap_or(u32::MAX);
+        return Ok(Some(download_limit.min(LOW_BANDWIDTH_DOWNLOAD_LIMIT)));
+    }
+    Ok(download_limit)
+}
+
 pub(crate) async fn download_msgs(context: &Context, session: &mut Session) -> Result<()> {
     let rfc724_mids = context
         .sql
@@ -323,6 +347,13 @@ pub(crate) async fn download_known_post_messages_without_pre_message(
     context: &Context,
     session: &mut Session,
 ) -> Result<()> {
+    if context.is_low_bandwidth().await? {
+        // Pre-Messages larger than the lowered download limit
+        // may still wait in the download queue,
+        // do not download the Post-Messages instead.
+        return Ok(());
+    }
+
     let rfc724_mids = context
         .sql
         .query_map_vec("SELECT rfc724_mid FROM available_post_msgs", (), |row| {
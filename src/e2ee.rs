@@ -67,6 +67,25 @@ pub async fn encrypt_raw(
         Ok(ctext)
     }
 
+    /// Renders the passed in `mail` and creates a detached signature over it.
+    ///
+    /// Returns the rendered part and the armored signature
+    /// for use in a `multipart/signed` message.
+    pub async fn sign(
+        &self,
+        context: &Context,
+        mail_to_sign: MimePart<'static>,
+    ) -> Result<(String, String)> {
+        let sign_key = load_self_secret_key(context).await?;
+
+        let mut raw_message = Vec::new();
+        let cursor = Cursor::new(&mut raw_message);
+        mail_to_sign.write_part(cursor).ok();
+        let signature = pgp::pk_calc_signature(&raw_message, &sign_key)?;
+
+        Ok((String::from_utf8(raw_message)?, signature))
+    }
+
     /// Symmetrically encrypt the message. This is used for broadcast channels.
     /// `shared secret` is the secret that will be used for symmetric encryption.
     pub async fn encrypt_symmetrically(
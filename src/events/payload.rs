@@ -163,6 +163,9 @@ pub enum EventType {
 
     /// A single message could not be sent. State changed from DC_STATE_OUT_PENDING or DC_STATE_OUT_DELIVERED to
     /// DC_STATE_OUT_FAILED, see dc_msg_get_state().
+    ///
+    /// Also emitted without changing the state if the message
+    /// could not be sent to some of the recipients, see dc_msg_get_error().
     MsgFailed {
         /// ID of the chat which the message belongs to.
         chat_id: ChatId,
@@ -340,12 +343,68 @@ pub enum EventType {
         msg_id: MsgId,
     },
 
+    /// Data received over the realtime channel of a chat.
+    ChatRealtimeData {
+        /// Chat ID.
+        chat_id: ChatId,
+
+        /// Realtime data.
+        data: Vec<u8>,
+    },
+
+    /// Advertisement for the realtime channel of a chat received.
+    ChatRealtimeAdvertisementReceived {
+        /// Chat ID.
+        chat_id: ChatId,
+    },
+
+    /// A contact started or stopped typing in a chat.
+    ///
+    /// The contact should be shown as not typing anymore
+    /// if no new event is received within [`crate::peer_channels::TYPING_TIMEOUT`] seconds.
+    ContactTyping {
+        /// Chat ID.
+        chat_id: ChatId,
+
+        /// ID of the typing contact.
+        contact_id: ContactId,
+
+        /// True if the contact is typing, false if the contact stopped typing.
+        typing: bool,
+    },
+
     /// Inform that a message containing a webxdc instance has been deleted.
     WebxdcInstanceDeleted {
         /// ID of the deleted message.
         msg_id: MsgId,
     },
 
+    /// The app of a webxdc instance was replaced, e.g. by a new version,
+    /// or its state was reset.
+    ///
+    /// UI should reload the webxdc if it is open.
+    WebxdcInstanceUpdated {
+        /// ID of the webxdc instance.
+        msg_id: MsgId,
+    },
+
+    /// Received status updates of a webxdc instance were dropped
+    /// because they exceed the storage quota of the instance.
+    ///
+    /// The instance sender can compact the updates by sending a snapshot update.
+    WebxdcStatusUpdatesDropped {
+        /// ID of the webxdc instance.
+        msg_id: MsgId,
+    },
+
+    /// A newer version of a webxdc app used in chats is available in the webxdc catalog.
+    ///
+    /// The app can be sent again using `install_webxdc_from_catalog()`.
+    WebxdcAppUpdateAvailable {
+        /// ID of the app in the webxdc catalog.
+        app_id: String,
+    },
+
     /// Tells that the Background fetch was completed (or timed out).
     /// This event acts as a marker, when you reach this event you can be sure
     /// that all events emitted during the background fetch were processed.
@@ -411,6 +470,10 @@ pub enum EventType {
     },
 
     /// Call ended.
+    ///
+    /// For group calls, this is emitted when the last participant left
+    /// and when the incoming call was declined on another device
+    /// while other participants stay in the call.
     CallEnded {
         /// ID of the message referring to the call.
         msg_id: MsgId,
@@ -418,6 +481,28 @@ pub enum EventType {
         chat_id: ChatId,
     },
 
+    /// A member joined the group call.
+    CallParticipantJoined {
+        /// ID of the message referring to the call.
+        msg_id: MsgId,
+        /// ID of the chat which the message belongs to.
+        chat_id: ChatId,
+        /// ID of the contact who joined the call.
+        contact_id: ContactId,
+        /// User-defined info as passed to accept_incoming_call()
+        join_call_info: String,
+    },
+
+    /// A participant left the group call.
+    CallParticipantLeft {
+        /// ID of the message referring to the call.
+        msg_id: MsgId,
+        /// ID of the chat which the message belongs to.
+        chat_id: ChatId,
+        /// ID of the contact who left the call.
+        contact_id: ContactId,
+    },
+
     /// One or more transports has changed or another transport is primary now.
     ///
     /// UI should update the list.
@@ -427,6 +512,19 @@ pub enum EventType {
     /// but not when the UI modifies the transport list by itself.
     TransportsModified,
 
+    /// Connections are made through another proxy now,
+    /// because the previously used proxy failed
+    /// or proxy configuration changed.
+    ///
+    /// UI may update the proxy settings and the connectivity view.
+    ActiveProxyChanged {
+        /// Host and port of the proxy in use.
+        ///
+        /// The URL is not reported as it may contain credentials
+        /// which should not end up in the logs.
+        proxy: String,
+    },
+
     /// Event for using in tests, e.g. as a fence between normally generated events.
     #[cfg(test)]
     Test,
@@ -102,6 +102,9 @@ pub enum HeaderDef {
     /// This message obsoletes the text of the message defined here by rfc724_mid.
     ChatEdit,
 
+    /// This message replaces the app of the webxdc instance defined here by rfc724_mid.
+    ChatWebxdcReplace,
+
     /// The secret shared amongst all recipients of this broadcast channel,
     /// used to encrypt and decrypt messages.
     /// This secret is sent to a new member in the member-addition message.
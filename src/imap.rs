@@ -27,6 +27,7 @@
 use crate::constants::{Blocked, DC_VERSION_STR};
 use crate::contact::ContactId;
 use crate::context::Context;
+use crate::download::get_download_limit;
 use crate::ensure_and_debug_assert;
 use crate::events::EventType;
 use crate::headerdef::{HeaderDef, HeaderDefMap};
@@ -52,8 +53,9 @@
 };
 
 pub(crate) mod capabilities;
-mod client;
+pub(crate) mod client;
 mod idle;
+mod notify;
 pub mod select_folder;
 pub(crate) mod session;
 
@@ -120,9 +122,9 @@ pub(crate) struct Imap {
 }
 
 #[derive(Debug)]
-struct OAuth2 {
-    user: String,
-    access_token: String,
+pub(crate) struct OAuth2 {
+    pub user: String,
+    pub access_token: String,
 }
 
 #[derive(Debug, Default)]
@@ -169,6 +171,9 @@ pub enum FolderMeaning {
     Inbox,
     Trash,
 
+    /// Sent folder.
+    Sent,
+
     /// Virtual folders.
     ///
     /// On Gmail there are virtual folders marked as \\All, \\Important and \\Flagged.
@@ -178,6 +183,20 @@ pub enum FolderMeaning {
     Virtual,
 }
 
+impl FolderMeaning {
+    /// Returns true if UIDs of the folders with this meaning
+    /// are synchronized by [`Session::resync_folders`].
+    ///
+    /// Sent folder is only watched for new messages
+    /// and messages are never moved there, so it is not resynchronized.
+    fn needs_resync(self) -> bool {
+        !matches!(
+            self,
+            FolderMeaning::Virtual | FolderMeaning::Unknown | FolderMeaning::Sent
+        )
+    }
+}
+
 struct UidGrouper<T: Iterator<Item = (i64, u32, String)>> {
     inner: Peekable<T>,
 }
@@ -507,6 +526,7 @@ pub async fn fetch_move_delete(
         context: &Context,
         session: &mut Session,
         watch_folder: &str,
+        folder_meaning: FolderMeaning,
     ) -> Result<()> {
         ensure_and_debug_assert!(!watch_folder.is_empty(), "Watched folder cannot be empty");
         if !context.sql.is_open().await {
@@ -515,7 +535,7 @@ pub async fn fetch_move_delete(
         }
 
         let msgs_fetched = self
-            .fetch_new_messages(context, session, watch_folder)
+            .fetch_new_messages(context, session, watch_folder, folder_meaning)
             .await
             .context("fetch_new_messages")?;
         if msgs_fetched && context.get_config_delete_device_after().await?.is_some() {
@@ -549,6 +569,7 @@ pub(crate) async fn fetch_new_messages(
         context: &Context,
         session: &mut Session,
         folder: &str,
+        folder_meaning: FolderMeaning,
     ) -> Result<bool> {
         let transport_id = session.transport_id();
 
@@ -575,7 +596,8 @@ pub(crate) async fn fetch_new_messages(
         let mut read_cnt = 0;
         loop {
             let (n, fetch_more) =
-                Box::pin(self.fetch_new_msg_batch(context, session, folder)).await?;
+                Box::pin(self.fetch_new_msg_batch(context, session, folder, folder_meaning))
+                    .await?;
             read_cnt += n;
             if !fetch_more {
                 return Ok(read_cnt > 0);
@@ -590,6 +612,7 @@ async fn fetch_new_msg_batch(
         context: &Context,
         session: &mut Session,
         folder: &str,
+        folder_meaning: FolderMeaning,
     ) -> Result<(usize, bool)> {
         let transport_id = self.transport_id;
         let uid_validity = get_uidvalidity(context, transport_id, folder).await?;
@@ -613,10 +636,7 @@ async fn fetch_new_msg_batch(
         let mut uid_message_ids = BTreeMap::new();
         let mut largest_uid_skipped = None;
 
-        let download_limit: Option<u32> = context
-            .get_config_parsed(Config::DownloadLimit)
-            .await?
-            .filter(|&l| 0 < l);
+        let download_limit = get_download_limit(context).await?;
 
         // Store the info about IMAP messages in the database.
         for (uid, ref fetch_response) in msgs {
@@ -659,7 +679,11 @@ async fn fetch_new_msg_batch(
                 info!(context, "Deleting locally deleted message {message_id}.");
             }
 
-            let target = if delete { "" } else { folder };
+            let target = if delete {
+                String::new()
+            } else {
+                target_folder(context, folder, folder_meaning, &headers).await?
+            };
 
             context
                 .sql
@@ -675,7 +699,7 @@ async fn fetch_new_msg_batch(
                         &folder,
                         uid,
                         uid_validity,
-                        target,
+                        &target,
                     ),
                 )
                 .await?;
@@ -686,7 +710,10 @@ async fn fetch_new_msg_batch(
             // same time. Even in single device case it is possible to fail downloading the first
             // message, move it to the movebox and then download the second message before
             // downloading the first one, if downloading from inbox before moving is allowed.
+            //
+            // Messages remaining in the Spam folder are never downloaded.
             if folder == target
+                && folder_meaning != FolderMeaning::Spam
                 && prefetch_should_download(context, &headers, &message_id, fetch_response.flags())
                     .await
                     .context("prefetch_should_download")?
@@ -833,10 +860,7 @@ pub(crate) async fn resync_folders(&mut self, context: &Context) -> Result<()> {
             .context("listing folders for resync")?;
         for folder in all_folders {
             let folder_meaning = get_folder_meaning(&folder);
-            if !matches!(
-                folder_meaning,
-                FolderMeaning::Virtual | FolderMeaning::Unknown
-            ) {
+            if folder_meaning.needs_resync() {
                 self.resync_folder_uids(context, folder.name(), folder_meaning)
                     .await?;
             }
@@ -1385,7 +1409,7 @@ pub(crate) async fn fetch_many_msgs(
                     context,
                     "Passing message UID {} to receive_imf().", request_uid
                 );
-                let res = receive_imf_inner(context, rfc724_mid, body, is_seen).await;
+                let res = Box::pin(receive_imf_inner(context, rfc724_mid, body, is_seen)).await;
 
                 // If there was an error receiving the message, show a device message:
                 let received_msg = match res {
@@ -1696,12 +1720,12 @@ impl Session {
     /// or flags have been changed.
     /// In this case we may want to skip next IDLE and do a round
     /// of fetching new messages and synchronizing seen flags.
-    fn drain_unsolicited_responses(&self, context: &Context) -> Result<bool> {
+    fn drain_unsolicited_responses(&mut self, context: &Context) -> Result<bool> {
         use UnsolicitedResponse::*;
         use async_imap::imap_proto::Response;
         use async_imap::imap_proto::ResponseCode;
 
-        let folder = self.selected_folder.as_deref().unwrap_or_default();
+        let folder = self.selected_folder.clone().unwrap_or_default();
         let mut should_refetch = false;
         while let Ok(response) = self.unsolicited_responses.try_recv() {
             match response {
@@ -1713,6 +1737,14 @@ fn drain_unsolicited_responses(&self, context: &Context) -> Result<bool> {
                     should_refetch = true;
                 }
 
+                Status { ref mailbox, .. } if self.mark_notify_pending(mailbox) => {
+                    info!(
+                        context,
+                        "Need to fetch {mailbox:?}, got NOTIFY STATUS {response:?}"
+                    );
+                    should_refetch = true;
+                }
+
                 Expunge(_) | Recent(_) => {}
                 Other(ref response_data) => {
                     match response_data.parsed() {
@@ -1922,6 +1954,7 @@ fn get_folder_meaning_by_attrs(folder_attrs: &[NameAttribute]) -> FolderMeaning
         match attr {
             NameAttribute::Trash => return FolderMeaning::Trash,
             NameAttribute::Junk => return FolderMeaning::Spam,
+            NameAttribute::Sent => return FolderMeaning::Sent,
             NameAttribute::All | NameAttribute::Flagged => return FolderMeaning::Virtual,
             NameAttribute::Extension(label) => {
                 match label.as_ref() {
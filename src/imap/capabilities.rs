@@ -29,6 +29,10 @@ pub(crate) struct Capabilities {
     /// <https://tools.ietf.org/html/rfc4978>
     pub can_compress: bool,
 
+    /// True if the server has NOTIFY capability as defined in
+    /// <https://tools.ietf.org/html/rfc5465>
+    pub can_notify: bool,
+
     /// True if the server supports XDELTAPUSH capability.
     /// This capability means setting /private/devicetoken IMAP METADATA
     /// on the INBOX results in new mail notifications
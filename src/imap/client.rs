@@ -1,5 +1,6 @@
 use std::net::SocketAddr;
 use std::ops::{Deref, DerefMut};
+use std::sync::Arc;
 
 use anyhow::{Context as _, Result};
 use async_imap::Client as ImapClient;
@@ -13,6 +14,7 @@
 use crate::net::proxy::ProxyConfig;
 use crate::net::session::SessionStream;
 use crate::net::tls::wrap_tls;
+use crate::net::traffic::{MeteredStream, TrafficCounters, TrafficProtocol, host_counters};
 use crate::net::{connect_tcp_inner, run_connection_attempts, update_connection_history};
 use crate::tools::time;
 use crate::transport::ConnectionCandidate;
@@ -69,6 +71,7 @@ pub(crate) async fn determine_capabilities(
         can_condstore: caps.has_str("CONDSTORE"),
         can_metadata: caps.has_str("METADATA"),
         can_compress: caps.has_str("COMPRESS=DEFLATE"),
+        can_notify: caps.has_str("NOTIFY"),
         can_push: caps.has_str("XDELTAPUSH"),
         is_chatmail: caps.has_str("XCHATMAIL"),
         server_id,
@@ -116,6 +119,7 @@ async fn connection_attempt(
         security: ConnectionSecurity,
         resolved_addr: SocketAddr,
         strict_tls: bool,
+        counters: Arc<TrafficCounters>,
     ) -> Result<Self> {
         let context = &context;
         let host = &host;
@@ -125,12 +129,14 @@ async fn connection_attempt(
         );
         let res = match security {
             ConnectionSecurity::Tls => {
-                Client::connect_secure(context, resolved_addr, host, strict_tls).await
+                Client::connect_secure(context, resolved_addr, host, strict_tls, counters).await
             }
             ConnectionSecurity::Starttls => {
-                Client::connect_starttls(context, resolved_addr, host, strict_tls).await
+                Client::connect_starttls(context, resolved_addr, host, strict_tls, counters).await
+            }
+            ConnectionSecurity::Plain => {
+                Client::connect_insecure(context, resolved_addr, counters).await
             }
-            ConnectionSecurity::Plain => Client::connect_insecure(context, resolved_addr).await,
         };
         match res {
             Ok(client) => {
@@ -162,6 +168,26 @@ pub async fn connect(
         proxy_config: Option<ProxyConfig>,
         strict_tls: bool,
         candidate: &ConnectionCandidate,
+    ) -> Result<Self> {
+        let counters = host_counters(context, TrafficProtocol::Imap, &candidate.host).await?;
+        let res = Self::connect_metered(
+            context,
+            proxy_config,
+            strict_tls,
+            candidate,
+            counters.clone(),
+        )
+        .await;
+        counters.add_connection_attempt(res.is_err());
+        res
+    }
+
+    async fn connect_metered(
+        context: &Context,
+        proxy_config: Option<ProxyConfig>,
+        strict_tls: bool,
+        candidate: &ConnectionCandidate,
+        counters: Arc<TrafficCounters>,
     ) -> Result<Self> {
         let host = &candidate.host;
         let port = candidate.port;
@@ -169,15 +195,30 @@ pub async fn connect(
         if let Some(proxy_config) = proxy_config {
             let client = match security {
                 ConnectionSecurity::Tls => {
-                    Client::connect_secure_proxy(context, host, port, strict_tls, proxy_config)
-                        .await?
+                    Client::connect_secure_proxy(
+                        context,
+                        host,
+                        port,
+                        strict_tls,
+                        proxy_config,
+                        counters,
+                    )
+                    .await?
                 }
                 ConnectionSecurity::Starttls => {
-                    Client::connect_starttls_proxy(context, host, port, proxy_config, strict_tls)
-                        .await?
+                    Client::connect_starttls_proxy(
+                        context,
+                        host,
+                        port,
+                        proxy_config,
+                        strict_tls,
+                        counters,
+                    )
+                    .await?
                 }
                 ConnectionSecurity::Plain => {
-                    Client::connect_insecure_proxy(context, host, port, proxy_config).await?
+                    Client::connect_insecure_proxy(context, host, port, proxy_config, counters)
+                        .await?
                 }
             };
             update_connection_history(context, "imap", host, port, host, time()).await?;
@@ -195,7 +236,14 @@ pub async fn connect(
                     .map(|resolved_addr| {
                         let context = context.clone();
                         let host = host.to_string();
-                        Self::connection_attempt(context, host, security, resolved_addr, strict_tls)
+                        Self::connection_attempt(
+                            context,
+                            host,
+                            security,
+                            resolved_addr,
+                            strict_tls,
+                            counters.clone(),
+                        )
                     });
             run_connection_attempts(connection_futures).await
         }
@@ -206,9 +254,10 @@ async fn connect_secure(
         addr: SocketAddr,
         hostname: &str,
         strict_tls: bool,
+        counters: Arc<TrafficCounters>,
     ) -> Result<Self> {
         let use_sni = true;
-        let tcp_stream = connect_tcp_inner(addr).await?;
+        let tcp_stream = MeteredStream::new(connect_tcp_inner(addr).await?, counters);
         let account_id = context.get_id();
         let events = context.events.clone();
         let logging_stream = LoggingStream::new(tcp_stream, account_id, events)?;
@@ -234,8 +283,12 @@ async fn connect_secure(
         Ok(client)
     }
 
-    async fn connect_insecure(context: &Context, addr: SocketAddr) -> Result<Self> {
-        let tcp_stream = connect_tcp_inner(addr).await?;
+    async fn connect_insecure(
+        context: &Context,
+        addr: SocketAddr,
+        counters: Arc<TrafficCounters>,
+    ) -> Result<Self> {
+        let tcp_stream = MeteredStream::new(connect_tcp_inner(addr).await?, counters);
         let account_id = context.get_id();
         let events = context.events.clone();
         let logging_stream = LoggingStream::new(tcp_stream, account_id, events)?;
@@ -254,9 +307,10 @@ async fn connect_starttls(
         addr: SocketAddr,
         host: &str,
         strict_tls: bool,
+        counters: Arc<TrafficCounters>,
     ) -> Result<Self> {
         let use_sni = false;
-        let tcp_stream = connect_tcp_inner(addr).await?;
+        let tcp_stream = MeteredStream::new(connect_tcp_inner(addr).await?, counters);
 
         let account_id = context.get_id();
         let events = context.events.clone();
@@ -301,11 +355,13 @@ async fn connect_secure_proxy(
         port: u16,
         strict_tls: bool,
         proxy_config: ProxyConfig,
+        counters: Arc<TrafficCounters>,
     ) -> Result<Self> {
         let use_sni = true;
         let proxy_stream = proxy_config
             .connect(context, domain, port, strict_tls)
             .await?;
+        let proxy_stream = MeteredStream::new(proxy_stream, counters);
         let tls_stream = wrap_tls(
             strict_tls,
             domain,
@@ -333,8 +389,10 @@ async fn connect_insecure_proxy(
         domain: &str,
         port: u16,
         proxy_config: ProxyConfig,
+        counters: Arc<TrafficCounters>,
     ) -> Result<Self> {
         let proxy_stream = proxy_config.connect(context, domain, port, false).await?;
+        let proxy_stream = MeteredStream::new(proxy_stream, counters);
         let buffered_stream = BufWriter::new(proxy_stream);
         let session_stream: Box<dyn SessionStream> = Box::new(buffered_stream);
         let mut client = Client::new(session_stream);
@@ -351,11 +409,13 @@ async fn connect_starttls_proxy(
         port: u16,
         proxy_config: ProxyConfig,
         strict_tls: bool,
+        counters: Arc<TrafficCounters>,
     ) -> Result<Self> {
         let use_sni = false;
         let proxy_stream = proxy_config
             .connect(context, hostname, port, strict_tls)
             .await?;
+        let proxy_stream = MeteredStream::new(proxy_stream, counters);
 
         // Run STARTTLS command and convert the client back into a stream.
         let buffered_proxy_stream = BufWriter::new(proxy_stream);
@@ -3,6 +3,7 @@
 use anyhow::{Context as _, Result};
 use async_channel::Receiver;
 use async_imap::extensions::idle::IdleResponse;
+use async_imap::imap_proto::{MailboxDatum, Response};
 use tokio::time::timeout;
 
 use super::Imap;
@@ -20,6 +21,13 @@
 /// For example, Dovecot sends keepalives every 2 minutes by default.
 const IDLE_TIMEOUT: Duration = Duration::from_secs(5 * 60);
 
+/// Interval of polling the server if IDLE is not used.
+const FAKE_IDLE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Interval of polling the server if IDLE is not used
+/// and bandwidth is saved.
+const LOW_BANDWIDTH_FAKE_IDLE_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
 impl Session {
     pub async fn idle(
         mut self,
@@ -85,9 +93,14 @@ pub async fn idle(
             })
         };
 
+        // Folder watched with IMAP NOTIFY for which a STATUS response was received.
+        let mut notify_folder = None;
         match idle_wait.await {
             Ok(IdleResponse::NewData(x)) => {
                 info!(context, "{folder:?}: Idle has NewData {x:?}");
+                if let Response::MailboxData(MailboxDatum::Status { mailbox, .. }) = x.parsed() {
+                    notify_folder = Some(mailbox.to_string());
+                }
             }
             Ok(IdleResponse::Timeout) => {
                 info!(context, "{folder:?}: Idle-wait timeout or interruption.");
@@ -111,6 +124,10 @@ pub async fn idle(
         session.as_mut().set_read_timeout(Some(TIMEOUT));
         self.inner = session;
 
+        if let Some(notify_folder) = notify_folder {
+            self.mark_notify_pending(&notify_folder);
+        }
+
         // Fetch mail once we exit IDLE.
         self.new_mail = true;
 
@@ -125,8 +142,14 @@ pub(crate) async fn fake_idle(&mut self, context: &Context, watch_folder: &str)
 
         info!(context, "IMAP-fake-IDLEing folder={:?}", watch_folder);
 
-        // Wait for 60 seconds or until we are interrupted.
-        match timeout(Duration::from_secs(60), self.idle_interrupt_receiver.recv()).await {
+        let interval = if context.is_low_bandwidth().await? {
+            LOW_BANDWIDTH_FAKE_IDLE_INTERVAL
+        } else {
+            FAKE_IDLE_INTERVAL
+        };
+
+        // Wait for the interval or until we are interrupted.
+        match timeout(interval, self.idle_interrupt_receiver.recv()).await {
             Err(_) => info!(context, "Fake IDLE finished."),
             Ok(_) => info!(context, "Fake IDLE interrupted."),
         }
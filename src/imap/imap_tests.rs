@@ -9,6 +9,16 @@ fn test_get_folder_meaning_by_name() {
     assert_eq!(get_folder_meaning_by_name("Trash"), FolderMeaning::Trash);
 }
 
+#[test]
+fn test_folder_meaning_needs_resync() {
+    assert!(FolderMeaning::Inbox.needs_resync());
+    assert!(FolderMeaning::Spam.needs_resync());
+    assert!(FolderMeaning::Trash.needs_resync());
+    assert!(!FolderMeaning::Sent.needs_resync());
+    assert!(!FolderMeaning::Virtual.needs_resync());
+    assert!(!FolderMeaning::Unknown.needs_resync());
+}
+
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
 async fn test_set_uid_next_validity() {
     let t = TestContext::new_alice().await;
@@ -0,0 +1,314 @@
+//! # IMAP NOTIFY.
+//!
+//! NOTIFY extension defined in <https://tools.ietf.org/html/rfc5465>
+//! allows to watch multiple folders over a single connection.
+//! In addition to the selected folder, the spam and sent folders
+//! are watched and fetched when the server reports changes in them
+//! with unsolicited STATUS responses.
+//!
+//! If the server does not support NOTIFY or rejects the command,
+//! only the selected folder is watched as before.
+
+use anyhow::{Context as _, Result};
+
+use super::session::Session;
+use super::{FolderMeaning, Imap, get_folder_meaning};
+use crate::context::Context;
+use crate::log::warn;
+
+impl Session {
+    /// Requests IMAP NOTIFY events for the spam and sent folders.
+    ///
+    /// Does nothing if NOTIFY is already set up for this session
+    /// or the server does not support it.
+    pub(crate) async fn setup_notify(
+        &mut self,
+        context: &Context,
+        watch_folder: &str,
+    ) -> Result<()> {
+        if self.notify_folders.is_some() || !self.can_notify() {
+            return Ok(());
+        }
+        let transport_id = self.transport_id();
+
+        let folders: Vec<(String, FolderMeaning)> = self
+            .list_folders()
+            .await
+            .context("Failed to list folders")?
+            .iter()
+            .filter(|folder| folder.name() != watch_folder)
+            .filter_map(|folder| match get_folder_meaning(folder) {
+                meaning @ (FolderMeaning::Spam | FolderMeaning::Sent) => {
+                    Some((folder.name().to_string(), meaning))
+                }
+                _ => None,
+            })
+            .collect();
+        if folders.is_empty() {
+            info!(
+                context,
+                "Transport {transport_id}: No folders to watch with NOTIFY."
+            );
+            self.notify_folders = Some(Vec::new());
+            return Ok(());
+        }
+
+        let command = notify_set_command(folders.iter().map(|(folder, _)| folder.as_str()));
+        match self.run_command_and_check_ok(&command).await {
+            Ok(()) => {
+                info!(
+                    context,
+                    "Transport {transport_id}: Watching {folders:?} with NOTIFY."
+                );
+
+                // Fetch all the folders once as we may have missed
+                // the changes while NOTIFY was not set up.
+                self.notify_pending = folders.iter().map(|(folder, _)| folder.clone()).collect();
+                self.notify_folders = Some(folders);
+            }
+            Err(err) => {
+                warn!(
+                    context,
+                    "Transport {transport_id}: NOTIFY failed, watching only {watch_folder:?}: {err:#}."
+                );
+                self.notify_folders = Some(Vec::new());
+            }
+        }
+        Ok(())
+    }
+
+    /// Marks the folder as having new mail
+    /// if it is watched with IMAP NOTIFY.
+    ///
+    /// Returns true if the folder is watched.
+    pub(super) fn mark_notify_pending(&mut self, folder: &str) -> bool {
+        let watched = self
+            .notify_folders
+            .iter()
+            .flatten()
+            .any(|(notify_folder, _)| notify_folder == folder);
+        if watched {
+            self.notify_pending.insert(folder.to_string());
+        }
+        watched
+    }
+
+    /// Returns folders watched with IMAP NOTIFY that may have new mail
+    /// and clears the list.
+    fn take_notify_pending(&mut self) -> Vec<(String, FolderMeaning)> {
+        let pending = std::mem::take(&mut self.notify_pending);
+        self.notify_folders
+            .iter()
+            .flatten()
+            .filter(|(folder, _)| pending.contains(folder))
+            .cloned()
+            .collect()
+    }
+}
+
+impl Imap {
+    /// Fetches the folders watched with IMAP NOTIFY
+    /// for which the server reported changes.
+    pub(crate) async fn fetch_notify_folders(
+        &mut self,
+        context: &Context,
+        session: &mut Session,
+    ) -> Result<()> {
+        if session
+            .notify_folders
+            .as_ref()
+            .is_none_or(|folders| folders.is_empty())
+        {
+            return Ok(());
+        }
+
+        // Collect STATUS responses received while fetching other folders.
+        if session.drain_unsolicited_responses(context)? {
+            session.new_mail = true;
+        }
+
+        for (folder, folder_meaning) in session.take_notify_pending() {
+            info!(context, "Fetching {folder:?} watched with NOTIFY.");
+            self.fetch_move_delete(context, session, &folder, folder_meaning)
+                .await
+                .with_context(|| format!("Failed to fetch {folder:?}"))?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns NOTIFY SET command requesting events
+/// for the selected folder and the given folders.
+fn notify_set_command<'a>(folders: impl Iterator<Item = &'a str>) -> String {
+    let mailboxes = folders
+        .map(|folder| {
+            let escaped = folder.replace('\\', "\\\\").replace('"', "\\\"");
+            format!("\"{escaped}\"")
+        })
+        .collect::<Vec<_>>()
+        .join(" ");
+    format!(
+        "NOTIFY SET (SELECTED (MessageNew MessageExpunge FlagChange)) \
+         (MAILBOXES ({mailboxes}) (MessageNew MessageExpunge))"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::{TcpListener, TcpStream};
+
+    use super::*;
+    use crate::imap::capabilities::Capabilities;
+    use crate::net::session::SessionStream;
+    use crate::test_utils::TestContext;
+
+    /// Starts a local IMAP server stand-in.
+    ///
+    /// It lists INBOX, Junk, Sent and Drafts folders,
+    /// accepts or rejects NOTIFY command depending on `accept_notify`
+    /// and reports new mail in Sent folder on NOOP.
+    ///
+    /// Returns the session connected to it
+    /// and the receiver of NOTIFY commands sent by the client.
+    async fn imap_stand_in(
+        can_notify: bool,
+        accept_notify: bool,
+    ) -> Result<(Session, async_channel::Receiver<String>)> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let (notify_sender, notify_receiver) = async_channel::unbounded();
+        tokio::spawn(async move {
+            let (stream, _addr) = listener.accept().await.unwrap();
+            let (reader, mut writer) = stream.into_split();
+            let mut lines = BufReader::new(reader).lines();
+            writer.write_all(b"* OK IMAP4rev1 ready\r\n").await.unwrap();
+            while let Some(line) = lines.next_line().await.unwrap() {
+                let (tag, command) = line.split_once(' ').unwrap();
+                let response = if command.starts_with("LIST") {
+                    format!(
+                        "* LIST (\\HasNoChildren) \"/\" \"INBOX\"\r\n\
+                         * LIST (\\HasNoChildren \\Junk) \"/\" \"Junk\"\r\n\
+                         * LIST (\\HasNoChildren \\Sent) \"/\" \"Sent\"\r\n\
+                         * LIST (\\HasNoChildren) \"/\" \"Drafts\"\r\n\
+                         {tag} OK LIST completed\r\n"
+                    )
+                } else if command.starts_with("NOTIFY") {
+                    notify_sender.send(command.to_string()).await.unwrap();
+                    if accept_notify {
+                        format!("{tag} OK NOTIFY completed\r\n")
+                    } else {
+                        format!("{tag} NO [NOTIFICATIONOVERFLOW] Too many mailboxes\r\n")
+                    }
+                } else if command.starts_with("NOOP") {
+                    format!(
+                        "* STATUS \"Sent\" (MESSAGES 3 UIDNEXT 4)\r\n{tag} OK NOOP completed\r\n"
+                    )
+                } else {
+                    format!("{tag} OK completed\r\n")
+                };
+                writer.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+
+        let tcp_stream = Box::pin(tokio_io_timeout::TimeoutStream::new(
+            TcpStream::connect(addr).await?,
+        ));
+        let session_stream: Box<dyn SessionStream> = Box::new(tcp_stream);
+        let mut client = async_imap::Client::new(session_stream);
+        let _greeting = client
+            .read_response()
+            .await?
+            .context("Failed to read greeting")?;
+        let inner = client
+            .login("alice", "password")
+            .await
+            .map_err(|(err, _client)| err)?;
+        let capabilities = Capabilities {
+            can_idle: true,
+            can_move: true,
+            can_check_quota: false,
+            can_condstore: false,
+            can_metadata: false,
+            can_compress: false,
+            can_notify,
+            can_push: false,
+            is_chatmail: false,
+            server_id: None,
+        };
+        let (resync_request_sender, _resync_request_receiver) = async_channel::bounded(1);
+        let session = Session::new(inner, capabilities, resync_request_sender, 1);
+        Ok((session, notify_receiver))
+    }
+
+    #[test]
+    fn test_notify_set_command() {
+        assert_eq!(
+            notify_set_command(["Junk", "Sent \"old\""].into_iter()),
+            "NOTIFY SET (SELECTED (MessageNew MessageExpunge FlagChange)) \
+             (MAILBOXES (\"Junk\" \"Sent \\\"old\\\"\") (MessageNew MessageExpunge))"
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_setup_notify() -> Result<()> {
+        let t = &TestContext::new().await;
+        let (mut session, notify_receiver) = imap_stand_in(true, true).await?;
+
+        session.setup_notify(t, "INBOX").await?;
+        assert_eq!(
+            notify_receiver.recv().await?,
+            notify_set_command(["Junk", "Sent"].into_iter())
+        );
+        assert_eq!(
+            session.notify_folders,
+            Some(vec![
+                ("Junk".to_string(), FolderMeaning::Spam),
+                ("Sent".to_string(), FolderMeaning::Sent),
+            ])
+        );
+
+        // All folders are fetched once after setting up NOTIFY.
+        assert_eq!(session.take_notify_pending().len(), 2);
+        assert!(session.take_notify_pending().is_empty());
+
+        // Setting up NOTIFY again does nothing.
+        session.setup_notify(t, "INBOX").await?;
+        assert!(notify_receiver.is_empty());
+
+        // STATUS response marks the folder as having new mail.
+        session.noop().await?;
+        assert!(session.drain_unsolicited_responses(t)?);
+        assert_eq!(
+            session.take_notify_pending(),
+            vec![("Sent".to_string(), FolderMeaning::Sent)]
+        );
+
+        // Folders not watched with NOTIFY are ignored.
+        assert!(!session.mark_notify_pending("Drafts"));
+        assert!(session.take_notify_pending().is_empty());
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_setup_notify_fallback() -> Result<()> {
+        let t = &TestContext::new().await;
+
+        // Server rejects NOTIFY.
+        let (mut session, notify_receiver) = imap_stand_in(true, false).await?;
+        session.setup_notify(t, "INBOX").await?;
+        assert_eq!(notify_receiver.len(), 1);
+        assert_eq!(session.notify_folders, Some(Vec::new()));
+        assert!(session.take_notify_pending().is_empty());
+
+        session.noop().await?;
+        assert!(!session.drain_unsolicited_responses(t)?);
+
+        // Server does not support NOTIFY.
+        let (mut session, notify_receiver) = imap_stand_in(false, true).await?;
+        session.setup_notify(t, "INBOX").await?;
+        assert!(notify_receiver.is_empty());
+        assert_eq!(session.notify_folders, None);
+        Ok(())
+    }
+}
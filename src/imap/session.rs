@@ -1,4 +1,4 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::ops::{Deref, DerefMut};
 
 use anyhow::{Context as _, Result};
@@ -6,6 +6,7 @@
 use async_imap::types::Mailbox;
 use futures::TryStreamExt;
 
+use crate::imap::FolderMeaning;
 use crate::imap::capabilities::Capabilities;
 use crate::net::session::SessionStream;
 
@@ -49,6 +50,14 @@ pub(crate) struct Session {
     /// Should be false if no folder is currently selected.
     pub new_mail: bool,
 
+    /// Folders watched with IMAP NOTIFY in addition to the selected folder.
+    ///
+    /// `None` if NOTIFY has not been set up for this session yet.
+    pub(super) notify_folders: Option<Vec<(String, FolderMeaning)>>,
+
+    /// Folders watched with IMAP NOTIFY that may have new mail.
+    pub(super) notify_pending: BTreeSet<String>,
+
     pub resync_request_sender: async_channel::Sender<()>,
 }
 
@@ -81,6 +90,8 @@ pub(crate) fn new(
             selected_mailbox: None,
             selected_folder_needs_expunge: false,
             new_mail: false,
+            notify_folders: None,
+            notify_pending: BTreeSet::new(),
             resync_request_sender,
         }
     }
@@ -110,6 +121,10 @@ pub fn can_metadata(&self) -> bool {
         self.capabilities.can_metadata
     }
 
+    pub fn can_notify(&self) -> bool {
+        self.capabilities.can_notify
+    }
+
     pub fn can_push(&self) -> bool {
         self.capabilities.can_push
     }
@@ -5,6 +5,7 @@
 use deltachat_contact_tools::EmailAddress;
 
 use crate::chat::ChatId;
+use crate::constants::KeyProfile;
 use crate::context::Context;
 use crate::key;
 use crate::key::DcKey;
@@ -29,7 +30,7 @@ pub async fn save_broadcast_secret(context: &Context, chat_id: ChatId, secret: &
 }
 
 pub fn create_dummy_keypair(addr: &str) -> Result<key::SignedSecretKey> {
-    pgp::create_keypair(EmailAddress::new(addr)?)
+    pgp::create_keypair(EmailAddress::new(addr)?, KeyProfile::Classic)
 }
 
 pub fn create_broadcast_secret() -> String {
@@ -7,6 +7,7 @@
 use anyhow::{Context as _, Result, bail, ensure};
 use base64::Engine as _;
 use deltachat_contact_tools::EmailAddress;
+use num_traits::FromPrimitive;
 use pgp::composed::{Deserializable, SignedKeyDetails};
 pub use pgp::composed::{SignedPublicKey, SignedSecretKey};
 use pgp::crypto::aead::AeadAlgorithm;
@@ -21,6 +22,8 @@
 use rand_old::thread_rng;
 use tokio::runtime::Handle;
 
+use crate::config::Config;
+use crate::constants::KeyProfile;
 use crate::context::Context;
 use crate::events::EventType;
 use crate::log::LogExt;
@@ -455,6 +458,8 @@ fn dc_fingerprint(&self) -> Fingerprint {
 async fn generate_keypair(context: &Context) -> Result<SignedSecretKey> {
     let addr = context.get_primary_self_addr().await?;
     let addr = EmailAddress::new(&addr)?;
+    let profile =
+        KeyProfile::from_i32(context.get_config_int(Config::KeyProfile).await?).unwrap_or_default();
     let _public_key_guard = context.self_public_key.lock().await;
 
     // Check if the key appeared while we were waiting on the lock.
@@ -464,7 +469,7 @@ async fn generate_keypair(context: &Context) -> Result<SignedSecretKey> {
             let start = tools::Time::now();
             info!(context, "Generating keypair.");
             let keypair = Handle::current()
-                .spawn_blocking(move || crate::pgp::create_keypair(addr))
+                .spawn_blocking(move || crate::pgp::create_keypair(addr, profile))
                 .await??;
 
             store_self_keypair(context, &keypair).await?;
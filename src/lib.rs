@@ -65,6 +65,7 @@
 pub mod contact;
 pub mod context;
 mod decrypt;
+pub mod diagnostics;
 pub mod download;
 mod e2ee;
 pub mod ephemeral;
@@ -99,6 +100,7 @@
 mod transport;
 mod update_helper;
 pub mod webxdc;
+pub mod wkd;
 #[macro_use]
 mod dehtml;
 pub mod color;
@@ -39,6 +39,9 @@
     sanitize_filename, time, timestamp_to_str,
 };
 
+mod crypto_info;
+pub use crypto_info::{CryptoInfo, EncryptionType};
+
 /// Message ID, including reserved IDs.
 ///
 /// Some message IDs are reserved to identify special message types.
@@ -266,6 +269,15 @@ pub async fn get_info(self, context: &Context) -> Result<String> {
             }
         }
 
+        for report in get_msg_delivery_report(context, self).await? {
+            let fts = timestamp_to_str(report.timestamp);
+            ret += &format!("{}: {fts} for {}", report.status, report.addr);
+            if let Some(reason) = &report.reason {
+                ret += &format!(" ({reason})");
+            }
+            ret += "\n";
+        }
+
         ret += &format!("State: {}", msg.state);
 
         if msg.has_location() {
@@ -278,6 +290,35 @@ pub async fn get_info(self, context: &Context) -> Result<String> {
 
         ret += "\n";
 
+        let crypto_info = msg.get_crypto_info(context).await?;
+        if crypto_info.encryption == EncryptionType::None && crypto_info.signature_valid {
+            // Unencrypted message with a valid OpenPGP/MIME signature.
+            for fingerprint in &crypto_info.signer_fingerprints {
+                ret += &format!("Signed by: {}\n", fingerprint.hex());
+            }
+            if crypto_info.signer_matches_from {
+                ret += "Signature: valid\n";
+            } else {
+                ret += "Signature: valid, but not made by the sender\n";
+            }
+        } else if crypto_info.encryption != EncryptionType::None {
+            ret += &format!("Encryption: {}\n", crypto_info.encryption);
+            for fingerprint in &crypto_info.signer_fingerprints {
+                ret += &format!("Signed by: {}\n", fingerprint.hex());
+            }
+            if !crypto_info.signature_valid {
+                ret += "Signature: not valid\n";
+            } else if !crypto_info.signer_matches_from {
+                ret += "Signature: not made by the sender\n";
+            }
+            if crypto_info.header_protection {
+                ret += "Header protection: yes\n";
+            }
+            if !crypto_info.gossip_addrs.is_empty() {
+                ret += &format!("Gossip: {}\n", crypto_info.gossip_addrs.join(", "));
+            }
+        }
+
         let reactions = get_msg_reactions(context, self).await?;
         if !reactions.is_empty() {
             ret += &format!("Reactions: {reactions}\n");
@@ -1425,6 +1466,65 @@ fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
     }
 }
 
+/// Delivery status of a message for a single recipient
+/// as reported with a Delivery Status Notification (RFC 3464)
+/// or by the SMTP server rejecting the recipient.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    FromPrimitive,
+    ToPrimitive,
+    ToSql,
+    FromSql,
+    Serialize,
+    Deserialize,
+)]
+#[repr(u32)]
+pub enum DeliveryStatus {
+    /// Message was delivered to the recipient
+    /// or relayed to a server that does not send delivery reports.
+    Delivered = 1,
+
+    /// Delivery is delayed, the server keeps trying.
+    Delayed = 2,
+
+    /// Delivery has failed.
+    Failed = 3,
+}
+
+impl std::fmt::Display for DeliveryStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::Delivered => "Delivered",
+                Self::Delayed => "Delayed",
+                Self::Failed => "Failed",
+            }
+        )
+    }
+}
+
+/// Delivery status of a message for a single recipient.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecipientDeliveryStatus {
+    /// Address of the recipient.
+    pub addr: String,
+
+    /// Delivery status.
+    pub status: DeliveryStatus,
+
+    /// Diagnostic code returned by the server, if any.
+    pub reason: Option<String>,
+
+    /// Timestamp of receiving the report.
+    pub timestamp: i64,
+}
+
 impl MessageState {
     /// Returns true if the message can transition to `OutFailed` state from the current state.
     pub fn can_fail(self) -> bool {
@@ -1472,6 +1572,35 @@ pub async fn get_msg_read_receipts(
         .await
 }
 
+/// Returns delivery status of the message for each recipient
+/// reported with a Delivery Status Notification
+/// or rejected by the SMTP server when sending.
+pub async fn get_msg_delivery_report(
+    context: &Context,
+    msg_id: MsgId,
+) -> Result<Vec<RecipientDeliveryStatus>> {
+    context
+        .sql
+        .query_map_vec(
+            "SELECT addr, status, reason, timestamp FROM msgs_delivery
+             WHERE msg_id=? ORDER BY timestamp, addr",
+            (msg_id,),
+            |row| {
+                let addr: String = row.get(0)?;
+                let status: DeliveryStatus = row.get(1)?;
+                let reason: String = row.get(2)?;
+                let timestamp: i64 = row.get(3)?;
+                Ok(RecipientDeliveryStatus {
+                    addr,
+                    status,
+                    reason: Some(reason).filter(|reason| !reason.is_empty()),
+                    timestamp,
+                })
+            },
+        )
+        .await
+}
+
 /// Returns count of read receipts on message.
 ///
 /// This view count is meant as a feedback measure for the channel owner only.
@@ -2042,6 +2171,45 @@ pub(crate) async fn set_msg_failed(
     Ok(())
 }
 
+/// Records recipients rejected by the server
+/// when the message was sent to the other recipients.
+///
+/// Unlike [`set_msg_failed`], the message state is not changed.
+/// Rejections are stored as failed deliveries
+/// returned by [`get_msg_delivery_report`]
+/// together with the server responses.
+pub(crate) async fn set_msg_recipients_failed(
+    context: &Context,
+    msg_id: MsgId,
+    rejected: &[(String, String)],
+) -> Result<()> {
+    let Some(chat_id) = context
+        .sql
+        .query_get_value("SELECT chat_id FROM msgs WHERE id=?", (msg_id,))
+        .await?
+    else {
+        return Ok(());
+    };
+    let timestamp = time();
+    context
+        .sql
+        .transaction(|transaction| {
+            for (addr, reason) in rejected {
+                warn!(context, "{msg_id} was not sent to {addr}: {reason}");
+                transaction.execute(
+                    "INSERT OR REPLACE INTO msgs_delivery (msg_id, addr, status, reason, timestamp)
+                     VALUES (?, ?, ?, ?, ?)",
+                    (msg_id, addr, DeliveryStatus::Failed, reason, timestamp),
+                )?;
+            }
+            Ok(())
+        })
+        .await?;
+    context.emit_event(EventType::MsgFailed { chat_id, msg_id });
+    chatlist_events::emit_chatlist_item_changed(context, chat_id);
+    Ok(())
+}
+
 /// Inserts a tombstone into `msgs` table
 /// to prevent downloading the same message in the future.
 ///
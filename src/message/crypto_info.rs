@@ -0,0 +1,275 @@
+//! Cryptographic details of a message, e.g. for a "message security details" view.
+
+use anyhow::Result;
+use deltachat_contact_tools::addr_cmp;
+use deltachat_derive::{FromSql, ToSql};
+
+use crate::contact::{Contact, ContactId};
+use crate::context::Context;
+use crate::key::{DcKey, Fingerprint, SignedPublicKey, self_fingerprint};
+use crate::message::{Message, MsgId};
+use crate::pgp::user_id_addresses;
+
+/// How the message was encrypted.
+#[derive(
+    Debug, Default, Display, Clone, Copy, PartialEq, Eq, FromPrimitive, ToPrimitive, FromSql, ToSql,
+)]
+#[repr(u8)]
+pub enum EncryptionType {
+    /// The message is not encrypted.
+    #[default]
+    None = 0,
+
+    /// The message is encrypted to the public keys of the recipients.
+    PublicKey = 1,
+
+    /// The message is encrypted with a shared secret,
+    /// e.g. in a broadcast channel or during Securejoin.
+    SharedSecret = 2,
+}
+
+/// Cryptographic details of a message.
+///
+/// See [`Message::get_crypto_info()`].
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct CryptoInfo {
+    /// How the message was encrypted.
+    pub encryption: EncryptionType,
+
+    /// Fingerprints of the keys that made valid signatures over the message.
+    pub signer_fingerprints: Vec<Fingerprint>,
+
+    /// True if the message has a valid signature.
+    ///
+    /// Encrypted messages without valid signature are displayed as unencrypted.
+    /// Unencrypted messages may have a valid OpenPGP/MIME `multipart/signed` signature,
+    /// but are still displayed as unencrypted.
+    pub signature_valid: bool,
+
+    /// True if the message was signed with the key of the sender contact.
+    pub signer_matches_from: bool,
+
+    /// True if the message headers were protected
+    /// as defined in <https://www.rfc-editor.org/rfc/rfc9788.html>.
+    pub header_protection: bool,
+
+    /// Addresses of the recipients whose keys were gossiped
+    /// in `Autocrypt-Gossip` headers of the message.
+    pub gossip_addrs: Vec<String>,
+}
+
+impl CryptoInfo {
+    /// Saves cryptographic details of a sent or received message.
+    pub(crate) async fn save(&self, context: &Context, msg_id: MsgId) -> Result<()> {
+        let signer_fingerprints = self
+            .signer_fingerprints
+            .iter()
+            .map(|fp| fp.hex())
+            .collect::<Vec<_>>()
+            .join(" ");
+        context
+            .sql
+            .execute(
+                "INSERT OR REPLACE INTO msgs_crypto_info
+                 (msg_id, encryption, signer_fingerprints, signature_valid, header_protection, gossip_addrs)
+                 VALUES (?, ?, ?, ?, ?, ?)",
+                (
+                    msg_id,
+                    self.encryption,
+                    signer_fingerprints,
+                    self.signature_valid,
+                    self.header_protection,
+                    self.gossip_addrs.join(" "),
+                ),
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Loads cryptographic details of a message, if saved.
+    async fn load(context: &Context, msg_id: MsgId) -> Result<Option<Self>> {
+        context
+            .sql
+            .query_row_optional(
+                "SELECT encryption, signer_fingerprints, signature_valid, header_protection, gossip_addrs
+                 FROM msgs_crypto_info WHERE msg_id=?",
+                (msg_id,),
+                |row| {
+                    let encryption: EncryptionType = row.get(0)?;
+                    let signer_fingerprints: String = row.get(1)?;
+                    let signature_valid: bool = row.get(2)?;
+                    let header_protection: bool = row.get(3)?;
+                    let gossip_addrs: String = row.get(4)?;
+                    Ok(Self {
+                        encryption,
+                        signer_fingerprints: signer_fingerprints
+                            .split_ascii_whitespace()
+                            .filter_map(|fp| fp.parse().ok())
+                            .collect(),
+                        signature_valid,
+                        signer_matches_from: false,
+                        header_protection,
+                        gossip_addrs: gossip_addrs
+                            .split_ascii_whitespace()
+                            .map(|addr| addr.to_string())
+                            .collect(),
+                    })
+                },
+            )
+            .await
+    }
+}
+
+/// Returns true if any of the known signer keys has a User ID with the address.
+async fn signers_have_user_id(
+    context: &Context,
+    signer_fingerprints: &[Fingerprint],
+    addr: &str,
+) -> Result<bool> {
+    for fingerprint in signer_fingerprints {
+        let Some(public_key_bytes) = context
+            .sql
+            .query_row_optional(
+                "SELECT public_key FROM public_keys WHERE fingerprint=?",
+                (fingerprint.hex(),),
+                |row| {
+                    let bytes: Vec<u8> = row.get(0)?;
+                    Ok(bytes)
+                },
+            )
+            .await?
+        else {
+            continue;
+        };
+        let public_key = SignedPublicKey::from_slice(&public_key_bytes)?;
+        if user_id_addresses(&public_key)
+            .iter()
+            .any(|user_id_addr| addr_cmp(user_id_addr, addr))
+        {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+impl Message {
+    /// Returns cryptographic details of the message:
+    /// encryption type, signer fingerprints, signature verification result,
+    /// whether the signer matches the sender, header protection
+    /// and Autocrypt gossip.
+    pub async fn get_crypto_info(&self, context: &Context) -> Result<CryptoInfo> {
+        let Some(mut info) = CryptoInfo::load(context, self.id).await? else {
+            return Ok(CryptoInfo::default());
+        };
+        let from_contact = Contact::get_by_id(context, self.from_id).await?;
+        info.signer_matches_from = if self.from_id == ContactId::SELF {
+            let self_fingerprint = self_fingerprint(context).await?;
+            info.signer_fingerprints
+                .iter()
+                .any(|fp| fp.hex() == self_fingerprint)
+        } else if let Some(from_fingerprint) = from_contact.fingerprint() {
+            info.signer_fingerprints.contains(&from_fingerprint)
+        } else {
+            // Unencrypted signed messages come from address-contacts without a key,
+            // so check that the signing key belongs to the sender address.
+            signers_have_user_id(context, &info.signer_fingerprints, from_contact.get_addr())
+                .await?
+        };
+        Ok(info)
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chat::create_broadcast;
+    use crate::config::Config;
+    use crate::key::load_self_public_key;
+    use crate::receive_imf::receive_imf;
+    use crate::securejoin::get_securejoin_qr;
+    use crate::test_utils::TestContextManager;
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_get_crypto_info() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = &tcm.alice().await;
+        let bob = &tcm.bob().await;
+        let charlie = &tcm.charlie().await;
+
+        let alice_fp = load_self_public_key(alice).await?.dc_fingerprint();
+
+        let chat_id = alice
+            .create_group_with_members("Group", &[bob, charlie])
+            .await;
+        let sent = alice.send_text(chat_id, "Hello!").await;
+        let sent_msg = Message::load_from_db(alice, sent.sender_msg_id).await?;
+        let info = sent_msg.get_crypto_info(alice).await?;
+        assert_eq!(info.encryption, EncryptionType::PublicKey);
+        assert_eq!(info.signer_fingerprints, vec![alice_fp.clone()]);
+        assert!(info.signature_valid);
+        assert!(info.signer_matches_from);
+        assert!(info.header_protection);
+        assert!(info.gossip_addrs.contains(&"bob@example.net".to_string()));
+        assert!(
+            info.gossip_addrs
+                .contains(&"charlie@example.net".to_string())
+        );
+
+        // Details of sent messages do not change with the configuration.
+        alice
+            .set_config_bool(Config::StdHeaderProtectionComposing, false)
+            .await?;
+        assert_eq!(sent_msg.get_crypto_info(alice).await?, info);
+        alice
+            .set_config_bool(Config::StdHeaderProtectionComposing, true)
+            .await?;
+
+        let msg = bob.recv_msg(&sent).await;
+        let info = msg.get_crypto_info(bob).await?;
+        assert_eq!(info.encryption, EncryptionType::PublicKey);
+        assert_eq!(info.signer_fingerprints, vec![alice_fp.clone()]);
+        assert!(info.signature_valid);
+        assert!(info.signer_matches_from);
+        assert!(info.header_protection);
+        assert!(
+            info.gossip_addrs
+                .contains(&"charlie@example.net".to_string())
+        );
+
+        let info_text = msg.id.get_info(bob).await?;
+        assert!(info_text.contains("Encryption: PublicKey\n"));
+        assert!(info_text.contains(&format!("Signed by: {}\n", alice_fp.hex())));
+
+        // Broadcast channel messages are encrypted with a shared secret.
+        let broadcast_id = create_broadcast(alice, "Channel".to_string()).await?;
+        let qr = get_securejoin_qr(alice, Some(broadcast_id)).await?;
+        tcm.exec_securejoin_qr(bob, alice, &qr).await;
+        let sent = alice.send_text(broadcast_id, "Hello channel!").await;
+        let sent_msg = Message::load_from_db(alice, sent.sender_msg_id).await?;
+        let info = sent_msg.get_crypto_info(alice).await?;
+        assert_eq!(info.encryption, EncryptionType::SharedSecret);
+        assert!(info.gossip_addrs.is_empty());
+        let msg = bob.recv_msg(&sent).await;
+        let info = msg.get_crypto_info(bob).await?;
+        assert_eq!(info.encryption, EncryptionType::SharedSecret);
+        assert!(info.signer_matches_from);
+
+        // Unencrypted messages have no details.
+        alice.allow_unencrypted().await?;
+        let received = receive_imf(
+            alice,
+            b"From: Claire <claire@example.com>\n\
+              To: alice@example.org\n\
+              Message-ID: <789@example.com>\n\
+              Date: Fri, 29 Jan 2021 21:37:55 +0000\n\
+              \n\
+              hello\n",
+            false,
+        )
+        .await?
+        .unwrap();
+        let msg = Message::load_from_db(alice, received.msg_ids[0]).await?;
+        assert_eq!(msg.get_crypto_info(alice).await?, CryptoInfo::default());
+
+        Ok(())
+    }
+}
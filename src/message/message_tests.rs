@@ -453,6 +453,33 @@ async fn assert_state(t: &Context, msg_id: MsgId, state: MessageState) {
     Ok(())
 }
 
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_set_msg_recipients_failed() -> Result<()> {
+    let alice = &TestContext::new_alice().await;
+    let bob = &TestContext::new_bob().await;
+    let alice_chat = alice.create_chat(bob).await;
+
+    let msg_id = alice.send_text(alice_chat.id, "hi!").await.sender_msg_id;
+    set_msg_recipients_failed(
+        alice,
+        msg_id,
+        &[("bob@example.net".to_string(), "User unknown".to_string())],
+    )
+    .await?;
+
+    // Message stays delivered, the rejection is in the delivery report.
+    msg_id.set_delivered(alice).await?;
+    let msg = Message::load_from_db(alice, msg_id).await?;
+    assert_eq!(msg.get_state(), MessageState::OutDelivered);
+    assert_eq!(msg.error(), None);
+    let report = get_msg_delivery_report(alice, msg_id).await?;
+    assert_eq!(report.len(), 1);
+    assert_eq!(report[0].addr, "bob@example.net");
+    assert_eq!(report[0].status, DeliveryStatus::Failed);
+    assert_eq!(report[0].reason.as_deref(), Some("User unknown"));
+    Ok(())
+}
+
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
 async fn test_is_bot() -> Result<()> {
     let mut tcm = TestContextManager::new();
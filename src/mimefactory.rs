@@ -28,7 +28,7 @@
 use crate::key::{DcKey, SignedPublicKey, self_fingerprint};
 use crate::location;
 use crate::log::warn;
-use crate::message::{Message, MsgId, Viewtype};
+use crate::message::{CryptoInfo, EncryptionType, Message, MsgId, Viewtype};
 use crate::mimeparser::{SystemMessage, is_hidden};
 use crate::param::Param;
 use crate::peer_channels::{create_iroh_header, get_iroh_topic_for_msg};
@@ -169,6 +169,10 @@ pub struct RenderedEmail {
     pub message: String,
     // pub envelope: Envelope,
     pub is_encrypted: bool,
+
+    /// Cryptographic details of the message,
+    /// saved for the sent message as they are for received ones.
+    pub crypto_info: CryptoInfo,
     pub last_added_location_id: Option<u32>,
 
     /// A comma-separated string of sync-IDs that are used by the rendered email and must be deleted
@@ -932,6 +936,14 @@ pub async fn render(mut self, context: &Context) -> Result<RenderedEmail> {
                     mail_builder::headers::message_id::MessageId::new(rfc724_mid_list.to_string())
                         .into(),
                 ));
+            } else if let Some(instance_rfc724_mid) = msg.param.get(Param::WebxdcReplaceFor) {
+                headers.push((
+                    "Chat-Webxdc-Replace",
+                    mail_builder::headers::message_id::MessageId::new(
+                        instance_rfc724_mid.to_string(),
+                    )
+                    .into(),
+                ));
             }
         }
 
@@ -1041,6 +1053,7 @@ pub async fn render(mut self, context: &Context) -> Result<RenderedEmail> {
         let use_std_header_protection = context
             .get_config_bool(Config::StdHeaderProtectionComposing)
             .await?;
+        let mut crypto_info = CryptoInfo::default();
         let outer_message = if let Some(encryption_pubkeys) = self.encryption_pubkeys {
             let mut message = add_headers_to_encrypted_part(
                 message,
@@ -1056,6 +1069,7 @@ pub async fn render(mut self, context: &Context) -> Result<RenderedEmail> {
 
             let gossip_period = context.get_config_i64(Config::GossipPeriod).await?;
             let now = time();
+            let mut gossip_addrs = Vec::new();
 
             match &self.loaded {
                 Loaded::Message { chat, msg } => {
@@ -1120,6 +1134,7 @@ pub async fn render(mut self, context: &Context) -> Result<RenderedEmail> {
                                 "Autocrypt-Gossip",
                                 mail_builder::headers::raw::Raw::new(header),
                             );
+                            gossip_addrs.push(addr.clone());
 
                             context
                                 .sql
@@ -1174,6 +1189,19 @@ pub async fn render(mut self, context: &Context) -> Result<RenderedEmail> {
                 message = hook(context, message);
             }
 
+            crypto_info = CryptoInfo {
+                encryption: if shared_secret.is_some() {
+                    EncryptionType::SharedSecret
+                } else {
+                    EncryptionType::PublicKey
+                },
+                signer_fingerprints: vec![encrypt_helper.public_key.dc_fingerprint()],
+                signature_valid: true,
+                signer_matches_from: true,
+                header_protection: use_std_header_protection,
+                gossip_addrs,
+            };
+
             let encrypted = if let Some(shared_secret) = shared_secret {
                 let sign = true;
                 encrypt_helper
@@ -1227,6 +1255,20 @@ pub async fn render(mut self, context: &Context) -> Result<RenderedEmail> {
                     message.header(header, value)
                 });
             let message = MimePart::new("multipart/mixed", vec![message]);
+            let message = if !is_securejoin_message
+                && context.get_config_bool(Config::SignUnencrypted).await?
+            {
+                let (signed, signature) = encrypt_helper.sign(context, message).await?;
+                crypto_info = CryptoInfo {
+                    signer_fingerprints: vec![encrypt_helper.public_key.dc_fingerprint()],
+                    signature_valid: true,
+                    signer_matches_from: true,
+                    ..Default::default()
+                };
+                wrap_signed_part(signed, signature)
+            } else {
+                message
+            };
             let message = protected_headers
                 .iter()
                 .fold(message, |message, (header, value)| {
@@ -1252,6 +1294,7 @@ pub async fn render(mut self, context: &Context) -> Result<RenderedEmail> {
             message,
             // envelope: Envelope::new,
             is_encrypted,
+            crypto_info,
             last_added_location_id,
             sync_ids_to_delete: self.sync_ids_to_delete,
             rfc724_mid,
@@ -1582,20 +1625,28 @@ async fn render_message(
                 ));
             }
             SystemMessage::IrohNodeAddr => {
-                let node_addr = context
-                    .get_or_try_init_peer_channel()
-                    .await?
-                    .get_node_addr()
-                    .await?;
+                let (node_addr, relay_disabled) = {
+                    let iroh = context.get_or_try_init_peer_channel().await?;
+                    (iroh.get_node_addr().await?, iroh.relay_disabled)
+                };
 
                 // We should not send `null` as relay URL
-                // as this is the only way to reach the node.
-                debug_assert!(node_addr.relay_url().is_some());
+                // as this is the only way to reach the node
+                // unless relays are disabled.
+                debug_assert!(node_addr.relay_url().is_some() || relay_disabled);
                 headers.push((
                     HeaderDef::IrohNodeAddr.into(),
                     mail_builder::headers::text::Text::new(serde_json::to_string(&node_addr)?)
                         .into(),
                 ));
+
+                // Advertisements of chat realtime channels carry the topic of the chat.
+                if let Some(topic) = msg.param.get(Param::Arg) {
+                    headers.push((
+                        HeaderDef::IrohGossipTopic.get_headername(),
+                        mail_builder::headers::raw::Raw::new(topic.to_string()).into(),
+                    ));
+                }
             }
             SystemMessage::CallAccepted => {
                 headers.push((
@@ -1950,6 +2001,21 @@ pub(crate) fn wrap_encrypted_part(encrypted: String) -> MimePart<'static> {
     )
 }
 
+/// Takes the rendered signed part and its detached signature
+/// and wraps them into an OpenPGP/MIME `multipart/signed` part
+/// as defined in <https://www.rfc-editor.org/rfc/rfc3156#section-5>.
+pub(crate) fn wrap_signed_part(signed: String, signature: String) -> MimePart<'static> {
+    MimePart::new(
+        "multipart/signed; protocol=\"application/pgp-signature\"; micalg=pgp-sha256",
+        vec![
+            // The signed part is already rendered
+            // and must be kept byte for byte for the signature to stay valid.
+            MimePart::raw(signed),
+            MimePart::new("application/pgp-signature", signature),
+        ],
+    )
+}
+
 fn add_headers_to_encrypted_part(
     message: MimePart<'static>,
     unprotected_headers: &[(&'static str, HeaderType<'static>)],
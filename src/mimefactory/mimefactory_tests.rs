@@ -15,7 +15,7 @@
 use crate::chatlist::Chatlist;
 use crate::constants;
 use crate::contact::{Origin, import_vcard};
-use crate::headerdef::HeaderDef;
+use crate::headerdef::{HeaderDef, HeaderDefMap};
 use crate::message;
 use crate::mimeparser::MimeMessage;
 use crate::receive_imf::receive_imf;
@@ -914,3 +914,58 @@ async fn test_fallback_to_seipdv1() -> Result<()> {
 
     Ok(())
 }
+
+/// Tests sending unencrypted messages as OpenPGP/MIME `multipart/signed`.
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_sign_unencrypted() -> Result<()> {
+    let mut tcm = TestContextManager::new();
+    let alice = &tcm.alice().await;
+    let bob = &tcm.bob().await;
+    alice.allow_unencrypted().await?;
+    bob.allow_unencrypted().await?;
+    alice.set_config_bool(Config::SignUnencrypted, true).await?;
+
+    let bob_id = alice.add_or_lookup_address_contact_id(bob).await;
+    let chat_id = ChatId::create_for_contact(alice, bob_id).await?;
+    let sent = alice.send_text(chat_id, "Hello!").await;
+    let parsed = mailparse::parse_mail(sent.payload().as_bytes())?;
+    assert_eq!(parsed.ctype.mimetype, "multipart/signed");
+    assert_eq!(
+        parsed.subparts[1].ctype.mimetype,
+        "application/pgp-signature"
+    );
+    assert!(
+        parsed
+            .headers
+            .get_header_value(HeaderDef::Subject)
+            .is_some()
+    );
+
+    let sent_msg = Message::load_from_db(alice, sent.sender_msg_id).await?;
+    let info = sent_msg.get_crypto_info(alice).await?;
+    assert_eq!(info.encryption, message::EncryptionType::None);
+    assert!(info.signature_valid);
+    assert!(info.signer_matches_from);
+
+    let msg = bob.recv_msg(&sent).await;
+    assert_eq!(msg.get_text(), "Hello!");
+    assert!(!msg.get_showpadlock());
+    let info = msg.get_crypto_info(bob).await?;
+    assert_eq!(info.encryption, message::EncryptionType::None);
+    assert!(info.signature_valid);
+    assert!(info.signer_matches_from);
+    let info_text = msg.id.get_info(bob).await?;
+    assert!(info_text.contains("Signature: valid\n"));
+
+    // Without the option, unencrypted messages are not signed.
+    alice
+        .set_config_bool(Config::SignUnencrypted, false)
+        .await?;
+    let sent = alice.send_text(chat_id, "Unsigned").await;
+    let parsed = mailparse::parse_mail(sent.payload().as_bytes())?;
+    assert_eq!(parsed.ctype.mimetype, "multipart/mixed");
+    let msg = bob.recv_msg(&sent).await;
+    assert!(!msg.get_crypto_info(bob).await?.signature_valid);
+
+    Ok(())
+}
@@ -20,14 +20,17 @@
 use crate::constants;
 use crate::contact::{ContactId, import_public_key};
 use crate::context::Context;
-use crate::decrypt::{self, validate_detached_signature};
+use crate::decrypt::{self, get_detached_signature, validate_detached_signature};
 use crate::dehtml::dehtml;
 use crate::download::PostMsgMetadata;
 use crate::events::EventType;
 use crate::headerdef::{HeaderDef, HeaderDefMap};
 use crate::key::{self, DcKey, Fingerprint, SignedPublicKey};
 use crate::log::warn;
-use crate::message::{self, Message, MsgId, Viewtype, get_vcard_summary, set_msg_failed};
+use crate::message::{
+    self, CryptoInfo, DeliveryStatus, EncryptionType, Message, MsgId, Viewtype, get_vcard_summary,
+    set_msg_failed,
+};
 use crate::param::{Param, Params};
 use crate::simplify::{SimplifiedText, simplify};
 use crate::sync::SyncItems;
@@ -97,6 +100,9 @@ pub(crate) struct MimeMessage {
     /// this is `None`.
     pub signature: Option<(Fingerprint, HashSet<Fingerprint>)>,
 
+    /// Cryptographic details of the message to be saved for message info.
+    pub(crate) crypto_info: CryptoInfo,
+
     /// The addresses for which there was a gossip header
     /// and their respective gossiped keys.
     pub gossiped_keys: BTreeMap<String, GossipedKey>,
@@ -337,6 +343,7 @@ pub(crate) async fn from_bytes(context: &Context, body: &[u8]) -> Result<Self> {
         let mut from = from.context("No from in message")?;
 
         let mut gossiped_keys = Default::default();
+        let mut header_protection = false;
 
         let from_is_not_self_addr = !context.is_self_addr(&from.addr).await?;
 
@@ -458,26 +465,20 @@ pub(crate) async fn from_bytes(context: &Context, body: &[u8]) -> Result<Self> {
             }
             None => None,
         } {
-            for issuer_fingerprint in signature.issuer_fingerprint() {
-                let issuer_fingerprint =
-                    crate::key::Fingerprint::from(issuer_fingerprint.clone()).hex();
-                if let Some(public_key_bytes) = context
-                    .sql
-                    .query_row_optional(
-                        "SELECT public_key
-                         FROM public_keys
-                         WHERE fingerprint=?",
-                        (&issuer_fingerprint,),
-                        |row| {
-                            let bytes: Vec<u8> = row.get(0)?;
-                            Ok(bytes)
-                        },
-                    )
-                    .await?
-                {
-                    let public_key = SignedPublicKey::from_slice(&public_key_bytes)?;
-                    public_keyring.push(public_key)
-                }
+            let issuer_fingerprints = signature
+                .issuer_fingerprint()
+                .into_iter()
+                .map(|fingerprint| crate::key::Fingerprint::from(fingerprint.clone()));
+            public_keyring.extend(load_public_keys(context, issuer_fingerprints).await?);
+        } else if decrypted_msg.is_none()
+            && let Some(signature) = mail.as_ref().ok().and_then(get_detached_signature)
+        {
+            // Unencrypted `multipart/signed` message, e.g. from a classic email client.
+            // The key may be known from a certificate imported before.
+            match crate::pgp::detached_signature_issuers(&signature) {
+                Ok(issuer_fingerprints) => public_keyring
+                    .extend(load_public_keys(context, issuer_fingerprints.into_iter()).await?),
+                Err(err) => warn!(context, "Cannot parse detached signature: {err:#}."),
             }
         }
 
@@ -487,6 +488,11 @@ pub(crate) async fn from_bytes(context: &Context, body: &[u8]) -> Result<Self> {
             HashMap::new()
         };
 
+        // Valid signatures of an unencrypted `multipart/signed` message.
+        // They are only reported in the message info
+        // and do not make the message displayed as encrypted.
+        let mut unencrypted_signatures = HashSet::new();
+
         let mail = mail.as_ref().map(|mail| {
             let (content, signatures_detached) = validate_detached_signature(mail, &public_keyring)
                 .unwrap_or((mail, Default::default()));
@@ -496,10 +502,20 @@ pub(crate) async fn from_bytes(context: &Context, body: &[u8]) -> Result<Self> {
                     .map(|fp| (fp, Vec::new()))
                     .collect::<HashMap<_, _>>();
                 signatures.extend(signatures_detached);
+            } else {
+                unencrypted_signatures = signatures_detached;
             }
             content
         });
 
+        let encryption = if !is_encrypted {
+            EncryptionType::None
+        } else if expected_sender_fingerprint.is_some() {
+            EncryptionType::SharedSecret
+        } else {
+            EncryptionType::PublicKey
+        };
+
         if let Some(expected_sender_fingerprint) = expected_sender_fingerprint {
             ensure!(
                 !signatures.is_empty(),
@@ -516,6 +532,9 @@ pub(crate) async fn from_bytes(context: &Context, body: &[u8]) -> Result<Self> {
         }
 
         if let (Ok(mail), true) = (mail, is_encrypted) {
+            header_protection = mail.ctype.params.contains_key("hp")
+                || mail.ctype.params.contains_key("protected-headers");
+
             if !signatures.is_empty() {
                 // Unsigned "Subject" mustn't be prepended to messages shown as encrypted
                 // (<https://github.com/deltachat/deltachat-core-rust/issues/1790>).
@@ -617,6 +636,19 @@ pub(crate) async fn from_bytes(context: &Context, body: &[u8]) -> Result<Self> {
             };
         }
 
+        let crypto_info = CryptoInfo {
+            encryption,
+            signer_fingerprints: signatures
+                .keys()
+                .chain(unencrypted_signatures.iter())
+                .cloned()
+                .collect(),
+            signature_valid: !signatures.is_empty() || !unencrypted_signatures.is_empty(),
+            signer_matches_from: false,
+            header_protection,
+            gossip_addrs: gossiped_keys.keys().cloned().collect(),
+        };
+
         let signature = signatures
             .into_iter()
             .last()
@@ -646,6 +678,7 @@ pub(crate) async fn from_bytes(context: &Context, body: &[u8]) -> Result<Self> {
 
             // only non-empty if it was a valid autocrypt message
             signature,
+            crypto_info,
             autocrypt_fingerprint,
             gossiped_keys,
             is_forwarded: false,
@@ -1821,8 +1854,11 @@ fn process_delivery_status(
         context: &Context,
         report: &mailparse::ParsedMail<'_>,
     ) -> Result<Option<DeliveryReport>> {
-        // Assume failure.
-        let mut failure = true;
+        let mut recipients = Vec::new();
+
+        // Assume failure if there is no action.
+        let mut any_action = false;
+        let mut any_failed = false;
 
         if let Some(status_part) = report.subparts.get(1) {
             // RFC 3464 defines `message/delivery-status`
@@ -1842,18 +1878,67 @@ fn process_delivery_status(
             // Skip per-message fields.
             let (_, sz) = mailparse::parse_headers(&status_body)?;
 
-            // Parse first set of per-recipient fields
-            if let Some(status_body) = status_body.get(sz..) {
-                let (status_fields, _) = mailparse::parse_headers(status_body)?;
-                if let Some(action) = status_fields.get_first_value("action") {
-                    if action != "failed" {
-                        info!(context, "DSN with {:?} action", action);
-                        failure = false;
+            // Parse sets of per-recipient fields separated by empty lines.
+            let mut rest = status_body.get(sz..).unwrap_or_default();
+            let mut any_fields = false;
+            loop {
+                let (status_fields, sz) = match mailparse::parse_headers(rest) {
+                    Ok((status_fields, sz)) if !status_fields.is_empty() => (status_fields, sz),
+                    Ok(_) => break,
+                    Err(err) if any_fields => {
+                        warn!(
+                            context,
+                            "Failed to parse DSN per-recipient fields: {err:#}."
+                        );
+                        break;
                     }
-                } else {
+                    Err(err) => return Err(err.into()),
+                };
+                any_fields = true;
+                rest = rest.get(sz..).unwrap_or_default();
+
+                let Some(action) = status_fields.get_first_value("action") else {
                     warn!(context, "DSN without action");
+                    continue;
+                };
+                any_action = true;
+                let status = match action.to_lowercase().as_str() {
+                    "failed" => DeliveryStatus::Failed,
+                    "delayed" => DeliveryStatus::Delayed,
+                    "delivered" | "relayed" | "expanded" => DeliveryStatus::Delivered,
+                    _ => {
+                        warn!(context, "DSN with unknown {action:?} action");
+                        continue;
+                    }
+                };
+                if status == DeliveryStatus::Failed {
+                    any_failed = true;
+                } else {
+                    info!(context, "DSN with {:?} action", action);
                 }
-            } else {
+
+                // `Final-Recipient` and `Original-Recipient` fields
+                // have the form `rfc822; alice@example.org`.
+                let Some(addr) = status_fields
+                    .get_first_value("final-recipient")
+                    .or_else(|| status_fields.get_first_value("original-recipient"))
+                    .map(|recipient| dsn_field_value(&recipient).to_lowercase())
+                    .filter(|addr| addr.contains('@'))
+                else {
+                    warn!(context, "DSN without recipient");
+                    continue;
+                };
+                let reason = status_fields
+                    .get_first_value("diagnostic-code")
+                    .or_else(|| status_fields.get_first_value("status"))
+                    .map(|reason| dsn_field_value(&reason).to_string());
+                recipients.push(RecipientReport {
+                    addr,
+                    status,
+                    reason,
+                });
+            }
+            if !any_fields {
                 warn!(context, "DSN without per-recipient fields");
             }
         } else {
@@ -1876,7 +1961,8 @@ fn process_delivery_status(
             {
                 return Ok(Some(DeliveryReport {
                     rfc724_mid: original_message_id,
-                    failure,
+                    failure: any_failed || !any_action,
+                    recipients,
                 }));
             }
 
@@ -1973,6 +2059,7 @@ async fn heuristically_parse_ndn(&mut self, context: &Context) {
                     self.delivery_report = Some(DeliveryReport {
                         rfc724_mid: original_message_id,
                         failure: true,
+                        recipients: Vec::new(),
                     })
                 }
             }
@@ -1997,6 +2084,12 @@ pub async fn handle_reports(&self, context: &Context, from_id: ContactId, parts:
             }
         }
 
+        if let Some(delivery_report) = &self.delivery_report
+            && let Err(err) = handle_dsn(context, delivery_report, self.timestamp_sent).await
+        {
+            warn!(context, "Could not handle DSN: {err:#}.");
+        }
+
         if let Some(delivery_report) = &self.delivery_report
             && delivery_report.failure
         {
@@ -2159,6 +2252,60 @@ pub(crate) struct Report {
 pub(crate) struct DeliveryReport {
     pub rfc724_mid: String,
     pub failure: bool,
+
+    /// Per-recipient delivery status.
+    pub recipients: Vec<RecipientReport>,
+}
+
+/// Per-recipient fields of a Delivery Status Notification.
+#[derive(Debug)]
+pub(crate) struct RecipientReport {
+    /// Address of the recipient.
+    pub addr: String,
+
+    pub status: DeliveryStatus,
+
+    /// `Diagnostic-Code` or `Status` field.
+    pub reason: Option<String>,
+}
+
+/// Returns the value of a DSN field without the type,
+/// e.g. `alice@example.org` for `rfc822; alice@example.org`.
+fn dsn_field_value(value: &str) -> &str {
+    value
+        .split_once(';')
+        .map_or(value, |(_type, value)| value)
+        .trim()
+        .trim_start_matches('<')
+        .trim_end_matches('>')
+}
+
+/// Loads known public keys with the given fingerprints
+/// to validate signatures made by them.
+async fn load_public_keys(
+    context: &Context,
+    fingerprints: impl Iterator<Item = crate::key::Fingerprint>,
+) -> Result<Vec<SignedPublicKey>> {
+    let mut public_keys = Vec::new();
+    for fingerprint in fingerprints {
+        if let Some(public_key_bytes) = context
+            .sql
+            .query_row_optional(
+                "SELECT public_key
+                 FROM public_keys
+                 WHERE fingerprint=?",
+                (fingerprint.hex(),),
+                |row| {
+                    let bytes: Vec<u8> = row.get(0)?;
+                    Ok(bytes)
+                },
+            )
+            .await?
+        {
+            public_keys.push(SignedPublicKey::from_slice(&public_key_bytes)?);
+        }
+    }
+    Ok(public_keys)
 }
 
 pub(crate) fn parse_message_ids(ids: &str) -> Vec<String> {
@@ -2215,7 +2362,11 @@ fn is_protected(key: &str) -> bool {
 pub(crate) fn is_hidden(key: &str) -> bool {
     matches!(
         key,
-        "chat-user-avatar" | "chat-group-avatar" | "chat-delete" | "chat-edit"
+        "chat-user-avatar"
+            | "chat-group-avatar"
+            | "chat-delete"
+            | "chat-edit"
+            | "chat-webxdc-replace"
     )
 }
 
@@ -2512,6 +2663,54 @@ async fn handle_mdn(
     Ok(())
 }
 
+/// Records per-recipient delivery status after a DSN (delivery-status-notification) arrived.
+async fn handle_dsn(context: &Context, report: &DeliveryReport, timestamp: i64) -> Result<()> {
+    if report.rfc724_mid.is_empty() || report.recipients.is_empty() {
+        return Ok(());
+    }
+
+    let msgs = context
+        .sql
+        .query_map_vec(
+            "SELECT id, chat_id FROM msgs WHERE rfc724_mid=? AND from_id=1",
+            (&report.rfc724_mid,),
+            |row| {
+                let msg_id: MsgId = row.get(0)?;
+                let chat_id: ChatId = row.get(1)?;
+                Ok((msg_id, chat_id))
+            },
+        )
+        .await?;
+
+    for (msg_id, chat_id) in msgs {
+        for recipient in &report.recipients {
+            // Reports may arrive out of order,
+            // a late report about delay does not replace the final status.
+            context
+                .sql
+                .execute(
+                    "INSERT INTO msgs_delivery (msg_id, addr, status, reason, timestamp)
+                     VALUES (?, ?, ?, ?, ?)
+                     ON CONFLICT(msg_id, addr) DO UPDATE
+                     SET status=excluded.status, reason=excluded.reason, timestamp=excluded.timestamp
+                     WHERE excluded.status!=? OR msgs_delivery.status=?",
+                    (
+                        msg_id,
+                        &recipient.addr,
+                        recipient.status,
+                        recipient.reason.as_deref().unwrap_or_default(),
+                        timestamp,
+                        DeliveryStatus::Delayed,
+                        DeliveryStatus::Delayed,
+                    ),
+                )
+                .await?;
+        }
+        context.emit_msgs_changed(chat_id, msg_id);
+    }
+    Ok(())
+}
+
 /// Marks a message as failed after an ndn (non-delivery-notification) arrived.
 /// Where appropriate, also adds an info message telling the user which of the recipients of a group message failed.
 async fn handle_ndn(
@@ -11,20 +11,21 @@
 use tokio_io_timeout::TimeoutStream;
 
 use crate::context::Context;
-use crate::net::session::SessionStream;
-use crate::net::tls::{SpkiHashStore, TlsSessionStore};
 use crate::sql::Sql;
 use crate::tools::time;
 
 pub(crate) mod dns;
+pub(crate) mod doh;
 pub(crate) mod http;
 pub(crate) mod proxy;
 pub(crate) mod session;
 pub(crate) mod tls;
+pub(crate) mod traffic;
 
 use dns::lookup_host_with_cache;
 pub use http::{Response as HttpResponse, read_url, read_url_blob};
-use tls::wrap_tls;
+pub use tls::SpkiPin;
+pub use traffic::{TrafficProtocol, TrafficStats};
 
 /// Connection, write and read timeout.
 ///
@@ -122,34 +123,6 @@ pub(crate) async fn connect_tcp_inner(
     Ok(Box::pin(timeout_stream))
 }
 
-/// Attempts to establish TLS connection
-/// given the result of the hostname to address resolution.
-pub(crate) async fn connect_tls_inner(
-    addr: SocketAddr,
-    host: &str,
-    strict_tls: bool,
-    alpn: &str,
-    tls_session_store: &TlsSessionStore,
-    spki_hash_store: &SpkiHashStore,
-    sql: &Sql,
-) -> Result<impl SessionStream + 'static> {
-    let use_sni = true;
-    let tcp_stream = connect_tcp_inner(addr).await?;
-    let tls_stream = wrap_tls(
-        strict_tls,
-        host,
-        addr.port(),
-        use_sni,
-        alpn,
-        tcp_stream,
-        tls_session_store,
-        spki_hash_store,
-        sql,
-    )
-    .await?;
-    Ok(tls_stream)
-}
-
 /// Runs connection attempt futures.
 ///
 /// Accepts iterator of connection attempt futures
@@ -14,6 +14,9 @@
 //! It can be thought of as an extension
 //! of the system resolver.
 //!
+//! Instead of the system resolver, in-memory cache may use
+//! DNS-over-HTTPS endpoints if configured, see [`super::doh`].
+//!
 //! Persistent `dns_cache` SQL table is used to collect
 //! all IP addresses ever seen for the hostname
 //! together with the timestamp
@@ -41,6 +44,7 @@
 //! retrieving them from in-memory cache is used.
 
 use anyhow::{Context as _, Result, ensure};
+use num_traits::FromPrimitive;
 use std::collections::HashMap;
 use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
 use std::str::FromStr;
@@ -48,9 +52,12 @@
 use tokio::net::lookup_host;
 use tokio::time::timeout;
 
-use super::load_connection_timestamp;
+use super::{doh, load_connection_timestamp};
+use crate::config::Config;
+use crate::constants::DohMode;
 use crate::context::Context;
 use crate::log::warn;
+use crate::net::proxy::ensure_direct_connection_allowed;
 use crate::tools::time;
 
 /// Inserts entry into DNS cache
@@ -83,7 +90,15 @@ pub(crate) async fn prune_dns_cache(context: &Context) -> Result<()> {
     Ok(())
 }
 
-/// Map from hostname to IP addresses.
+/// Map from DNS-over-HTTPS mode and hostname to IP addresses.
+type LookupHostCache = HashMap<(DohMode, String), Vec<IpAddr>>;
+
+/// In-memory cache of DNS lookup results.
+///
+/// The cache is shared by all accounts,
+/// so results are kept separately for each DNS-over-HTTPS mode.
+/// Otherwise an account requiring DNS-over-HTTPS could use results
+/// another account got from the system resolver.
 ///
 /// NOTE: sync RwLock is used, so it must not be held across `.await`
 /// to avoid deadlocks.
@@ -91,25 +106,59 @@ pub(crate) async fn prune_dns_cache(context: &Context) -> Result<()> {
 /// <https://docs.rs/tokio/1.40.0/tokio/sync/struct.Mutex.html#which-kind-of-mutex-should-you-use>
 /// and
 /// <https://stackoverflow.com/questions/63712823/why-do-i-get-a-deadlock-when-using-tokio-with-a-stdsyncmutex>.
-static LOOKUP_HOST_CACHE: LazyLock<parking_lot::RwLock<HashMap<String, Vec<IpAddr>>>> =
+static LOOKUP_HOST_CACHE: LazyLock<parking_lot::RwLock<LookupHostCache>> =
     LazyLock::new(Default::default);
 
 /// Wrapper for `lookup_host` that returns IP addresses.
-async fn lookup_ips(host: impl tokio::net::ToSocketAddrs) -> Result<impl Iterator<Item = IpAddr>> {
+pub(crate) async fn lookup_ips(
+    host: impl tokio::net::ToSocketAddrs,
+) -> Result<impl Iterator<Item = IpAddr>> {
     Ok(lookup_host(host)
         .await
         .context("DNS lookup failure")?
         .map(|addr| addr.ip()))
 }
 
+/// Resolves the hostname with the resolvers configured for the context.
+///
+/// DNS-over-HTTPS is tried first if enabled,
+/// the system resolver is used unless DNS-over-HTTPS is required.
+async fn resolve(
+    context: &Context,
+    hostname: &str,
+    port: u16,
+    doh_mode: DohMode,
+) -> Result<Vec<IpAddr>> {
+    if let Ok(ip) = hostname.parse::<IpAddr>() {
+        return Ok(vec![ip]);
+    }
+
+    if doh_mode != DohMode::Off {
+        match Box::pin(doh::lookup(context, hostname)).await {
+            Ok(res) => return Ok(res),
+            Err(err) if doh_mode == DohMode::Preferred => {
+                warn!(
+                    context,
+                    "DNS-over-HTTPS lookup of {hostname} failed, using system resolver: {err:#}."
+                );
+            }
+            Err(err) => return Err(err.context("DNS-over-HTTPS lookup failure")),
+        }
+    }
+    Ok(lookup_ips((hostname, port)).await?.collect())
+}
+
 async fn lookup_host_with_memory_cache(
     context: &Context,
     hostname: &str,
     port: u16,
 ) -> Result<Vec<IpAddr>> {
+    let doh_mode =
+        DohMode::from_i32(context.get_config_int(Config::DohMode).await?).unwrap_or_default();
+    let key = (doh_mode, hostname.to_string());
     let stale_result = {
         let rwlock_read_guard = LOOKUP_HOST_CACHE.read();
-        rwlock_read_guard.get(hostname).cloned()
+        rwlock_read_guard.get(&key).cloned()
     };
     if let Some(stale_result) = stale_result {
         // Revalidate the cache in the background.
@@ -117,9 +166,9 @@ async fn lookup_host_with_memory_cache(
             let context = context.clone();
             let hostname = hostname.to_string();
             tokio::spawn(async move {
-                match lookup_ips((hostname.clone(), port)).await {
+                match resolve(&context, &hostname, port, doh_mode).await {
                     Ok(res) => {
-                        LOOKUP_HOST_CACHE.write().insert(hostname, res.collect());
+                        LOOKUP_HOST_CACHE.write().insert(key, res);
                     }
                     Err(err) => {
                         warn!(
@@ -141,15 +190,13 @@ async fn lookup_host_with_memory_cache(
             context,
             "No memory-cached DNS resolution for {hostname} available, waiting for the resolver."
         );
-        let res: Vec<IpAddr> = lookup_ips((hostname, port)).await?.collect();
+        let res: Vec<IpAddr> = resolve(context, hostname, port, doh_mode).await?;
 
         // Insert initial result into the cache.
         //
         // There may already be a result from a parallel
         // task stored, overwriting it is not a problem.
-        LOOKUP_HOST_CACHE
-            .write()
-            .insert(hostname.to_string(), res.clone());
+        LOOKUP_HOST_CACHE.write().insert(key, res.clone());
         Ok(res)
     }
 }
@@ -745,7 +792,7 @@ pub(crate) async fn update_connect_timestamp(
     ])
 });
 
-async fn lookup_cache(
+pub(crate) async fn lookup_cache(
     context: &Context,
     host: &str,
     port: u16,
@@ -832,6 +879,14 @@ pub(crate) async fn lookup_host_with_cache(
     alpn: &str,
     load_cache: bool,
 ) -> Result<Vec<SocketAddr>> {
+    // Onion service names must not be leaked to DNS resolvers,
+    // see <https://www.rfc-editor.org/rfc/rfc7686>.
+    ensure!(
+        !is_onion_host(hostname),
+        "{hostname} is an onion service, enable Tor mode to connect to it"
+    );
+    ensure_direct_connection_allowed(context, hostname).await?;
+
     let now = time();
     let resolved_addrs = match lookup_host_and_update_cache(context, hostname, port, now).await {
         Ok(res) => {
@@ -872,6 +927,14 @@ pub(crate) async fn lookup_host_with_cache(
     Ok(addrs)
 }
 
+/// Returns true if the host is a Tor onion service.
+pub(crate) fn is_onion_host(hostname: &str) -> bool {
+    hostname
+        .trim_end_matches('.')
+        .to_ascii_lowercase()
+        .ends_with(".onion")
+}
+
 /// Merges results received from DNS with cached results.
 ///
 /// At most 10 results are returned.
@@ -1129,6 +1192,27 @@ async fn test_lookup_cache() {
         );
     }
 
+    #[test]
+    fn test_is_onion_host() {
+        assert!(is_onion_host("example.onion"));
+        assert!(is_onion_host("imap.Example.ONION."));
+        assert!(!is_onion_host("onion"));
+        assert!(!is_onion_host("example.org"));
+        assert!(!is_onion_host("onion.example.org"));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_lookup_onion_host() -> Result<()> {
+        let t = &TestContext::new().await;
+        let load_cache = true;
+        assert!(
+            lookup_host_with_cache(t, "example.onion", 993, "imap", load_cache)
+                .await
+                .is_err()
+        );
+        Ok(())
+    }
+
     #[test]
     fn test_merge_with_cache() {
         let first_addr = IpAddr::V4(Ipv4Addr::new(192, 168, 1, 1));
@@ -0,0 +1,392 @@
+//! DNS-over-HTTPS resolver.
+//!
+//! Implements a minimal [RFC 8484](https://www.rfc-editor.org/rfc/rfc8484) client
+//! that only asks for A and AAAA records.
+//! Queries are sent with HTTP POST to the endpoints from `DohUrls` config
+//! in the configured order until one of them succeeds.
+//!
+//! The resolver is used as a replacement of the system resolver
+//! by the in-memory DNS cache in [`crate::net::dns`],
+//! so results are cached the same way.
+//! Endpoint hostnames themselves are always resolved with the system resolver.
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::time::Duration;
+
+use anyhow::{Context as _, Result, bail, ensure, format_err};
+use tokio::time::timeout;
+
+use crate::config::Config;
+use crate::context::Context;
+use crate::log::warn;
+use crate::net::http::post_dns_message;
+
+/// Timeout for a single DNS-over-HTTPS request.
+///
+/// Should be smaller than [`crate::net::TIMEOUT`]
+/// so there is time left to try other endpoints.
+const DOH_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Resource record type of IPv4 addresses.
+const TYPE_A: u16 = 1;
+
+/// Resource record type of IPv6 addresses.
+const TYPE_AAAA: u16 = 28;
+
+/// Internet class of resource records.
+const CLASS_IN: u16 = 1;
+
+/// Returns configured DNS-over-HTTPS endpoint URLs.
+async fn load_endpoints(context: &Context) -> Result<Vec<String>> {
+    let urls = context
+        .get_config(Config::DohUrls)
+        .await?
+        .unwrap_or_default();
+    Ok(urls
+        .split('\n')
+        .map(|url| url.trim())
+        .filter(|url| !url.is_empty())
+        .map(|url| url.to_string())
+        .collect())
+}
+
+/// Resolves the hostname into IP addresses
+/// using the configured DNS-over-HTTPS endpoints.
+pub(crate) async fn lookup(context: &Context, hostname: &str) -> Result<Vec<IpAddr>> {
+    let endpoints = load_endpoints(context).await?;
+    let mut first_error = None;
+    for url in endpoints {
+        match timeout(DOH_TIMEOUT, lookup_with_endpoint(context, &url, hostname)).await {
+            Ok(Ok(res)) => return Ok(res),
+            Ok(Err(err)) => {
+                warn!(
+                    context,
+                    "DNS-over-HTTPS lookup of {hostname} with {url} failed: {err:#}."
+                );
+                first_error.get_or_insert(err);
+            }
+            Err(_) => {
+                warn!(
+                    context,
+                    "DNS-over-HTTPS lookup of {hostname} with {url} timed out."
+                );
+                first_error.get_or_insert_with(|| format_err!("DNS-over-HTTPS lookup timeout"));
+            }
+        }
+    }
+    Err(first_error.unwrap_or_else(|| format_err!("No DNS-over-HTTPS endpoints configured")))
+}
+
+/// Asks a single endpoint for A and AAAA records of the hostname.
+async fn lookup_with_endpoint(context: &Context, url: &str, hostname: &str) -> Result<Vec<IpAddr>> {
+    let (res_a, res_aaaa) = tokio::join!(
+        query(context, url, hostname, TYPE_A),
+        query(context, url, hostname, TYPE_AAAA)
+    );
+    let res = match (res_a, res_aaaa) {
+        (Ok(mut res), Ok(res_aaaa)) => {
+            res.extend(res_aaaa);
+            res
+        }
+        (Ok(res), Err(err)) | (Err(err), Ok(res)) => {
+            warn!(
+                context,
+                "DNS-over-HTTPS query for {hostname} failed partially: {err:#}."
+            );
+            res
+        }
+        (Err(err), Err(_)) => return Err(err),
+    };
+    ensure!(!res.is_empty(), "No addresses found for {hostname}");
+    Ok(res)
+}
+
+async fn query(context: &Context, url: &str, hostname: &str, qtype: u16) -> Result<Vec<IpAddr>> {
+    let query = build_query(hostname, qtype)?;
+    let response = post_dns_message(context, url, query).await?;
+    parse_response(&response, qtype)
+}
+
+/// Builds DNS query message for the given hostname and record type.
+fn build_query(hostname: &str, qtype: u16) -> Result<Vec<u8>> {
+    let mut query = Vec::new();
+
+    // Header. ID is set to 0 as recommended by RFC 8484 for caching,
+    // only "recursion desired" flag is set, there is one question.
+    query.extend_from_slice(&0u16.to_be_bytes());
+    query.extend_from_slice(&0x0100u16.to_be_bytes());
+    query.extend_from_slice(&1u16.to_be_bytes());
+    query.extend_from_slice(&[0; 6]);
+
+    // Question.
+    for label in hostname.trim_end_matches('.').split('.') {
+        let len = u8::try_from(label.len())
+            .ok()
+            .filter(|len| (1..=63).contains(len))
+            .with_context(|| format!("Invalid hostname {hostname:?}"))?;
+        query.push(len);
+        query.extend_from_slice(label.as_bytes());
+    }
+    query.push(0);
+    ensure!(query.len() <= 12 + 255, "Hostname {hostname:?} is too long");
+    query.extend_from_slice(&qtype.to_be_bytes());
+    query.extend_from_slice(&CLASS_IN.to_be_bytes());
+    Ok(query)
+}
+
+/// Reader of DNS message fields.
+struct Reader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn read_bytes(&mut self, len: usize) -> Result<&'a [u8]> {
+        let end = self.pos.checked_add(len).context("Overflow")?;
+        let bytes = self
+            .buf
+            .get(self.pos..end)
+            .context("Truncated DNS message")?;
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn read_u8(&mut self) -> Result<u8> {
+        let [byte] = self.read_bytes(1)?.try_into()?;
+        Ok(byte)
+    }
+
+    fn read_u16(&mut self) -> Result<u16> {
+        Ok(u16::from_be_bytes(self.read_bytes(2)?.try_into()?))
+    }
+
+    /// Skips a possibly compressed domain name.
+    fn skip_name(&mut self) -> Result<()> {
+        loop {
+            let len = self.read_u8()?;
+            if len == 0 {
+                return Ok(());
+            }
+            if len & 0xC0 == 0xC0 {
+                // Compression pointer, the name ends here.
+                self.read_u8()?;
+                return Ok(());
+            }
+            self.read_bytes(len.into())?;
+        }
+    }
+}
+
+/// Extracts IP addresses of the given record type from DNS response message.
+///
+/// Owner names of the records are not checked,
+/// so addresses following a CNAME chain are returned as well.
+fn parse_response(response: &[u8], qtype: u16) -> Result<Vec<IpAddr>> {
+    let mut reader = Reader {
+        buf: response,
+        pos: 0,
+    };
+    let _id = reader.read_u16()?;
+    let flags = reader.read_u16()?;
+    ensure!(flags & 0x8000 != 0, "DNS message is not a response");
+    match flags & 0x000F {
+        0 => {}
+        3 => bail!("Domain name does not exist"),
+        rcode => bail!("DNS server returned error code {rcode}"),
+    }
+    let qdcount = reader.read_u16()?;
+    let ancount = reader.read_u16()?;
+    let _nscount = reader.read_u16()?;
+    let _arcount = reader.read_u16()?;
+
+    for _ in 0..qdcount {
+        reader.skip_name()?;
+        reader.read_bytes(4)?;
+    }
+
+    let mut res = Vec::new();
+    for _ in 0..ancount {
+        reader.skip_name()?;
+        let rtype = reader.read_u16()?;
+        let class = reader.read_u16()?;
+        let _ttl = reader.read_bytes(4)?;
+        let rdlength = reader.read_u16()?;
+        let rdata = reader.read_bytes(rdlength.into())?;
+        if rtype != qtype || class != CLASS_IN {
+            continue;
+        }
+        match rtype {
+            TYPE_A => {
+                let octets: [u8; 4] = rdata.try_into().context("Invalid A record")?;
+                res.push(IpAddr::V4(Ipv4Addr::from(octets)));
+            }
+            TYPE_AAAA => {
+                let octets: [u8; 16] = rdata.try_into().context("Invalid AAAA record")?;
+                res.push(IpAddr::V6(Ipv6Addr::from(octets)));
+            }
+            _ => {}
+        }
+    }
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    use super::*;
+    use crate::constants::DohMode;
+    use crate::net::dns::lookup_host_with_cache;
+    use crate::test_utils::TestContext;
+
+    /// Builds a response to the query with an A record
+    /// pointing to the question name with a compression pointer.
+    fn build_response(query: &[u8], addrs: &[Ipv4Addr]) -> Vec<u8> {
+        let mut response = query.to_vec();
+        response[2..4].copy_from_slice(&0x8180u16.to_be_bytes());
+        response[6..8].copy_from_slice(&(addrs.len() as u16).to_be_bytes());
+        for addr in addrs {
+            response.extend_from_slice(&[0xC0, 12]);
+            response.extend_from_slice(&TYPE_A.to_be_bytes());
+            response.extend_from_slice(&CLASS_IN.to_be_bytes());
+            response.extend_from_slice(&300u32.to_be_bytes());
+            response.extend_from_slice(&4u16.to_be_bytes());
+            response.extend_from_slice(&addr.octets());
+        }
+        response
+    }
+
+    #[test]
+    fn test_build_query() {
+        let query = build_query("example.org.", TYPE_AAAA).unwrap();
+        assert_eq!(
+            query,
+            b"\x00\x00\x01\x00\x00\x01\x00\x00\x00\x00\x00\x00\
+              \x07example\x03org\x00\x00\x1c\x00\x01"
+        );
+
+        assert!(build_query("example..org", TYPE_A).is_err());
+        assert!(build_query(&"a".repeat(64), TYPE_A).is_err());
+        assert!(build_query(&["a"; 200].join("."), TYPE_A).is_err());
+    }
+
+    #[test]
+    fn test_parse_response() {
+        let query = build_query("example.org", TYPE_A).unwrap();
+        let addrs = [Ipv4Addr::new(192, 0, 2, 1), Ipv4Addr::new(192, 0, 2, 2)];
+        let response = build_response(&query, &addrs);
+        assert_eq!(
+            parse_response(&response, TYPE_A).unwrap(),
+            vec![IpAddr::V4(addrs[0]), IpAddr::V4(addrs[1])]
+        );
+        assert!(parse_response(&response, TYPE_AAAA).unwrap().is_empty());
+
+        // Queries are not responses.
+        assert!(parse_response(&query, TYPE_A).is_err());
+
+        // Truncated response.
+        assert!(parse_response(&response[..response.len() - 1], TYPE_A).is_err());
+
+        // NXDOMAIN.
+        let mut response = build_response(&query, &[]);
+        response[3] |= 3;
+        assert!(parse_response(&response, TYPE_A).is_err());
+    }
+
+    /// Starts a local DNS-over-HTTP server
+    /// that resolves all A queries into 192.0.2.1
+    /// and returns its URL.
+    async fn start_doh_server() -> Result<String> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let url = format!("http://{}/dns-query", listener.local_addr()?);
+        tokio::spawn(async move {
+            while let Ok((mut stream, _addr)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let mut buf = Vec::new();
+                    let mut chunk = [0; 1024];
+                    let (header_len, content_length) = loop {
+                        let n = stream.read(&mut chunk).await.unwrap();
+                        assert!(n > 0);
+                        buf.extend_from_slice(&chunk[..n]);
+                        if let Some(pos) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+                            let headers = String::from_utf8_lossy(&buf[..pos]).to_lowercase();
+                            // The request target may be in absolute form.
+                            assert!(headers.starts_with("post "));
+                            let target = headers.split(' ').nth(1).unwrap();
+                            assert_eq!(target.parse::<hyper::Uri>().unwrap().path(), "/dns-query");
+                            assert!(headers.contains("content-type: application/dns-message"));
+                            let content_length: usize = headers
+                                .lines()
+                                .find_map(|line| line.strip_prefix("content-length: "))
+                                .unwrap()
+                                .parse()
+                                .unwrap();
+                            break (pos + 4, content_length);
+                        }
+                    };
+                    while buf.len() < header_len + content_length {
+                        let n = stream.read(&mut chunk).await.unwrap();
+                        assert!(n > 0);
+                        buf.extend_from_slice(&chunk[..n]);
+                    }
+                    let query = &buf[header_len..header_len + content_length];
+                    let qtype =
+                        u16::from_be_bytes([query[query.len() - 4], query[query.len() - 3]]);
+                    let addrs = if qtype == TYPE_A {
+                        vec![Ipv4Addr::new(192, 0, 2, 1)]
+                    } else {
+                        vec![]
+                    };
+                    let body = build_response(query, &addrs);
+                    let head = format!(
+                        "HTTP/1.1 200 OK\r\n\
+                         Content-Type: application/dns-message\r\n\
+                         Content-Length: {}\r\n\r\n",
+                        body.len()
+                    );
+                    stream.write_all(head.as_bytes()).await.unwrap();
+                    stream.write_all(&body).await.unwrap();
+                });
+            }
+        });
+        Ok(url)
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_doh_lookup() -> Result<()> {
+        let t = &TestContext::new().await;
+        let url = start_doh_server().await?;
+
+        // Unreachable endpoints are skipped.
+        t.set_config(
+            Config::DohUrls,
+            Some(&format!("http://127.0.0.1:1/dns-query\n{url}")),
+        )
+        .await?;
+        assert_eq!(
+            lookup(t, "doh.example.org").await?,
+            vec![IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1))]
+        );
+
+        // Plain HTTP is only allowed for loopback addresses.
+        t.set_config(Config::DohUrls, Some("http://192.0.2.2/dns-query"))
+            .await?;
+        assert!(lookup(t, "doh.example.org").await.is_err());
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_lookup_host_with_doh() -> Result<()> {
+        let t = &TestContext::new().await;
+        let url = start_doh_server().await?;
+        t.set_config(Config::DohUrls, Some(&url)).await?;
+        t.set_config_u32(Config::DohMode, DohMode::Required as u32)
+            .await?;
+
+        let load_cache = false;
+        let addrs = lookup_host_with_cache(t, "doh-only.example.org", 443, "", load_cache).await?;
+        assert_eq!(addrs, vec!["192.0.2.1:443".parse()?]);
+        Ok(())
+    }
+}
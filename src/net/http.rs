@@ -1,5 +1,8 @@
 //! # HTTP module.
 
+use std::net::SocketAddr;
+use std::sync::Arc;
+
 use anyhow::{Context as _, Result, anyhow, bail};
 use bytes::Bytes;
 use http_body_util::BodyExt;
@@ -11,9 +14,12 @@
 use crate::blob::BlobObject;
 use crate::context::Context;
 use crate::log::warn;
+use crate::net::dns::lookup_ips;
 use crate::net::proxy::ProxyConfig;
 use crate::net::session::SessionStream;
 use crate::net::tls::wrap_rustls;
+use crate::net::traffic::{MeteredStream, TrafficCounters, TrafficProtocol};
+use crate::net::{connect_tcp_inner, run_connection_attempts};
 use crate::tools::time;
 
 /// User-Agent for HTTP requests if a resource usage policy requires it.
@@ -49,9 +55,42 @@ async fn get_http_sender<B>(
     B::Data: Send,
     B::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
 {
+    let proxy_config_opt = ProxyConfig::load(context).await?;
+    get_http_sender_with_proxy(context, parsed_url, proxy_config_opt).await
+}
+
+async fn get_http_sender_with_proxy<B>(
+    context: &Context,
+    parsed_url: hyper::Uri,
+    proxy_config_opt: Option<ProxyConfig>,
+) -> Result<hyper::client::conn::http1::SendRequest<B>>
+where
+    B: hyper::body::Body + 'static + Send,
+    B::Data: Send,
+    B::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    let counters = context.traffic_metrics.counters(0, TrafficProtocol::Http);
+    let res = connect_http_stream(context, &parsed_url, proxy_config_opt, counters.clone()).await;
+    counters.add_connection_attempt(res.is_err());
+    let stream = res?;
+
+    let io = TokioIo::new(stream);
+    let (sender, conn) = hyper::client::conn::http1::handshake(io).await?;
+    tokio::task::spawn(conn);
+
+    Ok(sender)
+}
+
+/// Returns a plaintext or TLS stream for the URL,
+/// counting the traffic with the given counters.
+async fn connect_http_stream(
+    context: &Context,
+    parsed_url: &hyper::Uri,
+    proxy_config_opt: Option<ProxyConfig>,
+    counters: Arc<TrafficCounters>,
+) -> Result<Box<dyn SessionStream>> {
     let scheme = parsed_url.scheme_str().context("URL has no scheme")?;
     let host = parsed_url.host().context("URL has no host")?;
-    let proxy_config_opt = ProxyConfig::load(context).await?;
 
     let stream: Box<dyn SessionStream> = match scheme {
         "http" => {
@@ -66,10 +105,10 @@ async fn get_http_sender<B>(
                 let proxy_stream = proxy_config
                     .connect(context, host, port, load_cache)
                     .await?;
-                Box::new(proxy_stream)
+                Box::new(MeteredStream::new(proxy_stream, counters))
             } else {
                 let tcp_stream = crate::net::connect_tcp(context, host, port, load_cache).await?;
-                Box::new(tcp_stream)
+                Box::new(MeteredStream::new(tcp_stream, counters))
             }
         }
         "https" => {
@@ -80,6 +119,7 @@ async fn get_http_sender<B>(
                 let proxy_stream = proxy_config
                     .connect(context, host, port, load_cache)
                     .await?;
+                let proxy_stream = MeteredStream::new(proxy_stream, counters);
                 let tls_stream = wrap_rustls(
                     host,
                     port,
@@ -94,6 +134,7 @@ async fn get_http_sender<B>(
                 Box::new(tls_stream)
             } else {
                 let tcp_stream = crate::net::connect_tcp(context, host, port, load_cache).await?;
+                let tcp_stream = MeteredStream::new(tcp_stream, counters);
                 let tls_stream = wrap_rustls(
                     host,
                     port,
@@ -110,12 +151,46 @@ async fn get_http_sender<B>(
         }
         _ => bail!("Unknown URL scheme"),
     };
+    Ok(stream)
+}
 
-    let io = TokioIo::new(stream);
-    let (sender, conn) = hyper::client::conn::http1::handshake(io).await?;
-    tokio::task::spawn(conn);
+/// Returns a plaintext or TLS stream to the DNS-over-HTTPS endpoint.
+///
+/// Endpoint hostname is resolved with the system resolver
+/// rather than with [`crate::net::dns::lookup_host_with_cache`],
+/// because resolving it may need DNS-over-HTTPS itself.
+async fn connect_dns_endpoint(
+    context: &Context,
+    parsed_url: &hyper::Uri,
+    counters: Arc<TrafficCounters>,
+) -> Result<Box<dyn SessionStream>> {
+    let scheme = parsed_url.scheme_str().context("URL has no scheme")?;
+    let host = parsed_url.host().context("URL has no host")?;
+    let port = parsed_url
+        .port_u16()
+        .unwrap_or(if scheme == "https" { 443 } else { 80 });
 
-    Ok(sender)
+    let connection_futures = lookup_ips((host.trim_start_matches('[').trim_end_matches(']'), port))
+        .await?
+        .map(|ip| connect_tcp_inner(SocketAddr::new(ip, port)));
+    let tcp_stream = run_connection_attempts(connection_futures).await?;
+    let tcp_stream = MeteredStream::new(tcp_stream, counters);
+    if scheme != "https" {
+        return Ok(Box::new(tcp_stream));
+    }
+    let use_sni = true;
+    let tls_stream = wrap_rustls(
+        host,
+        port,
+        use_sni,
+        "",
+        tcp_stream,
+        &context.tls_session_store,
+        &context.spki_hash_store,
+        &context.sql,
+    )
+    .await?;
+    Ok(Box::new(tls_stream))
 }
 
 /// Converts the URL to expiration and stale timestamps.
@@ -463,6 +538,60 @@ pub(crate) async fn post_form<T: Serialize + ?Sized>(
     Ok(bytes)
 }
 
+/// Sends a DNS query to the DNS-over-HTTPS endpoint
+/// as defined in [RFC 8484](https://www.rfc-editor.org/rfc/rfc8484)
+/// and returns the DNS response message.
+///
+/// The request is never sent over a proxy,
+/// because DNS-over-HTTPS may be needed to resolve the proxy hostname.
+/// Plain HTTP is only allowed for loopback addresses,
+/// e.g. for a local DNS-over-HTTPS proxy.
+pub(crate) async fn post_dns_message(
+    context: &Context,
+    url: &str,
+    query: Vec<u8>,
+) -> Result<Vec<u8>> {
+    let parsed_url = url
+        .parse::<hyper::Uri>()
+        .with_context(|| format!("Failed to parse URL {url:?}"))?;
+    let scheme = parsed_url.scheme_str().context("URL has no scheme")?;
+    let host = parsed_url.host().context("URL has no host")?;
+    let is_loopback = host
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .parse::<std::net::IpAddr>()
+        .is_ok_and(|ip| ip.is_loopback());
+    if scheme != "https" && !(scheme == "http" && is_loopback) {
+        bail!("DNS-over-HTTPS requests to non-HTTPS URLs are not allowed");
+    }
+
+    let counters = context.traffic_metrics.counters(0, TrafficProtocol::Http);
+    let res = connect_dns_endpoint(context, &parsed_url, counters.clone()).await;
+    counters.add_connection_attempt(res.is_err());
+    let io = TokioIo::new(res?);
+    let (mut sender, conn) = hyper::client::conn::http1::handshake(io).await?;
+    tokio::task::spawn(conn);
+
+    let authority = parsed_url
+        .authority()
+        .context("URL has no authority")?
+        .clone();
+    let request = hyper::Request::post(parsed_url)
+        .header(hyper::header::HOST, authority.as_str())
+        .header("content-type", "application/dns-message")
+        .header("accept", "application/dns-message")
+        .body(http_body_util::Full::new(Bytes::from(query)))?;
+    let response = sender.send_request(request).await?;
+    if !response.status().is_success() {
+        bail!(
+            "The server returned a non-successful response code: {}",
+            response.status().as_u16()
+        );
+    }
+    let body = response.collect().await?.to_bytes();
+    Ok(body.to_vec())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
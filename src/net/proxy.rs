@@ -3,7 +3,10 @@
 //! Delta Chat supports HTTP(S) CONNECT, SOCKS5 and Shadowsocks protocols.
 
 use std::fmt;
+use std::net::Ipv4Addr;
 use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
 
 use anyhow::{Context as _, Result, bail, format_err};
 use base64::Engine;
@@ -14,20 +17,32 @@
 use fast_socks5::util::target_addr::ToTargetAddr;
 use percent_encoding::{NON_ALPHANUMERIC, percent_encode, utf8_percent_encode};
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
-use tokio::net::TcpStream;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
 use tokio_io_timeout::TimeoutStream;
 use url::Url;
 
 use crate::config::Config;
 use crate::context::Context;
-use crate::net::connect_tcp;
+use crate::events::EventType;
+use crate::log::{LogExt, warn};
 use crate::net::session::SessionStream;
 use crate::net::tls::wrap_rustls;
+use crate::net::{connect_tcp, update_connection_history};
 use crate::sql::Sql;
+use crate::tools::{create_id, time};
 
 /// Default SOCKS5 port according to [RFC 1928](https://tools.ietf.org/html/rfc1928).
 pub const DEFAULT_SOCKS_PORT: u16 = 1080;
 
+/// SOCKS5 proxy of the local Tor daemon,
+/// used in Tor mode if no SOCKS5 proxy is configured.
+pub const DEFAULT_TOR_PROXY_URL: &str = "socks5://127.0.0.1:9050";
+
+/// Application protocol stored in `connection_history`
+/// for successful connections to proxy servers.
+const PROXY_ALPN: &str = "proxy";
+
 #[derive(Debug, Clone)]
 pub struct ShadowsocksConfig {
     pub server_config: shadowsocks::config::ServerConfig,
@@ -115,11 +130,7 @@ async fn connect(
         target_port: u16,
         load_dns_cache: bool,
     ) -> Result<Socks5Stream<Pin<Box<TimeoutStream<TcpStream>>>>> {
-        let hostname = match &self.host {
-            url::Host::Domain(domain) => domain.to_string(),
-            url::Host::Ipv4(addr) => addr.to_string(),
-            url::Host::Ipv6(addr) => addr.to_string(),
-        };
+        let hostname = host_to_string(&self.host);
 
         let tcp_stream = connect_tcp(context, &hostname, self.port, load_dns_cache)
             .await
@@ -174,6 +185,15 @@ pub enum ProxyConfig {
     Shadowsocks(ShadowsocksConfig),
 }
 
+/// Converts the host into a string that can be resolved.
+fn host_to_string(host: &url::Host) -> String {
+    match host {
+        url::Host::Domain(domain) => domain.to_string(),
+        url::Host::Ipv4(addr) => addr.to_string(),
+        url::Host::Ipv6(addr) => addr.to_string(),
+    }
+}
+
 /// Constructs HTTP/1.1 `CONNECT` request for HTTP(S) proxy.
 fn http_connect_request(host: &str, port: u16, auth: Option<(&str, &str)>) -> String {
     // According to <https://datatracker.ietf.org/doc/html/rfc7230#section-5.4>
@@ -368,29 +388,114 @@ async fn migrate_socks_config(sql: &Sql) -> Result<()> {
     }
 
     /// Reads proxy configuration from the database.
+    ///
+    /// Returns the proxy that should be tried first,
+    /// see [`ProxyConfig::load_all`].
     pub async fn load(context: &Context) -> Result<Option<Self>> {
+        Ok(Self::load_all(context).await?.into_iter().next())
+    }
+
+    /// Reads all configured proxies from the database
+    /// in the order they should be tried.
+    ///
+    /// The proxy through which the last successful connection was made comes first,
+    /// other proxies follow in the configured order.
+    /// Returns an empty list if proxy is disabled.
+    ///
+    /// In Tor mode only SOCKS5 proxies are returned,
+    /// falling back to [`DEFAULT_TOR_PROXY_URL`],
+    /// so the list is never empty.
+    pub(crate) async fn load_all(context: &Context) -> Result<Vec<Self>> {
         Self::migrate_socks_config(&context.sql)
             .await
             .context("Failed to migrate legacy SOCKS config")?;
 
-        let enabled = context.get_config_bool(Config::ProxyEnabled).await?;
-        if !enabled {
-            return Ok(None);
-        }
-
         let proxy_url = context
             .get_config(Config::ProxyUrl)
             .await?
             .unwrap_or_default();
-        let proxy_url = proxy_url
-            .split_once('\n')
-            .map_or(proxy_url.clone(), |(first_url, _rest)| {
-                first_url.to_string()
+
+        if context.get_config_bool(Config::TorMode).await? {
+            let mut proxies: Vec<Self> = proxy_url
+                .split('\n')
+                .filter_map(|url| Self::from_url(url).ok())
+                .filter(|proxy_config| matches!(proxy_config, Self::Socks5(_)))
+                .collect();
+            if proxies.is_empty() {
+                proxies.push(Self::from_url(DEFAULT_TOR_PROXY_URL)?);
+            }
+            return Ok(proxies);
+        }
+
+        let enabled = context.get_config_bool(Config::ProxyEnabled).await?;
+        if !enabled {
+            return Ok(Vec::new());
+        }
+        let mut proxies = Vec::new();
+        for (i, url) in proxy_url.split('\n').enumerate() {
+            match Self::from_url(url) {
+                Ok(proxy_config) => proxies.push(proxy_config),
+                // The first proxy is the one selected by the user,
+                // it is an error if it cannot be used.
+                Err(err) if i == 0 => return Err(err.context("Failed to parse proxy URL")),
+                Err(err) => warn!(context, "Skipping invalid proxy URL: {err:#}."),
+            }
+        }
+
+        if let Some((host, port)) = load_active_proxy(context).await?
+            && let Some(index) = proxies.iter().position(|proxy_config| {
+                let (proxy_host, proxy_port) = proxy_config.host_port();
+                proxy_host == host && proxy_port == port
+            })
+        {
+            let active = proxies.remove(index);
+            proxies.insert(0, active);
+        }
+        Ok(proxies)
+    }
+
+    /// Returns host and port of the proxy server.
+    ///
+    /// IPv6 addresses are returned without square brackets,
+    /// as they are passed to DNS resolution.
+    pub(crate) fn host_port(&self) -> (String, u16) {
+        match self {
+            Self::Http(http_config) | Self::Https(http_config) => {
+                (host_to_string(&http_config.host), http_config.port)
+            }
+            Self::Socks5(socks5_config) => {
+                (host_to_string(&socks5_config.host), socks5_config.port)
+            }
+            Self::Shadowsocks(ShadowsocksConfig { server_config }) => {
+                let server_addr = server_config.addr();
+                (server_addr.host(), server_addr.port())
+            }
+        }
+    }
+
+    /// Remembers successful connection through the proxy in `connection_history`.
+    ///
+    /// Emits [`EventType::ActiveProxyChanged`]
+    /// if the previous connection was made through another proxy.
+    async fn remember_success(&self, context: &Context) -> Result<()> {
+        let (host, port) = self.host_port();
+        let changed = load_active_proxy(context).await? != Some((host.clone(), port));
+        update_connection_history(context, PROXY_ALPN, &host, port, &host, time()).await?;
+        if changed {
+            info!(context, "Using proxy {host}:{port}.");
+            context.emit_event(EventType::ActiveProxyChanged {
+                proxy: format!("{host}:{port}"),
             });
-        let proxy_config = Self::from_url(&proxy_url).context("Failed to parse proxy URL")?;
-        Ok(Some(proxy_config))
+        }
+        Ok(())
     }
 
+    /// Connects to the target host through the proxy.
+    ///
+    /// If connection through this proxy fails,
+    /// other configured proxies are tried in order
+    /// and the first error is returned if all of them fail.
+    ///
     /// If `load_dns_cache` is true, loads cached DNS resolution results.
     /// Use this only if the connection is going to be protected with TLS checks.
     pub(crate) async fn connect(
@@ -399,15 +504,63 @@ pub(crate) async fn connect(
         target_host: &str,
         target_port: u16,
         load_dns_cache: bool,
+    ) -> Result<Box<dyn SessionStream>> {
+        let first_err = match self
+            .connect_single(context, target_host, target_port, load_dns_cache)
+            .await
+        {
+            Ok(stream) => {
+                self.remember_success(context).await.log_err(context).ok();
+                return Ok(stream);
+            }
+            Err(err) => err,
+        };
+        let (host, port) = self.host_port();
+        warn!(
+            context,
+            "Connection through proxy {host}:{port} failed: {first_err:#}."
+        );
+
+        for proxy_config in Self::load_all(context).await? {
+            if &proxy_config == self {
+                continue;
+            }
+            let (host, port) = proxy_config.host_port();
+            match proxy_config
+                .connect_single(context, target_host, target_port, load_dns_cache)
+                .await
+            {
+                Ok(stream) => {
+                    proxy_config
+                        .remember_success(context)
+                        .await
+                        .log_err(context)
+                        .ok();
+                    return Ok(stream);
+                }
+                Err(err) => {
+                    warn!(
+                        context,
+                        "Connection through proxy {host}:{port} failed: {err:#}."
+                    )
+                }
+            }
+        }
+        Err(first_err)
+    }
+
+    /// Connects to the target host through this proxy only.
+    async fn connect_single(
+        &self,
+        context: &Context,
+        target_host: &str,
+        target_port: u16,
+        load_dns_cache: bool,
     ) -> Result<Box<dyn SessionStream>> {
         match self {
             ProxyConfig::Http(http_config) => {
                 let load_cache = false;
-                let hostname = match &http_config.host {
-                    url::Host::Domain(domain) => domain.to_string(),
-                    url::Host::Ipv4(addr) => addr.to_string(),
-                    url::Host::Ipv6(addr) => addr.to_string(),
-                };
+                let hostname = host_to_string(&http_config.host);
                 let tcp_stream =
                     crate::net::connect_tcp(context, &hostname, http_config.port, load_cache)
                         .await?;
@@ -421,11 +574,7 @@ pub(crate) async fn connect(
             }
             ProxyConfig::Https(https_config) => {
                 let load_cache = true;
-                let hostname = match &https_config.host {
-                    url::Host::Domain(domain) => domain.to_string(),
-                    url::Host::Ipv4(addr) => addr.to_string(),
-                    url::Host::Ipv6(addr) => addr.to_string(),
-                };
+                let hostname = host_to_string(&https_config.host);
 
                 let tcp_stream =
                     crate::net::connect_tcp(context, &hostname, https_config.port, load_cache)
@@ -483,6 +632,193 @@ pub(crate) async fn connect(
     }
 }
 
+/// Returns host and port of the proxy
+/// through which the last successful connection was made.
+pub(crate) async fn load_active_proxy(context: &Context) -> Result<Option<(String, u16)>> {
+    context
+        .sql
+        .query_row_optional(
+            "SELECT host, port FROM connection_history
+             WHERE alpn=?
+             ORDER BY timestamp DESC LIMIT 1",
+            (PROXY_ALPN,),
+            |row| {
+                let host: String = row.get(0)?;
+                let port: u16 = row.get(1)?;
+                Ok((host, port))
+            },
+        )
+        .await
+}
+
+/// Returns an error if Tor mode is enabled
+/// and the host is not one of the Tor SOCKS5 proxies.
+///
+/// Must be called before making direct connections
+/// to avoid leaking DNS requests and IP address.
+pub(crate) async fn ensure_direct_connection_allowed(context: &Context, host: &str) -> Result<()> {
+    if !context.get_config_bool(Config::TorMode).await? {
+        return Ok(());
+    }
+    for proxy_config in ProxyConfig::load_all(context).await? {
+        let (proxy_host, _proxy_port) = proxy_config.host_port();
+        if proxy_host == host {
+            return Ok(());
+        }
+    }
+    bail!("Direct connection to {host} is not allowed in Tor mode");
+}
+
+/// Parses the head of HTTP/1.1 `CONNECT` request
+/// and returns the target host, port
+/// and the value of `Proxy-Authorization` header if there is one.
+fn parse_http_connect_request(request: &[u8]) -> Result<(String, u16, Option<&str>)> {
+    let request = std::str::from_utf8(request).context("CONNECT request is not valid UTF-8")?;
+    let request_line = request.lines().next().context("Empty CONNECT request")?;
+    let mut parts = request_line.split(' ');
+    if parts.next() != Some("CONNECT") {
+        bail!("Not a CONNECT request: {request_line:?}");
+    }
+    let target = parts.next().context("CONNECT request has no target")?;
+    let (host, port) = target
+        .rsplit_once(':')
+        .context("CONNECT target has no port")?;
+    // Remove square brackets around IPv6 address.
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    let port = port.parse().context("Invalid CONNECT target port")?;
+    let authorization = request.lines().skip(1).find_map(|line| {
+        let (name, value) = line.split_once(':')?;
+        name.trim()
+            .eq_ignore_ascii_case("Proxy-Authorization")
+            .then(|| value.trim())
+    });
+    Ok((host.to_string(), port, authorization))
+}
+
+/// Returns true if `Proxy-Authorization` header value
+/// contains basic authentication with given `username:password` credentials.
+///
+/// Both standard and URL-safe base64 alphabets are accepted
+/// as iroh encodes the credentials with the latter.
+fn is_basic_authorization(authorization: &str, credentials: &str) -> bool {
+    let Some(encoded) = authorization.strip_prefix("Basic ") else {
+        return false;
+    };
+    let decoded = base64::engine::general_purpose::STANDARD
+        .decode(encoded)
+        .or_else(|_| base64::engine::general_purpose::URL_SAFE.decode(encoded));
+    decoded.is_ok_and(|decoded| decoded == credentials.as_bytes())
+}
+
+/// Serves a single HTTP `CONNECT` tunnel of the local proxy,
+/// forwarding it through the configured proxies.
+///
+/// `credentials` are the expected `username:password`
+/// of the basic authentication.
+async fn handle_local_proxy_connection(
+    context: &Context,
+    mut conn: TcpStream,
+    credentials: &str,
+) -> Result<()> {
+    let mut buffer = BytesMut::with_capacity(4096);
+    loop {
+        if buffer.len() >= 4096 {
+            bail!("CONNECT request exceeded buffer size");
+        }
+        let n = conn.read_buf(&mut buffer).await?;
+        if n == 0 {
+            bail!("Unexpected end of CONNECT request");
+        }
+        if buffer.ends_with(b"\r\n\r\n") {
+            break;
+        }
+    }
+    let (host, port, authorization) = parse_http_connect_request(&buffer)?;
+    if !authorization
+        .is_some_and(|authorization| is_basic_authorization(authorization, credentials))
+    {
+        conn.write_all(b"HTTP/1.1 407 Proxy Authentication Required\r\n\r\n")
+            .await?;
+        bail!("Local proxy CONNECT request is not authorized");
+    }
+
+    let proxy_config = ProxyConfig::load(context)
+        .await?
+        .context("No proxy configured")?;
+    let load_dns_cache = false;
+    let mut stream = match proxy_config
+        .connect(context, &host, port, load_dns_cache)
+        .await
+    {
+        Ok(stream) => stream,
+        Err(err) => {
+            conn.write_all(b"HTTP/1.1 502 Bad Gateway\r\n\r\n").await?;
+            return Err(err);
+        }
+    };
+    conn.write_all(b"HTTP/1.1 200 Connection established\r\n\r\n")
+        .await?;
+    tokio::io::copy_bidirectional(&mut conn, &mut stream).await?;
+    Ok(())
+}
+
+/// Starts a local HTTP `CONNECT` proxy on the loopback interface
+/// forwarding connections through the configured proxies.
+///
+/// This is used in Tor mode for libraries that only support HTTP proxies,
+/// such as iroh connecting to the relay.
+///
+/// The proxy URL contains a random password
+/// so other local processes cannot use the proxy.
+///
+/// Returns the proxy URL and the handle of the task accepting connections.
+/// The task should be aborted once the proxy is not used anymore.
+pub(crate) async fn spawn_local_http_proxy(context: &Context) -> Result<(Url, JoinHandle<()>)> {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await?;
+    let username = "deltachat";
+    let password = create_id();
+    let url = Url::parse(&format!(
+        "http://{username}:{password}@{}",
+        listener.local_addr()?
+    ))?;
+    let credentials: Arc<str> = format!("{username}:{password}").into();
+    let context = context.clone();
+    let handle = tokio::spawn(async move {
+        loop {
+            let conn = match listener.accept().await {
+                Ok((conn, _addr)) => conn,
+                Err(err) => {
+                    // Errors such as running out of file descriptors
+                    // are likely to repeat, avoid busy-looping.
+                    warn!(context, "Local proxy failed to accept connection: {err:#}.");
+                    tokio::time::sleep(Duration::from_secs(1)).await;
+                    continue;
+                }
+            };
+            let context = context.clone();
+            let credentials = Arc::clone(&credentials);
+            tokio::spawn(async move {
+                handle_local_proxy_connection(&context, conn, &credentials)
+                    .await
+                    .context("Local proxy connection failed")
+                    .log_err(&context)
+                    .ok();
+            });
+        }
+    });
+    Ok((url, handle))
+}
+
+/// Forgets through which proxies connections were made,
+/// so the proxy configured first is tried first again.
+pub(crate) async fn forget_active_proxy(context: &Context) -> Result<()> {
+    context
+        .sql
+        .execute("DELETE FROM connection_history WHERE alpn=?", (PROXY_ALPN,))
+        .await?;
+    Ok(())
+}
+
 impl fmt::Display for Socks5Config {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
@@ -504,7 +840,7 @@ mod tests {
     use super::*;
     use crate::config::Config;
     use crate::test_utils::TestContext;
-    use std::net::{Ipv4Addr, Ipv6Addr};
+    use std::net::Ipv6Addr;
 
     #[test]
     fn test_socks5_url() {
@@ -777,4 +1113,135 @@ async fn test_socks5_migration_empty() -> Result<()> {
         );
         Ok(())
     }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_load_all_proxies() -> Result<()> {
+        let t = TestContext::new().await;
+        t.set_config(
+            Config::ProxyUrl,
+            Some("socks5://127.0.0.1:9050\nfoobar://127.0.0.1:1234\nhttp://127.0.0.1:8080"),
+        )
+        .await?;
+        assert!(ProxyConfig::load_all(&t).await?.is_empty());
+        t.set_config_bool(Config::ProxyEnabled, true).await?;
+
+        // Invalid URLs except for the first one are skipped.
+        let socks5 = ProxyConfig::from_url("socks5://127.0.0.1:9050")?;
+        let http = ProxyConfig::from_url("http://127.0.0.1:8080")?;
+        assert_eq!(
+            ProxyConfig::load_all(&t).await?,
+            vec![socks5.clone(), http.clone()]
+        );
+        assert_eq!(load_active_proxy(&t).await?, None);
+
+        // The proxy that worked last is tried first.
+        http.remember_success(&t).await?;
+        assert_eq!(
+            load_active_proxy(&t).await?,
+            Some(("127.0.0.1".to_string(), 8080))
+        );
+        assert_eq!(
+            ProxyConfig::load_all(&t).await?,
+            vec![http.clone(), socks5.clone()]
+        );
+        assert_eq!(ProxyConfig::load(&t).await?, Some(http.clone()));
+
+        // Changing the proxy list resets the order.
+        t.set_config(
+            Config::ProxyUrl,
+            Some("socks5://127.0.0.1:9050\nhttp://127.0.0.1:8080"),
+        )
+        .await?;
+        assert_eq!(load_active_proxy(&t).await?, None);
+        assert_eq!(ProxyConfig::load(&t).await?, Some(socks5));
+
+        // The first URL must be valid.
+        t.set_config(Config::ProxyUrl, Some("foobar://127.0.0.1:1234"))
+            .await?;
+        assert!(ProxyConfig::load_all(&t).await.is_err());
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_tor_mode() -> Result<()> {
+        let t = TestContext::new().await;
+        ensure_direct_connection_allowed(&t, "example.org").await?;
+
+        // Local Tor daemon is used by default.
+        t.set_config_bool(Config::TorMode, true).await?;
+        assert_eq!(
+            ProxyConfig::load_all(&t).await?,
+            vec![ProxyConfig::from_url(DEFAULT_TOR_PROXY_URL)?]
+        );
+
+        // Only SOCKS5 proxies are used, even if proxy is disabled.
+        t.set_config(
+            Config::ProxyUrl,
+            Some("http://127.0.0.1:8080\nsocks5://192.168.1.1:9150"),
+        )
+        .await?;
+        assert_eq!(
+            ProxyConfig::load_all(&t).await?,
+            vec![ProxyConfig::from_url("socks5://192.168.1.1:9150")?]
+        );
+
+        // Only connections to the Tor proxy are allowed.
+        ensure_direct_connection_allowed(&t, "192.168.1.1").await?;
+        assert!(
+            ensure_direct_connection_allowed(&t, "example.org")
+                .await
+                .is_err()
+        );
+        assert!(
+            ensure_direct_connection_allowed(&t, "127.0.0.1")
+                .await
+                .is_err()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_http_connect_request() -> Result<()> {
+        let request = http_connect_request("relay.example.org", 443, None);
+        assert_eq!(
+            parse_http_connect_request(request.as_bytes())?,
+            ("relay.example.org".to_string(), 443, None)
+        );
+        let request =
+            http_connect_request("relay.example.org", 443, Some(("aladdin", "opensesame")));
+        assert_eq!(
+            parse_http_connect_request(request.as_bytes())?,
+            (
+                "relay.example.org".to_string(),
+                443,
+                Some("Basic YWxhZGRpbjpvcGVuc2VzYW1l")
+            )
+        );
+        assert_eq!(
+            parse_http_connect_request(b"CONNECT [::1]:8443 HTTP/1.1\r\n\r\n")?,
+            ("::1".to_string(), 8443, None)
+        );
+
+        // Credentials encoded with `+` and `/` in standard base64 alphabet.
+        let credentials = "deltachat:>>>???";
+        let standard = base64::engine::general_purpose::STANDARD.encode(credentials);
+        let url_safe = base64::engine::general_purpose::URL_SAFE.encode(credentials);
+        assert_ne!(standard, url_safe);
+        assert!(is_basic_authorization(
+            &format!("Basic {standard}"),
+            credentials
+        ));
+        assert!(is_basic_authorization(
+            &format!("Basic {url_safe}"),
+            credentials
+        ));
+        assert!(!is_basic_authorization(
+            &format!("Basic {standard}"),
+            "deltachat:wrong"
+        ));
+        assert!(!is_basic_authorization(standard.as_str(), credentials));
+        assert!(parse_http_connect_request(b"GET / HTTP/1.1\r\n\r\n").is_err());
+        assert!(parse_http_connect_request(b"CONNECT example.org HTTP/1.1\r\n\r\n").is_err());
+        Ok(())
+    }
 }
@@ -5,6 +5,7 @@
 
 use anyhow::Result;
 
+use crate::config::Config;
 use crate::net::session::SessionStream;
 use crate::sql::Sql;
 use crate::tools::time;
@@ -17,7 +18,7 @@
 use danger::CustomCertificateVerifier;
 
 mod spki;
-pub use spki::SpkiHashStore;
+pub use spki::{SpkiHashStore, SpkiPin};
 
 #[expect(clippy::too_many_arguments)]
 pub async fn wrap_tls<'a>(
@@ -148,10 +149,20 @@ pub async fn wrap_rustls<'a>(
     config.resumption = resumption;
     config.enable_sni = use_sni;
 
+    let (spki_hash, strict_pinning) = match spki_hash_store.get_spki_hash(hostname, sql).await? {
+        Some((spki_hash, true)) => (Some(spki_hash), true),
+        Some((spki_hash, false)) => (
+            Some(spki_hash),
+            sql.get_raw_config_bool(Config::StrictCertificatePinning.as_ref())
+                .await?,
+        ),
+        None => (None, false),
+    };
     config
         .dangerous()
         .set_certificate_verifier(Arc::new(CustomCertificateVerifier::new(
-            spki_hash_store.get_spki_hash(hostname, sql).await?,
+            spki_hash,
+            strict_pinning,
         )));
 
     let tls = tokio_rustls::TlsConnector::from(Arc::new(config));
@@ -17,15 +17,20 @@ pub(super) struct CustomCertificateVerifier {
 
     /// Expected SPKI hash as a base64 of SHA-256.
     spki_hash: Option<String>,
+
+    /// Whether to reject certificates with SPKI hash
+    /// different from the expected one.
+    strict_pinning: bool,
 }
 
 impl CustomCertificateVerifier {
-    pub(super) fn new(spki_hash: Option<String>) -> Self {
+    pub(super) fn new(spki_hash: Option<String>, strict_pinning: bool) -> Self {
         let root_cert_store =
             RootCertStore::from_iter(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
         Self {
             root_cert_store,
             spki_hash,
+            strict_pinning,
         }
     }
 }
@@ -53,6 +58,18 @@ fn verify_server_cert(
 
         let provider = rustls::crypto::ring::default_provider();
 
+        if self.strict_pinning
+            && let Some(hash) = &self.spki_hash
+        {
+            let received_hash = spki_hash(&spki);
+            if received_hash != *hash {
+                return Err(rustls::Error::General(format!(
+                    "Certificate key of {} has changed: pinned SPKI hash is {hash}, but server presented {received_hash}",
+                    server_name.to_str()
+                )));
+            }
+        }
+
         if let ServerName::DnsName(dns_name) = server_name
             && dns_name.as_ref().starts_with("_")
         {
@@ -120,3 +137,54 @@ fn supported_verify_schemes(&self) -> Vec<rustls::SignatureScheme> {
             .supported_schemes()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use rustls::client::danger::ServerCertVerifier;
+
+    use super::*;
+
+    /// Tests that strict pinning rejects a certificate with a changed key
+    /// and names both the pinned and the presented SPKI hash in the error.
+    #[test]
+    fn test_strict_pinning_rejects_changed_key() {
+        let certificate =
+            CertificateDer::from(&include_bytes!("../../../test-data/tls/example.org.der")[..]);
+        let server_name = ServerName::try_from("example.org").unwrap();
+        let received_hash = spki_hash(
+            &ParsedCertificate::try_from(&certificate)
+                .unwrap()
+                .subject_public_key_info(),
+        );
+        let pinned_hash = "47DEQpj8HBSa+/TImW+5JCeuQeRkm5NMpJWZG3hSuFU=".to_string();
+
+        let verifier = CustomCertificateVerifier::new(Some(pinned_hash.clone()), true);
+        let err = verifier
+            .verify_server_cert(&certificate, &[], &server_name, &[], UnixTime::now())
+            .unwrap_err();
+        let rustls::Error::General(message) = &err else {
+            panic!("Unexpected error {err:?}");
+        };
+        assert_eq!(
+            *message,
+            format!(
+                "Certificate key of example.org has changed: pinned SPKI hash is {pinned_hash}, but server presented {received_hash}"
+            )
+        );
+
+        // Without strict pinning the self-signed certificate is checked against the root certificates.
+        let verifier = CustomCertificateVerifier::new(Some(pinned_hash), false);
+        let err = verifier
+            .verify_server_cert(&certificate, &[], &server_name, &[], UnixTime::now())
+            .unwrap_err();
+        assert!(!err.to_string().contains(&received_hash));
+
+        // The pinned key is accepted even though the certificate is self-signed.
+        let verifier = CustomCertificateVerifier::new(Some(received_hash), true);
+        assert!(
+            verifier
+                .verify_server_cert(&certificate, &[], &server_name, &[], UnixTime::now())
+                .is_ok()
+        );
+    }
+}
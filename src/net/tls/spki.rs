@@ -3,15 +3,20 @@
 //! We store hashes of Subject Public Key Info from TLS certificates
 //! after successful connection to allow connecting when
 //! server certificate expires as long as the key is not changed.
+//!
+//! Hashes can also be pinned manually.
+//! Manual pins and, if `StrictCertificatePinning` is enabled,
+//! remembered hashes are enforced: connection fails if the key changes.
 
 use std::collections::BTreeMap;
 
-use anyhow::Result;
+use anyhow::{Context as _, Result, ensure};
 use base64::Engine as _;
 use parking_lot::RwLock;
 use sha2::{Digest, Sha256};
 use tokio_rustls::rustls::pki_types::SubjectPublicKeyInfoDer;
 
+use crate::context::Context;
 use crate::sql::Sql;
 use crate::tools::time;
 
@@ -31,11 +36,59 @@ pub fn spki_hash(spki: &SubjectPublicKeyInfoDer) -> String {
     base64::engine::general_purpose::STANDARD.encode(spki_hash)
 }
 
+impl Context {
+    /// Returns SPKI hashes of TLS certificates
+    /// remembered after successful connections or pinned manually.
+    pub async fn get_pinned_certificates(&self) -> Result<Vec<SpkiPin>> {
+        self.spki_hash_store.list_pins(&self.sql).await
+    }
+
+    /// Pins the SPKI hash of the TLS certificate for the host,
+    /// e.g. a hash scanned from a QR code.
+    ///
+    /// Connections to the host fail if the server presents another key,
+    /// even if the certificate is otherwise valid.
+    pub async fn pin_certificate(&self, host: &str, spki_hash: &str) -> Result<()> {
+        self.spki_hash_store
+            .pin_spki_hash(host, spki_hash, &self.sql)
+            .await
+    }
+
+    /// Removes the SPKI hash stored for the host.
+    ///
+    /// The key of the next successfully verified certificate is remembered again.
+    pub async fn unpin_certificate(&self, host: &str) -> Result<()> {
+        self.spki_hash_store.remove_spki_hash(host, &self.sql).await
+    }
+}
+
+/// SPKI hash pinned for a host.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpkiPin {
+    /// Hostname.
+    pub host: String,
+
+    /// Base64 of SPKI SHA-256 hash.
+    pub spki_hash: String,
+
+    /// Timestamp of the last time the key was seen
+    /// or pinned manually.
+    pub timestamp: i64,
+
+    /// True if the hash was pinned manually
+    /// rather than remembered after successful connection.
+    ///
+    /// Manual pins never expire and are always enforced,
+    /// even if strict certificate pinning is disabled.
+    pub manual: bool,
+}
+
 /// Write-through cache for SPKI hashes.
 #[derive(Debug)]
 pub struct SpkiHashStore {
-    /// Map from hostnames to base64 of SHA-256 hashes.
-    pub hash_store: RwLock<BTreeMap<String, String>>,
+    /// Map from hostnames to base64 of SHA-256 hashes
+    /// and flags telling if the hash was pinned manually.
+    pub hash_store: RwLock<BTreeMap<String, (String, bool)>>,
 }
 
 impl SpkiHashStore {
@@ -45,28 +98,32 @@ pub fn new() -> Self {
         }
     }
 
-    /// Returns base64 of SPKI hash if we have previously successfully connected to given hostname.
-    pub async fn get_spki_hash(&self, hostname: &str, sql: &Sql) -> Result<Option<String>> {
-        if let Some(hash) = self.hash_store.read().get(hostname).cloned() {
-            return Ok(Some(hash));
+    /// Returns base64 of SPKI hash if we have previously successfully connected to given hostname
+    /// or the hash was pinned manually.
+    ///
+    /// The flag returned together with the hash is true for manual pins.
+    pub async fn get_spki_hash(&self, hostname: &str, sql: &Sql) -> Result<Option<(String, bool)>> {
+        if let Some(pin) = self.hash_store.read().get(hostname).cloned() {
+            return Ok(Some(pin));
         }
 
         match sql
             .query_row_optional(
-                "SELECT spki_hash FROM tls_spki WHERE host=?",
+                "SELECT spki_hash, manual FROM tls_spki WHERE host=?",
                 (hostname,),
                 |row| {
                     let spki_hash: String = row.get(0)?;
-                    Ok(spki_hash)
+                    let manual: bool = row.get(1)?;
+                    Ok((spki_hash, manual))
                 },
             )
             .await?
         {
-            Some(hash) => {
+            Some(pin) => {
                 self.hash_store
                     .write()
-                    .insert(hostname.to_string(), hash.clone());
-                Ok(Some(hash))
+                    .insert(hostname.to_string(), pin.clone());
+                Ok(Some(pin))
             }
             None => Ok(None),
         }
@@ -81,24 +138,88 @@ pub async fn save_spki(
         timestamp: i64,
     ) -> Result<()> {
         let hash = spki_hash(spki);
+        // Manual pin is kept, the connection would fail
+        // if the key did not match it.
+        let manual = sql
+            .call_write(|conn| {
+                let manual = conn.query_row(
+                    "INSERT INTO tls_spki (host, spki_hash, timestamp) VALUES (?, ?, ?)
+                     ON CONFLICT (host) DO UPDATE
+                     SET spki_hash=excluded.spki_hash, timestamp=excluded.timestamp
+                     RETURNING manual",
+                    (hostname, &hash, timestamp),
+                    |row| {
+                        let manual: bool = row.get(0)?;
+                        Ok(manual)
+                    },
+                )?;
+                Ok(manual)
+            })
+            .await?;
         self.hash_store
             .write()
-            .insert(hostname.to_string(), hash.clone());
+            .insert(hostname.to_string(), (hash, manual));
+        Ok(())
+    }
+
+    /// Pins SPKI hash for the hostname manually.
+    ///
+    /// `hash` is a base64 of SPKI SHA-256 hash as returned by [`spki_hash`].
+    pub async fn pin_spki_hash(&self, hostname: &str, hash: &str, sql: &Sql) -> Result<()> {
+        let decoded = base64::engine::general_purpose::STANDARD
+            .decode(hash)
+            .context("SPKI hash is not valid base64")?;
+        ensure!(
+            decoded.len() == 32,
+            "SPKI hash must be a base64 of SHA-256 hash"
+        );
+        ensure!(!hostname.is_empty(), "Hostname is empty");
+
         sql.execute(
-            "INSERT OR REPLACE INTO tls_spki (host, spki_hash, timestamp) VALUES (?, ?, ?)",
-            (hostname, hash, timestamp),
+            "INSERT OR REPLACE INTO tls_spki (host, spki_hash, timestamp, manual) VALUES (?, ?, ?, 1)",
+            (hostname, hash, time()),
         )
         .await?;
+        self.hash_store
+            .write()
+            .insert(hostname.to_string(), (hash.to_string(), true));
+        Ok(())
+    }
+
+    /// Removes SPKI hash of the hostname,
+    /// regardless of whether it was pinned manually.
+    pub async fn remove_spki_hash(&self, hostname: &str, sql: &Sql) -> Result<()> {
+        sql.execute("DELETE FROM tls_spki WHERE host=?", (hostname,))
+            .await?;
+        self.hash_store.write().remove(hostname);
         Ok(())
     }
 
+    /// Returns all stored SPKI hashes.
+    pub async fn list_pins(&self, sql: &Sql) -> Result<Vec<SpkiPin>> {
+        sql.query_map_vec(
+            "SELECT host, spki_hash, timestamp, manual FROM tls_spki ORDER BY host",
+            (),
+            |row| {
+                Ok(SpkiPin {
+                    host: row.get(0)?,
+                    spki_hash: row.get(1)?,
+                    timestamp: row.get(2)?,
+                    manual: row.get(3)?,
+                })
+            },
+        )
+        .await
+    }
+
     /// Removes stale entries from SPKI storage.
     pub async fn cleanup(&self, sql: &Sql) -> Result<()> {
         let now = time();
         let removed_hosts = sql
             .transaction(|transaction| {
-                let mut stmt = transaction
-                    .prepare("DELETE FROM tls_spki WHERE ? > timestamp + ? RETURNING host")?;
+                let mut stmt = transaction.prepare(
+                    "DELETE FROM tls_spki WHERE manual=0 AND ? > timestamp + ? RETURNING host",
+                )?;
                 let mut res = Vec::new();
                 for row in stmt.query_map((now, 30 * 24 * 60 * 60), |row| {
                     let host: String = row.get(0)?;
@@ -131,3 +252,64 @@ pub async fn cleanup(&self, sql: &Sql) -> Result<()> {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TestContext;
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_pin_certificate() -> Result<()> {
+        let t = &TestContext::new().await;
+        let store = &t.spki_hash_store;
+        let spki = SubjectPublicKeyInfoDer::from(b"spki".as_slice());
+        let hash = spki_hash(&spki);
+        let old_timestamp = time() - 31 * 24 * 60 * 60;
+
+        store
+            .save_spki("imap.example.org", &spki, &t.sql, old_timestamp)
+            .await?;
+        assert_eq!(
+            t.get_pinned_certificates().await?,
+            vec![SpkiPin {
+                host: "imap.example.org".to_string(),
+                spki_hash: hash.clone(),
+                timestamp: old_timestamp,
+                manual: false,
+            }]
+        );
+
+        assert!(
+            t.pin_certificate("smtp.example.org", "foobar")
+                .await
+                .is_err()
+        );
+        t.pin_certificate("smtp.example.org", &hash).await?;
+        assert_eq!(
+            store.get_spki_hash("smtp.example.org", &t.sql).await?,
+            Some((hash.clone(), true))
+        );
+
+        // Successful connection does not turn manual pin into a remembered one.
+        store
+            .save_spki("smtp.example.org", &spki, &t.sql, old_timestamp)
+            .await?;
+        store.hash_store.write().clear();
+        assert_eq!(
+            store.get_spki_hash("smtp.example.org", &t.sql).await?,
+            Some((hash.clone(), true))
+        );
+
+        // Manual pins do not expire.
+        store.cleanup(&t.sql).await?;
+        let pins = t.get_pinned_certificates().await?;
+        assert_eq!(pins.len(), 1);
+        assert_eq!(pins[0].host, "smtp.example.org");
+        assert!(pins[0].manual);
+
+        t.unpin_certificate("smtp.example.org").await?;
+        assert!(t.get_pinned_certificates().await?.is_empty());
+        assert_eq!(store.get_spki_hash("smtp.example.org", &t.sql).await?, None);
+        Ok(())
+    }
+}
@@ -0,0 +1,605 @@
+//! # Traffic accounting.
+//!
+//! Bytes sent and received, connection attempts and failures,
+//! IMAP IDLE durations and fetch latencies are counted in memory
+//! per transport and protocol.
+//! Counters are regularly added to the `traffic_stats` table
+//! which has one row per UTC day, transport and protocol,
+//! so UIs can show data usage over time.
+//!
+//! Bytes are counted on the TCP level, so TLS overhead is included.
+//! For iroh, only the size of realtime data payloads is counted.
+
+use std::collections::BTreeMap;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::task::Poll;
+use std::time::Duration;
+
+use anyhow::Result;
+use pin_project::pin_project;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+use crate::context::Context;
+use crate::net::session::SessionStream;
+use crate::tools::{Time, time, time_elapsed, usize_to_u64};
+use crate::transport::ConfiguredLoginParam;
+
+/// Days to keep traffic statistics for.
+const TRAFFIC_STATS_DAYS: i64 = 90;
+
+/// Minimum number of bytes received during a fetch
+/// to estimate the download throughput.
+///
+/// Smaller fetches are dominated by round trips.
+const THROUGHPUT_SAMPLE_MIN_BYTES: u64 = 64 * 1024;
+
+/// Minimum number of bytes received during a fetch
+/// to estimate the download throughput while it is low.
+///
+/// Downloads are limited to [`crate::download::LOW_BANDWIDTH_DOWNLOAD_LIMIT`]
+/// while bandwidth is saved, so fetches rarely reach [`THROUGHPUT_SAMPLE_MIN_BYTES`]
+/// and the estimate would never become high again.
+const LOW_THROUGHPUT_SAMPLE_MIN_BYTES: u64 = 8 * 1024;
+
+/// Download throughput in bytes per second
+/// below which bandwidth is considered low.
+///
+/// Bandwidth is considered high again when throughput
+/// exceeds twice this value, so automatic low-bandwidth mode
+/// does not flap on connections close to the threshold.
+const LOW_THROUGHPUT: u64 = 32 * 1024;
+
+/// Protocol the traffic is accounted for.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    strum_macros::AsRefStr,
+    strum_macros::EnumString,
+)]
+#[strum(serialize_all = "lowercase")]
+pub enum TrafficProtocol {
+    /// IMAP connections.
+    Imap,
+
+    /// SMTP connections.
+    Smtp,
+
+    /// HTTP(S) requests, e.g. for autoconfig, OAuth 2 or map tiles.
+    Http,
+
+    /// Realtime channels.
+    Iroh,
+}
+
+/// In-memory traffic counters of a single transport and protocol.
+#[derive(Debug, Default)]
+pub(crate) struct TrafficCounters {
+    bytes_sent: AtomicU64,
+    bytes_received: AtomicU64,
+    connection_attempts: AtomicU64,
+    connection_failures: AtomicU64,
+    idle_count: AtomicU64,
+    idle_duration_ms: AtomicU64,
+    fetch_count: AtomicU64,
+    fetch_duration_ms: AtomicU64,
+
+    /// Time spent waiting for data to read from the network in microseconds.
+    ///
+    /// This is only used to estimate the download throughput
+    /// and not stored in the database.
+    read_wait_us: AtomicU64,
+}
+
+impl TrafficCounters {
+    pub(crate) fn add_sent(&self, bytes: u64) {
+        self.bytes_sent.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    pub(crate) fn add_received(&self, bytes: u64) {
+        self.bytes_received.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    /// Returns the number of bytes received and not yet stored in the database.
+    pub(crate) fn bytes_received(&self) -> u64 {
+        self.bytes_received.load(Ordering::Relaxed)
+    }
+
+    fn add_read_wait(&self, duration: Duration) {
+        let us = duration.as_micros().try_into().unwrap_or(u64::MAX);
+        self.read_wait_us.fetch_add(us, Ordering::Relaxed);
+    }
+
+    /// Returns the total time spent waiting for data to read from the network.
+    pub(crate) fn read_wait(&self) -> Duration {
+        Duration::from_micros(self.read_wait_us.load(Ordering::Relaxed))
+    }
+
+    /// Counts a connection attempt and whether it failed.
+    pub(crate) fn add_connection_attempt(&self, failed: bool) {
+        self.connection_attempts.fetch_add(1, Ordering::Relaxed);
+        if failed {
+            self.connection_failures.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub(crate) fn add_idle(&self, duration: Duration) {
+        self.idle_count.fetch_add(1, Ordering::Relaxed);
+        self.idle_duration_ms
+            .fetch_add(duration_to_ms(duration), Ordering::Relaxed);
+    }
+
+    pub(crate) fn add_fetch(&self, duration: Duration) {
+        self.fetch_count.fetch_add(1, Ordering::Relaxed);
+        self.fetch_duration_ms
+            .fetch_add(duration_to_ms(duration), Ordering::Relaxed);
+    }
+
+    fn fields(&self) -> [&AtomicU64; 8] {
+        [
+            &self.bytes_sent,
+            &self.bytes_received,
+            &self.connection_attempts,
+            &self.connection_failures,
+            &self.idle_count,
+            &self.idle_duration_ms,
+            &self.fetch_count,
+            &self.fetch_duration_ms,
+        ]
+    }
+}
+
+fn duration_to_ms(duration: Duration) -> u64 {
+    duration.as_millis().try_into().unwrap_or(u64::MAX)
+}
+
+/// In-memory traffic counters of all transports.
+#[derive(Debug, Default)]
+pub(crate) struct TrafficMetrics {
+    counters: parking_lot::Mutex<BTreeMap<(u32, TrafficProtocol), Arc<TrafficCounters>>>,
+
+    /// True if the last download throughput estimate was low.
+    throughput_low: AtomicBool,
+}
+
+impl TrafficMetrics {
+    /// Returns counters of the transport.
+    ///
+    /// Transport ID 0 is used for traffic not related to a transport.
+    pub(crate) fn counters(
+        &self,
+        transport_id: u32,
+        protocol: TrafficProtocol,
+    ) -> Arc<TrafficCounters> {
+        self.counters
+            .lock()
+            .entry((transport_id, protocol))
+            .or_default()
+            .clone()
+    }
+
+    /// Updates the download throughput estimate
+    /// with the number of bytes received over the given duration.
+    ///
+    /// Returns true if the estimate changed between low and high.
+    pub(crate) fn record_throughput(&self, bytes: u64, duration: Duration) -> bool {
+        let was_low = self.throughput_low.load(Ordering::Relaxed);
+        let min_bytes = if was_low {
+            LOW_THROUGHPUT_SAMPLE_MIN_BYTES
+        } else {
+            THROUGHPUT_SAMPLE_MIN_BYTES
+        };
+        if bytes < min_bytes {
+            return false;
+        }
+        let throughput = bytes
+            .saturating_mul(1000)
+            .checked_div(duration_to_ms(duration))
+            .unwrap_or(u64::MAX);
+        let is_low = if was_low {
+            throughput <= LOW_THROUGHPUT.saturating_mul(2)
+        } else {
+            throughput < LOW_THROUGHPUT
+        };
+        self.throughput_low.store(is_low, Ordering::Relaxed);
+        was_low != is_low
+    }
+
+    /// Returns true if the last download throughput estimate was low.
+    pub(crate) fn is_throughput_low(&self) -> bool {
+        self.throughput_low.load(Ordering::Relaxed)
+    }
+
+    /// Adds in-memory counters to the `traffic_stats` table.
+    pub(crate) async fn flush(&self, context: &Context) -> Result<()> {
+        let day = utc_day(time());
+        let counters: Vec<_> = self
+            .counters
+            .lock()
+            .iter()
+            .map(|(key, counters)| (*key, counters.clone()))
+            .collect();
+        for ((transport_id, protocol), counters) in counters {
+            let values = counters
+                .fields()
+                .map(|counter| counter.load(Ordering::Relaxed));
+            if values.iter().all(|value| *value == 0) {
+                continue;
+            }
+            let [
+                bytes_sent,
+                bytes_received,
+                connection_attempts,
+                connection_failures,
+                idle_count,
+                idle_duration_ms,
+                fetch_count,
+                fetch_duration_ms,
+            ] = values.map(|value| i64::try_from(value).unwrap_or(i64::MAX));
+            context
+                .sql
+                .execute(
+                    "INSERT INTO traffic_stats
+                     (day, transport_id, protocol,
+                      bytes_sent, bytes_received,
+                      connection_attempts, connection_failures,
+                      idle_count, idle_duration_ms,
+                      fetch_count, fetch_duration_ms)
+                     VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+                     ON CONFLICT (day, transport_id, protocol) DO UPDATE SET
+                     bytes_sent=bytes_sent+excluded.bytes_sent,
+                     bytes_received=bytes_received+excluded.bytes_received,
+                     connection_attempts=connection_attempts+excluded.connection_attempts,
+                     connection_failures=connection_failures+excluded.connection_failures,
+                     idle_count=idle_count+excluded.idle_count,
+                     idle_duration_ms=idle_duration_ms+excluded.idle_duration_ms,
+                     fetch_count=fetch_count+excluded.fetch_count,
+                     fetch_duration_ms=fetch_duration_ms+excluded.fetch_duration_ms",
+                    (
+                        &day,
+                        transport_id,
+                        protocol.as_ref(),
+                        bytes_sent,
+                        bytes_received,
+                        connection_attempts,
+                        connection_failures,
+                        idle_count,
+                        idle_duration_ms,
+                        fetch_count,
+                        fetch_duration_ms,
+                    ),
+                )
+                .await?;
+
+            // Only subtract what was stored,
+            // counters may have been increased in the meantime.
+            for (counter, value) in counters.fields().into_iter().zip(values) {
+                counter.fetch_sub(value, Ordering::Relaxed);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Returns the UTC day of the timestamp in `YYYY-MM-DD` format.
+fn utc_day(timestamp: i64) -> String {
+    chrono::DateTime::from_timestamp(timestamp, 0)
+        .unwrap_or_default()
+        .format("%Y-%m-%d")
+        .to_string()
+}
+
+/// Returns counters for connections to the host.
+///
+/// The transport is looked up by the hostname
+/// among IMAP or SMTP servers of configured transports.
+/// Connections to unknown hosts, e.g. during configuration,
+/// are accounted to transport ID 0.
+pub(crate) async fn host_counters(
+    context: &Context,
+    protocol: TrafficProtocol,
+    host: &str,
+) -> Result<Arc<TrafficCounters>> {
+    let mut transport_id = 0;
+    if matches!(protocol, TrafficProtocol::Imap | TrafficProtocol::Smtp) {
+        for (id, param) in ConfiguredLoginParam::load_all(context).await? {
+            let servers = match protocol {
+                TrafficProtocol::Imap => &param.imap,
+                _ => &param.smtp,
+            };
+            if servers.iter().any(|server| server.connection.host == host) {
+                transport_id = id;
+                break;
+            }
+        }
+    }
+    Ok(context.traffic_metrics.counters(transport_id, protocol))
+}
+
+/// Traffic statistics of a single day, transport and protocol.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrafficStats {
+    /// UTC day in `YYYY-MM-DD` format.
+    pub day: String,
+
+    /// Transport ID, 0 for traffic not related to a transport.
+    pub transport_id: u32,
+
+    /// Address of the transport if it still exists.
+    pub addr: Option<String>,
+
+    /// Protocol.
+    pub protocol: TrafficProtocol,
+
+    /// Number of bytes sent.
+    pub bytes_sent: u64,
+
+    /// Number of bytes received.
+    pub bytes_received: u64,
+
+    /// Number of connection attempts.
+    pub connection_attempts: u64,
+
+    /// Number of failed connection attempts.
+    pub connection_failures: u64,
+
+    /// Number of IMAP IDLE commands.
+    pub idle_count: u64,
+
+    /// Total duration of IMAP IDLE commands in milliseconds.
+    pub idle_duration_ms: u64,
+
+    /// Number of IMAP fetches.
+    pub fetch_count: u64,
+
+    /// Total duration of IMAP fetches in milliseconds.
+    pub fetch_duration_ms: u64,
+}
+
+impl Context {
+    /// Returns daily traffic statistics of the last 90 days,
+    /// newest first.
+    pub async fn get_traffic_stats(&self) -> Result<Vec<TrafficStats>> {
+        self.traffic_metrics.flush(self).await?;
+        self.sql
+            .query_map_vec(
+                "SELECT s.day, s.transport_id, t.addr, s.protocol,
+                        s.bytes_sent, s.bytes_received,
+                        s.connection_attempts, s.connection_failures,
+                        s.idle_count, s.idle_duration_ms,
+                        s.fetch_count, s.fetch_duration_ms
+                 FROM traffic_stats s
+                 LEFT JOIN transports t ON t.id=s.transport_id
+                 ORDER BY s.day DESC, s.transport_id, s.protocol",
+                (),
+                |row| {
+                    let protocol: String = row.get(3)?;
+                    let get_u64 = |idx: usize| -> rusqlite::Result<u64> {
+                        let value: i64 = row.get(idx)?;
+                        Ok(u64::try_from(value).unwrap_or_default())
+                    };
+                    Ok(TrafficStats {
+                        day: row.get(0)?,
+                        transport_id: row.get(1)?,
+                        addr: row.get(2)?,
+                        protocol: protocol.parse()?,
+                        bytes_sent: get_u64(4)?,
+                        bytes_received: get_u64(5)?,
+                        connection_attempts: get_u64(6)?,
+                        connection_failures: get_u64(7)?,
+                        idle_count: get_u64(8)?,
+                        idle_duration_ms: get_u64(9)?,
+                        fetch_count: get_u64(10)?,
+                        fetch_duration_ms: get_u64(11)?,
+                    })
+                },
+            )
+            .await
+    }
+}
+
+/// Removes traffic statistics older than 90 days.
+pub(crate) async fn prune_traffic_stats(context: &Context) -> Result<()> {
+    let oldest_day = utc_day(time().saturating_sub(TRAFFIC_STATS_DAYS.saturating_mul(86400)));
+    context
+        .sql
+        .execute("DELETE FROM traffic_stats WHERE day < ?", (oldest_day,))
+        .await?;
+    Ok(())
+}
+
+/// Stream that counts bytes sent and received.
+#[derive(Debug)]
+#[pin_project]
+pub(crate) struct MeteredStream<S: SessionStream> {
+    #[pin]
+    inner: S,
+
+    counters: Arc<TrafficCounters>,
+
+    /// Time when the pending read started waiting for data.
+    read_pending_since: Option<Time>,
+}
+
+impl<S: SessionStream> MeteredStream<S> {
+    pub(crate) fn new(inner: S, counters: Arc<TrafficCounters>) -> Self {
+        Self {
+            inner,
+            counters,
+            read_pending_since: None,
+        }
+    }
+}
+
+impl<S: SessionStream> AsyncRead for MeteredStream<S> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let this = self.project();
+        let old_filled = buf.filled().len();
+        let res = this.inner.poll_read(cx, buf);
+        let n = buf.filled().len().saturating_sub(old_filled);
+        this.counters.add_received(usize_to_u64(n));
+        if res.is_pending() {
+            this.read_pending_since.get_or_insert_with(Time::now);
+        } else if let Some(pending_since) = this.read_pending_since.take() {
+            this.counters.add_read_wait(time_elapsed(&pending_since));
+        }
+        res
+    }
+}
+
+impl<S: SessionStream> AsyncWrite for MeteredStream<S> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        let this = self.project();
+        let res = this.inner.poll_write(cx, buf);
+        if let Poll::Ready(Ok(n)) = res {
+            this.counters.add_sent(usize_to_u64(n));
+        }
+        res
+    }
+
+    fn poll_flush(
+        self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        self.project().inner.poll_flush(cx)
+    }
+
+    fn poll_shutdown(
+        self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        self.project().inner.poll_shutdown(cx)
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+        bufs: &[std::io::IoSlice<'_>],
+    ) -> Poll<std::io::Result<usize>> {
+        let this = self.project();
+        let res = this.inner.poll_write_vectored(cx, bufs);
+        if let Poll::Ready(Ok(n)) = res {
+            this.counters.add_sent(usize_to_u64(n));
+        }
+        res
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.inner.is_write_vectored()
+    }
+}
+
+impl<S: SessionStream> SessionStream for MeteredStream<S> {
+    fn set_read_timeout(&mut self, timeout: Option<Duration>) {
+        self.inner.set_read_timeout(timeout)
+    }
+
+    fn peer_addr(&self) -> Result<std::net::SocketAddr> {
+        self.inner.peer_addr()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+
+    use super::*;
+    use crate::test_utils::TestContext;
+
+    #[test]
+    fn test_utc_day() {
+        assert_eq!(utc_day(0), "1970-01-01");
+        assert_eq!(utc_day(1_700_000_000), "2023-11-14");
+    }
+
+    #[test]
+    fn test_record_throughput() {
+        let metrics = TrafficMetrics::default();
+        assert!(!metrics.is_throughput_low());
+
+        // Small samples are ignored.
+        assert!(!metrics.record_throughput(1000, Duration::from_secs(10)));
+        assert!(!metrics.is_throughput_low());
+
+        // 10 KiB/s.
+        assert!(metrics.record_throughput(100 * 1024, Duration::from_secs(10)));
+        assert!(metrics.is_throughput_low());
+
+        // 50 KiB/s is not enough to leave low-bandwidth mode.
+        assert!(!metrics.record_throughput(500 * 1024, Duration::from_secs(10)));
+        assert!(metrics.is_throughput_low());
+
+        // 100 KiB/s.
+        assert!(metrics.record_throughput(1000 * 1024, Duration::from_secs(10)));
+        assert!(!metrics.is_throughput_low());
+
+        // Smaller samples are used while throughput is low,
+        // because downloads are limited then.
+        assert!(metrics.record_throughput(100 * 1024, Duration::from_secs(10)));
+        assert!(metrics.record_throughput(16 * 1024, Duration::from_millis(100)));
+        assert!(!metrics.is_throughput_low());
+        assert!(!metrics.record_throughput(16 * 1024, Duration::from_secs(10)));
+        assert!(!metrics.is_throughput_low());
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_metered_stream() -> Result<()> {
+        let t = &TestContext::new().await;
+
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        tokio::spawn(async move {
+            let (mut stream, _addr) = listener.accept().await.unwrap();
+            let mut buf = [0; 5];
+            stream.read_exact(&mut buf).await.unwrap();
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            stream.write_all(b"Hello, world!").await.unwrap();
+        });
+
+        let counters = t.traffic_metrics.counters(0, TrafficProtocol::Http);
+        let tcp_stream = Box::pin(tokio_io_timeout::TimeoutStream::new(
+            TcpStream::connect(addr).await?,
+        ));
+        let mut stream = MeteredStream::new(tcp_stream, counters.clone());
+        stream.write_all(b"Hello").await?;
+        let mut buf = Vec::new();
+        stream.read_to_end(&mut buf).await?;
+        counters.add_connection_attempt(false);
+
+        // Time waiting for the response is measured.
+        assert!(counters.read_wait() >= Duration::from_millis(100));
+
+        let stats = t.get_traffic_stats().await?;
+        assert_eq!(stats.len(), 1);
+        assert_eq!(stats[0].day, utc_day(time()));
+        assert_eq!(stats[0].transport_id, 0);
+        assert_eq!(stats[0].addr, None);
+        assert_eq!(stats[0].protocol, TrafficProtocol::Http);
+        assert_eq!(stats[0].bytes_sent, 5);
+        assert_eq!(stats[0].bytes_received, 13);
+        assert_eq!(stats[0].connection_attempts, 1);
+        assert_eq!(stats[0].connection_failures, 0);
+
+        // Flushed counters are not added twice.
+        counters.add_received(7);
+        let stats = t.get_traffic_stats().await?;
+        assert_eq!(stats[0].bytes_received, 20);
+        assert_eq!(stats[0].bytes_sent, 5);
+        Ok(())
+    }
+}
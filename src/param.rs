@@ -208,6 +208,10 @@ pub enum Param {
     /// the List-Id of the mailing list (which is also used as the group id of the chat).
     ListId = b's',
 
+    /// For Contacts: The key of the contact was imported manually by the user,
+    /// see `import_openpgp_certificate()`.
+    KeyImportedManually = b'X',
+
     /// For Contacts: timestamp of status (aka signature or footer) update.
     StatusTimestamp = b'j',
 
@@ -256,6 +260,10 @@ pub enum Param {
     /// For messages: Message text was edited.
     IsEdited = b'L',
 
+    /// For messages: Message replaces the app of a webxdc instance.
+    /// The value of this parameter is the rfc724_mid of the instance.
+    WebxdcReplaceFor = b'!',
+
     /// For info messages: Contact ID in added or removed to a group.
     ContactAddedRemoved = b'5',
 
@@ -22,32 +22,67 @@
 //! 5. Upon receiving an announcement message, other peers store the sender's [NodeAddr] in the database
 //!    (scoped per WebXDC app instance/message-id). The other peers can then join the gossip with `joinRealtimeChannel().setListener()`
 //!    and `joinRealtimeChannel().send()` just like the other peers.
+//!
+//! Realtime channels can also be bound to a chat instead of a webxdc,
+//! e.g. for typing indicators or live presence, see [`send_chat_realtime_advertisement`].
+//! The advertisement of a chat realtime channel carries the topic of the chat
+//! in the `Iroh-Gossip-Topic` header, the topic and peers are stored per chat.
+//! Chat realtime messages start with a one byte kind,
+//! [`CHAT_DATA`] for data passed to the UI and [`CHAT_TYPING`] for typing indicators.
+//!
+//! Realtime messages are ephemeral by default,
+//! [`Config::WebxdcRealtimeReliable`] enables buffering and replaying them to peers that missed them.
 
-use anyhow::{Context as _, Result, anyhow, bail};
+mod reliable;
+mod typing;
+
+use anyhow::{Context as _, Result, anyhow, bail, ensure};
 use data_encoding::BASE32_NOPAD;
 use futures_lite::StreamExt;
+use iroh::dns::DnsResolver;
 use iroh::{Endpoint, NodeAddr, NodeId, PublicKey, RelayMode, RelayUrl, SecretKey};
 use iroh_gossip::net::{Event, GOSSIP_ALPN, Gossip, GossipEvent, JoinOptions};
 use iroh_gossip::proto::TopicId;
 use parking_lot::Mutex;
-use std::collections::{BTreeSet, HashMap};
+use ratelimit::Ratelimit;
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::env;
+use std::fmt;
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6};
+use std::sync::Arc;
 use tokio::sync::{RwLock, oneshot};
 use tokio::task::JoinHandle;
+use tokio_util::task::AbortOnDropHandle;
 use url::Url;
 
 use crate::EventType;
-use crate::chat::send_msg;
+use crate::chat::{Chat, ChatId, send_msg};
 use crate::config::Config;
+use crate::contact::ContactId;
 use crate::context::Context;
 use crate::log::warn;
 use crate::message::{Message, MsgId, Viewtype};
 use crate::mimeparser::SystemMessage;
+use crate::net::proxy::spawn_local_http_proxy;
+use crate::net::traffic::TrafficProtocol;
+use crate::param::Param;
+use crate::tools::usize_to_u64;
+use crate::webxdc::WebxdcPermission;
+use reliable::{
+    Inserted, RealtimeMessage, ReliableState, SYNC_REPLAY, SYNC_REQUEST, SyncRequest, UNSEQUENCED,
+};
+pub use typing::{TYPING_TIMEOUT, join_typing, set_typing};
 
 /// The length of an ed25519 `PublicKey`, in bytes.
 const PUBLIC_KEY_LENGTH: usize = 32;
 const PUBLIC_KEY_STUB: &[u8] = "static_string".as_bytes();
 
+/// Kind of a chat realtime message carrying data for the UI.
+const CHAT_DATA: u8 = 0;
+
+/// Kind of a chat realtime message carrying a typing indicator.
+const CHAT_TYPING: u8 = 1;
+
 /// Store Iroh peer channels for the context.
 #[derive(Debug)]
 pub struct Iroh {
@@ -67,6 +102,24 @@ pub struct Iroh {
     ///
     /// This is attached to every message to work around `iroh_gossip` deduplication.
     pub(crate) public_key: PublicKey,
+
+    /// True if relays are disabled, see [`Config::IrohRelayDisabled`].
+    pub(crate) relay_disabled: bool,
+
+    /// Rate limits of typing indicators by chat.
+    typing_ratelimits: Mutex<HashMap<ChatId, Ratelimit>>,
+
+    /// Chat realtime topics advertised with the current [`Self::public_key`].
+    ///
+    /// Members identify our typing indicators by the advertised key,
+    /// so these topics can be rejoined without advertising them again.
+    advertised_chat_topics: Mutex<HashSet<TopicId>>,
+
+    /// Task of the local HTTP proxy forwarding relay connections through Tor,
+    /// see [`Config::TorMode`].
+    ///
+    /// The task is aborted when the endpoint is dropped.
+    _tor_proxy: Option<AbortOnDropHandle<()>>,
 }
 
 impl Iroh {
@@ -80,7 +133,7 @@ pub(crate) async fn close(self) -> Result<()> {
         self.router.shutdown().await.context("Closing iroh failed")
     }
 
-    /// Join a topic and create the subscriber loop for it.
+    /// Join the topic of a webxdc and create the subscriber loop for it.
     ///
     /// If there is no gossip, create it.
     ///
@@ -90,9 +143,20 @@ async fn join_and_subscribe_gossip(
         ctx: &Context,
         msg_id: MsgId,
     ) -> Result<Option<oneshot::Receiver<()>>> {
-        let topic = get_iroh_topic_for_msg(ctx, msg_id)
+        self.join_and_subscribe(ctx, ChannelOwner::Webxdc(msg_id))
+            .await
+    }
+
+    /// Join the topic of `owner` and create the subscriber loop for it.
+    async fn join_and_subscribe(
+        &self,
+        ctx: &Context,
+        owner: ChannelOwner,
+    ) -> Result<Option<oneshot::Receiver<()>>> {
+        let topic = owner
+            .topic(ctx)
             .await?
-            .with_context(|| format!("Message {msg_id} has no gossip topic"))?;
+            .with_context(|| format!("{owner} has no gossip topic"))?;
 
         // Take exclusive lock to make sure
         // no other thread can create a second gossip subscription
@@ -104,7 +168,7 @@ async fn join_and_subscribe_gossip(
             return Ok(None);
         }
 
-        let peers = get_iroh_gossip_peers(ctx, msg_id).await?;
+        let peers = owner.peers(ctx).await?;
         let node_ids = peers.iter().map(|p| p.node_id).collect::<Vec<_>>();
 
         info!(
@@ -121,6 +185,24 @@ async fn join_and_subscribe_gossip(
 
         let (join_tx, join_rx) = oneshot::channel();
 
+        let reliable = if ctx.get_config_bool(Config::WebxdcRealtimeReliable).await? {
+            let (sender, receiver) = self
+                .gossip
+                .subscribe_with_opts(
+                    reliable::sync_topic(&topic),
+                    JoinOptions::with_bootstrap(node_ids.clone()),
+                )
+                .split();
+            Some(SyncChannel {
+                state: Arc::new(Mutex::new(ReliableState::default())),
+                sender,
+                receiver,
+            })
+        } else {
+            None
+        };
+        let reliable_state = reliable.as_ref().map(|sync| sync.state.clone());
+
         let (gossip_sender, gossip_receiver) = self
             .gossip
             .subscribe_with_opts(topic, JoinOptions::with_bootstrap(node_ids))
@@ -128,38 +210,79 @@ async fn join_and_subscribe_gossip(
 
         let ctx = ctx.clone();
         let subscribe_loop = tokio::spawn(async move {
-            if let Err(e) = subscribe_loop(&ctx, gossip_receiver, topic, msg_id, join_tx).await {
+            if let Err(e) =
+                subscribe_loop(&ctx, gossip_receiver, reliable, topic, owner, join_tx).await
+            {
                 warn!(ctx, "subscribe_loop failed: {e}")
             }
         });
 
-        iroh_channels.insert(topic, ChannelState::new(subscribe_loop, gossip_sender));
+        iroh_channels.insert(
+            topic,
+            ChannelState::new(subscribe_loop, gossip_sender, reliable_state),
+        );
 
         Ok(Some(join_rx))
     }
 
     /// Add gossip peer to realtime channel if it is already active.
     pub async fn maybe_add_gossip_peer(&self, topic: TopicId, peer: NodeAddr) -> Result<()> {
-        if self.iroh_channels.read().await.get(&topic).is_some() {
+        if let Some(channel) = self.iroh_channels.read().await.get(&topic) {
             self.router.endpoint().add_node_addr(peer.clone())?;
             self.gossip.subscribe(topic, vec![peer.node_id])?;
+            if channel.reliable.is_some() {
+                self.gossip
+                    .subscribe(reliable::sync_topic(&topic), vec![peer.node_id])?;
+            }
         }
         Ok(())
     }
 
-    /// Send realtime data to the gossip swarm.
+    /// Send realtime data to the gossip swarm of a webxdc.
     pub async fn send_webxdc_realtime_data(
         &self,
         ctx: &Context,
         msg_id: MsgId,
+        data: Vec<u8>,
+    ) -> Result<()> {
+        self.send_realtime_data(ctx, ChannelOwner::Webxdc(msg_id), data)
+            .await
+    }
+
+    /// Send realtime data to the gossip swarm of `owner`.
+    async fn send_realtime_data(
+        &self,
+        ctx: &Context,
+        owner: ChannelOwner,
+        data: Vec<u8>,
+    ) -> Result<()> {
+        let ephemeral = false;
+        self.send_realtime_data_ex(ctx, owner, data, ephemeral)
+            .await
+    }
+
+    /// Send realtime data to the gossip swarm of `owner`.
+    ///
+    /// If `ephemeral` is set, the data is only sent to the direct neighbours
+    /// and never buffered or replayed, even if the channel is reliable.
+    async fn send_realtime_data_ex(
+        &self,
+        ctx: &Context,
+        owner: ChannelOwner,
         mut data: Vec<u8>,
+        ephemeral: bool,
     ) -> Result<()> {
-        let topic = get_iroh_topic_for_msg(ctx, msg_id)
+        let topic = owner
+            .topic(ctx)
             .await?
-            .with_context(|| format!("Message {msg_id} has no gossip topic"))?;
-        self.join_and_subscribe_gossip(ctx, msg_id).await?;
+            .with_context(|| format!("{owner} has no gossip topic"))?;
+        self.join_and_subscribe(ctx, owner).await?;
 
-        let seq_num = self.get_and_incr(&topic);
+        let seq_num = if ephemeral {
+            UNSEQUENCED
+        } else {
+            self.get_and_incr(&topic)
+        };
 
         let mut iroh_channels = self.iroh_channels.write().await;
         let state = iroh_channels
@@ -167,8 +290,19 @@ pub async fn send_webxdc_realtime_data(
             .context("Just created state does not exist")?;
         data.extend(seq_num.to_le_bytes());
         data.extend(self.public_key.as_bytes());
+        if let Some(reliable) = &state.reliable {
+            reliable.lock().insert(&data)?;
+        }
 
-        state.sender.broadcast(data.into()).await?;
+        let len = usize_to_u64(data.len());
+        if ephemeral {
+            state.sender.broadcast_neighbors(data.into()).await?;
+        } else {
+            state.sender.broadcast(data.into()).await?;
+        }
+        ctx.traffic_metrics
+            .counters(0, TrafficProtocol::Iroh)
+            .add_sent(len);
 
         if env::var("REALTIME_DEBUG").is_ok() {
             info!(ctx, "Sent realtime data");
@@ -189,10 +323,13 @@ fn get_and_incr(&self, topic: &TopicId) -> i32 {
     /// The address is guaranteed to have home relay URL set
     /// as it is the only way to reach the node
     /// without global discovery mechanisms.
+    /// If relays are disabled, direct addresses are kept instead.
     pub(crate) async fn get_node_addr(&self) -> Result<NodeAddr> {
         let mut addr = self.router.endpoint().node_addr().await?;
-        addr.direct_addresses = BTreeSet::new();
-        debug_assert!(addr.relay_url().is_some());
+        if !self.relay_disabled {
+            addr.direct_addresses = BTreeSet::new();
+            debug_assert!(addr.relay_url().is_some());
+        }
         Ok(addr)
     }
 
@@ -220,17 +357,112 @@ pub(crate) struct ChannelState {
     subscribe_loop: JoinHandle<()>,
 
     sender: iroh_gossip::net::GossipSender,
+
+    /// Buffered messages if the channel is reliable.
+    reliable: Option<Arc<Mutex<ReliableState>>>,
 }
 
 impl ChannelState {
-    fn new(subscribe_loop: JoinHandle<()>, sender: iroh_gossip::net::GossipSender) -> Self {
+    fn new(
+        subscribe_loop: JoinHandle<()>,
+        sender: iroh_gossip::net::GossipSender,
+        reliable: Option<Arc<Mutex<ReliableState>>>,
+    ) -> Self {
         Self {
             subscribe_loop,
             sender,
+            reliable,
+        }
+    }
+}
+
+/// Sync topic of a reliable channel, owned by the subscribe loop.
+struct SyncChannel {
+    state: Arc<Mutex<ReliableState>>,
+    sender: iroh_gossip::net::GossipSender,
+    receiver: iroh_gossip::net::GossipReceiver,
+}
+
+/// What a realtime channel is bound to.
+#[derive(Debug, Clone, Copy)]
+enum ChannelOwner {
+    /// Webxdc instance.
+    Webxdc(MsgId),
+
+    /// Chat, see [`send_chat_realtime_advertisement`].
+    Chat(ChatId),
+}
+
+impl fmt::Display for ChannelOwner {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Webxdc(msg_id) => write!(f, "Webxdc {msg_id}"),
+            Self::Chat(chat_id) => write!(f, "{chat_id}"),
         }
     }
 }
 
+impl ChannelOwner {
+    /// Returns the gossip topic of the channel.
+    async fn topic(self, ctx: &Context) -> Result<Option<TopicId>> {
+        match self {
+            Self::Webxdc(msg_id) => get_iroh_topic_for_msg(ctx, msg_id).await,
+            Self::Chat(chat_id) => get_chat_realtime_topic(ctx, chat_id).await,
+        }
+    }
+
+    /// Returns the known peers of the channel.
+    async fn peers(self, ctx: &Context) -> Result<Vec<NodeAddr>> {
+        match self {
+            Self::Webxdc(msg_id) => get_iroh_gossip_peers(ctx, msg_id).await,
+            Self::Chat(chat_id) => get_chat_realtime_peers(ctx, chat_id).await,
+        }
+    }
+
+    /// Remembers a peer of the channel.
+    async fn add_peer(self, ctx: &Context, topic: TopicId, peer: NodeId) -> Result<()> {
+        match self {
+            Self::Webxdc(msg_id) => iroh_add_peer_for_topic(ctx, msg_id, topic, peer, None).await,
+            Self::Chat(chat_id) => {
+                add_chat_realtime_peer(ctx, chat_id, topic, peer, "", None).await
+            }
+        }
+    }
+
+    /// Handles a realtime message received over the channel.
+    ///
+    /// `delivered_from` is the neighbour that delivered the message,
+    /// [`None`] for replayed messages.
+    async fn handle_message(
+        self,
+        context: &Context,
+        message: RealtimeMessage<'_>,
+        delivered_from: Option<PublicKey>,
+    ) -> Result<()> {
+        match self {
+            Self::Webxdc(msg_id) => context.emit_event(EventType::WebxdcRealtimeData {
+                msg_id,
+                data: message.payload.into(),
+            }),
+            Self::Chat(chat_id) => match message.payload.split_first() {
+                Some((&CHAT_DATA, data)) => context.emit_event(EventType::ChatRealtimeData {
+                    chat_id,
+                    data: data.into(),
+                }),
+                Some((&CHAT_TYPING, typing)) => {
+                    typing::handle_typing(context, chat_id, &message, delivered_from, typing)
+                        .await?
+                }
+                _ => warn!(
+                    context,
+                    "IROH_REALTIME: Ignoring unknown realtime message in {chat_id}."
+                ),
+            },
+        }
+        Ok(())
+    }
+}
+
 impl Context {
     /// Create iroh endpoint and gossip.
     async fn init_peer_channels(&self) -> Result<Iroh> {
@@ -238,27 +470,74 @@ async fn init_peer_channels(&self) -> Result<Iroh> {
         let secret_key = SecretKey::generate(rand_old::rngs::OsRng);
         let public_key = secret_key.public();
 
-        let relay_mode = if let Some(relay_url) = self
+        let relay_disabled = self.get_config_bool(Config::IrohRelayDisabled).await?;
+        let tor_mode = self.get_config_bool(Config::TorMode).await?;
+        let relay_url = self
             .metadata
             .read()
             .await
             .as_ref()
-            .and_then(|conf| conf.iroh_relay.clone())
-        {
+            .and_then(|conf| conf.iroh_relay.clone());
+        let relay_mode = if relay_disabled {
+            RelayMode::Disabled
+        } else if let Some(relay_url) = relay_url.clone() {
             RelayMode::Custom(RelayUrl::from(relay_url).into())
         } else {
+            // Default relays are not used in Tor mode
+            // as they are probed by iroh bypassing the proxy.
+            ensure!(
+                !tor_mode,
+                "Realtime channels need a configured relay in Tor mode"
+            );
             // FIXME: this should be RelayMode::Disabled instead.
             // Currently using default relays because otherwise Rust tests fail.
             RelayMode::Default
         };
 
-        let endpoint = Endpoint::builder()
+        let mut builder = Endpoint::builder()
             .tls_x509() // For compatibility with iroh <0.34.0
             .secret_key(secret_key)
             .alpns(vec![GOSSIP_ALPN.to_vec()])
-            .relay_mode(relay_mode)
-            .bind()
-            .await?;
+            .relay_mode(relay_mode);
+
+        let tor_proxy = if tor_mode {
+            // Iroh only supports HTTP proxies for relay connections,
+            // so they are tunneled through a local proxy forwarding them to Tor.
+            // UDP sockets are bound to the loopback interface
+            // so no direct connections to peers are made.
+            ensure!(
+                !relay_disabled,
+                "Realtime channels need a relay in Tor mode"
+            );
+
+            // Iroh network reports including captive portal check
+            // cannot be disabled and connect to the relay directly
+            // after resolving its hostname.
+            // Resolving with a nameserver on the loopback interface
+            // that does not exist makes these checks fail without leaving the device.
+            // Relay connection itself goes through the proxy
+            // and does not need name resolution.
+            ensure!(
+                relay_url
+                    .as_ref()
+                    .is_some_and(|url| matches!(url.host(), Some(url::Host::Domain(_)))),
+                "Realtime channels need a relay with a domain name in Tor mode"
+            );
+            let (proxy_url, tor_proxy) = spawn_local_http_proxy(self).await?;
+            builder = builder
+                .proxy_url(proxy_url)
+                .dns_resolver(DnsResolver::with_nameserver(SocketAddr::from((
+                    Ipv4Addr::LOCALHOST,
+                    0,
+                ))))
+                .bind_addr_v4(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 0))
+                .bind_addr_v6(SocketAddrV6::new(Ipv6Addr::LOCALHOST, 0, 0, 0));
+            Some(AbortOnDropHandle::new(tor_proxy))
+        } else {
+            None
+        };
+
+        let endpoint = builder.bind().await?;
 
         // create gossip
         // Allow messages up to 128 KB in size.
@@ -280,6 +559,10 @@ async fn init_peer_channels(&self) -> Result<Iroh> {
             sequence_numbers: Mutex::new(HashMap::new()),
             iroh_channels: RwLock::new(HashMap::new()),
             public_key,
+            relay_disabled,
+            typing_ratelimits: Mutex::new(HashMap::new()),
+            advertised_chat_topics: Mutex::new(HashSet::new()),
+            _tor_proxy: tor_proxy,
         })
     }
 
@@ -296,7 +579,7 @@ pub async fn get_peer_channels(&self) -> Option<tokio::sync::RwLockReadGuard<'_,
     pub async fn get_or_try_init_peer_channel(
         &self,
     ) -> Result<tokio::sync::RwLockReadGuard<'_, Iroh>> {
-        if !self.get_config_bool(Config::WebxdcRealtimeEnabled).await? {
+        if !is_realtime_enabled(self).await? {
             bail!("Attempt to initialize Iroh when realtime is disabled");
         }
 
@@ -357,10 +640,7 @@ pub async fn add_gossip_peer_from_header(
     instance_id: MsgId,
     node_addr: &str,
 ) -> Result<()> {
-    if !context
-        .get_config_bool(Config::WebxdcRealtimeEnabled)
-        .await?
-    {
+    if !is_realtime_enabled(context).await? {
         return Ok(());
     }
 
@@ -444,31 +724,151 @@ pub(crate) async fn get_iroh_topic_for_msg(
         .await
         .context("Couldn't restore topic from db")?
     {
-        let topic_id = TopicId::from_bytes(
-            bytes
-                .try_into()
-                .map_err(|_| anyhow!("Could not convert stored topic ID"))?,
-        );
-        Ok(Some(topic_id))
+        Ok(Some(topic_from_bytes(bytes)?))
     } else {
         Ok(None)
     }
 }
 
+/// Converts a topic ID stored in the database.
+fn topic_from_bytes(bytes: Vec<u8>) -> Result<TopicId> {
+    let topic_id = TopicId::from_bytes(
+        bytes
+            .try_into()
+            .map_err(|_| anyhow!("Could not convert stored topic ID"))?,
+    );
+    Ok(topic_id)
+}
+
+/// Get the realtime topic of a chat.
+pub(crate) async fn get_chat_realtime_topic(
+    ctx: &Context,
+    chat_id: ChatId,
+) -> Result<Option<TopicId>> {
+    ctx.sql
+        .query_get_value::<Vec<u8>>(
+            "SELECT topic FROM chat_realtime_peers WHERE chat_id=? AND public_key=?",
+            (chat_id, PUBLIC_KEY_STUB),
+        )
+        .await
+        .context("Couldn't restore chat topic from db")?
+        .map(topic_from_bytes)
+        .transpose()
+}
+
+/// Sets the realtime topic of a chat, forgetting the peers of the previous topic.
+async fn set_chat_realtime_topic(ctx: &Context, chat_id: ChatId, topic: TopicId) -> Result<()> {
+    ctx.sql
+        .transaction(move |transaction| {
+            transaction.execute(
+                "DELETE FROM chat_realtime_peers WHERE chat_id=?",
+                (chat_id,),
+            )?;
+            transaction.execute(
+                "INSERT INTO chat_realtime_peers (chat_id, topic, public_key) VALUES (?, ?, ?)",
+                (chat_id, topic.as_bytes(), PUBLIC_KEY_STUB),
+            )?;
+            Ok(())
+        })
+        .await
+}
+
+/// Cache a peers [NodeId] for the realtime topic of a chat.
+///
+/// `relay_server` is an empty string if the relay of the peer is unknown.
+/// `contact_id` is the contact that advertised the peer,
+/// peers only known from the gossip swarm do not replace advertised ones.
+async fn add_chat_realtime_peer(
+    ctx: &Context,
+    chat_id: ChatId,
+    topic: TopicId,
+    peer: NodeId,
+    relay_server: &str,
+    contact_id: Option<ContactId>,
+) -> Result<()> {
+    if let Some(contact_id) = contact_id {
+        ctx.sql
+            .execute(
+                "INSERT OR REPLACE INTO chat_realtime_peers (chat_id, topic, public_key, relay_server, contact_id) VALUES (?, ?, ?, ?, ?)",
+                (chat_id, topic.as_bytes(), peer.as_bytes(), relay_server, contact_id),
+            )
+            .await?;
+    } else {
+        ctx.sql
+            .execute(
+                "INSERT OR IGNORE INTO chat_realtime_peers (chat_id, topic, public_key, relay_server) VALUES (?, ?, ?, ?)",
+                (chat_id, topic.as_bytes(), peer.as_bytes(), relay_server),
+            )
+            .await?;
+    }
+    Ok(())
+}
+
+/// Get a list of [NodeAddr]s for the realtime topic of a chat.
+async fn get_chat_realtime_peers(ctx: &Context, chat_id: ChatId) -> Result<Vec<NodeAddr>> {
+    ctx.sql
+        .query_map_vec(
+            "SELECT public_key, relay_server FROM chat_realtime_peers WHERE chat_id=? AND public_key!=?",
+            (chat_id, PUBLIC_KEY_STUB),
+            |row| {
+                let key: Vec<u8> = row.get(0)?;
+                let server: String = row.get(1)?;
+                Ok((key, server))
+            },
+        )
+        .await?
+        .into_iter()
+        .map(|(key, server)| {
+            let server = if server.is_empty() {
+                None
+            } else {
+                Some(RelayUrl::from(Url::parse(&server)?))
+            };
+            let id = NodeId::from_bytes(
+                &key.try_into()
+                    .map_err(|_| anyhow!("Can't convert sql data to [u8; 32]"))?,
+            )?;
+            Ok(NodeAddr::from_parts(id, server, vec![]))
+        })
+        .collect()
+}
+
+/// Returns true if the realtime channel with the given topic is joined.
+async fn is_joined(ctx: &Context, topic: TopicId) -> bool {
+    match ctx.get_peer_channels().await {
+        Some(iroh) => iroh.iroh_channels.read().await.contains_key(&topic),
+        None => false,
+    }
+}
+
+/// Returns true if webxdc realtime channels are enabled
+/// and not suspended to save bandwidth.
+async fn is_realtime_enabled(ctx: &Context) -> Result<bool> {
+    let enabled = ctx.get_config_bool(Config::WebxdcRealtimeEnabled).await?;
+    Ok(enabled && !ctx.is_low_bandwidth().await?)
+}
+
 /// Send a gossip advertisement to the chat that [MsgId] belongs to.
 /// This method should be called from the frontend when `joinRealtimeChannel` is called.
 pub async fn send_webxdc_realtime_advertisement(
     ctx: &Context,
     msg_id: MsgId,
 ) -> Result<Option<oneshot::Receiver<()>>> {
-    if !ctx.get_config_bool(Config::WebxdcRealtimeEnabled).await? {
+    if !is_realtime_enabled(ctx).await? {
+        return Ok(None);
+    }
+    let webxdc = Message::load_from_db(ctx, msg_id).await?;
+    if !webxdc
+        .has_webxdc_permission(ctx, WebxdcPermission::Realtime)
+        .await?
+    {
+        warn!(ctx, "Webxdc {msg_id} has no realtime permission.");
         return Ok(None);
     }
 
     let iroh = ctx.get_or_try_init_peer_channel().await?;
     let conn = iroh.join_and_subscribe_gossip(ctx, msg_id).await?;
 
-    let webxdc = Message::load_from_db(ctx, msg_id).await?;
     let mut msg = Message::new(Viewtype::Text);
     msg.hidden = true;
     msg.param.set_cmd(SystemMessage::IrohNodeAddr);
@@ -480,9 +880,16 @@ pub async fn send_webxdc_realtime_advertisement(
 
 /// Send realtime data to other peers using iroh.
 pub async fn send_webxdc_realtime_data(ctx: &Context, msg_id: MsgId, data: Vec<u8>) -> Result<()> {
-    if !ctx.get_config_bool(Config::WebxdcRealtimeEnabled).await? {
+    if !is_realtime_enabled(ctx).await? {
         return Ok(());
     }
+    let webxdc = Message::load_from_db(ctx, msg_id).await?;
+    ensure!(
+        webxdc
+            .has_webxdc_permission(ctx, WebxdcPermission::Realtime)
+            .await?,
+        "Webxdc {msg_id} has no realtime permission."
+    );
 
     let iroh = ctx.get_or_try_init_peer_channel().await?;
     iroh.send_webxdc_realtime_data(ctx, msg_id, data).await?;
@@ -507,6 +914,218 @@ pub async fn leave_webxdc_realtime(ctx: &Context, msg_id: MsgId) -> Result<()> {
     Ok(())
 }
 
+/// Send a gossip advertisement for the realtime channel of a chat
+/// and join the channel.
+///
+/// Unlike webxdc realtime channels, chat realtime channels are not bound to a message.
+/// The topic is created by the first member advertising the channel
+/// and sent in the advertisement.
+/// If members advertise different topics concurrently, all of them switch to the smaller one.
+pub async fn send_chat_realtime_advertisement(
+    ctx: &Context,
+    chat_id: ChatId,
+) -> Result<Option<oneshot::Receiver<()>>> {
+    if !is_realtime_enabled(ctx).await? {
+        return Ok(None);
+    }
+    ensure!(
+        Chat::load_from_db(ctx, chat_id)
+            .await?
+            .is_encrypted(ctx)
+            .await?,
+        "Realtime channels are only available in encrypted chats."
+    );
+    let topic = match get_chat_realtime_topic(ctx, chat_id).await? {
+        Some(topic) => topic,
+        None => {
+            let topic = create_random_topic();
+            set_chat_realtime_topic(ctx, chat_id, topic).await?;
+            topic
+        }
+    };
+
+    let iroh = ctx.get_or_try_init_peer_channel().await?;
+    let conn = iroh
+        .join_and_subscribe(ctx, ChannelOwner::Chat(chat_id))
+        .await?;
+
+    let mut msg = Message::new(Viewtype::Text);
+    msg.hidden = true;
+    msg.param.set_cmd(SystemMessage::IrohNodeAddr);
+    msg.param.set(Param::Arg, iroh_topic_to_str(&topic));
+    send_msg(ctx, chat_id, &mut msg).await?;
+    iroh.advertised_chat_topics.lock().insert(topic);
+    info!(
+        ctx,
+        "IROH_REALTIME: Sent realtime advertisement to {chat_id}"
+    );
+    Ok(conn)
+}
+
+/// Send realtime data to the peers of a chat.
+///
+/// [`send_chat_realtime_advertisement`] must be called first
+/// or an advertisement of another member must be received.
+pub async fn send_chat_realtime_data(ctx: &Context, chat_id: ChatId, data: Vec<u8>) -> Result<()> {
+    if !is_realtime_enabled(ctx).await? {
+        return Ok(());
+    }
+
+    let mut message = vec![CHAT_DATA];
+    message.extend(data);
+    let iroh = ctx.get_or_try_init_peer_channel().await?;
+    iroh.send_realtime_data(ctx, ChannelOwner::Chat(chat_id), message)
+        .await?;
+    Ok(())
+}
+
+/// Leave the realtime channel of a chat.
+pub async fn leave_chat_realtime(ctx: &Context, chat_id: ChatId) -> Result<()> {
+    let Some(iroh) = ctx.get_peer_channels().await else {
+        return Ok(());
+    };
+    let Some(topic) = get_chat_realtime_topic(ctx, chat_id).await? else {
+        return Ok(());
+    };
+    iroh.leave_realtime(topic).await?;
+    info!(ctx, "IROH_REALTIME: Left gossip for {chat_id}");
+
+    Ok(())
+}
+
+/// Retires the realtime topic of a chat after a member was removed.
+///
+/// Like group keys, the topic must change on membership changes
+/// so that removed members cannot follow the channel anymore.
+/// The retired topic is never adopted again, even if it is still advertised
+/// by members who did not see the removal yet.
+/// If the channel was joined, a new topic is created and advertised to the remaining members.
+pub(crate) async fn rotate_chat_realtime_topic(ctx: &Context, chat_id: ChatId) -> Result<()> {
+    let Some(topic) = get_chat_realtime_topic(ctx, chat_id).await? else {
+        return Ok(());
+    };
+    let was_joined = is_joined(ctx, topic).await;
+    ctx.sql
+        .transaction(move |transaction| {
+            transaction.execute(
+                "INSERT OR IGNORE INTO chat_realtime_retired_topics (chat_id, topic) VALUES (?, ?)",
+                (chat_id, topic.as_bytes()),
+            )?;
+            transaction.execute(
+                "DELETE FROM chat_realtime_peers WHERE chat_id=?",
+                (chat_id,),
+            )?;
+            Ok(())
+        })
+        .await?;
+    info!(
+        ctx,
+        "IROH_REALTIME: Retired the realtime topic of {chat_id}"
+    );
+
+    if !was_joined {
+        return Ok(());
+    }
+    if let Some(iroh) = ctx.get_peer_channels().await {
+        iroh.leave_realtime(topic).await?;
+    }
+    if Chat::load_from_db(ctx, chat_id)
+        .await?
+        .is_self_in_chat(ctx)
+        .await?
+    {
+        send_chat_realtime_advertisement(ctx, chat_id).await?;
+    }
+    Ok(())
+}
+
+/// Returns true if the topic was the realtime topic of the chat before a member was removed.
+async fn is_retired_chat_realtime_topic(
+    ctx: &Context,
+    chat_id: ChatId,
+    topic: TopicId,
+) -> Result<bool> {
+    ctx.sql
+        .exists(
+            "SELECT COUNT(*) FROM chat_realtime_retired_topics WHERE chat_id=? AND topic=?",
+            (chat_id, topic.as_bytes()),
+        )
+        .await
+}
+
+/// Add gossip peer from an advertisement of the realtime channel of `chat_id`.
+pub(crate) async fn add_chat_realtime_peer_from_header(
+    context: &Context,
+    chat_id: ChatId,
+    from_id: ContactId,
+    topic: &str,
+    node_addr: &str,
+) -> Result<()> {
+    if !is_realtime_enabled(context).await? {
+        return Ok(());
+    }
+
+    let topic = iroh_topic_from_str(topic)?;
+    let node_addr =
+        serde_json::from_str::<NodeAddr>(node_addr).context("Failed to parse node address")?;
+
+    info!(
+        context,
+        "Adding iroh peer with node id {} to the topic of {chat_id}.", node_addr.node_id
+    );
+
+    if is_retired_chat_realtime_topic(context, chat_id, topic).await? {
+        info!(
+            context,
+            "Ignoring retired realtime topic advertised to {chat_id}."
+        );
+        return Ok(());
+    }
+
+    context.emit_event(EventType::ChatRealtimeAdvertisementReceived { chat_id });
+
+    match get_chat_realtime_topic(context, chat_id).await? {
+        Some(current) if current == topic => {}
+        Some(current) if current.as_bytes() < topic.as_bytes() => {
+            // The peer switches to our topic when it receives our advertisement.
+            info!(
+                context,
+                "Ignoring different realtime topic advertised to {chat_id}."
+            );
+            if is_joined(context, current).await {
+                send_chat_realtime_advertisement(context, chat_id).await?;
+            }
+            return Ok(());
+        }
+        current => {
+            set_chat_realtime_topic(context, chat_id, topic).await?;
+            if let Some(current) = current
+                && is_joined(context, current).await
+                && let Some(iroh) = context.get_peer_channels().await
+            {
+                iroh.leave_realtime(current).await?;
+                iroh.join_and_subscribe(context, ChannelOwner::Chat(chat_id))
+                    .await?;
+            }
+        }
+    }
+
+    let node_id = node_addr.node_id;
+    let relay_server = node_addr.relay_url().map_or("", |relay| relay.as_str());
+    add_chat_realtime_peer(
+        context,
+        chat_id,
+        topic,
+        node_id,
+        relay_server,
+        Some(from_id),
+    )
+    .await?;
+
+    context.maybe_add_gossip_peer(topic, node_addr).await?;
+    Ok(())
+}
+
 /// Creates a new random gossip topic.
 fn create_random_topic() -> TopicId {
     TopicId::from_bytes(rand::random())
@@ -517,8 +1136,12 @@ fn create_random_topic() -> TopicId {
 pub(crate) async fn create_iroh_header(ctx: &Context, msg_id: MsgId) -> Result<String> {
     let topic = create_random_topic();
     insert_topic_stub(ctx, msg_id, topic).await?;
-    let topic_string = BASE32_NOPAD.encode(topic.as_bytes()).to_ascii_lowercase();
-    Ok(topic_string)
+    Ok(iroh_topic_to_str(&topic))
+}
+
+/// Converts iroh topic ID to `Iroh-Gossip-Header` contents.
+fn iroh_topic_to_str(topic: &TopicId) -> String {
+    BASE32_NOPAD.encode(topic.as_bytes()).to_ascii_lowercase()
 }
 
 /// Converts `Iroh-Gossip-Header` contents to iroh topic ID.
@@ -533,20 +1156,45 @@ pub(crate) fn iroh_topic_from_str(topic: &str) -> Result<TopicId> {
     Ok(topic)
 }
 
-#[expect(clippy::arithmetic_side_effects)]
 async fn subscribe_loop(
     context: &Context,
     mut stream: iroh_gossip::net::GossipReceiver,
+    mut reliable: Option<SyncChannel>,
     topic: TopicId,
-    msg_id: MsgId,
+    owner: ChannelOwner,
     join_tx: oneshot::Sender<()>,
 ) -> Result<()> {
     let mut join_tx = Some(join_tx);
 
-    while let Some(event) = stream.try_next().await? {
+    loop {
+        let (event, is_sync_event) = match &mut reliable {
+            Some(sync) => tokio::select! {
+                event = stream.try_next() => (event?, false),
+                event = sync.receiver.try_next() => (event?, true),
+            },
+            None => (stream.try_next().await?, false),
+        };
+        if is_sync_event {
+            if let (Some(event), Some(sync)) = (event, &reliable) {
+                handle_sync_event(context, sync, owner, event).await?;
+            } else {
+                warn!(context, "IROH_REALTIME: Sync topic of {topic} closed.");
+                reliable = None;
+            }
+            continue;
+        }
+        let Some(event) = event else {
+            break;
+        };
+
         match event {
             Event::Gossip(event) => match event {
                 GossipEvent::Joined(nodes) => {
+                    info!(
+                        context,
+                        "IROH_REALTIME: Joined gossip with {} neighbours.",
+                        nodes.len()
+                    );
                     if let Some(join_tx) = join_tx.take() {
                         // Try to notify that at least one peer joined,
                         // but ignore the error if receiver is dropped and nobody listens.
@@ -554,24 +1202,33 @@ async fn subscribe_loop(
                     }
 
                     for node in nodes {
-                        iroh_add_peer_for_topic(context, msg_id, topic, node, None).await?;
+                        owner.add_peer(context, topic, node).await?;
                     }
                 }
                 GossipEvent::NeighborUp(node) => {
                     info!(context, "IROH_REALTIME: NeighborUp: {}", node.to_string());
-                    iroh_add_peer_for_topic(context, msg_id, topic, node, None).await?;
+                    owner.add_peer(context, topic, node).await?;
                 }
                 GossipEvent::NeighborDown(_node) => {}
                 GossipEvent::Received(message) => {
                     info!(context, "IROH_REALTIME: Received realtime data");
-                    context.emit_event(EventType::WebxdcRealtimeData {
-                        msg_id,
-                        data: message
-                            .content
-                            .get(0..message.content.len() - 4 - PUBLIC_KEY_LENGTH)
-                            .context("too few bytes in iroh message")?
-                            .into(),
-                    });
+                    context
+                        .traffic_metrics
+                        .counters(0, TrafficProtocol::Iroh)
+                        .add_received(usize_to_u64(message.content.len()));
+                    if let Some(sync) = &reliable {
+                        let inserted = sync.state.lock().insert(&message.content)?;
+                        match inserted {
+                            Inserted::Duplicate => continue,
+                            Inserted::New => {}
+                            Inserted::NewWithGap => send_sync_request(sync).await?,
+                        }
+                    }
+                    let delivered_from = message.delivered_from;
+                    let message = RealtimeMessage::parse(&message.content)?;
+                    owner
+                        .handle_message(context, message, Some(delivered_from))
+                        .await?;
                 }
             },
             Event::Lagged => {
@@ -582,6 +1239,52 @@ async fn subscribe_loop(
     Ok(())
 }
 
+/// Handles an event of the sync topic of a reliable channel.
+async fn handle_sync_event(
+    context: &Context,
+    sync: &SyncChannel,
+    owner: ChannelOwner,
+    event: Event,
+) -> Result<()> {
+    match event {
+        Event::Gossip(GossipEvent::Joined(_)) => send_sync_request(sync).await?,
+        Event::Gossip(GossipEvent::Received(message)) => match message.content.split_first() {
+            Some((&SYNC_REQUEST, request)) => {
+                let request: SyncRequest = serde_json::from_slice(request)?;
+                let replay = sync.state.lock().replay(&request);
+                for content in replay {
+                    let mut data = vec![SYNC_REPLAY];
+                    data.extend(request.id.to_le_bytes());
+                    data.extend(content);
+                    sync.sender.broadcast_neighbors(data.into()).await?;
+                }
+            }
+            Some((&SYNC_REPLAY, replay)) => {
+                let content = replay.get(4..).context("too few bytes in replay")?;
+                if sync.state.lock().insert(content)? != Inserted::Duplicate {
+                    let message = RealtimeMessage::parse(content)?;
+                    owner.handle_message(context, message, None).await?;
+                }
+            }
+            _ => warn!(context, "IROH_REALTIME: Ignoring unknown sync message."),
+        },
+        Event::Gossip(_) => {}
+        Event::Lagged => {
+            warn!(context, "Gossip lost some sync messages");
+        }
+    }
+    Ok(())
+}
+
+/// Requests realtime messages not received yet from the neighbours.
+async fn send_sync_request(sync: &SyncChannel) -> Result<()> {
+    let request = sync.state.lock().request();
+    let mut data = vec![SYNC_REQUEST];
+    data.extend(serde_json::to_vec(&request)?);
+    sync.sender.broadcast_neighbors(data.into()).await?;
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1158,4 +1861,331 @@ async fn test_leave_webxdc_realtime_uninitialized() {
         leave_webxdc_realtime(alice, MsgId::new(1)).await.unwrap();
         assert!(alice.ctx.iroh.read().await.is_none());
     }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_peer_channels_tor_mode_needs_relay() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = &tcm.alice().await;
+        alice
+            .set_config_bool(Config::WebxdcRealtimeEnabled, true)
+            .await?;
+        alice.set_config_bool(Config::TorMode, true).await?;
+
+        // Default relays are not used in Tor mode.
+        assert!(alice.ctx.get_or_try_init_peer_channel().await.is_err());
+        assert!(alice.ctx.iroh.read().await.is_none());
+
+        alice
+            .set_config_bool(Config::IrohRelayDisabled, true)
+            .await?;
+        assert!(alice.ctx.get_or_try_init_peer_channel().await.is_err());
+        assert!(alice.ctx.iroh.read().await.is_none());
+        Ok(())
+    }
+
+    /// Makes the iroh endpoints of `contexts` known to each other.
+    ///
+    /// Without relays, the local endpoints only know each other by direct addresses.
+    pub(super) async fn add_local_node_addrs(contexts: &[&TestContext]) {
+        let mut node_addrs = Vec::new();
+        for t in contexts {
+            let iroh = t.get_or_try_init_peer_channel().await.unwrap();
+            node_addrs.push(iroh.router.endpoint().node_addr().await.unwrap());
+        }
+        for t in contexts {
+            let iroh = t.get_or_try_init_peer_channel().await.unwrap();
+            for node_addr in &node_addrs {
+                if node_addr.node_id != iroh.router.endpoint().node_id() {
+                    iroh.router
+                        .endpoint()
+                        .add_node_addr(node_addr.clone())
+                        .unwrap();
+                }
+            }
+        }
+    }
+
+    /// Waits until `t` received all of the `expected` realtime data, in any order.
+    async fn wait_for_realtime_data(t: &TestContext, expected: &[&[u8]]) {
+        let mut missing = expected.to_vec();
+        while !missing.is_empty() {
+            let event = t.evtracker.recv().await.unwrap();
+            if let EventType::WebxdcRealtimeData { data, .. } = event.typ {
+                let pos = missing
+                    .iter()
+                    .position(|expected| *expected == data.as_slice())
+                    .unwrap_or_else(|| {
+                        panic!(
+                            "Unexpected realtime data: {}",
+                            String::from_utf8_lossy(&data)
+                        )
+                    });
+                missing.remove(pos);
+            }
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_reliable_realtime_catch_up() {
+        let mut tcm = TestContextManager::new();
+        let alice = &tcm.alice().await;
+        let bob = &tcm.bob().await;
+        let fiona = &tcm.fiona().await;
+        for t in [alice, bob, fiona] {
+            t.set_config_bool(Config::WebxdcRealtimeReliable, true)
+                .await
+                .unwrap();
+            t.set_config_bool(Config::IrohRelayDisabled, true)
+                .await
+                .unwrap();
+        }
+
+        let group = alice
+            .create_group_with_members("Group", &[bob, fiona])
+            .await;
+        let mut instance = Message::new(Viewtype::File);
+        instance
+            .set_file_from_bytes(
+                alice,
+                "minimal.xdc",
+                include_bytes!("../test-data/webxdc/minimal.xdc"),
+                None,
+            )
+            .unwrap();
+        send_msg(alice, group, &mut instance).await.unwrap();
+        let sent = alice.pop_sent_msg().await;
+        let bob_webxdc = bob.recv_msg(&sent).await;
+        let fiona_webxdc = fiona.recv_msg(&sent).await;
+        bob_webxdc.chat_id.accept(bob).await.unwrap();
+        fiona_webxdc.chat_id.accept(fiona).await.unwrap();
+
+        add_local_node_addrs(&[alice, bob, fiona]).await;
+
+        // Alice and Bob join, Alice sends some messages.
+        let alice_join = send_webxdc_realtime_advertisement(alice, instance.id)
+            .await
+            .unwrap()
+            .unwrap();
+        let alice_advertisement = alice.pop_sent_msg().await;
+        bob.recv_msg_trash(&alice_advertisement).await;
+        fiona.recv_msg_trash(&alice_advertisement).await;
+        let bob_join = send_webxdc_realtime_advertisement(bob, bob_webxdc.id)
+            .await
+            .unwrap()
+            .unwrap();
+        alice.recv_msg_trash(&bob.pop_sent_msg().await).await;
+        alice_join.await.unwrap();
+        bob_join.await.unwrap();
+
+        for data in [b"1", b"2", b"3"] {
+            send_webxdc_realtime_data(alice, instance.id, data.to_vec())
+                .await
+                .unwrap();
+        }
+        wait_for_realtime_data(bob, &[b"1", b"2", b"3"]).await;
+
+        // Fiona joins late and gets the messages she missed from her neighbours.
+        let fiona_join = send_webxdc_realtime_advertisement(fiona, fiona_webxdc.id)
+            .await
+            .unwrap()
+            .unwrap();
+        alice.recv_msg_trash(&fiona.pop_sent_msg().await).await;
+        fiona_join.await.unwrap();
+        wait_for_realtime_data(fiona, &[b"1", b"2", b"3"]).await;
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_chat_realtime_channel() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = &mut tcm.alice().await;
+        let bob = &mut tcm.bob().await;
+
+        let alice_chat_id = alice.create_chat(bob).await.id;
+        let bob_chat_id = bob.create_chat(alice).await.id;
+        bob_chat_id.accept(bob).await?;
+
+        // Alice advertises the realtime channel of the chat.
+        send_chat_realtime_advertisement(alice, alice_chat_id).await?;
+        let alice_topic = get_chat_realtime_topic(alice, alice_chat_id)
+            .await?
+            .unwrap();
+        bob.recv_msg_trash(&alice.pop_sent_msg().await).await;
+        bob.evtracker
+            .get_matching(|evt| {
+                matches!(
+                    evt,
+                    EventType::ChatRealtimeAdvertisementReceived { chat_id } if *chat_id == bob_chat_id
+                )
+            })
+            .await;
+        assert_eq!(
+            get_chat_realtime_topic(bob, bob_chat_id).await?,
+            Some(alice_topic)
+        );
+
+        let alice_node_id = alice
+            .get_or_try_init_peer_channel()
+            .await?
+            .get_node_addr()
+            .await?
+            .node_id;
+        let members = get_chat_realtime_peers(bob, bob_chat_id)
+            .await?
+            .into_iter()
+            .map(|addr| addr.node_id)
+            .collect::<Vec<_>>();
+        assert_eq!(members, vec![alice_node_id]);
+
+        bob.get_or_try_init_peer_channel()
+            .await?
+            .join_and_subscribe(bob, ChannelOwner::Chat(bob_chat_id))
+            .await?
+            .unwrap()
+            .await?;
+
+        send_chat_realtime_data(alice, alice_chat_id, b"alice -> bob".into()).await?;
+        let data = bob
+            .evtracker
+            .get_matching(|evt| matches!(evt, EventType::ChatRealtimeData { .. }))
+            .await;
+        let EventType::ChatRealtimeData { chat_id, data } = data else {
+            unreachable!();
+        };
+        assert_eq!(chat_id, bob_chat_id);
+        assert_eq!(data, b"alice -> bob");
+
+        leave_chat_realtime(bob, bob_chat_id).await?;
+
+        // Deleting the chat removes the topic.
+        bob_chat_id.delete(bob).await?;
+        assert!(get_chat_realtime_topic(bob, bob_chat_id).await?.is_none());
+
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_chat_realtime_concurrent_topics() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = &mut tcm.alice().await;
+        let bob = &mut tcm.bob().await;
+
+        let alice_chat_id = alice.create_chat(bob).await.id;
+        let bob_chat_id = bob.create_chat(alice).await.id;
+        bob_chat_id.accept(bob).await?;
+
+        // Both advertise before receiving the advertisement of the other one.
+        send_chat_realtime_advertisement(alice, alice_chat_id).await?;
+        send_chat_realtime_advertisement(bob, bob_chat_id).await?;
+        let alice_sent = alice.pop_sent_msg().await;
+        let bob_sent = bob.pop_sent_msg().await;
+        let alice_topic = get_chat_realtime_topic(alice, alice_chat_id)
+            .await?
+            .unwrap();
+        let bob_topic = get_chat_realtime_topic(bob, bob_chat_id).await?.unwrap();
+        assert_ne!(alice_topic, bob_topic);
+
+        bob.recv_msg_trash(&alice_sent).await;
+        alice.recv_msg_trash(&bob_sent).await;
+
+        // The smaller topic wins.
+        let topic = if alice_topic.as_bytes() < bob_topic.as_bytes() {
+            alice_topic
+        } else {
+            bob_topic
+        };
+        assert_eq!(
+            get_chat_realtime_topic(alice, alice_chat_id).await?,
+            Some(topic)
+        );
+        assert_eq!(
+            get_chat_realtime_topic(bob, bob_chat_id).await?,
+            Some(topic)
+        );
+
+        // Unencrypted chats have no realtime channels.
+        let chat_id = ChatId::create_for_contact(
+            alice,
+            alice
+                .add_or_lookup_address_contact_id(&tcm.charlie().await)
+                .await,
+        )
+        .await?;
+        assert!(
+            send_chat_realtime_advertisement(alice, chat_id)
+                .await
+                .is_err()
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_chat_realtime_topic_rotated_on_member_removal() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = &mut tcm.alice().await;
+        let bob = &mut tcm.bob().await;
+        let fiona = &tcm.fiona().await;
+
+        let alice_chat_id = alice
+            .create_group_with_members("Group", &[bob, fiona])
+            .await;
+        let bob_chat_id = bob
+            .recv_msg(&alice.send_text(alice_chat_id, "Hi").await)
+            .await
+            .chat_id;
+        bob_chat_id.accept(bob).await?;
+
+        send_chat_realtime_advertisement(alice, alice_chat_id).await?;
+        let old_topic = get_chat_realtime_topic(alice, alice_chat_id)
+            .await?
+            .unwrap();
+        bob.recv_msg_trash(&alice.pop_sent_msg().await).await;
+        assert_eq!(
+            get_chat_realtime_topic(bob, bob_chat_id).await?,
+            Some(old_topic)
+        );
+
+        // Alice removes Fiona and advertises a new topic to the remaining members.
+        let alice_fiona_id = alice.add_or_lookup_contact_id(fiona).await;
+        chat::remove_contact_from_chat(alice, alice_chat_id, alice_fiona_id).await?;
+        let advertisement = alice.pop_sent_msg().await;
+        let removal = alice.pop_sent_msg().await;
+        assert!(!advertisement.recipients.contains("fiona@"));
+        let new_topic = get_chat_realtime_topic(alice, alice_chat_id)
+            .await?
+            .unwrap();
+        assert_ne!(new_topic, old_topic);
+        assert!(!is_joined(alice, old_topic).await);
+        assert!(is_joined(alice, new_topic).await);
+
+        // Bob retires the old topic on receiving the removal.
+        bob.recv_msg(&removal).await;
+        assert!(get_chat_realtime_topic(bob, bob_chat_id).await?.is_none());
+        bob.recv_msg_trash(&advertisement).await;
+        assert_eq!(
+            get_chat_realtime_topic(bob, bob_chat_id).await?,
+            Some(new_topic)
+        );
+
+        // Late advertisements of the old topic are ignored.
+        let node_addr = alice
+            .get_or_try_init_peer_channel()
+            .await?
+            .get_node_addr()
+            .await?;
+        add_chat_realtime_peer_from_header(
+            bob,
+            bob_chat_id,
+            bob.add_or_lookup_contact_id(alice).await,
+            &iroh_topic_to_str(&old_topic),
+            &serde_json::to_string(&node_addr)?,
+        )
+        .await?;
+        assert_eq!(
+            get_chat_realtime_topic(bob, bob_chat_id).await?,
+            Some(new_topic)
+        );
+
+        Ok(())
+    }
 }
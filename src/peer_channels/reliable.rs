@@ -0,0 +1,361 @@
+//! # Reliable realtime channels.
+//!
+//! If [`Config::WebxdcRealtimeReliable`](crate::config::Config::WebxdcRealtimeReliable) is set,
+//! recent realtime messages of each topic are buffered
+//! and replayed to peers that missed them, e.g. because they joined late or were offline.
+//!
+//! Each realtime message carries the sequence number and the public key of its sender.
+//! Sequence numbers start at 1 for each sender, topic and iroh session,
+//! so peers can detect missing ranges and request them from their neighbours.
+//! Messages may be delivered out of order then.
+//! Messages with the sequence number [`UNSEQUENCED`], e.g. typing indicators,
+//! are neither buffered nor replayed.
+//!
+//! Requests and replays are exchanged on a separate sync topic derived from the realtime topic,
+//! peers not using reliable mode do not join it and are not affected.
+//! Requests and replays are only sent to the direct neighbours and not forwarded,
+//! so they do not flood the swarm.
+//! A sync message starts with a one byte type:
+//! - [`SYNC_REQUEST`] is followed by a JSON-encoded [`SyncRequest`].
+//! - [`SYNC_REPLAY`] is followed by the 4 byte ID of the request
+//!   and the original realtime message.
+//!   The request ID avoids that the gossip layer drops replays as duplicates.
+
+use std::cmp::max;
+use std::collections::{BTreeSet, HashMap, VecDeque};
+
+use anyhow::{Context as _, Result};
+use iroh_gossip::proto::TopicId;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use super::PUBLIC_KEY_LENGTH;
+
+/// Sync message type of a [`SyncRequest`].
+pub(crate) const SYNC_REQUEST: u8 = 1;
+
+/// Sync message type of a replayed realtime message.
+pub(crate) const SYNC_REPLAY: u8 = 2;
+
+/// Sequence number of realtime messages that are not buffered and not replayed.
+pub(crate) const UNSEQUENCED: i32 = 0;
+
+/// Maximum number of buffered realtime messages per topic.
+const BUFFER_MAX_MESSAGES: usize = 500;
+
+/// Maximum total size of buffered realtime messages per topic.
+const BUFFER_MAX_BYTES: usize = 2 << 20;
+
+/// Returns the sync topic of the realtime topic `topic`.
+pub(crate) fn sync_topic(topic: &TopicId) -> TopicId {
+    let hash = Sha256::new()
+        .chain_update(topic.as_bytes())
+        .chain_update(b"reliable")
+        .finalize();
+    TopicId::from_bytes(hash.into())
+}
+
+/// Realtime message as sent over gossip.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct RealtimeMessage<'a> {
+    /// Data passed to the webxdc.
+    pub payload: &'a [u8],
+
+    /// Sequence number of the message.
+    pub seq: i32,
+
+    /// Public key of the sender.
+    pub sender: [u8; PUBLIC_KEY_LENGTH],
+}
+
+impl<'a> RealtimeMessage<'a> {
+    /// Parses the realtime message `content`,
+    /// the payload is followed by the sequence number and the public key of the sender.
+    pub(crate) fn parse(content: &'a [u8]) -> Result<Self> {
+        let payload_len = content
+            .len()
+            .checked_sub(4 + PUBLIC_KEY_LENGTH)
+            .context("too few bytes in iroh message")?;
+        let (payload, trailer) = content.split_at(payload_len);
+        let (seq, sender) = trailer.split_at(4);
+        Ok(Self {
+            payload,
+            seq: i32::from_le_bytes(seq.try_into()?),
+            sender: sender.try_into()?,
+        })
+    }
+}
+
+/// Request for realtime messages a peer has not received.
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct SyncRequest {
+    /// Random ID of the request.
+    pub id: u32,
+
+    /// Messages received from known senders.
+    /// All buffered messages of other senders are requested.
+    pub known: Vec<KnownSender>,
+}
+
+/// Messages received from a sender.
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct KnownSender {
+    /// Public key of the sender.
+    pub sender: [u8; PUBLIC_KEY_LENGTH],
+
+    /// Highest received sequence number.
+    pub last: i32,
+
+    /// Missing sequence numbers below `last`.
+    pub missing: Vec<i32>,
+}
+
+/// Result of [`ReliableState::insert`].
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum Inserted {
+    /// The message was received before.
+    Duplicate,
+
+    /// The message is new.
+    New,
+
+    /// The message is new and earlier messages of the sender are missing.
+    NewWithGap,
+}
+
+/// Buffered realtime message.
+#[derive(Debug)]
+struct BufferedMessage {
+    sender: [u8; PUBLIC_KEY_LENGTH],
+    seq: i32,
+    content: Vec<u8>,
+}
+
+/// Sequence numbers received from a sender.
+#[derive(Debug, Default)]
+struct ReceivedSeqs {
+    /// Highest received sequence number.
+    last: i32,
+
+    /// Missing sequence numbers below `last`.
+    missing: BTreeSet<i32>,
+}
+
+/// State of a reliable realtime channel.
+#[derive(Debug, Default)]
+pub(crate) struct ReliableState {
+    /// Buffered realtime messages, oldest first.
+    buffer: VecDeque<BufferedMessage>,
+
+    /// Total size of the buffered messages.
+    buffer_bytes: usize,
+
+    /// Received sequence numbers by sender.
+    received: HashMap<[u8; PUBLIC_KEY_LENGTH], ReceivedSeqs>,
+}
+
+impl ReliableState {
+    /// Records a sent or received realtime message and adds it to the buffer.
+    pub(crate) fn insert(&mut self, content: &[u8]) -> Result<Inserted> {
+        let message = RealtimeMessage::parse(content)?;
+        if message.seq == UNSEQUENCED {
+            return Ok(Inserted::New);
+        }
+        let received = self.received.entry(message.sender).or_default();
+        let inserted = if message.seq > received.last {
+            // Only track gaps that can still be in the buffers of other peers.
+            let first_missing = max(
+                received.last.saturating_add(1),
+                message.seq.saturating_sub(BUFFER_MAX_MESSAGES as i32),
+            );
+            received.missing.extend(first_missing..message.seq);
+            received.last = message.seq;
+            if first_missing < message.seq {
+                Inserted::NewWithGap
+            } else {
+                Inserted::New
+            }
+        } else if received.missing.remove(&message.seq) {
+            Inserted::New
+        } else {
+            return Ok(Inserted::Duplicate);
+        };
+        while received.missing.len() > BUFFER_MAX_MESSAGES {
+            received.missing.pop_first();
+        }
+
+        self.buffer_bytes = self.buffer_bytes.saturating_add(content.len());
+        self.buffer.push_back(BufferedMessage {
+            sender: message.sender,
+            seq: message.seq,
+            content: content.to_vec(),
+        });
+        while self.buffer.len() > BUFFER_MAX_MESSAGES || self.buffer_bytes > BUFFER_MAX_BYTES {
+            let Some(evicted) = self.buffer.pop_front() else {
+                break;
+            };
+            self.buffer_bytes = self.buffer_bytes.saturating_sub(evicted.content.len());
+        }
+        Ok(inserted)
+    }
+
+    /// Returns a request for the messages not received yet.
+    pub(crate) fn request(&self) -> SyncRequest {
+        SyncRequest {
+            id: rand::random(),
+            known: self
+                .received
+                .iter()
+                .map(|(sender, received)| KnownSender {
+                    sender: *sender,
+                    last: received.last,
+                    missing: received.missing.iter().copied().collect(),
+                })
+                .collect(),
+        }
+    }
+
+    /// Returns the buffered messages requested by `request`.
+    pub(crate) fn replay(&self, request: &SyncRequest) -> Vec<Vec<u8>> {
+        self.buffer
+            .iter()
+            .filter(|buffered| {
+                match request
+                    .known
+                    .iter()
+                    .find(|known| known.sender == buffered.sender)
+                {
+                    Some(known) => {
+                        buffered.seq > known.last || known.missing.contains(&buffered.seq)
+                    }
+                    None => true,
+                }
+            })
+            .map(|buffered| buffered.content.clone())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(payload: &[u8], seq: i32, sender: u8) -> Vec<u8> {
+        let mut content = payload.to_vec();
+        content.extend(seq.to_le_bytes());
+        content.extend([sender; PUBLIC_KEY_LENGTH]);
+        content
+    }
+
+    #[test]
+    fn test_parse_realtime_message() {
+        let content = message(b"hello", 7, 3);
+        assert_eq!(
+            RealtimeMessage::parse(&content).unwrap(),
+            RealtimeMessage {
+                payload: b"hello",
+                seq: 7,
+                sender: [3; PUBLIC_KEY_LENGTH],
+            }
+        );
+        assert!(RealtimeMessage::parse(b"too short").is_err());
+    }
+
+    #[test]
+    fn test_reliable_state_gaps() {
+        let mut state = ReliableState::default();
+        assert_eq!(state.insert(&message(b"1", 1, 1)).unwrap(), Inserted::New);
+        assert_eq!(
+            state.insert(&message(b"1", 1, 1)).unwrap(),
+            Inserted::Duplicate
+        );
+        assert_eq!(
+            state.insert(&message(b"4", 4, 1)).unwrap(),
+            Inserted::NewWithGap
+        );
+
+        let request = state.request();
+        assert_eq!(request.known.len(), 1);
+        assert_eq!(request.known[0].last, 4);
+        assert_eq!(request.known[0].missing, vec![2, 3]);
+
+        assert_eq!(state.insert(&message(b"3", 3, 1)).unwrap(), Inserted::New);
+        assert_eq!(
+            state.insert(&message(b"3", 3, 1)).unwrap(),
+            Inserted::Duplicate
+        );
+        assert_eq!(state.request().known[0].missing, vec![2]);
+
+        // Late joiners miss all messages sent before.
+        assert_eq!(
+            state.insert(&message(b"x", 3, 2)).unwrap(),
+            Inserted::NewWithGap
+        );
+    }
+
+    #[test]
+    fn test_reliable_state_replay() {
+        let mut state = ReliableState::default();
+        for seq in 1..=5 {
+            state.insert(&message(b"a", seq, 1)).unwrap();
+        }
+        state.insert(&message(b"b", 1, 2)).unwrap();
+
+        // Unsequenced messages are not buffered.
+        assert_eq!(
+            state.insert(&message(b"c", UNSEQUENCED, 3)).unwrap(),
+            Inserted::New
+        );
+
+        // A new peer gets everything.
+        let request = ReliableState::default().request();
+        assert_eq!(state.replay(&request).len(), 6);
+
+        // A peer missing some messages only gets those.
+        let mut peer = ReliableState::default();
+        peer.insert(&message(b"a", 2, 1)).unwrap();
+        peer.insert(&message(b"a", 4, 1)).unwrap();
+        peer.insert(&message(b"b", 1, 2)).unwrap();
+        let replay = state.replay(&peer.request());
+        assert_eq!(
+            replay,
+            vec![
+                message(b"a", 1, 1),
+                message(b"a", 3, 1),
+                message(b"a", 5, 1)
+            ]
+        );
+        for content in &replay {
+            assert_ne!(peer.insert(content).unwrap(), Inserted::Duplicate);
+        }
+        assert!(state.replay(&peer.request()).is_empty());
+    }
+
+    #[test]
+    fn test_reliable_state_bounds() {
+        let mut state = ReliableState::default();
+        let payload = vec![0; 100 << 10];
+        for seq in 1..=100 {
+            state.insert(&message(&payload, seq, 1)).unwrap();
+        }
+        assert!(state.buffer_bytes <= BUFFER_MAX_BYTES);
+        assert!(state.buffer.len() < 100);
+
+        let mut state = ReliableState::default();
+        for seq in 1..=(BUFFER_MAX_MESSAGES as i32 * 2) {
+            state.insert(&message(b"", seq, 1)).unwrap();
+        }
+        assert_eq!(state.buffer.len(), BUFFER_MAX_MESSAGES);
+
+        // Gaps are bounded as well.
+        state.insert(&message(b"", i32::MAX, 2)).unwrap();
+        assert_eq!(state.request().known.len(), 2);
+        assert!(
+            state
+                .request()
+                .known
+                .iter()
+                .all(|known| known.missing.len() <= BUFFER_MAX_MESSAGES)
+        );
+    }
+}
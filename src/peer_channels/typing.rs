@@ -0,0 +1,368 @@
+//! # Typing indicators.
+//!
+//! Typing indicators are sent over the realtime channel of the chat and never by email,
+//! so they only reach members that are online and joined the channel.
+//! UIs join the channel with [`join_typing`] when the chat is opened,
+//! typing indicators are only sent over an already joined channel.
+//! The channel is advertised by email only once per topic,
+//! afterwards it is rejoined using the stored peers of the chat.
+//!
+//! A typing indicator is a [`CHAT_TYPING`] realtime message followed by one byte,
+//! 1 if the user is typing and 0 if the user stopped typing.
+//! It is only sent to the direct neighbours in the gossip swarm and never buffered or replayed,
+//! so the sender is the neighbour that delivered it.
+//! It is identified by the iroh public key the contact advertised in the chat.
+
+use std::time::Duration;
+
+use anyhow::Result;
+use iroh::PublicKey;
+use ratelimit::Ratelimit;
+
+use super::{
+    CHAT_TYPING, ChannelOwner, RealtimeMessage, get_chat_realtime_topic, is_joined,
+    is_realtime_enabled, send_chat_realtime_advertisement,
+};
+use crate::EventType;
+use crate::chat::{Chat, ChatId, get_chat_contacts, is_contact_in_chat};
+use crate::config::Config;
+use crate::constants::Chattype;
+use crate::contact::ContactId;
+use crate::context::Context;
+use crate::log::warn;
+
+/// Seconds after which a contact should be shown as not typing anymore
+/// if no new typing indicator was received.
+pub const TYPING_TIMEOUT: u64 = 10;
+
+/// Typing indicators are only sent in chats with at most this many members.
+///
+/// Typing indicators are only delivered to direct neighbours in the gossip swarm,
+/// which are at most 5 peers by default (the active view of iroh-gossip),
+/// so with more members some of them would not receive the indicators.
+const TYPING_MAX_CHAT_SIZE: usize = 6;
+
+/// Window in which at most one typing indicator is sent per chat.
+///
+/// Must be shorter than [`TYPING_TIMEOUT`]
+/// so that the indicator is refreshed while the user keeps typing.
+const TYPING_RATELIMIT_WINDOW: Duration = Duration::from_secs(3);
+
+/// Returns whether typing indicators are enabled at all.
+async fn is_typing_enabled(context: &Context) -> Result<bool> {
+    Ok(context
+        .get_config_bool(Config::TypingIndicatorsEnabled)
+        .await?
+        && is_realtime_enabled(context).await?)
+}
+
+/// Returns whether typing indicators can be sent in the chat.
+async fn is_typing_chat(context: &Context, chat_id: ChatId) -> Result<bool> {
+    let chat = Chat::load_from_db(context, chat_id).await?;
+    Ok(
+        matches!(chat.get_type(), Chattype::Single | Chattype::Group)
+            && !chat.is_self_talk()
+            && !chat.is_device_talk()
+            && !chat.is_contact_request()
+            && chat.is_encrypted(context).await?
+            && get_chat_contacts(context, chat_id).await?.len() <= TYPING_MAX_CHAT_SIZE,
+    )
+}
+
+/// Returns whether the realtime channel of the chat is joined.
+async fn is_chat_joined(context: &Context, chat_id: ChatId) -> Result<bool> {
+    Ok(match get_chat_realtime_topic(context, chat_id).await? {
+        Some(topic) => is_joined(context, topic).await,
+        None => false,
+    })
+}
+
+/// Joins the realtime channel of the chat to exchange typing indicators.
+///
+/// The channel is advertised to the chat members
+/// unless its topic was already advertised,
+/// then it is rejoined using the stored peers without sending an email.
+///
+/// UIs should call this when the chat is opened,
+/// before the user starts typing.
+///
+/// Does nothing if typing indicators cannot be sent in the chat, see [`set_typing`].
+pub async fn join_typing(context: &Context, chat_id: ChatId) -> Result<()> {
+    if !is_typing_enabled(context).await?
+        || is_chat_joined(context, chat_id).await?
+        || !is_typing_chat(context, chat_id).await?
+    {
+        return Ok(());
+    }
+    if let Some(topic) = get_chat_realtime_topic(context, chat_id).await? {
+        let iroh = context.get_or_try_init_peer_channel().await?;
+        if iroh.advertised_chat_topics.lock().contains(&topic) {
+            iroh.join_and_subscribe(context, ChannelOwner::Chat(chat_id))
+                .await?;
+            return Ok(());
+        }
+    }
+    send_chat_realtime_advertisement(context, chat_id).await?;
+    Ok(())
+}
+
+/// Notifies the members of the chat that the user started or stopped typing.
+///
+/// UIs should call this with `typing` set when the user types into the message field
+/// and unset when the message is sent or the field is cleared.
+/// Typing indicators are rate-limited, so it is fine to call this on each keystroke.
+/// They are only sent if the realtime channel of the chat was joined, see [`join_typing`].
+///
+/// Does nothing if [`Config::TypingIndicatorsEnabled`] is unset,
+/// in unencrypted chats, contact requests and chats with many members.
+pub async fn set_typing(context: &Context, chat_id: ChatId, typing: bool) -> Result<()> {
+    if !is_typing_enabled(context).await? || !is_chat_joined(context, chat_id).await? {
+        return Ok(());
+    }
+    let Some(iroh) = context.get_peer_channels().await else {
+        return Ok(());
+    };
+    {
+        let mut ratelimits = iroh.typing_ratelimits.lock();
+        if typing {
+            let ratelimit = ratelimits
+                .entry(chat_id)
+                .or_insert_with(|| Ratelimit::new(TYPING_RATELIMIT_WINDOW, 1.0));
+            if !ratelimit.can_send() {
+                return Ok(());
+            }
+            ratelimit.send();
+        } else {
+            // Typing again right after sending a message should be shown immediately.
+            ratelimits.remove(&chat_id);
+        }
+    }
+    if !is_typing_chat(context, chat_id).await? {
+        return Ok(());
+    }
+
+    let ephemeral = true;
+    iroh.send_realtime_data_ex(
+        context,
+        ChannelOwner::Chat(chat_id),
+        vec![CHAT_TYPING, typing.into()],
+        ephemeral,
+    )
+    .await
+}
+
+/// Handles a typing indicator `message` delivered by the neighbour `delivered_from`.
+///
+/// Typing indicators are only sent to direct neighbours,
+/// so indicators not delivered by their sender are forged or replayed and ignored.
+pub(super) async fn handle_typing(
+    context: &Context,
+    chat_id: ChatId,
+    message: &RealtimeMessage<'_>,
+    delivered_from: Option<PublicKey>,
+    payload: &[u8],
+) -> Result<()> {
+    if !context
+        .get_config_bool(Config::TypingIndicatorsEnabled)
+        .await?
+    {
+        return Ok(());
+    }
+    let typing = match payload {
+        [0] => false,
+        [1] => true,
+        _ => {
+            warn!(context, "Ignoring invalid typing indicator in {chat_id}.");
+            return Ok(());
+        }
+    };
+    if delivered_from.is_none_or(|delivered_from| *delivered_from.as_bytes() != message.sender) {
+        warn!(
+            context,
+            "Ignoring typing indicator in {chat_id} not delivered by its sender."
+        );
+        return Ok(());
+    }
+    let Some(contact_id) = context
+        .sql
+        .query_get_value::<ContactId>(
+            "SELECT contact_id FROM chat_realtime_peers
+             WHERE chat_id=? AND public_key=? AND contact_id!=0",
+            (chat_id, message.sender.as_slice()),
+        )
+        .await?
+    else {
+        info!(
+            context,
+            "Ignoring typing indicator of a peer not advertised in {chat_id}."
+        );
+        return Ok(());
+    };
+    if contact_id == ContactId::SELF || !is_contact_in_chat(context, chat_id, contact_id).await? {
+        return Ok(());
+    }
+
+    context.emit_event(EventType::ContactTyping {
+        chat_id,
+        contact_id,
+        typing,
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use iroh::SecretKey;
+
+    use super::*;
+    use crate::peer_channels::tests::add_local_node_addrs;
+    use crate::peer_channels::{PUBLIC_KEY_LENGTH, UNSEQUENCED, leave_chat_realtime};
+    use crate::test_utils::TestContextManager;
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_typing_indicator() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = &mut tcm.alice().await;
+        let bob = &mut tcm.bob().await;
+        for t in [&alice, &bob] {
+            t.set_config_bool(Config::IrohRelayDisabled, true).await?;
+        }
+        add_local_node_addrs(&[alice, bob]).await;
+
+        let alice_chat_id = alice.create_chat(bob).await.id;
+        let bob_chat_id = bob.create_chat(alice).await.id;
+        bob_chat_id.accept(bob).await?;
+
+        // Typing indicators are not sent before the realtime channel is joined.
+        set_typing(alice, alice_chat_id, true).await?;
+        assert!(alice.pop_sent_msg_opt(Duration::ZERO).await.is_none());
+
+        // Opening the chat joins and advertises its realtime channel.
+        join_typing(alice, alice_chat_id).await?;
+        bob.recv_msg_trash(&alice.pop_sent_msg().await).await;
+        send_chat_realtime_advertisement(bob, bob_chat_id)
+            .await?
+            .unwrap()
+            .await?;
+        alice.recv_msg_trash(&bob.pop_sent_msg().await).await;
+
+        // The channel is advertised only once.
+        join_typing(alice, alice_chat_id).await?;
+        assert!(alice.pop_sent_msg_opt(Duration::ZERO).await.is_none());
+
+        // The channel is rejoined with the stored peers without advertising it again.
+        leave_chat_realtime(alice, alice_chat_id).await?;
+        assert!(!is_chat_joined(alice, alice_chat_id).await?);
+        alice.evtracker.clear_events();
+        join_typing(alice, alice_chat_id).await?;
+        assert!(is_chat_joined(alice, alice_chat_id).await?);
+        assert!(alice.pop_sent_msg_opt(Duration::ZERO).await.is_none());
+        alice
+            .evtracker
+            .get_info_contains("IROH_REALTIME: Joined gossip with")
+            .await;
+
+        set_typing(alice, alice_chat_id, true).await?;
+        let event = bob
+            .evtracker
+            .get_matching(|evt| matches!(evt, EventType::ContactTyping { .. }))
+            .await;
+        let alice_contact_id = bob.add_or_lookup_contact_id(alice).await;
+        assert_eq!(
+            event,
+            EventType::ContactTyping {
+                chat_id: bob_chat_id,
+                contact_id: alice_contact_id,
+                typing: true
+            }
+        );
+
+        set_typing(alice, alice_chat_id, false).await?;
+        let event = bob
+            .evtracker
+            .get_matching(|evt| matches!(evt, EventType::ContactTyping { .. }))
+            .await;
+        assert_eq!(
+            event,
+            EventType::ContactTyping {
+                chat_id: bob_chat_id,
+                contact_id: alice_contact_id,
+                typing: false
+            }
+        );
+
+        // Typing indicators not delivered by their sender are ignored.
+        let sender: [u8; PUBLIC_KEY_LENGTH] = bob
+            .sql
+            .query_get_value::<Vec<u8>>(
+                "SELECT public_key FROM chat_realtime_peers WHERE chat_id=? AND contact_id=?",
+                (bob_chat_id, alice_contact_id),
+            )
+            .await?
+            .unwrap()
+            .try_into()
+            .unwrap();
+        let message = RealtimeMessage {
+            payload: &[CHAT_TYPING, 1],
+            seq: UNSEQUENCED,
+            sender,
+        };
+        handle_typing(bob, bob_chat_id, &message, None, &[1]).await?;
+        let other_key = SecretKey::generate(rand_old::rngs::OsRng).public();
+        handle_typing(bob, bob_chat_id, &message, Some(other_key), &[1]).await?;
+        assert!(
+            bob.evtracker
+                .get_matching_opt(bob, |evt| matches!(evt, EventType::ContactTyping { .. }))
+                .await
+                .is_none()
+        );
+        let sender_key = PublicKey::from_bytes(&sender)?;
+        handle_typing(bob, bob_chat_id, &message, Some(sender_key), &[1]).await?;
+        bob.evtracker
+            .get_matching(|evt| matches!(evt, EventType::ContactTyping { typing: true, .. }))
+            .await;
+
+        // Typing indicators are neither sent nor shown if disabled.
+        bob.set_config_bool(Config::TypingIndicatorsEnabled, false)
+            .await?;
+        set_typing(alice, alice_chat_id, true).await?;
+        set_typing(bob, bob_chat_id, true).await?;
+        assert!(
+            bob.evtracker
+                .get_matching_opt(bob, |evt| matches!(evt, EventType::ContactTyping { .. }))
+                .await
+                .is_none()
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_typing_not_sent_to_contact_requests() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = &mut tcm.alice().await;
+        let bob = &mut tcm.bob().await;
+
+        let bob_chat_id = bob.create_chat(alice).await.id;
+        let alice_chat_id = alice
+            .recv_msg(&bob.send_text(bob_chat_id, "hi").await)
+            .await
+            .chat_id;
+        assert!(
+            Chat::load_from_db(alice, alice_chat_id)
+                .await?
+                .is_contact_request()
+        );
+
+        join_typing(alice, alice_chat_id).await?;
+        set_typing(alice, alice_chat_id, true).await?;
+        assert!(alice.pop_sent_msg_opt(Duration::ZERO).await.is_none());
+        assert!(
+            get_chat_realtime_topic(alice, alice_chat_id)
+                .await?
+                .is_none()
+        );
+
+        Ok(())
+    }
+}
@@ -13,8 +13,9 @@
 use pgp::crypto::aead::{AeadAlgorithm, ChunkSize};
 use pgp::crypto::ecc_curve::ECCCurve;
 use pgp::crypto::hash::HashAlgorithm;
+use pgp::crypto::public_key::PublicKeyAlgorithm;
 use pgp::crypto::sym::SymmetricKeyAlgorithm;
-use pgp::packet::{Signature, Subpacket, SubpacketData};
+use pgp::packet::{Signature, SignatureConfig, SignatureType, Subpacket, SubpacketData};
 use pgp::types::{
     CompressionAlgorithm, Imprint, KeyDetails, KeyVersion, Password, SignedUser, SigningKey as _,
     StringToKey,
@@ -23,6 +24,7 @@
 use sha2::Sha256;
 use tokio::runtime::Handle;
 
+use crate::constants::KeyProfile;
 use crate::key::{DcKey, Fingerprint};
 
 /// Preferred symmetric encryption algorithm.
@@ -32,12 +34,42 @@
 ///
 /// Both secret and public key consist of signing primary key and encryption subkey
 /// as [described in the Autocrypt standard](https://autocrypt.org/level1.html#openpgp-based-key-data).
-pub(crate) fn create_keypair(addr: EmailAddress) -> Result<SignedSecretKey> {
-    let signing_key_type = PgpKeyType::Ed25519Legacy;
-    let encryption_key_type = PgpKeyType::ECDH(ECCCurve::Curve25519);
+///
+/// With [`KeyProfile::Hybrid`] a v6 key is generated
+/// which additionally has an ML-KEM-768+X25519 encryption subkey
+/// as described in <https://datatracker.ietf.org/doc/draft-ietf-openpgp-pqc/>.
+/// The classical X25519 subkey is kept so peers without post-quantum support
+/// can still encrypt to the key.
+pub(crate) fn create_keypair(addr: EmailAddress, profile: KeyProfile) -> Result<SignedSecretKey> {
+    let (version, signing_key_type, encryption_key_types) = match profile {
+        KeyProfile::Classic => (
+            KeyVersion::V4,
+            PgpKeyType::Ed25519Legacy,
+            vec![PgpKeyType::ECDH(ECCCurve::Curve25519)],
+        ),
+        KeyProfile::Hybrid => (
+            KeyVersion::V6,
+            PgpKeyType::Ed25519,
+            vec![PgpKeyType::X25519, PgpKeyType::MlKem768X25519],
+        ),
+    };
+
+    let subkeys = encryption_key_types
+        .into_iter()
+        .map(|encryption_key_type| {
+            SubkeyParamsBuilder::default()
+                .version(version)
+                .key_type(encryption_key_type)
+                .can_encrypt(EncryptionCaps::All)
+                .passphrase(None)
+                .build()
+                .context("failed to build subkey parameters")
+        })
+        .collect::<Result<Vec<_>>>()?;
 
     let user_id = format!("<{addr}>");
     let key_params = SecretKeyParamsBuilder::default()
+        .version(version)
         .key_type(signing_key_type)
         .can_certify(true)
         .can_sign(true)
@@ -59,14 +91,7 @@ pub(crate) fn create_keypair(addr: EmailAddress) -> Result<SignedSecretKey> {
             CompressionAlgorithm::ZLIB,
             CompressionAlgorithm::ZIP,
         ])
-        .subkey(
-            SubkeyParamsBuilder::default()
-                .key_type(encryption_key_type)
-                .can_encrypt(EncryptionCaps::All)
-                .passphrase(None)
-                .build()
-                .context("failed to build subkey parameters")?,
-        )
+        .subkeys(subkeys)
         .build()
         .context("failed to build key parameters")?;
 
@@ -81,15 +106,32 @@ pub(crate) fn create_keypair(addr: EmailAddress) -> Result<SignedSecretKey> {
     Ok(secret_key)
 }
 
+/// Returns true if the algorithm is a post-quantum hybrid encryption algorithm.
+fn is_pq_encryption_algorithm(algorithm: PublicKeyAlgorithm) -> bool {
+    matches!(
+        algorithm,
+        PublicKeyAlgorithm::MlKem768X25519 | PublicKeyAlgorithm::MlKem1024X448
+    )
+}
+
 /// Selects a subkey of the public key to use for encryption.
 ///
+/// If `prefer_pq` is true, post-quantum hybrid subkeys are preferred,
+/// otherwise classical subkeys are preferred.
+/// In both cases any other encryption subkey is used as a fallback.
+///
 /// Returns `None` if the public key cannot be used for encryption.
 ///
 /// TODO: take key flags and expiration dates into account
-fn select_pk_for_encryption(key: &SignedPublicKey) -> Option<&SignedPublicSubKey> {
-    key.public_subkeys
+fn select_pk_for_encryption(key: &SignedPublicKey, prefer_pq: bool) -> Option<&SignedPublicSubKey> {
+    let mut encryption_subkeys = key
+        .public_subkeys
         .iter()
-        .find(|subkey| subkey.algorithm().can_encrypt())
+        .filter(|subkey| subkey.algorithm().can_encrypt());
+    encryption_subkeys
+        .clone()
+        .find(|subkey| is_pq_encryption_algorithm(subkey.algorithm()) == prefer_pq)
+        .or_else(|| encryption_subkeys.next())
 }
 
 /// Version of SEIPD packet to use.
@@ -120,9 +162,14 @@ pub async fn pk_encrypt(
         .spawn_blocking(move || {
             let mut rng = thread_rng();
 
+            // Post-quantum encryption only protects the message
+            // if the session key is not additionally encrypted to a classical subkey,
+            // so use it only if all recipients support it.
+            let prefer_pq = matches!(seipd_version, SeipdVersion::V2)
+                && public_keys_for_encryption.iter().all(pubkey_supports_pq);
             let pkeys = public_keys_for_encryption
                 .iter()
-                .filter_map(select_pk_for_encryption);
+                .filter_map(|key| select_pk_for_encryption(key, prefer_pq));
             let subpkts = {
                 let mut hashed = Vec::with_capacity(1 + public_keys_for_encryption.len() + 1);
                 hashed.push(Subpacket::critical(SubpacketData::SignatureCreationTime(
@@ -251,6 +298,58 @@ pub fn pk_validate(
     Ok(ret)
 }
 
+/// Creates an armored detached signature over `plain`
+/// for use in OpenPGP/MIME `multipart/signed` messages,
+/// see <https://www.rfc-editor.org/rfc/rfc3156#section-5>.
+///
+/// SHA-256 is always used as the hash algorithm,
+/// so the `micalg` parameter is `pgp-sha256`.
+pub fn pk_calc_signature(
+    plain: &[u8],
+    private_key_for_signing: &SignedSecretKey,
+) -> Result<String> {
+    let mut rng = thread_rng();
+    let mut config = SignatureConfig::from_key(
+        &mut rng,
+        &private_key_for_signing.primary_key,
+        SignatureType::Binary,
+    )?;
+    config.hash_alg = HashAlgorithm::Sha256;
+    config.hashed_subpackets = vec![
+        Subpacket::critical(SubpacketData::SignatureCreationTime(
+            pgp::types::Timestamp::now(),
+        ))?,
+        Subpacket::regular(SubpacketData::IssuerFingerprint(
+            private_key_for_signing.fingerprint(),
+        ))?,
+    ];
+    if private_key_for_signing.version() <= KeyVersion::V4 {
+        config.unhashed_subpackets = vec![Subpacket::regular(SubpacketData::IssuerKeyId(
+            private_key_for_signing.legacy_key_id(),
+        ))?];
+    }
+    let signature = config.sign(
+        &private_key_for_signing.primary_key,
+        &Password::empty(),
+        plain,
+    )?;
+    let signature = DetachedSignature::new(signature).to_armored_string(Default::default())?;
+    Ok(signature)
+}
+
+/// Returns issuer fingerprints of an armored detached signature.
+///
+/// These are used to look up the keys needed to validate the signature.
+pub(crate) fn detached_signature_issuers(signature: &[u8]) -> Result<Vec<Fingerprint>> {
+    let detached_signature = DetachedSignature::from_armor_single(Cursor::new(signature))?.0;
+    Ok(detached_signature
+        .signature
+        .issuer_fingerprint()
+        .into_iter()
+        .map(|fingerprint| Fingerprint::from(fingerprint.clone()))
+        .collect())
+}
+
 /// Symmetrically encrypt the message.
 /// This is used for broadcast channels and for version 2 of the Securejoin protocol.
 /// `shared secret` is the secret that will be used for symmetric encryption.
@@ -454,6 +553,30 @@ pub(crate) fn addresses_from_public_key(public_key: &SignedPublicKey) -> Option<
     None
 }
 
+/// Returns email addresses from the User IDs of the public key.
+///
+/// User IDs are usually formatted as `Name <addr>`,
+/// but may also consist of the bare address.
+/// User IDs which do not contain a valid address are skipped.
+pub(crate) fn user_id_addresses(public_key: &SignedPublicKey) -> Vec<String> {
+    public_key
+        .details
+        .users
+        .iter()
+        .filter_map(|user| user.id.as_str())
+        .filter_map(|user_id| {
+            let addr = match (user_id.rfind('<'), user_id.rfind('>')) {
+                (Some(start), Some(end)) if start < end => {
+                    user_id.get(start..end)?.strip_prefix('<')?
+                }
+                _ => user_id,
+            };
+            let addr = addr.trim();
+            may_be_valid_addr(addr).then(|| addr.to_string())
+        })
+        .collect()
+}
+
 /// Returns true if public key advertises SEIPDv2 feature.
 pub(crate) fn pubkey_supports_seipdv2(public_key: &SignedPublicKey) -> bool {
     // If any Direct Key Signature or any User ID signature has SEIPDv2 feature,
@@ -483,6 +606,14 @@ pub(crate) fn pubkey_supports_seipdv2(public_key: &SignedPublicKey) -> bool {
         })
 }
 
+/// Returns true if public key has a post-quantum hybrid encryption subkey.
+pub(crate) fn pubkey_supports_pq(public_key: &SignedPublicKey) -> bool {
+    public_key
+        .public_subkeys
+        .iter()
+        .any(|subkey| is_pq_encryption_algorithm(subkey.algorithm()))
+}
+
 #[cfg(test)]
 mod tests {
     use std::sync::LazyLock;
@@ -544,9 +675,38 @@ async fn pk_decrypt_and_validate<'a>(
 
     #[test]
     fn test_create_keypair() {
-        let keypair0 = create_keypair(EmailAddress::new("foo@bar.de").unwrap()).unwrap();
-        let keypair1 = create_keypair(EmailAddress::new("two@zwo.de").unwrap()).unwrap();
+        let keypair0 = create_keypair(
+            EmailAddress::new("foo@bar.de").unwrap(),
+            KeyProfile::Classic,
+        )
+        .unwrap();
+        let keypair1 = create_keypair(
+            EmailAddress::new("two@zwo.de").unwrap(),
+            KeyProfile::Classic,
+        )
+        .unwrap();
         assert_ne!(keypair0.public_key(), keypair1.public_key());
+        assert!(!pubkey_supports_pq(&keypair0.to_public_key()));
+    }
+
+    #[test]
+    fn test_create_keypair_hybrid() {
+        let keypair =
+            create_keypair(EmailAddress::new("foo@bar.de").unwrap(), KeyProfile::Hybrid).unwrap();
+        assert_eq!(keypair.version(), KeyVersion::V6);
+        let public_key = keypair.to_public_key();
+        assert!(pubkey_supports_pq(&public_key));
+        assert!(pubkey_supports_seipdv2(&public_key));
+
+        let pq_subkey = select_pk_for_encryption(&public_key, true).unwrap();
+        assert_eq!(pq_subkey.algorithm(), PublicKeyAlgorithm::MlKem768X25519);
+        let classic_subkey = select_pk_for_encryption(&public_key, false).unwrap();
+        assert_eq!(classic_subkey.algorithm(), PublicKeyAlgorithm::X25519);
+
+        // Classical keys fall back to the classical subkey.
+        let alice = alice_keypair().to_public_key();
+        let subkey = select_pk_for_encryption(&alice, true).unwrap();
+        assert!(!is_pq_encryption_algorithm(subkey.algorithm()));
     }
 
     /// [SignedSecretKey] and [SignedPublicKey] objects
@@ -848,6 +1008,36 @@ fn test_merge_openpgp_certificates() {
         assert!(merge_openpgp_certificates(bob.clone(), alice.clone()).is_err());
     }
 
+    #[test]
+    fn test_user_id_addresses() {
+        let bob = bob_keypair().to_public_key();
+        assert_eq!(user_id_addresses(&bob), vec!["bob@example.net".to_string()]);
+    }
+
+    #[test]
+    fn test_detached_signature() -> Result<()> {
+        let alice = alice_keypair();
+        let bob = bob_keypair();
+        let content = b"Content-Type: text/plain\r\n\r\nHello!\r\n";
+
+        let signature = pk_calc_signature(content, &alice)?;
+        assert_eq!(
+            detached_signature_issuers(signature.as_bytes())?,
+            vec![alice.dc_fingerprint()]
+        );
+        let valid = pk_validate(
+            content,
+            signature.as_bytes(),
+            &[alice.to_public_key(), bob.to_public_key()],
+        )?;
+        assert_eq!(valid, HashSet::from([alice.dc_fingerprint()]));
+
+        // Modified content does not validate.
+        let valid = pk_validate(b"Hello!", signature.as_bytes(), &[alice.to_public_key()])?;
+        assert!(valid.is_empty());
+        Ok(())
+    }
+
     /// Test PQC support.
     #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
     async fn test_pqc() -> Result<()> {
@@ -865,6 +1055,44 @@ async fn test_pqc() -> Result<()> {
         Ok(())
     }
 
+    /// Tests that hybrid keys can exchange messages with classical and hybrid peers.
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_hybrid_interop() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = &tcm.alice().await;
+        let hybrid = &TestContext::builder()
+            .with_key_pair(create_keypair(
+                EmailAddress::new("hybrid@example.org")?,
+                KeyProfile::Hybrid,
+            )?)
+            .with_address("hybrid@example.org".to_string())
+            .with_id_offset(8000)
+            .build(None)
+            .await;
+        let hybrid2 = &TestContext::builder()
+            .with_key_pair(create_keypair(
+                EmailAddress::new("hybrid2@example.org")?,
+                KeyProfile::Hybrid,
+            )?)
+            .with_address("hybrid2@example.org".to_string())
+            .with_id_offset(9000)
+            .build(None)
+            .await;
+
+        // Classical peer writes to a hybrid peer and gets a reply.
+        let rcvd = tcm.send_recv_accept(alice, hybrid, "Hi!").await;
+        let sent = hybrid.send_text(rcvd.chat_id, "Hello back!").await;
+        assert_eq!(alice.recv_msg(&sent).await.text, "Hello back!");
+
+        // Hybrid peers write to each other.
+        let rcvd = tcm.send_recv_accept(hybrid, hybrid2, "Hi!").await;
+        assert!(rcvd.get_showpadlock());
+        let sent = hybrid2.send_text(rcvd.chat_id, "Hello back!").await;
+        assert_eq!(hybrid.recv_msg(&sent).await.text, "Hello back!");
+
+        Ok(())
+    }
+
     /// Tests securejoin with inviter using PQC key.
     #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
     async fn test_securejoin_pqc_inviter() {
@@ -33,14 +33,17 @@
 };
 use crate::log::{LogExt as _, warn};
 use crate::message::{
-    self, Message, MessageState, MessengerMessage, MsgId, Viewtype, insert_tombstone,
-    rfc724_mid_exists,
+    self, EncryptionType, Message, MessageState, MessengerMessage, MsgId, Viewtype,
+    insert_tombstone, rfc724_mid_exists,
 };
 use crate::mimeparser::{
     AvatarAction, GossipedKey, MimeMessage, PreMessageMode, SystemMessage, parse_message_ids,
 };
 use crate::param::{Param, Params};
-use crate::peer_channels::{add_gossip_peer_from_header, insert_topic_stub, iroh_topic_from_str};
+use crate::peer_channels::{
+    add_chat_realtime_peer_from_header, add_gossip_peer_from_header, insert_topic_stub,
+    iroh_topic_from_str, rotate_chat_realtime_topic,
+};
 use crate::reaction::{Reaction, set_msg_reaction};
 use crate::rusqlite::OptionalExtension;
 use crate::securejoin::{
@@ -1171,10 +1174,16 @@ async fn decide_chat_assignment(
         true
     } else if mime_parser.get_header(HeaderDef::ChatEdit).is_some()
         || mime_parser.get_header(HeaderDef::ChatDelete).is_some()
+        || mime_parser
+            .get_header(HeaderDef::ChatWebxdcReplace)
+            .is_some()
         || mime_parser.get_header(HeaderDef::IrohNodeAddr).is_some()
         || mime_parser.sync_items.is_some()
     {
-        info!(context, "Chat edit/delete/iroh/sync message (TRASH).");
+        info!(
+            context,
+            "Chat edit/delete/webxdc-replace/iroh/sync message (TRASH)."
+        );
         true
     } else if mime_parser.is_system_message == SystemMessage::CallAccepted
         || mime_parser.is_system_message == SystemMessage::CallEnded
@@ -2058,7 +2067,32 @@ async fn add_parts(
         .await?;
     }
 
-    if let Some(node_addr) = mime_parser.get_header(HeaderDef::IrohNodeAddr) {
+    if let Some(node_addr) = mime_parser.get_header(HeaderDef::IrohNodeAddr)
+        && let Some(topic) = mime_parser.get_header(HeaderDef::IrohGossipTopic)
+    {
+        // Advertisement of a chat realtime channel.
+        match lookup_realtime_chat(context, mime_parser, from_id, to_id).await? {
+            Some(realtime_chat_id) => {
+                if let Err(err) = Box::pin(add_chat_realtime_peer_from_header(
+                    context,
+                    realtime_chat_id,
+                    from_id,
+                    topic,
+                    node_addr,
+                ))
+                .await
+                {
+                    warn!(context, "Failed to add iroh peer from header: {err:#}.");
+                }
+            }
+            None => {
+                warn!(
+                    context,
+                    "Cannot add iroh peer because the chat does not exist or the sender is not a member."
+                );
+            }
+        }
+    } else if let Some(node_addr) = mime_parser.get_header(HeaderDef::IrohNodeAddr) {
         match mime_parser.get_header(HeaderDef::InReplyTo) {
             Some(in_reply_to) => match rfc724_mid_exists(context, in_reply_to).await? {
                 Some(instance_id) => {
@@ -2085,6 +2119,7 @@ async fn add_parts(
     }
 
     handle_edit_delete(context, mime_parser, from_id).await?;
+    Box::pin(handle_webxdc_replace(context, mime_parser, from_id)).await?;
     handle_post_message(context, mime_parser, from_id, state).await?;
 
     if mime_parser.is_system_message == SystemMessage::CallAccepted
@@ -2277,6 +2312,15 @@ async fn add_parts(
         created_db_entries.push(row_id);
     }
 
+    if (mime_parser.crypto_info.encryption != EncryptionType::None
+        || mime_parser.crypto_info.signature_valid)
+        && !chat_id.is_trash()
+    {
+        for msg_id in &created_db_entries {
+            mime_parser.crypto_info.save(context, *msg_id).await?;
+        }
+    }
+
     // Maybe set logging xdc and add gossip topics for webxdcs.
     for (part, msg_id) in mime_parser.parts.iter().zip(&created_db_entries) {
         if mime_parser.pre_message != PreMessageMode::Post
@@ -2295,6 +2339,13 @@ async fn add_parts(
             *msg_id,
         )
         .await?;
+
+        if part.typ == Viewtype::Webxdc && !chat_id.is_trash() {
+            Box::pin(context.maybe_add_webxdc_to_catalog(*msg_id, from_id))
+                .await
+                .log_err(context)
+                .ok();
+        }
     }
 
     let unarchive = match mime_parser.get_header(HeaderDef::ChatGroupMemberRemoved) {
@@ -2348,6 +2399,38 @@ async fn add_parts(
     })
 }
 
+/// Returns the chat a realtime channel advertisement is meant for.
+///
+/// Advertisements are trashed, so the chat is looked up by the group ID
+/// or, for 1:1 chats, by the contact.
+/// Unencrypted advertisements and advertisements from non-members are ignored.
+async fn lookup_realtime_chat(
+    context: &Context,
+    mime_parser: &MimeMessage,
+    from_id: ContactId,
+    to_id: ContactId,
+) -> Result<Option<ChatId>> {
+    if !mime_parser.was_encrypted() {
+        return Ok(None);
+    }
+    if let Some(grpid) = mime_parser.get_chat_group_id() {
+        let Some((chat_id, _blocked)) = chat::get_chat_id_by_grpid(context, grpid).await? else {
+            return Ok(None);
+        };
+        if !is_contact_in_chat(context, chat_id, from_id).await? {
+            return Ok(None);
+        }
+        Ok(Some(chat_id))
+    } else {
+        let contact_id = if from_id == ContactId::SELF {
+            to_id
+        } else {
+            from_id
+        };
+        ChatId::lookup_by_contact(context, contact_id).await
+    }
+}
+
 /// Checks for "Chat-Edit" and "Chat-Delete" headers,
 /// and edits/deletes existing messages accordingly.
 async fn handle_edit_delete(
@@ -2429,6 +2512,62 @@ async fn handle_edit_delete(
     Ok(())
 }
 
+/// Checks for "Chat-Webxdc-Replace" header
+/// and replaces the app of the webxdc instance accordingly.
+async fn handle_webxdc_replace(
+    context: &Context,
+    mime_parser: &MimeMessage,
+    from_id: ContactId,
+) -> Result<()> {
+    let Some(rfc724_mid) = mime_parser.get_header(HeaderDef::ChatWebxdcReplace) else {
+        return Ok(());
+    };
+    let Some(instance_id) = rfc724_mid_exists(context, rfc724_mid).await? else {
+        warn!(
+            context,
+            "Replace webxdc: rfc724_mid {rfc724_mid:?} not found."
+        );
+        return Ok(());
+    };
+    let Some(mut instance) = Message::load_from_db_optional(context, instance_id).await? else {
+        warn!(context, "Replace webxdc: Database entry does not exist.");
+        return Ok(());
+    };
+    if instance.viewtype != Viewtype::Webxdc {
+        warn!(
+            context,
+            "Replace webxdc: {instance_id} is not a webxdc instance."
+        );
+        return Ok(());
+    }
+    // Only the app sender can replace the app.
+    if instance.from_id != from_id {
+        warn!(context, "Replace webxdc: Bad sender.");
+        return Ok(());
+    }
+    let Some(part) = mime_parser
+        .parts
+        .iter()
+        .find(|part| part.typ == Viewtype::Webxdc)
+    else {
+        warn!(context, "Replace webxdc: No webxdc attached.");
+        return Ok(());
+    };
+    if !part
+        .param
+        .get_bool(Param::GuaranteeE2ee)
+        .unwrap_or_default()
+        && instance.get_showpadlock()
+    {
+        warn!(context, "Replace webxdc: Not encrypted.");
+        return Ok(());
+    }
+
+    instance
+        .replace_webxdc_file(context, &part.param, part.bytes)
+        .await
+}
+
 async fn handle_post_message(
     context: &Context,
     mime_parser: &MimeMessage,
@@ -3255,6 +3394,17 @@ async fn apply_group_changes(
         better_msg = Some(String::new());
     }
 
+    if chat_contacts
+        .difference(&new_chat_contacts)
+        .next()
+        .is_some()
+    {
+        // Removed members must not be able to follow the realtime channel anymore.
+        if let Err(err) = Box::pin(rotate_chat_realtime_topic(context, chat.id)).await {
+            warn!(context, "Failed to rotate the realtime topic: {err:#}.");
+        }
+    }
+
     if send_event_chat_modified {
         context.emit_event(EventType::ChatModified(chat.id));
         chatlist_events::emit_chatlist_item_changed(context, chat.id);
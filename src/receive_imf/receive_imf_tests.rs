@@ -14,6 +14,7 @@
 use crate::imap::prefetch_should_download;
 use crate::imex::{ImexMode, imex};
 use crate::key;
+use crate::message::{DeliveryStatus, get_msg_delivery_report};
 use crate::securejoin::get_securejoin_qr;
 use crate::test_utils;
 use crate::test_utils::{
@@ -588,6 +589,91 @@ async fn test_parse_dsn_relayed() {
     .await;
 }
 
+/// Tests that per-recipient delivery status is recorded from DSN.
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_dsn_delivery_report() -> Result<()> {
+    let (t, msg_id) = test_parse_ndn(
+        "anon_1@posteo.de",
+        "anon_2@gmx.at",
+        "8b7b1a9d0c8cc588c7bcac47f5687634@posteo.de",
+        include_bytes!("../../test-data/message/dsn_relayed.eml"),
+        None,
+    )
+    .await;
+    let report = get_msg_delivery_report(&t, msg_id).await?;
+    assert_eq!(report.len(), 1);
+    assert_eq!(report[0].addr, "anon_2@gmx.at");
+    assert_eq!(report[0].status, DeliveryStatus::Delivered);
+    assert_eq!(
+        report[0].reason.as_deref(),
+        Some("250 Requested mail action okay, completed: id=1M9ohD-1lvXys2NFd-005r3O")
+    );
+
+    // Report about delay does not replace final status.
+    let dsn = |action: &str, status: &str| {
+        format!(
+            "From: MAILER-DAEMON@example.org\n\
+             To: anon_1@posteo.de\n\
+             Subject: Delivery Status Notification\n\
+             Message-ID: <dsn-{action}@example.org>\n\
+             Date: Sat, 12 Jun 2021 12:00:00 +0000\n\
+             Content-Type: multipart/report; report-type=delivery-status; boundary=\"b\"\n\
+             \n\
+             --b\n\
+             Content-Type: text/plain\n\
+             \n\
+             Delivery status.\n\
+             --b\n\
+             Content-Type: message/delivery-status\n\
+             \n\
+             Reporting-MTA: dns; example.org\n\
+             \n\
+             Final-Recipient: rfc822; anon_2@gmx.at\n\
+             Action: {action}\n\
+             Status: {status}\n\
+             \n\
+             Final-Recipient: rfc822; <anon_3@gmx.at>\n\
+             Action: {action}\n\
+             Status: {status}\n\
+             \n\
+             --b\n\
+             Content-Type: text/rfc822-headers\n\
+             \n\
+             Message-ID: <8b7b1a9d0c8cc588c7bcac47f5687634@posteo.de>\n\
+             \n\
+             --b--\n"
+        )
+    };
+    receive_imf(&t, dsn("delayed", "4.4.7").as_bytes(), false).await?;
+    let report = get_msg_delivery_report(&t, msg_id).await?;
+    assert_eq!(report.len(), 2);
+    let anon_2 = report.iter().find(|r| r.addr == "anon_2@gmx.at").unwrap();
+    assert_eq!(anon_2.status, DeliveryStatus::Delivered);
+    let anon_3 = report.iter().find(|r| r.addr == "anon_3@gmx.at").unwrap();
+    assert_eq!(anon_3.status, DeliveryStatus::Delayed);
+    assert_eq!(anon_3.reason.as_deref(), Some("4.4.7"));
+    let msg = Message::load_from_db(&t, msg_id).await?;
+    assert_eq!(msg.state, MessageState::OutDelivered);
+    let info = msg_id.get_info(&t).await?;
+    assert!(info.contains("Delivered: "));
+    assert!(info.contains(" for anon_2@gmx.at (250 Requested mail action okay"));
+    assert!(info.contains(" for anon_3@gmx.at (4.4.7)"));
+
+    // A message relayed to the next server can still bounce later.
+    receive_imf(&t, dsn("failed", "5.1.1").as_bytes(), false).await?;
+    let report = get_msg_delivery_report(&t, msg_id).await?;
+    let anon_2 = report.iter().find(|r| r.addr == "anon_2@gmx.at").unwrap();
+    assert_eq!(anon_2.status, DeliveryStatus::Failed);
+    let anon_3 = report.iter().find(|r| r.addr == "anon_3@gmx.at").unwrap();
+    assert_eq!(anon_3.status, DeliveryStatus::Failed);
+    assert_eq!(anon_3.reason.as_deref(), Some("5.1.1"));
+
+    let info = msg_id.get_info(&t).await?;
+    assert!(info.contains("Failed: "));
+    assert!(info.contains(" for anon_3@gmx.at (5.1.1)"));
+    Ok(())
+}
+
 // ndn = Non Delivery Notification
 async fn test_parse_ndn(
     self_addr: &str,
@@ -17,9 +17,10 @@
 use crate::download::{download_known_post_messages_without_pre_message, download_msgs};
 use crate::ephemeral;
 use crate::events::EventType;
-use crate::imap::{Imap, session::Session};
+use crate::imap::{FolderMeaning, Imap, session::Session};
 use crate::location;
 use crate::log::{LogExt, warn};
+use crate::net::traffic::TrafficProtocol;
 use crate::smtp::{Smtp, send_smtp_messages};
 use crate::sql;
 use crate::stats::maybe_send_stats;
@@ -472,22 +473,69 @@ async fn fetch_idle(ctx: &Context, connection: &mut Imap, mut session: Session)
     let transport_id = session.transport_id();
 
     let watch_folder = connection.folder.clone();
+    let traffic_counters = ctx
+        .traffic_metrics
+        .counters(transport_id, TrafficProtocol::Imap);
 
     session
         .store_seen_flags_on_imap(ctx)
         .await
         .context("store_seen_flags_on_imap")?;
 
+    session
+        .setup_notify(ctx, &watch_folder)
+        .await
+        .context("Failed to set up IMAP NOTIFY")
+        .log_err(ctx)
+        .ok();
+
     // Fetch the watched folder.
+    let fetch_start = tools::Time::now();
+    let received_before_fetch = traffic_counters.bytes_received();
+    let read_wait_before_fetch = traffic_counters.read_wait();
     connection
-        .fetch_move_delete(ctx, &mut session, &watch_folder)
+        .fetch_move_delete(ctx, &mut session, &watch_folder, FolderMeaning::Inbox)
         .await
         .context("fetch_move_delete")?;
 
+    // Fetch other folders watched with IMAP NOTIFY.
+    connection
+        .fetch_notify_folders(ctx, &mut session)
+        .await
+        .context("fetch_notify_folders")?;
+
     download_known_post_messages_without_pre_message(ctx, &mut session).await?;
     download_msgs(ctx, &mut session)
         .await
         .context("download_msgs")?;
+    let fetch_duration = time_elapsed(&fetch_start);
+    traffic_counters.add_fetch(fetch_duration);
+    let received = traffic_counters
+        .bytes_received()
+        .saturating_sub(received_before_fetch);
+    // Only the time spent waiting for the network is used,
+    // processing of received messages does not lower the throughput.
+    let read_wait = traffic_counters
+        .read_wait()
+        .saturating_sub(read_wait_before_fetch);
+    if ctx.traffic_metrics.record_throughput(received, read_wait) {
+        let throughput_low = ctx.traffic_metrics.is_throughput_low();
+        info!(
+            ctx,
+            "Transport {transport_id}: Measured download throughput is {}.",
+            if throughput_low { "low" } else { "high" }
+        );
+        if !throughput_low {
+            // Send MDNs deferred while bandwidth was saved.
+            ctx.scheduler.interrupt_smtp().await;
+        }
+    }
+    ctx.traffic_metrics
+        .flush(ctx)
+        .await
+        .context("Failed to store traffic statistics")
+        .log_err(ctx)
+        .ok();
 
     // Synchronize Seen flags.
     session
@@ -525,6 +573,7 @@ async fn fetch_idle(ctx: &Context, connection: &mut Imap, mut session: Session)
         return Ok(session);
     }
 
+    let idle_start = tools::Time::now();
     let session = session
         .idle(
             ctx,
@@ -533,6 +582,7 @@ async fn fetch_idle(ctx: &Context, connection: &mut Imap, mut session: Session)
         )
         .await
         .context("idle")?;
+    traffic_counters.add_idle(time_elapsed(&idle_start));
 
     Ok(session)
 }
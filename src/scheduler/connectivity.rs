@@ -7,6 +7,7 @@
 
 use crate::context::Context;
 use crate::events::EventType;
+use crate::net::proxy::load_active_proxy;
 use crate::quota::{QUOTA_ERROR_THRESHOLD_PERCENTAGE, QUOTA_WARN_THRESHOLD_PERCENTAGE};
 use crate::stock_str;
 
@@ -341,7 +342,12 @@ pub async fn get_connectivity_html(&self) -> Result<String> {
         {
             let proxy_enabled = stock_str::proxy_enabled(self);
             let proxy_description = stock_str::proxy_description(self);
-            ret += &format!("<h3>{proxy_enabled}</h3><ul><li>{proxy_description}</li></ul>");
+            ret += &format!("<h3>{proxy_enabled}</h3><ul><li>{proxy_description}</li>");
+            if let Some((host, port)) = load_active_proxy(self).await? {
+                let proxy_active = stock_str::proxy_active(self, &format!("{host}:{port}"));
+                ret += &format!("<li>{}</li>", escaper::encode_minimal(&proxy_active));
+            }
+            ret += "</ul>";
         }
 
         // =============================================================================================
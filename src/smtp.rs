@@ -1,6 +1,6 @@
 //! # SMTP transport module.
 
-mod connect;
+pub(crate) mod connect;
 pub mod send;
 
 use anyhow::{Context as _, Error, Result, bail, format_err};
@@ -43,6 +43,12 @@ pub(crate) struct Smtp {
 
     /// If sending the last message failed, contains the error message.
     pub(crate) last_send_error: Option<String>,
+
+    /// True if the server rejected the last attempt to request DSN.
+    dsn_unsupported: bool,
+
+    /// Extensions advertised by the server in EHLO response when connecting.
+    extensions: Option<send::Extensions>,
 }
 
 impl Smtp {
@@ -60,6 +66,8 @@ pub fn disconnect(&mut self) {
             task::spawn(async move { transport.quit().await });
         }
         self.last_success = None;
+        self.dsn_unsupported = false;
+        self.extensions = None;
     }
 
     /// Return true if smtp was connected but is not known to
@@ -131,7 +139,7 @@ pub async fn connect(
         let mut first_error = None;
         for lp in login_params {
             info!(context, "SMTP trying to connect to {}.", &lp.connection);
-            let transport = match connect::connect_and_auth(
+            let (transport, extensions) = match connect::connect_and_auth(
                 context,
                 proxy_config,
                 strict_tls,
@@ -143,7 +151,7 @@ pub async fn connect(
             )
             .await
             {
-                Ok(transport) => transport,
+                Ok(res) => res,
                 Err(err) => {
                     warn!(context, "SMTP failed to connect and authenticate: {err:#}.");
                     first_error.get_or_insert(err);
@@ -152,6 +160,7 @@ pub async fn connect(
             };
 
             self.transport = Some(transport);
+            self.extensions = Some(extensions);
             self.last_success = Some(tools::Time::now());
 
             context.emit_event(EventType::SmtpConnected(format!(
@@ -202,9 +211,103 @@ pub(crate) async fn smtp_send(
         return SendResult::Retry;
     }
 
-    let send_result = smtp.send(context, recipients, message.as_bytes()).await;
+    let request_dsn = msg_id.is_some()
+        && context
+            .get_config_bool(Config::RequestDsn)
+            .await
+            .unwrap_or_default();
+    let mut attempt = try_send(context, recipients, message, smtp, request_dsn).await;
+
+    // The server may have rejected only some of the recipients
+    // without pipelining the commands, so it is not known which ones.
+    // In this case the message is sent to the remaining recipients
+    // instead of failing it for everyone.
+    if attempt.permanent_error
+        && recipients.len() > 1
+        && let Some(retry_attempt) =
+            send_to_accepted_recipients(context, recipients, message, smtp, request_dsn).await
+    {
+        attempt = retry_attempt;
+    }
+
+    let rejected: Vec<(String, String)> = attempt
+        .rejected_recipients
+        .into_iter()
+        .map(|(addr, response)| (addr.to_string(), response))
+        .collect();
+    let details = rejected
+        .iter()
+        .map(|(addr, response)| format!("{addr}: {response}"))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let status = match attempt.status {
+        SendResult::Failure(err) if !details.is_empty() => {
+            SendResult::Failure(format_err!("{err:#}\nRejected recipients:\n{details}"))
+        }
+        status => status,
+    };
+
+    if let Some(msg_id) = msg_id {
+        match &status {
+            SendResult::Success if !rejected.is_empty() => {
+                message::set_msg_recipients_failed(context, msg_id, &rejected)
+                    .await
+                    .log_err(context)
+                    .ok();
+            }
+            SendResult::Failure(err) => {
+                // We couldn't send the message, so mark it as failed
+                match Message::load_from_db(context, msg_id).await {
+                    Ok(mut msg) => {
+                        if let Err(err) =
+                            message::set_msg_failed(context, &mut msg, &err.to_string()).await
+                        {
+                            error!(context, "Failed to mark {msg_id} as failed: {err:#}.");
+                        }
+                    }
+                    Err(err) => {
+                        error!(
+                            context,
+                            "Failed to load {msg_id} to mark it as failed: {err:#}."
+                        );
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    status
+}
+
+/// Result of a single attempt to send a message.
+struct SendAttempt {
+    status: SendResult,
+
+    /// True if the server rejected the message with a permanent error.
+    permanent_error: bool,
+
+    /// Recipients rejected by the server together with the server responses.
+    ///
+    /// If the attempt succeeded, the message was sent to the other recipients.
+    rejected_recipients: Vec<(EmailAddress, String)>,
+}
+
+/// Sends the message over the connected SMTP transport
+/// and interprets the result.
+async fn try_send(
+    context: &Context,
+    recipients: &[EmailAddress],
+    message: &str,
+    smtp: &mut Smtp,
+    request_dsn: bool,
+) -> SendAttempt {
+    let send_result = smtp
+        .send(context, recipients, message.as_bytes(), request_dsn)
+        .await;
     smtp.last_send_error = send_result.as_ref().err().map(|e| e.to_string());
 
+    let mut permanent_error = false;
+
     let status = match send_result {
         Err(crate::smtp::send::Error::SmtpSend(err)) => {
             // Remote error, retry later.
@@ -242,6 +345,7 @@ pub(crate) async fn smtp_send(
                         SendResult::Retry
                     } else {
                         info!(context, "Permanent error, message sending failed.");
+                        permanent_error = true;
                         // If we do not retry, add an info message to the chat.
                         // Yandex error "554 5.7.1 [2] Message rejected under suspicion of SPAM; https://ya.cc/..."
                         // should definitely go here, because user has to open the link to
@@ -283,9 +387,14 @@ pub(crate) async fn smtp_send(
                 }
             };
 
-            // this clears last_success info
-            info!(context, "Failed to send message over SMTP, disconnecting.");
-            smtp.disconnect();
+            // If the server rejected the message, the transaction is reset
+            // so the other queued messages are sent over the same connection.
+            if !matches!(err, async_smtp::error::Error::Permanent(_)) || smtp.reset().await.is_err()
+            {
+                // this clears last_success info
+                info!(context, "Failed to send message over SMTP, disconnecting.");
+                smtp.disconnect();
+            }
 
             res
         }
@@ -307,29 +416,88 @@ pub(crate) async fn smtp_send(
             warn!(context, "Unable to load SMTP job: {err:#}.");
             SendResult::Failure(err)
         }
-        Ok(()) => SendResult::Success,
+        Ok(rejected_recipients) => {
+            return SendAttempt {
+                status: SendResult::Success,
+                permanent_error: false,
+                rejected_recipients,
+            };
+        }
     };
 
-    if let SendResult::Failure(err) = &status
-        && let Some(msg_id) = msg_id
+    SendAttempt {
+        status,
+        permanent_error,
+        rejected_recipients: Vec::new(),
+    }
+}
+
+/// Sends the message to the recipients accepted by the server
+/// after the message was rejected with a permanent error.
+///
+/// Returns `None` if the server did not reject any recipient individually,
+/// i.e. the message itself was rejected.
+async fn send_to_accepted_recipients(
+    context: &Context,
+    recipients: &[EmailAddress],
+    message: &str,
+    smtp: &mut Smtp,
+    request_dsn: bool,
+) -> Option<SendAttempt> {
+    if let Err(err) = smtp
+        .connect_configured(context)
+        .await
+        .context("Failed to open SMTP connection")
     {
-        // We couldn't send the message, so mark it as failed
-        match Message::load_from_db(context, msg_id).await {
-            Ok(mut msg) => {
-                if let Err(err) = message::set_msg_failed(context, &mut msg, &err.to_string()).await
-                {
-                    error!(context, "Failed to mark {msg_id} as failed: {err:#}.");
-                }
-            }
-            Err(err) => {
-                error!(
-                    context,
-                    "Failed to load {msg_id} to mark it as failed: {err:#}."
-                );
-            }
+        smtp.last_send_error = Some(format!("{err:#}"));
+        return Some(SendAttempt {
+            status: SendResult::Retry,
+            permanent_error: false,
+            rejected_recipients: Vec::new(),
+        });
+    }
+
+    let mut rejected = match smtp.check_recipients(recipients).await {
+        Ok(rejected) => rejected,
+        Err(err) => {
+            warn!(context, "Failed to check SMTP recipients: {err:#}.");
+            smtp.disconnect();
+            return None;
         }
+    };
+    if rejected.is_empty() {
+        return None;
     }
-    status
+
+    let accepted: Vec<EmailAddress> = recipients
+        .iter()
+        .filter(|addr| {
+            !rejected
+                .iter()
+                .any(|(rejected_addr, _)| rejected_addr == *addr)
+        })
+        .cloned()
+        .collect();
+    if accepted.is_empty() {
+        return Some(SendAttempt {
+            status: SendResult::Failure(format_err!(
+                "Permanent SMTP error, all recipients were rejected."
+            )),
+            permanent_error: true,
+            rejected_recipients: rejected,
+        });
+    }
+
+    info!(
+        context,
+        "{} of {} recipients were rejected, sending to the remaining recipients.",
+        rejected.len(),
+        recipients.len()
+    );
+    let mut attempt = try_send(context, &accepted, message, smtp, request_dsn).await;
+    rejected.append(&mut attempt.rejected_recipients);
+    attempt.rejected_recipients = rejected;
+    Some(attempt)
 }
 
 /// Sends message identified by `smtp` table rowid over SMTP connection.
@@ -519,6 +687,13 @@ pub(crate) async fn send_smtp_messages(context: &Context, connection: &mut Smtp)
         })
         .await?;
 
+    // In low-bandwidth mode, MDNs are only sent along with other messages
+    // instead of connecting to the server for each read receipt,
+    // so more MDNs to the same contact are aggregated.
+    // The SMTP loop is interrupted to send deferred MDNs
+    // once bandwidth is not saved anymore.
+    let mdns_deferred = rowids.is_empty() && context.is_low_bandwidth().await?;
+
     info!(context, "Selected rows from SMTP queue: {rowids:?}.");
     for rowid in rowids {
         send_msg_to_smtp(context, connection, rowid)
@@ -529,7 +704,7 @@ pub(crate) async fn send_smtp_messages(context: &Context, connection: &mut Smtp)
     // although by slow sending, ratelimit may have been expired meanwhile,
     // do not attempt to send MDNs if ratelimited happened before on status-updates/sync:
     // instead, let the caller recall this function so that more important status-updates/sync are sent out.
-    if !ratelimited {
+    if !ratelimited && !mdns_deferred {
         send_mdns(context, connection)
             .await
             .context("Failed to send MDNs")?;
@@ -1,20 +1,26 @@
 //! SMTP connection establishment.
 
 use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Poll, ready};
+use std::time::Duration;
 
 use anyhow::{Context as _, Result, bail};
 use async_smtp::{SmtpClient, SmtpTransport};
-use tokio::io::{AsyncBufRead, AsyncWrite, BufStream};
+use parking_lot::Mutex;
+use tokio::io::{AsyncBufRead, AsyncRead, AsyncWrite, BufStream, ReadBuf};
+
+use super::send::Extensions;
 
 use crate::context::Context;
 use crate::log::warn;
 use crate::net::dns::{lookup_host_with_cache, update_connect_timestamp};
 use crate::net::proxy::ProxyConfig;
-use crate::net::session::SessionBufStream;
+use crate::net::session::{SessionBufStream, SessionStream};
 use crate::net::tls::{SpkiHashStore, TlsSessionStore, wrap_tls};
-use crate::net::{
-    connect_tcp_inner, connect_tls_inner, run_connection_attempts, update_connection_history,
-};
+use crate::net::traffic::{MeteredStream, TrafficCounters, TrafficProtocol, host_counters};
+use crate::net::{connect_tcp_inner, run_connection_attempts, update_connection_history};
 use crate::oauth2::get_oauth2_access_token;
 use crate::sql::Sql;
 use crate::tools::time;
@@ -48,6 +54,112 @@ async fn new_smtp_transport<S: AsyncBufRead + AsyncWrite + Unpin>(
     Ok(transport)
 }
 
+/// Stream recording the data read from the server until the recording is taken.
+///
+/// [`SmtpTransport`] does not expose the EHLO response it receives,
+/// so the response is recorded while the transport is constructed.
+#[derive(Debug)]
+struct RecordingStream {
+    inner: Box<dyn SessionBufStream>,
+
+    /// Data read from the server, `None` once the recording is taken.
+    recording: Arc<Mutex<Option<Vec<u8>>>>,
+
+    /// Copy of the buffer returned by the last `poll_fill_buf()` while recording.
+    buffer: Vec<u8>,
+}
+
+impl AsyncRead for RecordingStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let this = self.get_mut();
+        let filled_len = buf.filled().len();
+        ready!(Pin::new(&mut this.inner).poll_read(cx, buf))?;
+        if let Some(recording) = this.recording.lock().as_mut() {
+            recording.extend_from_slice(buf.filled().get(filled_len..).unwrap_or_default());
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncBufRead for RecordingStream {
+    fn poll_fill_buf(
+        self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> Poll<std::io::Result<&[u8]>> {
+        let this = self.get_mut();
+        let buf = ready!(Pin::new(&mut this.inner).poll_fill_buf(cx))?;
+        if this.recording.lock().is_some() {
+            this.buffer.clear();
+            this.buffer.extend_from_slice(buf);
+        }
+        Poll::Ready(Ok(buf))
+    }
+
+    fn consume(self: Pin<&mut Self>, amt: usize) {
+        let this = self.get_mut();
+        if let Some(recording) = this.recording.lock().as_mut() {
+            recording.extend(this.buffer.drain(..).take(amt));
+        }
+        Pin::new(&mut this.inner).consume(amt);
+    }
+}
+
+impl AsyncWrite for RecordingStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        Pin::new(&mut self.get_mut().inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(
+        self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(
+        self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_shutdown(cx)
+    }
+}
+
+impl SessionStream for RecordingStream {
+    fn set_read_timeout(&mut self, timeout: Option<Duration>) {
+        self.inner.set_read_timeout(timeout);
+    }
+
+    fn peer_addr(&self) -> Result<SocketAddr> {
+        self.inner.peer_addr()
+    }
+}
+
+/// Constructs a new SMTP transport
+/// over a stream with already skipped SMTP greeting
+/// and returns it together with the extensions advertised in EHLO response.
+pub(crate) async fn new_smtp_transport_with_extensions(
+    stream: Box<dyn SessionBufStream>,
+) -> Result<(SmtpTransport<Box<dyn SessionBufStream>>, Extensions)> {
+    let recording = Arc::new(Mutex::new(Some(Vec::new())));
+    let stream: Box<dyn SessionBufStream> = Box::new(RecordingStream {
+        inner: stream,
+        recording: Arc::clone(&recording),
+        buffer: Vec::new(),
+    });
+    let transport = new_smtp_transport(stream).await?;
+    let ehlo_response = recording.lock().take().unwrap_or_default();
+    let extensions = Extensions::parse(&String::from_utf8_lossy(&ehlo_response));
+    Ok((transport, extensions))
+}
+
 #[expect(clippy::too_many_arguments)]
 pub(crate) async fn connect_and_auth(
     context: &Context,
@@ -58,13 +170,44 @@ pub(crate) async fn connect_and_auth(
     addr: &str,
     user: &str,
     password: &str,
-) -> Result<SmtpTransport<Box<dyn SessionBufStream>>> {
-    let session_stream = connect_stream(context, proxy_config.clone(), strict_tls, candidate)
-        .await
-        .context("SMTP failed to connect")?;
-    let mut transport = new_smtp_transport(session_stream).await?;
+) -> Result<(SmtpTransport<Box<dyn SessionBufStream>>, Extensions)> {
+    let (mut transport, extensions) = connect(context, proxy_config, strict_tls, candidate).await?;
+    authenticate(context, &mut transport, oauth2, addr, user, password).await?;
+    Ok((transport, extensions))
+}
 
-    // Authenticate.
+/// Connects to SMTP server and sends EHLO command.
+///
+/// Returns the transport together with the extensions advertised by the server.
+pub(crate) async fn connect(
+    context: &Context,
+    proxy_config: &Option<ProxyConfig>,
+    strict_tls: bool,
+    candidate: ConnectionCandidate,
+) -> Result<(SmtpTransport<Box<dyn SessionBufStream>>, Extensions)> {
+    let counters = host_counters(context, TrafficProtocol::Smtp, &candidate.host).await?;
+    let res = connect_stream(
+        context,
+        proxy_config.clone(),
+        strict_tls,
+        candidate,
+        counters.clone(),
+    )
+    .await
+    .context("SMTP failed to connect");
+    counters.add_connection_attempt(res.is_err());
+    new_smtp_transport_with_extensions(res?).await
+}
+
+/// Logs into SMTP server with the password or OAuth 2 token.
+pub(crate) async fn authenticate(
+    context: &Context,
+    transport: &mut SmtpTransport<Box<dyn SessionBufStream>>,
+    oauth2: bool,
+    addr: &str,
+    user: &str,
+    password: &str,
+) -> Result<()> {
     let (creds, mechanism) = if oauth2 {
         // oauth2
         let access_token = get_oauth2_access_token(context, addr, password, false)
@@ -94,7 +237,7 @@ pub(crate) async fn connect_and_auth(
         .try_login(&creds, &mechanism)
         .await
         .context("SMTP failed to login")?;
-    Ok(transport)
+    Ok(())
 }
 
 async fn connection_attempt(
@@ -103,6 +246,7 @@ async fn connection_attempt(
     security: ConnectionSecurity,
     resolved_addr: SocketAddr,
     strict_tls: bool,
+    counters: Arc<TrafficCounters>,
 ) -> Result<Box<dyn SessionBufStream>> {
     let context = &context;
     let host = &host;
@@ -119,6 +263,7 @@ async fn connection_attempt(
                 &context.tls_session_store,
                 &context.spki_hash_store,
                 &context.sql,
+                counters,
             )
             .await
         }
@@ -130,10 +275,11 @@ async fn connection_attempt(
                 &context.tls_session_store,
                 &context.spki_hash_store,
                 &context.sql,
+                counters,
             )
             .await
         }
-        ConnectionSecurity::Plain => connect_insecure(resolved_addr).await,
+        ConnectionSecurity::Plain => connect_insecure(resolved_addr, counters).await,
     };
     match res {
         Ok(stream) => {
@@ -173,6 +319,7 @@ async fn connect_stream(
     proxy_config: Option<ProxyConfig>,
     strict_tls: bool,
     candidate: ConnectionCandidate,
+    counters: Arc<TrafficCounters>,
 ) -> Result<Box<dyn SessionBufStream>> {
     let host = &candidate.host;
     let port = candidate.port;
@@ -181,14 +328,29 @@ async fn connect_stream(
     if let Some(proxy_config) = proxy_config {
         let stream = match security {
             ConnectionSecurity::Tls => {
-                connect_secure_proxy(context, host, port, strict_tls, proxy_config.clone()).await?
+                connect_secure_proxy(
+                    context,
+                    host,
+                    port,
+                    strict_tls,
+                    proxy_config.clone(),
+                    counters,
+                )
+                .await?
             }
             ConnectionSecurity::Starttls => {
-                connect_starttls_proxy(context, host, port, strict_tls, proxy_config.clone())
-                    .await?
+                connect_starttls_proxy(
+                    context,
+                    host,
+                    port,
+                    strict_tls,
+                    proxy_config.clone(),
+                    counters,
+                )
+                .await?
             }
             ConnectionSecurity::Plain => {
-                connect_insecure_proxy(context, host, port, proxy_config.clone()).await?
+                connect_insecure_proxy(context, host, port, proxy_config.clone(), counters).await?
             }
         };
         update_connection_history(context, "smtp", host, port, host, time()).await?;
@@ -205,7 +367,14 @@ async fn connect_stream(
             .map(|resolved_addr| {
                 let context = context.clone();
                 let host = host.to_string();
-                connection_attempt(context, host, security, resolved_addr, strict_tls)
+                connection_attempt(
+                    context,
+                    host,
+                    security,
+                    resolved_addr,
+                    strict_tls,
+                    counters.clone(),
+                )
             });
         run_connection_attempts(connection_futures).await
     }
@@ -244,11 +413,13 @@ async fn connect_secure_proxy(
     port: u16,
     strict_tls: bool,
     proxy_config: ProxyConfig,
+    counters: Arc<TrafficCounters>,
 ) -> Result<Box<dyn SessionBufStream>> {
     let use_sni = true;
     let proxy_stream = proxy_config
         .connect(context, hostname, port, strict_tls)
         .await?;
+    let proxy_stream = MeteredStream::new(proxy_stream, counters);
     let tls_stream = wrap_tls(
         strict_tls,
         hostname,
@@ -273,11 +444,13 @@ async fn connect_starttls_proxy(
     port: u16,
     strict_tls: bool,
     proxy_config: ProxyConfig,
+    counters: Arc<TrafficCounters>,
 ) -> Result<Box<dyn SessionBufStream>> {
     let use_sni = false;
     let proxy_stream = proxy_config
         .connect(context, hostname, port, strict_tls)
         .await?;
+    let proxy_stream = MeteredStream::new(proxy_stream, counters);
 
     // Run STARTTLS command and convert the client back into a stream.
     let mut buffered_stream = BufStream::new(proxy_stream);
@@ -307,8 +480,10 @@ async fn connect_insecure_proxy(
     hostname: &str,
     port: u16,
     proxy_config: ProxyConfig,
+    counters: Arc<TrafficCounters>,
 ) -> Result<Box<dyn SessionBufStream>> {
     let proxy_stream = proxy_config.connect(context, hostname, port, false).await?;
+    let proxy_stream = MeteredStream::new(proxy_stream, counters);
     let mut buffered_stream = BufStream::new(proxy_stream);
     skip_smtp_greeting(&mut buffered_stream).await?;
     let session_stream: Box<dyn SessionBufStream> = Box::new(buffered_stream);
@@ -322,12 +497,17 @@ async fn connect_secure(
     tls_session_store: &TlsSessionStore,
     spki_hash_store: &SpkiHashStore,
     sql: &Sql,
+    counters: Arc<TrafficCounters>,
 ) -> Result<Box<dyn SessionBufStream>> {
-    let tls_stream = connect_tls_inner(
-        addr,
-        hostname,
+    let use_sni = true;
+    let tcp_stream = MeteredStream::new(connect_tcp_inner(addr).await?, counters);
+    let tls_stream = wrap_tls(
         strict_tls,
+        hostname,
+        addr.port(),
+        use_sni,
         alpn(addr.port()),
+        tcp_stream,
         tls_session_store,
         spki_hash_store,
         sql,
@@ -346,9 +526,10 @@ async fn connect_starttls(
     tls_session_store: &TlsSessionStore,
     spki_hash_store: &SpkiHashStore,
     sql: &Sql,
+    counters: Arc<TrafficCounters>,
 ) -> Result<Box<dyn SessionBufStream>> {
     let use_sni = false;
-    let tcp_stream = connect_tcp_inner(addr).await?;
+    let tcp_stream = MeteredStream::new(connect_tcp_inner(addr).await?, counters);
 
     // Run STARTTLS command and convert the client back into a stream.
     let mut buffered_stream = BufStream::new(tcp_stream);
@@ -374,8 +555,11 @@ async fn connect_starttls(
     Ok(session_stream)
 }
 
-async fn connect_insecure(addr: SocketAddr) -> Result<Box<dyn SessionBufStream>> {
-    let tcp_stream = connect_tcp_inner(addr).await?;
+async fn connect_insecure(
+    addr: SocketAddr,
+    counters: Arc<TrafficCounters>,
+) -> Result<Box<dyn SessionBufStream>> {
+    let tcp_stream = MeteredStream::new(connect_tcp_inner(addr).await?, counters);
     let mut buffered_stream = BufStream::new(tcp_stream);
     skip_smtp_greeting(&mut buffered_stream).await?;
     let session_stream: Box<dyn SessionBufStream> = Box::new(buffered_stream);
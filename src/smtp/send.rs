@@ -1,5 +1,10 @@
 //! # SMTP message sending
 
+use std::fmt;
+
+use async_smtp::commands::{DataCommand, MailCommand, RcptCommand, RsetCommand};
+use async_smtp::extension::{MailBodyParameter, MailParameter, RcptParameter};
+use async_smtp::response::{Category, Code, Detail};
 use async_smtp::{EmailAddress, Envelope, SendableEmail};
 
 use super::Smtp;
@@ -23,15 +28,72 @@ pub enum Error {
     Other(#[from] anyhow::Error),
 }
 
+/// SMTP service extensions advertised by the server in the EHLO response.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Extensions {
+    /// PIPELINING extension defined in RFC 2920.
+    pub pipelining: bool,
+
+    /// CHUNKING extension defined in RFC 3030.
+    pub chunking: bool,
+
+    /// DSN extension defined in RFC 3461.
+    pub dsn: bool,
+
+    /// 8BITMIME extension defined in RFC 6152.
+    pub eight_bit_mime: bool,
+
+    /// SMTPUTF8 extension defined in RFC 6531.
+    pub smtp_utf8: bool,
+}
+
+impl Extensions {
+    /// Parses EHLO response as received from the server.
+    ///
+    /// The first line contains the server name and greeting,
+    /// each following line starts with the keyword of a supported extension.
+    pub(crate) fn parse(response: &str) -> Self {
+        let mut extensions = Self::default();
+        for line in response.lines().skip(1) {
+            // Skip the reply code and the separator.
+            let keyword = line
+                .get(4..)
+                .unwrap_or_default()
+                .split(' ')
+                .next()
+                .unwrap_or_default()
+                .to_ascii_uppercase();
+            match keyword.as_str() {
+                "PIPELINING" => extensions.pipelining = true,
+                "CHUNKING" => extensions.chunking = true,
+                "DSN" => extensions.dsn = true,
+                "8BITMIME" => extensions.eight_bit_mime = true,
+                "SMTPUTF8" => extensions.smtp_utf8 = true,
+                _ => {}
+            }
+        }
+        extensions
+    }
+}
+
 impl Smtp {
     /// Send a prepared mail to recipients.
-    /// On successful send out Ok() is returned.
+    ///
+    /// If `request_dsn` is set, Delivery Status Notifications
+    /// are requested for all recipients if the server advertises DSN extension.
+    /// If the server rejects the DSN parameters nevertheless,
+    /// the message is sent without them.
+    ///
+    /// If the server supports PIPELINING, recipients are rejected individually
+    /// and the message is sent to the accepted ones.
+    /// On success the rejected recipients are returned together with the server responses.
     pub async fn send(
         &mut self,
         context: &Context,
         recipients: &[EmailAddress],
         message: &[u8],
-    ) -> Result<()> {
+        request_dsn: bool,
+    ) -> Result<Vec<(EmailAddress, String)>> {
         if !context.get_config_bool(Config::Bot).await? {
             // Notify ratelimiter about sent message regardless of whether quota is exceeded or not.
             // Checking whether sending is allowed for low-priority messages should be done by the
@@ -42,12 +104,64 @@ pub async fn send(
         let message_len_bytes = message.len();
         let recipients_display = recipients
             .iter()
-            .map(|x| x.as_ref())
+            .map(AsRef::<str>::as_ref)
             .collect::<Vec<&str>>()
             .join(",");
 
         let envelope =
             Envelope::new(self.from.clone(), recipients.to_vec()).map_err(Error::Envelope)?;
+
+        let extensions = self.extensions.unwrap_or_default();
+
+        // Addresses in DSN parameters are not encoded,
+        // so DSN is only requested for ASCII addresses.
+        let request_dsn = request_dsn
+            && extensions.dsn
+            && !self.dsn_unsupported
+            && self
+                .from
+                .as_ref()
+                .is_some_and(|from| AsRef::<str>::as_ref(from).is_ascii())
+            && recipients
+                .iter()
+                .all(|addr| AsRef::<str>::as_ref(addr).is_ascii());
+
+        if let Ok(message) = std::str::from_utf8(message)
+            && (extensions.pipelining || extensions.chunking || request_dsn)
+        {
+            let mut res = self
+                .send_transaction(recipients, message, request_dsn, extensions)
+                .await;
+            if request_dsn
+                && let Err(async_smtp::error::Error::Permanent(response)) = &res
+                && is_unsupported_parameter(response.code)
+            {
+                info!(
+                    context,
+                    "Server does not support DSN, sending without it: {}.",
+                    response.message.join(" ")
+                );
+                self.dsn_unsupported = true;
+                self.reset().await?;
+                res = self
+                    .send_transaction(recipients, message, false, extensions)
+                    .await;
+            }
+            let rejected = res.map_err(Error::SmtpSend)?;
+
+            let info_msg = if request_dsn && !self.dsn_unsupported {
+                format!(
+                    "Message len={message_len_bytes} was SMTP-sent to {recipients_display} requesting DSN"
+                )
+            } else {
+                format!("Message len={message_len_bytes} was SMTP-sent to {recipients_display}")
+            };
+            info!(context, "{info_msg}.");
+            context.emit_event(EventType::SmtpMessageSent(info_msg));
+            self.last_success = Some(tools::Time::now());
+            return Ok(rejected);
+        }
+
         let mail = SendableEmail::new(envelope, message);
 
         if let Some(ref mut transport) = self.transport {
@@ -65,6 +179,547 @@ pub async fn send(
             );
             return Err(Error::NoTransport);
         }
+        Ok(Vec::new())
+    }
+
+    /// Sends the message in a single mail transaction.
+    ///
+    /// If the server supports PIPELINING, MAIL FROM and RCPT TO commands
+    /// are written at once together with DATA or BDAT command
+    /// and the responses are read afterwards as defined in RFC 2920,
+    /// so the number of round trips does not depend on the number of recipients.
+    ///
+    /// If the server supports CHUNKING, the message is sent
+    /// with a single BDAT command as defined in RFC 3030 instead of DATA command.
+    /// Together with PIPELINING the whole transaction then takes one round trip.
+    ///
+    /// If `request_dsn` is set, Delivery Status Notifications are requested
+    /// as defined in RFC 3461.
+    /// Only headers of the message are requested to be returned
+    /// in the notifications.
+    ///
+    /// The message is sent to the recipients accepted by the server,
+    /// rejected recipients are returned together with the server responses.
+    /// If all recipients are rejected, the first rejection is returned as an error.
+    async fn send_transaction(
+        &mut self,
+        recipients: &[EmailAddress],
+        message: &str,
+        request_dsn: bool,
+        extensions: Extensions,
+    ) -> std::result::Result<Vec<(EmailAddress, String)>, async_smtp::error::Error> {
+        let Some(transport) = self.transport.as_mut() else {
+            return Err(async_smtp::error::Error::Client("SMTP has no transport"));
+        };
+        let stream = transport.get_mut();
+
+        // Same parameters as `SmtpTransport::send()` uses.
+        let mut mail_parameters = Vec::new();
+        if extensions.eight_bit_mime {
+            mail_parameters.push(MailParameter::Body(MailBodyParameter::EightBitMime));
+        }
+        if extensions.smtp_utf8 {
+            mail_parameters.push(MailParameter::SmtpUtfEight);
+        }
+        let mut rcpt_parameters = Vec::new();
+        if request_dsn {
+            mail_parameters.push(MailParameter::Other {
+                keyword: "RET".to_string(),
+                value: Some("HDRS".to_string()),
+            });
+            rcpt_parameters.push(RcptParameter::Other {
+                keyword: "NOTIFY".to_string(),
+                value: Some("SUCCESS,FAILURE,DELAY".to_string()),
+            });
+        }
+        let mail_command = MailCommand::new(self.from.clone(), mail_parameters);
+        let rcpt_commands: Vec<RcptCommand> = recipients
+            .iter()
+            .map(|recipient| RcptCommand::new(recipient.clone(), rcpt_parameters.clone()))
+            .collect();
+
+        let mut rcpt_results = Vec::with_capacity(recipients.len());
+        let data_result = if extensions.pipelining {
+            let mut commands = mail_command.to_string();
+            for rcpt_command in &rcpt_commands {
+                commands += &rcpt_command.to_string();
+            }
+            if extensions.chunking {
+                commands += &Bdat(message).to_string();
+            } else {
+                commands += &DataCommand.to_string();
+            }
+            stream.send_command(commands).await?;
+
+            // All responses are read before looking at them
+            // to keep the connection usable.
+            let mail_result = stream.read_response().await;
+            for _ in &rcpt_commands {
+                rcpt_results.push(stream.read_response().await);
+            }
+            let data_result = stream.read_response().await;
+            mail_result?;
+            Some(data_result)
+        } else {
+            stream.command(mail_command).await?;
+            for rcpt_command in rcpt_commands {
+                rcpt_results.push(stream.command(rcpt_command).await);
+            }
+            None
+        };
+
+        let mut rejected = Vec::new();
+        let mut first_rejection = None;
+        for (recipient, rcpt_result) in recipients.iter().zip(rcpt_results) {
+            match rcpt_result {
+                Ok(_) => {}
+                Err(async_smtp::error::Error::Permanent(response)) => {
+                    rejected.push((recipient.clone(), response.message.join(" ")));
+                    first_rejection.get_or_insert(async_smtp::error::Error::Permanent(response));
+                }
+                // The transaction is aborted by closing the connection.
+                Err(err) => return Err(err),
+            }
+        }
+        if rejected.len() == recipients.len()
+            && let Some(err) = first_rejection
+        {
+            if !extensions.chunking
+                && let Some(Ok(_)) = data_result
+            {
+                // Server accepted DATA without recipients,
+                // end the message so the transaction can be reset.
+                stream.command(MessageData("")).await?;
+            }
+            return Err(err);
+        }
+
+        match data_result {
+            // The message was already sent together with BDAT command.
+            Some(bdat_result) if extensions.chunking => {
+                bdat_result?;
+            }
+            Some(data_result) => {
+                data_result?;
+                stream.command(MessageData(message)).await?;
+            }
+            None if extensions.chunking => {
+                stream.command(Bdat(message)).await?;
+            }
+            None => {
+                stream.command(DataCommand).await?;
+                stream.command(MessageData(message)).await?;
+            }
+        }
+        Ok(rejected)
+    }
+
+    /// Aborts the current mail transaction
+    /// so the connection can be used to send other messages.
+    pub(crate) async fn reset(&mut self) -> Result<()> {
+        let transport = self.transport.as_mut().ok_or(Error::NoTransport)?;
+        transport
+            .get_mut()
+            .command(RsetCommand)
+            .await
+            .map_err(Error::SmtpSend)?;
+        Ok(())
+    }
+
+    /// Checks which of the recipients are rejected by the server.
+    ///
+    /// Starts a mail transaction, issues RCPT TO command for each recipient
+    /// and aborts the transaction with RSET without sending the message.
+    ///
+    /// Returns rejected recipients together with the server responses.
+    pub(crate) async fn check_recipients(
+        &mut self,
+        recipients: &[EmailAddress],
+    ) -> Result<Vec<(EmailAddress, String)>> {
+        let transport = self.transport.as_mut().ok_or(Error::NoTransport)?;
+        let stream = transport.get_mut();
+        stream
+            .command(MailCommand::new(self.from.clone(), Vec::new()))
+            .await
+            .map_err(Error::SmtpSend)?;
+
+        let mut rejected = Vec::new();
+        for recipient in recipients {
+            match stream
+                .command(RcptCommand::new(recipient.clone(), Vec::new()))
+                .await
+            {
+                Ok(_) => {}
+                Err(async_smtp::error::Error::Permanent(response)) => {
+                    rejected.push((recipient.clone(), response.message.join(" ")));
+                }
+                Err(err) => return Err(Error::SmtpSend(err)),
+            }
+        }
+
+        stream.command(RsetCommand).await.map_err(Error::SmtpSend)?;
+        Ok(rejected)
+    }
+}
+
+/// Returns true if the response code means that
+/// the server does not support MAIL or RCPT parameters.
+fn is_unsupported_parameter(code: Code) -> bool {
+    matches!(
+        code,
+        // 555 MAIL FROM/RCPT TO parameters not recognized or not implemented.
+        Code {
+            category: Category::MailSystem,
+            detail: Detail::Five,
+            ..
+        }
+        // 501 Syntax error in parameters or arguments.
+        | Code {
+            category: Category::Syntax,
+            detail: Detail::One,
+            ..
+        }
+        // 504 Command parameter not implemented.
+        | Code {
+            category: Category::Syntax,
+            detail: Detail::Four,
+            ..
+        }
+    )
+}
+
+/// Message content sent after DATA command.
+///
+/// Lines starting with a dot are dot-stuffed
+/// and the content is terminated with a line containing a single dot.
+struct MessageData<'a>(&'a str);
+
+impl fmt::Display for MessageData<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for line in self.0.split_inclusive('\n') {
+            if line.starts_with('.') {
+                f.write_str(".")?;
+            }
+            f.write_str(line)?;
+        }
+        if !self.0.is_empty() && !self.0.ends_with('\n') {
+            f.write_str("\r\n")?;
+        }
+        f.write_str(".\r\n")
+    }
+}
+
+/// BDAT command sending the whole message as the last chunk.
+///
+/// Unlike DATA command, the message is sent as is.
+struct Bdat<'a>(&'a str);
+
+impl fmt::Display for Bdat<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "BDAT {} LAST\r\n{}", self.0.len(), self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader, BufStream};
+    use tokio::net::{TcpListener, TcpStream};
+
+    use super::*;
+    use crate::net::session::SessionBufStream;
+    use crate::smtp::connect::new_smtp_transport_with_extensions;
+    use crate::test_utils::TestContext;
+
+    /// Starts a local SMTP server stand-in
+    /// which rejects recipients at `unknown.example.org`.
+    ///
+    /// DSN extension is advertised if `advertise_dsn` is set
+    /// and DSN parameters are rejected unless `support_dsn` is set.
+    /// CHUNKING extension is advertised if `advertise_chunking` is set.
+    ///
+    /// Returns the connection to it
+    /// and the receiver of MAIL, RCPT and BDAT commands sent by the client.
+    async fn smtp_stand_in(
+        advertise_dsn: bool,
+        support_dsn: bool,
+        advertise_chunking: bool,
+    ) -> anyhow::Result<(Smtp, async_channel::Receiver<String>)> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let (command_sender, command_receiver) = async_channel::unbounded();
+        tokio::spawn(async move {
+            let (stream, _addr) = listener.accept().await.unwrap();
+            let (reader, mut writer) = stream.into_split();
+            let mut lines = BufReader::new(reader).lines();
+            let mut mail_accepted = false;
+            let mut rcpt_accepted = false;
+            while let Some(line) = lines.next_line().await.unwrap() {
+                if line.starts_with("MAIL FROM")
+                    || line.starts_with("RCPT TO")
+                    || line.starts_with("BDAT")
+                {
+                    command_sender.send(line.clone()).await.unwrap();
+                }
+                let response = if line.starts_with("EHLO") {
+                    let mut response = "250-localhost\r\n250-PIPELINING\r\n".to_string();
+                    if advertise_dsn {
+                        response += "250-DSN\r\n";
+                    }
+                    if advertise_chunking {
+                        response += "250-CHUNKING\r\n";
+                    }
+                    response += "250 8BITMIME\r\n";
+                    writer.write_all(response.as_bytes()).await.unwrap();
+                    continue;
+                } else if line.starts_with("MAIL FROM") && line.contains("RET=") && !support_dsn {
+                    "555 5.5.4 Unsupported option: RET\r\n"
+                } else if line.starts_with("MAIL FROM") {
+                    mail_accepted = true;
+                    "250 2.0.0 Ok\r\n"
+                } else if line.starts_with("RCPT TO") && !mail_accepted {
+                    "503 5.5.1 Error: need MAIL command\r\n"
+                } else if line.starts_with("RCPT TO") && line.contains("@unknown.example.org") {
+                    "550 5.1.1 Recipient address rejected: User unknown\r\n"
+                } else if line.starts_with("RCPT TO") {
+                    rcpt_accepted = true;
+                    "250 2.0.0 Ok\r\n"
+                } else if line.starts_with("DATA") && !rcpt_accepted {
+                    "554 5.5.1 Error: no valid recipients\r\n"
+                } else if line.starts_with("DATA") {
+                    writer
+                        .write_all(b"354 End data with <CR><LF>.<CR><LF>\r\n")
+                        .await
+                        .unwrap();
+                    while let Some(line) = lines.next_line().await.unwrap() {
+                        if line == "." {
+                            break;
+                        }
+                    }
+                    mail_accepted = false;
+                    rcpt_accepted = false;
+                    "250 2.0.0 Ok: queued\r\n"
+                } else if let Some(args) = line.strip_prefix("BDAT ") {
+                    let size: usize = args.trim_end_matches(" LAST").parse().unwrap();
+                    let mut chunk = vec![0; size];
+                    lines.get_mut().read_exact(&mut chunk).await.unwrap();
+                    let accepted = rcpt_accepted;
+                    mail_accepted = false;
+                    rcpt_accepted = false;
+                    if accepted {
+                        "250 2.0.0 Ok: queued\r\n"
+                    } else {
+                        "554 5.5.1 Error: no valid recipients\r\n"
+                    }
+                } else if line.starts_with("RSET") {
+                    mail_accepted = false;
+                    rcpt_accepted = false;
+                    "250 2.0.0 Ok\r\n"
+                } else if line.starts_with("QUIT") {
+                    "221 2.0.0 Bye\r\n"
+                } else {
+                    "250 2.0.0 Ok\r\n"
+                };
+                writer.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+
+        let tcp_stream = Box::pin(tokio_io_timeout::TimeoutStream::new(
+            TcpStream::connect(addr).await?,
+        ));
+        let session_stream: Box<dyn SessionBufStream> = Box::new(BufStream::new(tcp_stream));
+        let (transport, extensions) = new_smtp_transport_with_extensions(session_stream).await?;
+
+        let mut smtp = Smtp::new();
+        smtp.transport = Some(transport);
+        smtp.extensions = Some(extensions);
+        smtp.from = Some(EmailAddress::new("alice@example.org".to_string())?);
+        Ok((smtp, command_receiver))
+    }
+
+    #[test]
+    fn test_extensions_parse() {
+        let response = "250-localhost\r\n\
+                        250-PIPELINING\r\n\
+                        250-SIZE 10240000\r\n\
+                        250-dsn\r\n\
+                        250-CHUNKING\r\n\
+                        250-8BITMIME\r\n\
+                        250 SMTPUTF8\r\n";
+        assert_eq!(
+            Extensions::parse(response),
+            Extensions {
+                pipelining: true,
+                chunking: true,
+                dsn: true,
+                eight_bit_mime: true,
+                smtp_utf8: true,
+            }
+        );
+
+        // The first line is the greeting.
+        assert_eq!(
+            Extensions::parse("250 PIPELINING\r\n"),
+            Extensions::default()
+        );
+    }
+
+    #[test]
+    fn test_message_data() {
+        assert_eq!(
+            MessageData("Subject: Hi\r\n\r\n.hidden\r\nend").to_string(),
+            "Subject: Hi\r\n\r\n..hidden\r\nend\r\n.\r\n"
+        );
+        assert_eq!(MessageData("text\r\n").to_string(), "text\r\n.\r\n");
+        assert_eq!(
+            Bdat(".hidden\r\n").to_string(),
+            "BDAT 9 LAST\r\n.hidden\r\n"
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_send_with_dsn() -> anyhow::Result<()> {
+        let t = &TestContext::new().await;
+        let recipients = [EmailAddress::new("bob@example.net".to_string())?];
+        let message = b"Subject: Hi\r\n\r\nHello\r\n";
+
+        let (mut smtp, commands) = smtp_stand_in(true, true, false).await?;
+        smtp.send(t, &recipients, message, true).await?;
+        assert_eq!(
+            commands.recv().await?,
+            "MAIL FROM:<alice@example.org> BODY=8BITMIME RET=HDRS"
+        );
+        assert_eq!(
+            commands.recv().await?,
+            "RCPT TO:<bob@example.net> NOTIFY=SUCCESS,FAILURE,DELAY"
+        );
+
+        // Server does not advertise DSN, the message is sent without requesting it.
+        let (mut smtp, commands) = smtp_stand_in(false, false, false).await?;
+        smtp.send(t, &recipients, message, true).await?;
+        assert!(!smtp.dsn_unsupported);
+        assert_eq!(
+            commands.recv().await?,
+            "MAIL FROM:<alice@example.org> BODY=8BITMIME"
+        );
+        assert_eq!(commands.recv().await?, "RCPT TO:<bob@example.net>");
+
+        // Server advertises DSN but rejects the parameters, the message is sent without them.
+        let (mut smtp, commands) = smtp_stand_in(true, false, false).await?;
+        smtp.send(t, &recipients, message, true).await?;
+        assert!(smtp.dsn_unsupported);
+        assert!(commands.recv().await?.contains("RET=HDRS"));
+        assert!(commands.recv().await?.contains("NOTIFY="));
+        assert_eq!(
+            commands.recv().await?,
+            "MAIL FROM:<alice@example.org> BODY=8BITMIME"
+        );
+        assert_eq!(commands.recv().await?, "RCPT TO:<bob@example.net>");
+
+        // DSN is not requested again on the same connection.
+        smtp.send(t, &recipients, message, true).await?;
+        assert!(!commands.recv().await?.contains("RET=HDRS"));
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_check_recipients() -> anyhow::Result<()> {
+        let (mut smtp, _commands) = smtp_stand_in(true, true, false).await?;
+        let recipients = [
+            EmailAddress::new("bob@example.net".to_string())?,
+            EmailAddress::new("nobody@unknown.example.org".to_string())?,
+            EmailAddress::new("fiona@example.net".to_string())?,
+        ];
+
+        let rejected = smtp.check_recipients(&recipients).await?;
+        assert_eq!(rejected.len(), 1);
+        assert_eq!(rejected[0].0.to_string(), "nobody@unknown.example.org");
+        assert_eq!(
+            rejected[0].1,
+            "5.1.1 Recipient address rejected: User unknown"
+        );
+
+        // The connection can be used after aborting the transaction.
+        let rejected = smtp.check_recipients(&recipients[..1]).await?;
+        assert!(rejected.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_send_pipelined() -> anyhow::Result<()> {
+        let t = &TestContext::new().await;
+        let (mut smtp, commands) = smtp_stand_in(true, true, false).await?;
+        let recipients = [
+            EmailAddress::new("bob@example.net".to_string())?,
+            EmailAddress::new("nobody@unknown.example.org".to_string())?,
+        ];
+        let message = b"Subject: Hi\r\n\r\nHello\r\n";
+
+        // The message is sent to the accepted recipient.
+        let rejected = smtp.send(t, &recipients, message, false).await?;
+        assert_eq!(rejected.len(), 1);
+        assert_eq!(rejected[0].0.to_string(), "nobody@unknown.example.org");
+        assert_eq!(
+            commands.recv().await?,
+            "MAIL FROM:<alice@example.org> BODY=8BITMIME"
+        );
+        assert_eq!(commands.recv().await?, "RCPT TO:<bob@example.net>");
+        assert_eq!(
+            commands.recv().await?,
+            "RCPT TO:<nobody@unknown.example.org>"
+        );
+
+        // If all recipients are rejected, the message is not sent
+        // and the connection can be used to send the next message.
+        let res = smtp.send(t, &recipients[1..], message, false).await;
+        assert!(matches!(
+            res,
+            Err(Error::SmtpSend(async_smtp::error::Error::Permanent(_)))
+        ));
+        smtp.reset().await?;
+        assert!(
+            smtp.send(t, &recipients[..1], message, false)
+                .await?
+                .is_empty()
+        );
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_send_chunked() -> anyhow::Result<()> {
+        let t = &TestContext::new().await;
+        let (mut smtp, commands) = smtp_stand_in(false, false, true).await?;
+        let recipients = [
+            EmailAddress::new("bob@example.net".to_string())?,
+            EmailAddress::new("nobody@unknown.example.org".to_string())?,
+        ];
+        let message = b"Subject: Hi\r\n\r\n.Hello\r\n";
+
+        let rejected = smtp.send(t, &recipients, message, false).await?;
+        assert_eq!(rejected.len(), 1);
+        assert_eq!(
+            commands.recv().await?,
+            "MAIL FROM:<alice@example.org> BODY=8BITMIME"
+        );
+        assert_eq!(commands.recv().await?, "RCPT TO:<bob@example.net>");
+        assert_eq!(
+            commands.recv().await?,
+            "RCPT TO:<nobody@unknown.example.org>"
+        );
+        assert_eq!(commands.recv().await?, "BDAT 23 LAST");
+
+        // If all recipients are rejected, the server discards the chunk
+        // and the connection can be used to send the next message.
+        let res = smtp.send(t, &recipients[1..], message, false).await;
+        assert!(matches!(
+            res,
+            Err(Error::SmtpSend(async_smtp::error::Error::Permanent(_)))
+        ));
+        smtp.reset().await?;
+        assert!(
+            smtp.send(t, &recipients[..1], message, false)
+                .await?
+                .is_empty()
+        );
         Ok(())
     }
 }
@@ -21,6 +21,7 @@
 use crate::net::dns::prune_dns_cache;
 use crate::net::http::http_cache_cleanup;
 use crate::net::prune_connection_history;
+use crate::net::traffic::prune_traffic_stats;
 use crate::param::{Param, Params};
 use crate::tools::{SystemTime, delete_file, time};
 
@@ -851,6 +852,54 @@ pub async fn housekeeping(context: &Context) -> Result<()> {
         .log_err(context)
         .ok();
 
+    context
+        .sql
+        .execute(
+            "DELETE FROM msgs_delivery WHERE msg_id NOT IN \
+            (SELECT id FROM msgs WHERE chat_id!=?)",
+            (DC_CHAT_ID_TRASH,),
+        )
+        .await
+        .context("failed to remove old delivery reports")
+        .log_err(context)
+        .ok();
+
+    context
+        .sql
+        .execute(
+            "DELETE FROM call_participants WHERE msg_id NOT IN \
+            (SELECT id FROM msgs WHERE chat_id!=?)",
+            (DC_CHAT_ID_TRASH,),
+        )
+        .await
+        .context("failed to remove old call participants")
+        .log_err(context)
+        .ok();
+
+    context
+        .sql
+        .execute(
+            "DELETE FROM webxdc_permissions WHERE msg_id NOT IN \
+            (SELECT id FROM msgs WHERE chat_id!=?)",
+            (DC_CHAT_ID_TRASH,),
+        )
+        .await
+        .context("failed to remove old webxdc permissions")
+        .log_err(context)
+        .ok();
+
+    context
+        .sql
+        .execute(
+            "DELETE FROM webxdc_instance_apps WHERE msg_id NOT IN \
+            (SELECT id FROM msgs WHERE chat_id!=?)",
+            (DC_CHAT_ID_TRASH,),
+        )
+        .await
+        .context("failed to remove old webxdc instance apps")
+        .log_err(context)
+        .ok();
+
     context
         .sql
         .execute(
@@ -863,6 +912,18 @@ pub async fn housekeeping(context: &Context) -> Result<()> {
         .log_err(context)
         .ok();
 
+    context
+        .sql
+        .execute(
+            "DELETE FROM webxdc_status_updates_size WHERE msg_id NOT IN \
+            (SELECT id FROM msgs WHERE chat_id!=?)",
+            (DC_CHAT_ID_TRASH,),
+        )
+        .await
+        .context("failed to remove old webxdc status update sizes")
+        .log_err(context)
+        .ok();
+
     prune_connection_history(context)
         .await
         .context("Failed to prune connection history")
@@ -873,6 +934,18 @@ pub async fn housekeeping(context: &Context) -> Result<()> {
         .context("Failed to prune DNS cache")
         .log_err(context)
         .ok();
+    context
+        .traffic_metrics
+        .flush(context)
+        .await
+        .context("Failed to store traffic statistics")
+        .log_err(context)
+        .ok();
+    prune_traffic_stats(context)
+        .await
+        .context("Failed to prune traffic statistics")
+        .log_err(context)
+        .ok();
 
     context
         .spki_hash_store
@@ -989,6 +1062,25 @@ pub async fn remove_unused_files(context: &Context) -> Result<()> {
         .await
         .context("Failed to SELECT blobname FROM http_cache")?;
 
+    context
+        .sql
+        .query_map(
+            "SELECT icon FROM webxdc_catalog UNION ALL SELECT xdc FROM webxdc_catalog",
+            (),
+            |row| {
+                let row: String = row.get(0)?;
+                Ok(row)
+            },
+            |rows| {
+                for row in rows {
+                    maybe_add_file(&mut files_in_use, &row?);
+                }
+                Ok(())
+            },
+        )
+        .await
+        .context("Failed to SELECT blobs FROM webxdc_catalog")?;
+
     info!(context, "{} files in use.", files_in_use.len());
     /* go through directories and delete unused files */
     let blobdir = context.get_blobdir();
@@ -2434,6 +2434,177 @@ pub async fn run(context: &Context, sql: &Sql) -> Result<bool> {
         .await?;
     }
 
+    inc_and_check(&mut migration_version, 154)?;
+    if dbversion < migration_version {
+        sql.execute_migration(
+            "CREATE TABLE msgs_crypto_info (
+               msg_id INTEGER PRIMARY KEY,
+               encryption INTEGER NOT NULL, -- EncryptionType
+               signer_fingerprints TEXT NOT NULL, -- space-separated hex fingerprints
+               signature_valid INTEGER NOT NULL,
+               header_protection INTEGER NOT NULL,
+               gossip_addrs TEXT NOT NULL, -- space-separated addresses
+               FOREIGN KEY(msg_id) REFERENCES msgs(id) ON DELETE CASCADE
+             ) STRICT;",
+            migration_version,
+        )
+        .await?;
+    }
+
+    inc_and_check(&mut migration_version, 155)?;
+    if dbversion < migration_version {
+        sql.execute_migration(
+            "ALTER TABLE tls_spki ADD COLUMN manual INTEGER NOT NULL DEFAULT 0; -- 1 if pinned by the user",
+            migration_version,
+        )
+        .await?;
+    }
+
+    inc_and_check(&mut migration_version, 156)?;
+    if dbversion < migration_version {
+        sql.execute_migration(
+            "CREATE TABLE traffic_stats (
+               day TEXT NOT NULL, -- UTC day in YYYY-MM-DD format
+               transport_id INTEGER NOT NULL, -- 0 for traffic not related to a transport
+               protocol TEXT NOT NULL, -- imap, smtp, http or iroh
+               bytes_sent INTEGER NOT NULL DEFAULT 0,
+               bytes_received INTEGER NOT NULL DEFAULT 0,
+               connection_attempts INTEGER NOT NULL DEFAULT 0,
+               connection_failures INTEGER NOT NULL DEFAULT 0,
+               idle_count INTEGER NOT NULL DEFAULT 0,
+               idle_duration_ms INTEGER NOT NULL DEFAULT 0,
+               fetch_count INTEGER NOT NULL DEFAULT 0,
+               fetch_duration_ms INTEGER NOT NULL DEFAULT 0,
+               PRIMARY KEY(day, transport_id, protocol)
+             ) STRICT;",
+            migration_version,
+        )
+        .await?;
+    }
+
+    inc_and_check(&mut migration_version, 157)?;
+    if dbversion < migration_version {
+        sql.execute_migration(
+            "CREATE TABLE msgs_delivery (
+               msg_id INTEGER NOT NULL,
+               addr TEXT NOT NULL, -- recipient address from the delivery status notification
+               status INTEGER NOT NULL, -- DeliveryStatus
+               reason TEXT NOT NULL DEFAULT '', -- diagnostic code
+               timestamp INTEGER NOT NULL,
+               PRIMARY KEY(msg_id, addr)
+             ) STRICT;",
+            migration_version,
+        )
+        .await?;
+    }
+
+    inc_and_check(&mut migration_version, 158)?;
+    if dbversion < migration_version {
+        sql.execute_migration(
+            "CREATE TABLE call_participants (
+               msg_id INTEGER NOT NULL, -- ID of the call message
+               contact_id INTEGER NOT NULL,
+               joined_timestamp INTEGER NOT NULL,
+               left_timestamp INTEGER NOT NULL DEFAULT 0, -- 0 if the contact is still in the call
+               PRIMARY KEY(msg_id, contact_id)
+             ) STRICT;",
+            migration_version,
+        )
+        .await?;
+    }
+
+    inc_and_check(&mut migration_version, 159)?;
+    if dbversion < migration_version {
+        sql.execute_migration(
+            "CREATE TABLE webxdc_catalog (
+               app_id TEXT PRIMARY KEY, -- `app_id` from the manifest
+               name TEXT NOT NULL,
+               description TEXT NOT NULL DEFAULT '',
+               version TEXT NOT NULL DEFAULT '',
+               source_code_url TEXT NOT NULL DEFAULT '',
+               url TEXT NOT NULL DEFAULT '', -- download URL, empty if received from the catalog bot
+               icon TEXT NOT NULL DEFAULT '', -- blob name of the icon
+               xdc TEXT NOT NULL DEFAULT '', -- blob name of the .xdc file, empty if not downloaded yet
+               notified_version TEXT NOT NULL DEFAULT '', -- last version announced as an update
+               timestamp INTEGER NOT NULL
+             ) STRICT;",
+            migration_version,
+        )
+        .await?;
+    }
+
+    inc_and_check(&mut migration_version, 160)?;
+    if dbversion < migration_version {
+        sql.execute_migration(
+            "CREATE TABLE webxdc_permissions (
+               msg_id INTEGER NOT NULL, -- webxdc instance
+               permission TEXT NOT NULL, -- `WebxdcPermission`, eg. 'realtime'
+               granted INTEGER NOT NULL, -- 1=granted, 0=revoked by the user
+               PRIMARY KEY(msg_id, permission)
+             ) STRICT;",
+            migration_version,
+        )
+        .await?;
+    }
+
+    inc_and_check(&mut migration_version, 161)?;
+    if dbversion < migration_version {
+        sql.execute_migration(
+            "CREATE TABLE chat_realtime_peers (
+               chat_id INTEGER NOT NULL,
+               topic BLOB NOT NULL,
+               public_key BLOB NOT NULL, -- `PUBLIC_KEY_STUB` for the topic of the chat
+               relay_server TEXT NOT NULL DEFAULT '', -- empty string if unknown
+               contact_id INTEGER NOT NULL DEFAULT 0, -- 0 if the peer was not advertised
+               PRIMARY KEY(chat_id, public_key)
+             ) STRICT;",
+            migration_version,
+        )
+        .await?;
+    }
+
+    inc_and_check(&mut migration_version, 162)?;
+    if dbversion < migration_version {
+        sql.execute_migration(
+            "CREATE TABLE webxdc_instance_apps (
+               msg_id INTEGER PRIMARY KEY, -- webxdc instance
+               app_id TEXT NOT NULL, -- app_id from manifest.toml or empty string
+               version TEXT NOT NULL -- version from manifest.toml or empty string
+             ) STRICT;
+             CREATE INDEX webxdc_instance_apps_index1 ON webxdc_instance_apps (app_id);",
+            migration_version,
+        )
+        .await?;
+    }
+
+    inc_and_check(&mut migration_version, 163)?;
+    if dbversion < migration_version {
+        sql.execute_migration(
+            "CREATE TABLE webxdc_status_updates_size (
+               msg_id INTEGER PRIMARY KEY, -- webxdc instance
+               size INTEGER NOT NULL -- total size of the status updates in bytes
+             ) STRICT;
+             INSERT INTO webxdc_status_updates_size (msg_id, size)
+             SELECT msg_id, SUM(LENGTH(CAST(update_item AS BLOB)))
+             FROM msgs_status_updates GROUP BY msg_id;",
+            migration_version,
+        )
+        .await?;
+    }
+
+    inc_and_check(&mut migration_version, 164)?;
+    if dbversion < migration_version {
+        sql.execute_migration(
+            "CREATE TABLE chat_realtime_retired_topics (
+               chat_id INTEGER NOT NULL,
+               topic BLOB NOT NULL, -- topic used before a member was removed
+               PRIMARY KEY(chat_id, topic)
+             ) STRICT;",
+            migration_version,
+        )
+        .await?;
+    }
+
     let new_version = sql
         .get_raw_config_int(VERSION_CFG)
         .await?
@@ -421,6 +421,12 @@ pub enum StockMessage {
 
     #[strum(props(fallback = "Messages are end-to-end encrypted."))]
     MessagesAreE2ee = 242,
+
+    #[strum(props(fallback = "Connected through %1$s."))]
+    ProxyActive = 243,
+
+    #[strum(props(fallback = "I am not available for calls right now."))]
+    UnavailableForCalls = 244,
 }
 
 impl StockMessage {
@@ -807,6 +813,11 @@ pub(crate) fn missed_call(context: &Context) -> String {
     translated(context, StockMessage::MissedCall)
 }
 
+/// Stock string: `I am not available for calls right now.`.
+pub(crate) fn unavailable_for_calls(context: &Context) -> String {
+    translated(context, StockMessage::UnavailableForCalls)
+}
+
 /// Stock string: `Scan to chat with %1$s`.
 pub(crate) fn setup_contact_qr_description(
     context: &Context,
@@ -1191,6 +1202,11 @@ pub(crate) fn proxy_description(context: &Context) -> String {
     translated(context, StockMessage::ProxyEnabledDescription)
 }
 
+/// Stock string: `Connected through %1$s.`.
+pub(crate) fn proxy_active(context: &Context, proxy: &str) -> String {
+    translated(context, StockMessage::ProxyActive).replace1(proxy)
+}
+
 /// Stock string: `Messages in this chat use classic email and are not encrypted.`.
 pub(crate) fn chat_unencrypted_explanation(context: &Context) -> String {
     translated(context, StockMessage::ChatUnencryptedExplanation)
@@ -1,6 +1,10 @@
 //! Module to collect and display Disk Space Usage of a Profile.
-use crate::{context::Context, message::MsgId};
-use anyhow::Result;
+use crate::webxdc::STATUS_UPDATES_QUOTA;
+use crate::{
+    context::Context,
+    message::{Message, MsgId, Viewtype},
+};
+use anyhow::{Result, ensure};
 use humansize::{BINARY, format_size};
 use walkdir::WalkDir;
 
@@ -49,6 +53,51 @@ fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
     }
 }
 
+/// Storage usage of a single webxdc instance.
+#[derive(Debug)]
+pub struct WebxdcStorageUsage {
+    /// Total size of the status updates in bytes
+    pub status_updates_size: u64,
+    /// Number of status updates
+    pub status_updates_count: u64,
+    /// Maximum size of the status updates in bytes
+    pub status_updates_quota: u64,
+    /// Size of the .xdc file in bytes
+    pub xdc_size: u64,
+}
+
+/// Get storage usage of the webxdc instance `instance_msg_id`
+pub async fn get_webxdc_storage_usage(
+    ctx: &Context,
+    instance_msg_id: MsgId,
+) -> Result<WebxdcStorageUsage> {
+    let instance = Message::load_from_db(ctx, instance_msg_id).await?;
+    ensure!(
+        instance.get_viewtype() == Viewtype::Webxdc,
+        "{instance_msg_id} is not a webxdc instance"
+    );
+    let (status_updates_size, status_updates_count) = ctx
+        .sql
+        .query_row(
+            "SELECT IFNULL(SUM(LENGTH(update_item)), 0), COUNT(*)
+                 FROM msgs_status_updates WHERE msg_id=?",
+            (instance_msg_id,),
+            |row| {
+                let size: u64 = row.get(0)?;
+                let count: u64 = row.get(1)?;
+                Ok((size, count))
+            },
+        )
+        .await?;
+
+    Ok(WebxdcStorageUsage {
+        status_updates_size,
+        status_updates_count,
+        status_updates_quota: STATUS_UPDATES_QUOTA as u64,
+        xdc_size: instance.get_filebytes(ctx).await?.unwrap_or_default(),
+    })
+}
+
 /// Get storage usage information for the Context's database
 #[expect(clippy::arithmetic_side_effects)]
 pub async fn get_storage_usage(ctx: &Context) -> Result<StorageUsage> {
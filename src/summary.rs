@@ -253,7 +253,10 @@ async fn get_summary_text_without_prefix(&self, context: &Context) -> String {
                     .unwrap_or(CallState::Alerting);
                 emoji = Some(if has_video { "🎥" } else { "📞" });
                 type_name = Some(match call_state {
-                    CallState::Alerting | CallState::Active | CallState::Completed { .. } => {
+                    CallState::Alerting
+                    | CallState::Active
+                    | CallState::Completed { .. }
+                    | CallState::InProgress => {
                         if self.from_id == ContactId::SELF {
                             stock_str::outgoing_call(context, has_video)
                         } else {
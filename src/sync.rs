@@ -121,6 +121,12 @@ pub(crate) enum SyncData {
         /// Removed transports with the timestamp of removal.
         removed_transports: Vec<RemovedTransportData>,
     },
+
+    /// Clear call history.
+    ClearCallHistory {
+        /// Calls with this or older timestamp are cleared.
+        timestamp: i64,
+    },
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -328,6 +334,9 @@ pub(crate) async fn execute_sync_items(&self, items: &SyncItems, timestamp_sent:
                         transports,
                         removed_transports,
                     } => sync_transports(self, transports, removed_transports).await,
+                    SyncData::ClearCallHistory { timestamp } => {
+                        self.sync_clear_call_history(*timestamp).await
+                    }
                 },
                 SyncDataOrUnknown::Unknown(data) => {
                     warn!(self, "Ignored unknown sync item: {data}.");
@@ -1841,6 +1841,43 @@ pub(crate) fn create_test_image(width: u32, height: u32) -> Result<Vec<u8>> {
     Ok(bytes)
 }
 
+/// Creates a webxdc app with the given manifest.toml and an empty index.html.
+pub(crate) async fn create_xdc(manifest: &str) -> Result<Vec<u8>> {
+    use async_zip::tokio::write::ZipFileWriter;
+    use async_zip::{Compression, ZipEntryBuilder};
+    use futures::io::Cursor as FuturesCursor;
+    use tokio_util::compat::FuturesAsyncWriteCompatExt;
+
+    let futures_cursor = FuturesCursor::new(Vec::new());
+    let mut buffer = futures_cursor.compat_write();
+    let mut writer = ZipFileWriter::with_tokio(&mut buffer);
+    writer
+        .write_entry_whole(
+            ZipEntryBuilder::new("manifest.toml".into(), Compression::Stored),
+            manifest.as_bytes(),
+        )
+        .await?;
+    writer
+        .write_entry_whole(
+            ZipEntryBuilder::new("index.html".into(), Compression::Stored),
+            b"<html></html>",
+        )
+        .await?;
+    writer.close().await?;
+    Ok(buffer.into_inner().into_inner())
+}
+
+/// Sends an encrypted webxdc instance of an app with the given manifest.toml.
+pub(crate) async fn send_xdc(t: &TestContext, chat_id: ChatId, manifest: &str) -> Result<MsgId> {
+    use crate::message::Viewtype;
+    use crate::param::Param;
+
+    let mut instance = Message::new(Viewtype::Webxdc);
+    instance.set_file_from_bytes(t, "app.xdc", &create_xdc(manifest).await?, None)?;
+    instance.param.set_int(Param::GuaranteeE2ee, 1);
+    chat::send_msg(t, chat_id, &mut instance).await
+}
+
 mod tests {
     use super::*;
 
@@ -49,6 +49,9 @@ async fn test_parse_receive_headers_integration() {
 
     let raw = include_bytes!("../../test-data/message/encrypted_with_received_headers.eml");
     let expected = "State: Fresh, Encrypted
+Encryption: PublicKey
+Signed by: CCCB5AA9F6E1141C943165F1DB18B18CBCF70487
+Header protection: yes
 
 Message-ID: Mr.adQpEwndXLH.LPDdlFVJ7wG@example.net
 
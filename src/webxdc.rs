@@ -7,6 +7,13 @@
 //! - `update_item` - JSON representation of the status update
 //! - `uid` - "id" field of the update, used for deduplication
 //!
+//! Snapshot updates contain the whole state of the app,
+//! previous updates of the instance are deleted when a snapshot is stored.
+//! Only the sender of the instance can send snapshot updates.
+//!
+//! The total size of the status updates of each instance is kept
+//! in the `webxdc_status_updates_size` SQL table to enforce [`STATUS_UPDATES_QUOTA`].
+//!
 //! Status updates are scheduled for sending by adding a record
 //! to `smtp_status_updates_table` SQL table.
 //! `smtp_status_updates` contains the following columns:
@@ -15,8 +22,13 @@
 //! - `last_serial` - serial number of the last status update to send
 //! - `descr` - not used, set to empty string
 
+mod catalog;
 mod integration;
 mod maps_integration;
+mod permissions;
+
+pub use catalog::WebxdcCatalogEntry;
+pub use permissions::WebxdcPermission;
 
 use std::cmp::max;
 use std::collections::HashMap;
@@ -76,6 +88,19 @@ pub struct WebxdcManifest {
 
     /// Set to "map" to request integration.
     pub request_integration: Option<String>,
+
+    /// Unique ID of the app, used to find it in the webxdc catalog.
+    pub app_id: Option<String>,
+
+    /// Version of the app.
+    #[serde(alias = "tag_name")]
+    pub version: Option<String>,
+
+    /// Short description of the app.
+    pub description: Option<String>,
+
+    /// Permissions requested by the app, see [`WebxdcPermission`].
+    pub permissions: Option<Vec<String>>,
 }
 
 /// Parsed information from WebxdcManifest and fallbacks.
@@ -108,6 +133,12 @@ pub struct WebxdcInfo {
     /// and sent to self for this.
     pub internet_access: bool,
 
+    /// Permissions requested by the app.
+    pub permissions: Vec<WebxdcPermission>,
+
+    /// Permissions granted to this instance.
+    pub granted_permissions: Vec<WebxdcPermission>,
+
     /// Address to be used for `window.webxdc.selfAddr` in JS land.
     pub self_addr: String,
 
@@ -213,6 +244,11 @@ pub struct StatusUpdateItem {
     /// Array of other users `selfAddr` that should be notified about this update.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub notify: Option<HashMap<String, String>>,
+
+    /// If true, the update contains the whole state of the app
+    /// and supersedes all previous updates, which are deleted then.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub snapshot: Option<bool>,
 }
 
 /// Update items as passed to the UIs.
@@ -241,6 +277,17 @@ fn find_zip_entry<'a>(
 /// Status update JSON size soft limit.
 const STATUS_UPDATE_SIZE_MAX: usize = 100 << 10;
 
+/// Maximum total size of the status updates of a webxdc instance.
+///
+/// Snapshot updates are accepted even if the quota is exceeded
+/// as they supersede all previous updates, but must fit into the quota themselves.
+#[cfg(not(test))]
+pub(crate) const STATUS_UPDATES_QUOTA: usize = 50 << 20;
+
+/// Smaller quota for tests, so that they do not need to write large updates.
+#[cfg(test)]
+pub(crate) const STATUS_UPDATES_QUOTA: usize = 1 << 20;
+
 impl Context {
     /// check if a file is an acceptable webxdc for sending or receiving.
     pub(crate) async fn is_webxdc_file(&self, filename: &str, file: &[u8]) -> Result<bool> {
@@ -264,6 +311,40 @@ pub(crate) async fn is_webxdc_file(&self, filename: &str, file: &[u8]) -> Result
         Ok(true)
     }
 
+    /// Replaces the app of the webxdc instance `instance_msg_id` by the .xdc `file`,
+    /// e.g. to update the app to a new version.
+    ///
+    /// Only the app sender can replace the app, see [`WebxdcInfo::is_app_sender`].
+    /// Status updates of the instance are preserved.
+    pub async fn replace_webxdc(&self, instance_msg_id: MsgId, file: &str) -> Result<()> {
+        let mut instance = Message::load_from_db(self, instance_msg_id).await?;
+        ensure!(
+            instance.viewtype == Viewtype::Webxdc,
+            "{instance_msg_id} is not a webxdc instance"
+        );
+        ensure!(
+            instance.from_id == ContactId::SELF,
+            "Only the app sender can replace the app"
+        );
+
+        let mut msg = Message::new(Viewtype::Webxdc);
+        msg.set_file_and_deduplicate(self, Path::new(file), None, None)?;
+        let path = msg.get_file(self).context("No webxdc file")?;
+        self.ensure_sendable_webxdc_file(&path).await?;
+        msg.hidden = true;
+        msg.param.set(Param::WebxdcReplaceFor, &instance.rfc724_mid);
+        if instance.get_showpadlock() {
+            msg.param.set_int(Param::GuaranteeE2ee, 1);
+        }
+
+        let bytes = msg.get_filebytes(self).await?.unwrap_or_default();
+        instance
+            .replace_webxdc_file(self, &msg.param, bytes.try_into()?)
+            .await?;
+        chat::send_msg(self, instance.chat_id, &mut msg).await?;
+        Ok(())
+    }
+
     /// Ensure that a file is an acceptable webxdc for sending.
     pub(crate) async fn ensure_sendable_webxdc_file(&self, path: &Path) -> Result<()> {
         let filename = path.to_str().unwrap_or_default();
@@ -452,7 +533,9 @@ pub(crate) async fn write_status_update_inner(
         timestamp: i64,
     ) -> Result<Option<StatusUpdateSerial>> {
         let uid = status_update_item.uid.as_deref();
+        let is_snapshot = status_update_item.snapshot == Some(true);
         let status_update_item = serde_json::to_string(&status_update_item)?;
+        let size = status_update_item.len();
         let trans_fn = |t: &mut rusqlite::Transaction| {
             t.execute(
                 "UPDATE msgs SET timestamp_rcvd=? WHERE id=?",
@@ -470,6 +553,25 @@ pub(crate) async fn write_status_update_inner(
                     },
                 )
                 .optional()?;
+            if let Some(rowid) = rowid {
+                if is_snapshot {
+                    t.execute(
+                        "DELETE FROM msgs_status_updates WHERE msg_id=? AND id<?",
+                        (instance_id, rowid),
+                    )?;
+                    t.execute(
+                        "INSERT OR REPLACE INTO webxdc_status_updates_size (msg_id, size)
+                         VALUES (?, ?)",
+                        (instance_id, size),
+                    )?;
+                } else {
+                    t.execute(
+                        "INSERT INTO webxdc_status_updates_size (msg_id, size) VALUES (?, ?)
+                         ON CONFLICT (msg_id) DO UPDATE SET size=size+excluded.size",
+                        (instance_id, size),
+                    )?;
+                }
+            }
             Ok(rowid)
         };
         let Some(rowid) = self.sql.transaction(trans_fn).await? else {
@@ -481,6 +583,73 @@ pub(crate) async fn write_status_update_inner(
         Ok(Some(status_update_serial))
     }
 
+    /// Returns true if `status_update` fits into the storage quota of the webxdc instance,
+    /// see [`STATUS_UPDATES_QUOTA`].
+    async fn status_update_fits_quota(
+        &self,
+        instance_id: MsgId,
+        status_update: &StatusUpdateItem,
+    ) -> Result<bool> {
+        let size = serde_json::to_string(status_update)?.len();
+        if status_update.snapshot == Some(true) {
+            // Snapshots replace all previous updates.
+            return Ok(size <= STATUS_UPDATES_QUOTA);
+        }
+        let used: usize = self
+            .sql
+            .query_get_value(
+                "SELECT size FROM webxdc_status_updates_size WHERE msg_id=?",
+                (instance_id,),
+            )
+            .await?
+            .unwrap_or_default();
+        Ok(used.saturating_add(size) <= STATUS_UPDATES_QUOTA)
+    }
+
+    /// Resets the state of the webxdc instance on this device.
+    ///
+    /// All status updates, the summary and the document name are deleted,
+    /// other chat members are not affected.
+    /// [`EventType::WebxdcInstanceUpdated`] is emitted so that the UI reloads the app.
+    pub async fn reset_webxdc_state(&self, instance_msg_id: MsgId) -> Result<()> {
+        let mut instance = Message::load_from_db(self, instance_msg_id).await?;
+        ensure!(
+            instance.viewtype == Viewtype::Webxdc,
+            "{instance_msg_id} is not a webxdc instance"
+        );
+
+        self.sql
+            .transaction(|transaction| {
+                transaction.execute(
+                    "DELETE FROM msgs_status_updates WHERE msg_id=?",
+                    (instance_msg_id,),
+                )?;
+                transaction.execute(
+                    "DELETE FROM webxdc_status_updates_size WHERE msg_id=?",
+                    (instance_msg_id,),
+                )?;
+                transaction.execute(
+                    "DELETE FROM smtp_status_updates WHERE msg_id=?",
+                    (instance_msg_id,),
+                )?;
+                Ok(())
+            })
+            .await?;
+        instance
+            .param
+            .remove(Param::WebxdcSummary)
+            .remove(Param::WebxdcSummaryTimestamp)
+            .remove(Param::WebxdcDocument)
+            .remove(Param::WebxdcDocumentTimestamp);
+        instance.update_param(self).await?;
+
+        self.emit_event(EventType::WebxdcInstanceUpdated {
+            msg_id: instance_msg_id,
+        });
+        self.emit_msgs_changed(instance.chat_id, instance_msg_id);
+        Ok(())
+    }
+
     /// Returns the update_item with `status_update_serial` from the webxdc with message id `msg_id`.
     pub async fn get_status_update(
         &self,
@@ -548,6 +717,26 @@ pub async fn send_webxdc_status_update_struct(
             bail!("Cannot send to {chat_id}: {reason}.");
         }
 
+        if !instance
+            .has_webxdc_permission(self, WebxdcPermission::LargeUpdates)
+            .await?
+        {
+            let size = serde_json::to_string(&status_update)?.len();
+            ensure!(
+                size <= STATUS_UPDATE_SIZE_MAX,
+                "Webxdc update of {size} bytes needs the large-updates permission."
+            );
+        }
+        ensure!(
+            status_update.snapshot != Some(true) || instance.from_id == ContactId::SELF,
+            "Only the sender of webxdc {instance_msg_id} can send snapshot updates."
+        );
+        ensure!(
+            self.status_update_fits_quota(instance.id, &status_update)
+                .await?,
+            "Webxdc {instance_msg_id} exceeds its storage quota, send a snapshot update to compact it."
+        );
+
         let send_now = !matches!(
             instance.state,
             MessageState::Undefined | MessageState::OutDraft
@@ -701,7 +890,26 @@ pub(crate) async fn receive_status_update(
         }
 
         let updates: StatusUpdates = serde_json::from_str(json)?;
+        let mut dropped = false;
         for update_item in updates.updates {
+            if update_item.snapshot == Some(true) && from_id != instance.from_id {
+                warn!(
+                    self,
+                    "Ignoring snapshot update for {} not sent by the instance sender.", instance.id
+                );
+                continue;
+            }
+            if !self
+                .status_update_fits_quota(instance.id, &update_item)
+                .await?
+            {
+                warn!(
+                    self,
+                    "Ignoring status update for {}, storage quota exceeded.", instance.id
+                );
+                dropped = true;
+                continue;
+            }
             self.create_status_update_record(
                 instance,
                 update_item,
@@ -711,6 +919,11 @@ pub(crate) async fn receive_status_update(
             )
             .await?;
         }
+        if dropped {
+            self.emit_event(EventType::WebxdcStatusUpdatesDropped {
+                msg_id: instance.id,
+            });
+        }
 
         Ok(())
     }
@@ -906,6 +1119,52 @@ pub async fn get_webxdc_blob(&self, context: &Context, name: &str) -> Result<Vec
         get_blob(&mut archive, name).await
     }
 
+    /// Replaces the .xdc file of the webxdc instance by the file from `params`.
+    ///
+    /// Status updates, summary and document name of the instance are kept.
+    pub(crate) async fn replace_webxdc_file(
+        &mut self,
+        context: &Context,
+        params: &Params,
+        bytes: usize,
+    ) -> Result<()> {
+        for key in [Param::File, Param::Filename, Param::MimeType] {
+            match params.get(key) {
+                Some(value) => self.param.set(key, value),
+                None => self.param.remove(key),
+            };
+        }
+        let msg_id = self.id;
+        let param = self.param.to_string();
+        context
+            .sql
+            .transaction(move |transaction| {
+                transaction.execute(
+                    "UPDATE msgs SET param=?, bytes=? WHERE id=?",
+                    (param, bytes as isize, msg_id),
+                )?;
+                // The app is indexed again when looking for app updates.
+                transaction
+                    .execute("DELETE FROM webxdc_instance_apps WHERE msg_id=?", (msg_id,))?;
+                Ok(())
+            })
+            .await?;
+        context.webxdc_permissions_cache.write().remove(&msg_id);
+        context.emit_event(EventType::WebxdcInstanceUpdated { msg_id: self.id });
+        context.emit_msgs_changed(self.chat_id, self.id);
+        Ok(())
+    }
+
+    /// Returns parsed manifest.toml or defaults if there is no valid manifest.
+    async fn get_webxdc_manifest(&self, context: &Context) -> Result<WebxdcManifest> {
+        ensure!(self.viewtype == Viewtype::Webxdc, "No webxdc instance.");
+        let mut archive = self.get_webxdc_archive(context).await?;
+        Ok(get_blob(&mut archive, "manifest.toml")
+            .await
+            .map(|bytes| parse_webxdc_manifest(&bytes).unwrap_or_default())
+            .unwrap_or_default())
+    }
+
     /// Return info from manifest.toml or from fallbacks.
     pub async fn get_webxdc_info(&self, context: &Context) -> Result<WebxdcInfo> {
         ensure!(self.viewtype == Viewtype::Webxdc, "No webxdc instance.");
@@ -924,9 +1183,17 @@ pub async fn get_webxdc_info(&self, context: &Context) -> Result<WebxdcInfo> {
             }
         }
 
+        let (permissions, granted_permissions) =
+            self.get_webxdc_permissions(context, &manifest).await?;
         let request_integration = manifest.request_integration.unwrap_or_default();
         let is_integrated = self.is_set_as_webxdc_integration(context).await?;
-        let internet_access = is_integrated;
+        let internet_access = granted_permissions.contains(&WebxdcPermission::Internet);
+        let send_update_max_size = if granted_permissions.contains(&WebxdcPermission::LargeUpdates)
+        {
+            RECOMMENDED_FILE_SIZE as usize
+        } else {
+            STATUS_UPDATE_SIZE_MAX
+        };
 
         let self_addr = self.get_webxdc_self_addr(context).await?;
         let is_app_sender = self.from_id == ContactId::SELF;
@@ -971,11 +1238,13 @@ pub async fn get_webxdc_info(&self, context: &Context) -> Result<WebxdcInfo> {
             },
             request_integration,
             internet_access,
+            permissions,
+            granted_permissions,
             self_addr,
             is_app_sender,
             is_broadcast,
             send_update_interval: context.ratelimit.read().await.update_interval(),
-            send_update_max_size: RECOMMENDED_FILE_SIZE as usize,
+            send_update_max_size,
         })
     }
 
@@ -0,0 +1,724 @@
+//! # Catalog of webxdc apps.
+//!
+//! The catalog is stored in the `webxdc_catalog` SQL table and filled from two sources:
+//! - webxdc apps sent by the bot configured in [`Config::WebxdcCatalogBot`],
+//! - the JSON index at [`Config::WebxdcCatalogUrl`].
+//!   The index is a list of objects with the fields `app_id`, `name`, `description`,
+//!   `version`, `source_code_url`, `url` (download URL of the .xdc file)
+//!   and `icon_url`. Apps from the index are downloaded when they are installed first.
+//!
+//! Apps are identified by `app_id` from manifest.toml,
+//! apps without `app_id` are not added to the catalog.
+//! An entry is only replaced by a newer version of the app.
+//!
+//! The `app_id` and version of webxdc instances used in chats are indexed
+//! in the `webxdc_instance_apps` table, so that each instance is unpacked only once
+//! when looking for app updates.
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use anyhow::{Context as _, Result, bail, ensure};
+use serde::{Deserialize, Serialize};
+
+use crate::blob::BlobObject;
+use crate::chat::{ChatId, send_msg};
+use crate::config::Config;
+use crate::constants::DC_CHAT_ID_LAST_SPECIAL;
+use crate::contact::{Contact, ContactId};
+use crate::context::Context;
+use crate::download::DownloadState;
+use crate::events::EventType;
+use crate::log::{LogExt, warn};
+use crate::message::{Message, MsgId, Viewtype};
+use crate::net::http::{read_url, read_url_blob};
+use crate::param::Param;
+use crate::tools::time;
+use crate::webxdc::WEBXDC_DEFAULT_ICON;
+
+/// App in the webxdc catalog.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct WebxdcCatalogEntry {
+    /// Unique ID of the app, `app_id` from manifest.toml.
+    pub app_id: String,
+
+    /// Name of the app.
+    pub name: String,
+
+    /// Description of the app or an empty string.
+    pub description: String,
+
+    /// Version of the app or an empty string.
+    pub version: String,
+
+    /// URL of the app source code or an empty string.
+    pub source_code_url: String,
+
+    /// Absolute path of the app icon, `None` if the app has no icon.
+    pub icon: Option<PathBuf>,
+
+    /// Timestamp of the last update of the entry.
+    pub timestamp: i64,
+}
+
+/// Entry of the catalog index at [`Config::WebxdcCatalogUrl`].
+#[derive(Debug, Default, Deserialize)]
+struct CatalogIndexEntry {
+    app_id: String,
+    name: String,
+    #[serde(default)]
+    description: String,
+    #[serde(default)]
+    version: String,
+    #[serde(default)]
+    source_code_url: String,
+    #[serde(default)]
+    url: String,
+    #[serde(default)]
+    icon_url: String,
+}
+
+impl Context {
+    /// Returns apps from the webxdc catalog, sorted by name.
+    ///
+    /// If `query` is given, only apps with `query` in the name or description are returned.
+    pub async fn get_webxdc_catalog(&self, query: Option<&str>) -> Result<Vec<WebxdcCatalogEntry>> {
+        let query = query.unwrap_or_default().trim().to_lowercase();
+        let entries = self
+            .sql
+            .query_map_vec(
+                "SELECT app_id, name, description, version, source_code_url, icon, timestamp
+                 FROM webxdc_catalog
+                 ORDER BY name COLLATE NOCASE, app_id",
+                (),
+                |row| {
+                    let icon: String = row.get(5)?;
+                    Ok(WebxdcCatalogEntry {
+                        app_id: row.get(0)?,
+                        name: row.get(1)?,
+                        description: row.get(2)?,
+                        version: row.get(3)?,
+                        source_code_url: row.get(4)?,
+                        icon: (!icon.is_empty())
+                            .then(|| BlobObject::from_name(self, &icon).ok())
+                            .flatten()
+                            .map(|blob| blob.to_abs_path()),
+                        timestamp: row.get(6)?,
+                    })
+                },
+            )
+            .await?
+            .into_iter()
+            .filter(|entry| {
+                query.is_empty()
+                    || entry.name.to_lowercase().contains(&query)
+                    || entry.description.to_lowercase().contains(&query)
+            })
+            .collect();
+        Ok(entries)
+    }
+
+    /// Updates the webxdc catalog from the catalog bot and the catalog URL.
+    ///
+    /// Emits [`EventType::WebxdcAppUpdateAvailable`] for apps used in chats
+    /// if the catalog contains a newer version.
+    pub async fn update_webxdc_catalog(&self) -> Result<()> {
+        if let Some(bot_fingerprint) = self.get_webxdc_catalog_bot().await? {
+            let msg_ids = self
+                .sql
+                .query_map_vec(
+                    "SELECT m.id FROM msgs m INNER JOIN contacts c ON c.id=m.from_id
+                     WHERE c.fingerprint=? AND m.type=? AND m.chat_id>?
+                     ORDER BY m.timestamp, m.id",
+                    (bot_fingerprint, Viewtype::Webxdc, DC_CHAT_ID_LAST_SPECIAL),
+                    |row| {
+                        let msg_id: MsgId = row.get(0)?;
+                        Ok(msg_id)
+                    },
+                )
+                .await?;
+            for msg_id in msg_ids {
+                self.add_webxdc_to_catalog(msg_id).await.log_err(self).ok();
+            }
+        }
+
+        if let Some(url) = self
+            .get_config(Config::WebxdcCatalogUrl)
+            .await?
+            .filter(|url| !url.is_empty())
+        {
+            let index = read_url(self, &url).await?;
+            let entries: Vec<CatalogIndexEntry> =
+                serde_json::from_str(&index).context("Invalid webxdc catalog index")?;
+            for entry in entries {
+                if !self.should_update_catalog_entry(&entry).await? {
+                    continue;
+                }
+                let icon = if entry.icon_url.is_empty() {
+                    String::new()
+                } else {
+                    match read_url_blob(self, &entry.icon_url).await {
+                        Ok(response) => {
+                            let name = entry.icon_url.rsplit('/').next().unwrap_or_default();
+                            BlobObject::create_and_deduplicate_from_bytes(
+                                self,
+                                &response.blob,
+                                name,
+                            )?
+                            .as_name()
+                            .to_string()
+                        }
+                        Err(err) => {
+                            warn!(self, "Failed to download {:?}: {err:#}.", entry.icon_url);
+                            String::new()
+                        }
+                    }
+                };
+                self.upsert_catalog_entry(&entry, &icon, "").await?;
+            }
+        }
+
+        self.check_webxdc_app_updates(None).await
+    }
+
+    /// Sets the key-contact whose webxdc apps are added to the webxdc catalog,
+    /// see [`Config::WebxdcCatalogBot`].
+    ///
+    /// The bot is identified by its key, so apps sent from the same address
+    /// with another key are not added. `None` unsets the bot.
+    pub async fn set_webxdc_catalog_bot(&self, contact_id: Option<ContactId>) -> Result<()> {
+        let fingerprint = match contact_id {
+            Some(contact_id) => {
+                let contact = Contact::get_by_id(self, contact_id).await?;
+                let Some(fingerprint) = contact.fingerprint() else {
+                    bail!("Webxdc catalog bot {contact_id} is not a key-contact");
+                };
+                Some(fingerprint.hex())
+            }
+            None => None,
+        };
+        self.set_config(Config::WebxdcCatalogBot, fingerprint.as_deref())
+            .await
+    }
+
+    /// Sends the app `app_id` from the webxdc catalog to the chat `chat_id`.
+    ///
+    /// Apps from the catalog URL are downloaded first if needed.
+    pub async fn install_webxdc_from_catalog(
+        &self,
+        chat_id: ChatId,
+        app_id: &str,
+    ) -> Result<MsgId> {
+        let Some((name, url, xdc)) = self
+            .sql
+            .query_row_optional(
+                "SELECT name, url, xdc FROM webxdc_catalog WHERE app_id=?",
+                (app_id,),
+                |row| {
+                    let name: String = row.get(0)?;
+                    let url: String = row.get(1)?;
+                    let xdc: String = row.get(2)?;
+                    Ok((name, url, xdc))
+                },
+            )
+            .await?
+        else {
+            bail!("App {app_id:?} is not in the webxdc catalog");
+        };
+        let filename = format!("{name}.xdc");
+
+        let blob = if xdc.is_empty() {
+            ensure!(!url.is_empty(), "App {app_id:?} has no download URL");
+            let response = read_url_blob(self, &url).await?;
+            ensure!(
+                self.is_webxdc_file(&filename, &response.blob).await?,
+                "{url:?} is not a webxdc app"
+            );
+            let blob =
+                BlobObject::create_and_deduplicate_from_bytes(self, &response.blob, &filename)?;
+            self.sql
+                .execute(
+                    "UPDATE webxdc_catalog SET xdc=? WHERE app_id=?",
+                    (blob.as_name(), app_id),
+                )
+                .await?;
+            blob
+        } else {
+            BlobObject::from_name(self, &xdc)?
+        };
+
+        let mut msg = Message::new(Viewtype::Webxdc);
+        msg.set_file_and_deduplicate(self, &blob.to_abs_path(), Some(&filename), None)?;
+        send_msg(self, chat_id, &mut msg).await
+    }
+
+    /// Adds a webxdc app received from the catalog bot to the catalog.
+    ///
+    /// Does nothing if `from_id` is not the catalog bot.
+    pub(crate) async fn maybe_add_webxdc_to_catalog(
+        &self,
+        msg_id: MsgId,
+        from_id: ContactId,
+    ) -> Result<()> {
+        let Some(bot_fingerprint) = self.get_webxdc_catalog_bot().await? else {
+            return Ok(());
+        };
+        let contact = Contact::get_by_id(self, from_id).await?;
+        if contact
+            .fingerprint()
+            .is_none_or(|fingerprint| fingerprint.hex() != bot_fingerprint)
+        {
+            return Ok(());
+        }
+        if let Some(app_id) = self.add_webxdc_to_catalog(msg_id).await? {
+            self.check_webxdc_app_updates(Some(&app_id)).await?;
+        }
+        Ok(())
+    }
+
+    /// Returns the fingerprint of the catalog bot key.
+    async fn get_webxdc_catalog_bot(&self) -> Result<Option<String>> {
+        Ok(self
+            .get_config(Config::WebxdcCatalogBot)
+            .await?
+            .filter(|fingerprint| !fingerprint.is_empty()))
+    }
+
+    /// Adds the webxdc app `msg_id` to the catalog.
+    ///
+    /// Returns the `app_id` if the catalog was changed.
+    async fn add_webxdc_to_catalog(&self, msg_id: MsgId) -> Result<Option<String>> {
+        let msg = Message::load_from_db(self, msg_id).await?;
+        let Some(xdc) = msg.param.get(Param::File) else {
+            // Not downloaded yet.
+            return Ok(None);
+        };
+        let manifest = msg.get_webxdc_manifest(self).await?;
+        let Some(app_id) = manifest.app_id.filter(|app_id| !app_id.trim().is_empty()) else {
+            info!(
+                self,
+                "Webxdc {msg_id} has no app_id, not adding it to the catalog."
+            );
+            return Ok(None);
+        };
+        let info = msg.get_webxdc_info(self).await?;
+        let entry = CatalogIndexEntry {
+            app_id,
+            name: info.name,
+            description: manifest.description.unwrap_or_default(),
+            version: manifest.version.unwrap_or_default(),
+            source_code_url: info.source_code_url,
+            ..Default::default()
+        };
+        if !self.should_update_catalog_entry(&entry).await? {
+            return Ok(None);
+        }
+        let icon = if info.icon == WEBXDC_DEFAULT_ICON {
+            String::new()
+        } else {
+            let icon = msg.get_webxdc_blob(self, &info.icon).await?;
+            BlobObject::create_and_deduplicate_from_bytes(self, &icon, &info.icon)?
+                .as_name()
+                .to_string()
+        };
+        self.upsert_catalog_entry(&entry, &icon, xdc).await?;
+        Ok(Some(entry.app_id))
+    }
+
+    /// Returns whether the catalog has no entry for the app or an older version.
+    async fn should_update_catalog_entry(&self, entry: &CatalogIndexEntry) -> Result<bool> {
+        let version: Option<String> = self
+            .sql
+            .query_get_value(
+                "SELECT version FROM webxdc_catalog WHERE app_id=?",
+                (&entry.app_id,),
+            )
+            .await?;
+        Ok(version.is_none_or(|version| is_newer_version(&entry.version, &version)))
+    }
+
+    async fn upsert_catalog_entry(
+        &self,
+        entry: &CatalogIndexEntry,
+        icon: &str,
+        xdc: &str,
+    ) -> Result<()> {
+        self.sql
+            .execute(
+                "INSERT INTO webxdc_catalog
+                 (app_id, name, description, version, source_code_url, url, icon, xdc, timestamp)
+                 VALUES (?,?,?,?,?,?,?,?,?)
+                 ON CONFLICT(app_id) DO UPDATE SET
+                 name=excluded.name, description=excluded.description,
+                 version=excluded.version, source_code_url=excluded.source_code_url,
+                 url=excluded.url, icon=excluded.icon, xdc=excluded.xdc,
+                 timestamp=excluded.timestamp",
+                (
+                    &entry.app_id,
+                    &entry.name,
+                    &entry.description,
+                    &entry.version,
+                    &entry.source_code_url,
+                    &entry.url,
+                    icon,
+                    xdc,
+                    time(),
+                ),
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Emits [`EventType::WebxdcAppUpdateAvailable`] for apps used in chats
+    /// if the catalog has a newer version that was not announced yet.
+    ///
+    /// If `app_id` is given, only this app is checked.
+    async fn check_webxdc_app_updates(&self, app_id: Option<&str>) -> Result<()> {
+        if !self
+            .sql
+            .exists("SELECT COUNT(*) FROM webxdc_catalog", ())
+            .await?
+        {
+            return Ok(());
+        }
+        self.index_webxdc_instances().await?;
+
+        // Apps sent by the catalog bot are not considered as used.
+        let bot_fingerprint = self.get_webxdc_catalog_bot().await?.unwrap_or_default();
+        let apps = self
+            .sql
+            .query_map_vec(
+                "SELECT c.app_id, c.version, c.notified_version, a.version
+                 FROM webxdc_catalog c
+                 INNER JOIN webxdc_instance_apps a ON a.app_id=c.app_id
+                 INNER JOIN msgs m ON m.id=a.msg_id
+                 INNER JOIN contacts ct ON ct.id=m.from_id
+                 WHERE (?1 IS NULL OR c.app_id=?1) AND m.chat_id>?2 AND m.hidden=0
+                 AND (?3='' OR ct.fingerprint!=?3)",
+                (app_id, DC_CHAT_ID_LAST_SPECIAL, bot_fingerprint),
+                |row| {
+                    let app_id: String = row.get(0)?;
+                    let version: String = row.get(1)?;
+                    let notified_version: String = row.get(2)?;
+                    let used_version: String = row.get(3)?;
+                    Ok((app_id, version, notified_version, used_version))
+                },
+            )
+            .await?;
+        let mut used_versions: HashMap<String, (String, String, String)> = HashMap::new();
+        for (app_id, version, notified_version, used_version) in apps {
+            match used_versions.get(&app_id) {
+                Some((_, _, newest_used)) if !is_newer_version(&used_version, newest_used) => {}
+                _ => {
+                    used_versions.insert(app_id, (version, notified_version, used_version));
+                }
+            }
+        }
+
+        for (app_id, (version, notified_version, used_version)) in used_versions {
+            if is_newer_version(&version, &used_version) && version != notified_version {
+                self.sql
+                    .execute(
+                        "UPDATE webxdc_catalog SET notified_version=? WHERE app_id=?",
+                        (&version, &app_id),
+                    )
+                    .await?;
+                self.emit_event(EventType::WebxdcAppUpdateAvailable { app_id });
+            }
+        }
+        Ok(())
+    }
+
+    /// Adds the `app_id` and version of downloaded webxdc instances
+    /// that are not indexed yet to the `webxdc_instance_apps` table.
+    ///
+    /// Instances without `app_id` are indexed with an empty `app_id`,
+    /// so each instance is only unpacked once.
+    async fn index_webxdc_instances(&self) -> Result<()> {
+        let msg_ids = self
+            .sql
+            .query_map_vec(
+                "SELECT m.id FROM msgs m
+                 LEFT JOIN webxdc_instance_apps a ON a.msg_id=m.id
+                 WHERE m.type=? AND m.chat_id>? AND m.download_state=? AND a.msg_id IS NULL",
+                (
+                    Viewtype::Webxdc,
+                    DC_CHAT_ID_LAST_SPECIAL,
+                    DownloadState::Done,
+                ),
+                |row| {
+                    let msg_id: MsgId = row.get(0)?;
+                    Ok(msg_id)
+                },
+            )
+            .await?;
+        for msg_id in msg_ids {
+            let msg = Message::load_from_db(self, msg_id).await?;
+            if msg.param.get(Param::File).is_none() {
+                continue;
+            }
+            let manifest = msg.get_webxdc_manifest(self).await.unwrap_or_default();
+            self.sql
+                .execute(
+                    "INSERT OR REPLACE INTO webxdc_instance_apps (msg_id, app_id, version)
+                     VALUES (?,?,?)",
+                    (
+                        msg_id,
+                        manifest.app_id.unwrap_or_default(),
+                        manifest.version.unwrap_or_default(),
+                    ),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns whether `version` is newer than `other`.
+///
+/// Versions are compared by their dot-separated components,
+/// numeric components are compared as numbers, others as strings.
+/// A leading `v` and missing trailing components are ignored, so `v1.2` equals `1.2.0`.
+fn is_newer_version(version: &str, other: &str) -> bool {
+    let mut version = version.trim().trim_start_matches('v').split('.');
+    let mut other = other.trim().trim_start_matches('v').split('.');
+    loop {
+        let (a, b) = match (version.next(), other.next()) {
+            (None, None) => return false,
+            (a, b) => (a.unwrap_or("0"), b.unwrap_or("0")),
+        };
+        let ordering = match (a.parse::<u64>(), b.parse::<u64>()) {
+            (Ok(a), Ok(b)) => a.cmp(&b),
+            _ => a.cmp(b),
+        };
+        if ordering != Ordering::Equal {
+            return ordering == Ordering::Greater;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::{AsyncReadExt as _, AsyncWriteExt as _};
+    use tokio::net::TcpListener;
+
+    use super::*;
+    use crate::chat::create_group;
+    use crate::test_utils::{TestContextManager, create_xdc, send_xdc};
+
+    fn poll_manifest(version: &str) -> String {
+        format!(
+            "name = \"Poll\"\napp_id = \"org.example.poll\"\nversion = \"{version}\"\ndescription = \"Ask your friends\"\n"
+        )
+    }
+
+    #[test]
+    fn test_is_newer_version() {
+        assert!(is_newer_version("1.1", "1.0"));
+        assert!(is_newer_version("1.10", "1.9"));
+        assert!(is_newer_version("v2", "1.9.9"));
+        assert!(is_newer_version("1.0.1", "1.0"));
+        assert!(is_newer_version("1.0", ""));
+        assert!(!is_newer_version("1.0", "1.0"));
+        assert!(!is_newer_version("v1.2", "1.2.0"));
+        assert!(!is_newer_version("1.9", "1.10"));
+        assert!(!is_newer_version("", ""));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_catalog_from_bot() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = &tcm.alice().await;
+        let bot = &tcm.bob().await;
+        let bot_contact_id = alice.add_or_lookup_contact_id(bot).await;
+        alice.set_webxdc_catalog_bot(Some(bot_contact_id)).await?;
+        let bot_chat = bot.create_chat(alice).await;
+
+        // Apps sent from the bot address with another key are not added to the catalog.
+        let mallory = &tcm.unconfigured().await;
+        mallory.configure_addr("bob@example.net").await;
+        let mallory_chat = mallory.create_chat(alice).await;
+        send_xdc(mallory, mallory_chat.id, &poll_manifest("1.0")).await?;
+        alice.recv_msg(&mallory.pop_sent_msg().await).await;
+        assert!(alice.get_webxdc_catalog(None).await?.is_empty());
+
+        // Apps from other contacts are not added to the catalog.
+        let fiona = &tcm.fiona().await;
+        let fiona_chat = fiona.create_chat(alice).await;
+        send_xdc(fiona, fiona_chat.id, &poll_manifest("1.0")).await?;
+        alice.recv_msg(&fiona.pop_sent_msg().await).await;
+        assert!(alice.get_webxdc_catalog(None).await?.is_empty());
+
+        send_xdc(bot, bot_chat.id, &poll_manifest("1.0")).await?;
+        alice.recv_msg(&bot.pop_sent_msg().await).await;
+        let catalog = alice.get_webxdc_catalog(None).await?;
+        assert_eq!(catalog.len(), 1);
+        let entry = &catalog[0];
+        assert_eq!(entry.app_id, "org.example.poll");
+        assert_eq!(entry.name, "Poll");
+        assert_eq!(entry.description, "Ask your friends");
+        assert_eq!(entry.version, "1.0");
+        assert_eq!(entry.icon, None);
+        assert_eq!(alice.get_webxdc_catalog(Some("friends")).await?.len(), 1);
+        assert!(alice.get_webxdc_catalog(Some("chess")).await?.is_empty());
+
+        // Alice uses the app in a group.
+        let group_id = create_group(alice, "Group").await?;
+        let msg_id = alice
+            .install_webxdc_from_catalog(group_id, "org.example.poll")
+            .await?;
+        let msg = Message::load_from_db(alice, msg_id).await?;
+        assert_eq!(msg.viewtype, Viewtype::Webxdc);
+        assert_eq!(msg.get_filename().unwrap(), "Poll.xdc");
+        assert!(
+            alice
+                .install_webxdc_from_catalog(group_id, "org.example.unknown")
+                .await
+                .is_err()
+        );
+
+        // An older version does not replace the entry.
+        send_xdc(bot, bot_chat.id, &poll_manifest("0.9")).await?;
+        alice.recv_msg(&bot.pop_sent_msg().await).await;
+        assert_eq!(alice.get_webxdc_catalog(None).await?[0].version, "1.0");
+
+        // A newer version of the used app is announced once.
+        alice.evtracker.clear_events();
+        send_xdc(bot, bot_chat.id, &poll_manifest("1.1")).await?;
+        alice.recv_msg(&bot.pop_sent_msg().await).await;
+        assert_eq!(alice.get_webxdc_catalog(None).await?[0].version, "1.1");
+        let event = alice
+            .evtracker
+            .get_matching(|evt| matches!(evt, EventType::WebxdcAppUpdateAvailable { .. }))
+            .await;
+        assert_eq!(
+            event,
+            EventType::WebxdcAppUpdateAvailable {
+                app_id: "org.example.poll".to_string()
+            }
+        );
+        alice.update_webxdc_catalog().await?;
+        assert!(
+            alice
+                .evtracker
+                .get_matching_opt(alice, |evt| matches!(
+                    evt,
+                    EventType::WebxdcAppUpdateAvailable { .. }
+                ))
+                .await
+                .is_none()
+        );
+        Ok(())
+    }
+
+    /// Serves `files` by path over HTTP on the local `listener`.
+    fn serve_files(listener: TcpListener, files: HashMap<&'static str, Vec<u8>>) {
+        tokio::spawn(async move {
+            while let Ok((mut stream, _addr)) = listener.accept().await {
+                let mut buf = Vec::new();
+                let mut chunk = [0; 1024];
+                while !buf.windows(4).any(|w| w == b"\r\n\r\n") {
+                    let n = stream.read(&mut chunk).await.unwrap();
+                    assert!(n > 0);
+                    buf.extend_from_slice(&chunk[..n]);
+                }
+                let request = String::from_utf8_lossy(&buf);
+                // The request target may be in absolute form.
+                let target = request.split(' ').nth(1).unwrap();
+                let path = target.parse::<hyper::Uri>().unwrap();
+                let (status, body) = match files.get(path.path()) {
+                    Some(body) => ("200 OK", body.as_slice()),
+                    None => ("404 Not Found", &b""[..]),
+                };
+                let header = format!(
+                    "HTTP/1.1 {status}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    body.len()
+                );
+                stream.write_all(header.as_bytes()).await.unwrap();
+                stream.write_all(body).await.unwrap();
+                stream.flush().await.unwrap();
+            }
+        });
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_catalog_from_url() -> Result<()> {
+        let mut tcm = TestContextManager::new();
+        let alice = &tcm.alice().await;
+
+        // Alice uses version 1.0 of the app in a group.
+        let group_id = create_group(alice, "Group").await?;
+        send_xdc(alice, group_id, &poll_manifest("1.0")).await?;
+
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let url = format!("http://{}", listener.local_addr()?);
+        let index = serde_json::json!([
+            {
+                "app_id": "org.example.poll",
+                "name": "Poll",
+                "description": "Ask your friends",
+                "version": "2.0",
+                "url": format!("{url}/poll.xdc"),
+                "icon_url": format!("{url}/icon.png"),
+            },
+            {
+                "app_id": "org.example.chess",
+                "name": "Chess",
+            },
+        ]);
+        serve_files(
+            listener,
+            HashMap::from([
+                ("/index.json", index.to_string().into_bytes()),
+                ("/poll.xdc", create_xdc(&poll_manifest("2.0")).await?),
+                ("/icon.png", b"icon".to_vec()),
+            ]),
+        );
+        alice
+            .set_config(Config::WebxdcCatalogUrl, Some(&format!("{url}/index.json")))
+            .await?;
+
+        alice.evtracker.clear_events();
+        alice.update_webxdc_catalog().await?;
+        let catalog = alice.get_webxdc_catalog(None).await?;
+        assert_eq!(catalog.len(), 2);
+        assert_eq!(catalog[0].app_id, "org.example.chess");
+        assert_eq!(catalog[0].icon, None);
+        let entry = &catalog[1];
+        assert_eq!(entry.app_id, "org.example.poll");
+        assert_eq!(entry.version, "2.0");
+        assert_eq!(
+            tokio::fs::read(entry.icon.as_ref().unwrap()).await?,
+            b"icon"
+        );
+        let event = alice
+            .evtracker
+            .get_matching(|evt| matches!(evt, EventType::WebxdcAppUpdateAvailable { .. }))
+            .await;
+        assert_eq!(
+            event,
+            EventType::WebxdcAppUpdateAvailable {
+                app_id: "org.example.poll".to_string()
+            }
+        );
+
+        // The app is downloaded when it is installed.
+        let msg_id = alice
+            .install_webxdc_from_catalog(group_id, "org.example.poll")
+            .await?;
+        let msg = Message::load_from_db(alice, msg_id).await?;
+        assert_eq!(msg.get_filename().unwrap(), "Poll.xdc");
+        let manifest = msg.get_webxdc_manifest(alice).await?;
+        assert_eq!(manifest.version.unwrap(), "2.0");
+
+        // Apps without download URL cannot be installed.
+        assert!(
+            alice
+                .install_webxdc_from_catalog(group_id, "org.example.chess")
+                .await
+                .is_err()
+        );
+        Ok(())
+    }
+}
@@ -6,8 +6,8 @@
 use crate::context::Context;
 use crate::message::{Message, MsgId, Viewtype};
 use crate::param::Param;
-use crate::webxdc::{StatusUpdateItem, StatusUpdateSerial, maps_integration};
-use anyhow::Result;
+use crate::webxdc::{StatusUpdateItem, StatusUpdateSerial, WebxdcPermission, maps_integration};
+use anyhow::{Result, ensure};
 
 impl Context {
     /// Sets Webxdc file as integration.
@@ -97,6 +97,12 @@ pub(crate) async fn intercept_send_webxdc_status_update(
         instance: Message,
         status_update: StatusUpdateItem,
     ) -> Result<()> {
+        ensure!(
+            instance
+                .has_webxdc_permission(self, WebxdcPermission::Location)
+                .await?,
+            "Webxdc integration has no location permission."
+        );
         let chat_id = instance.webxdc_integrated_for();
         maps_integration::intercept_send_update(self, chat_id, status_update).await
     }
@@ -107,6 +113,12 @@ pub(crate) async fn intercept_get_webxdc_status_updates(
         instance: Message,
         last_known_serial: StatusUpdateSerial,
     ) -> Result<String> {
+        if !instance
+            .has_webxdc_permission(self, WebxdcPermission::Location)
+            .await?
+        {
+            return Ok("[]".to_string());
+        }
         let chat_id = instance.webxdc_integrated_for();
         maps_integration::intercept_get_updates(self, chat_id, last_known_serial).await
     }
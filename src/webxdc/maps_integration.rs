@@ -152,6 +152,7 @@ pub(crate) async fn intercept_get_updates(
                     summary: None,
                     uid: None,
                     notify: None,
+                    snapshot: None,
                 },
                 serial: StatusUpdateSerial(location.location_id),
                 max_serial: StatusUpdateSerial(location.location_id),
@@ -173,7 +174,7 @@ mod tests {
     use crate::chatlist::Chatlist;
     use crate::message::Message;
     use crate::test_utils::TestContextManager;
-    use crate::webxdc::StatusUpdateSerial;
+    use crate::webxdc::{StatusUpdateSerial, WebxdcPermission};
     use crate::{EventType, location};
     use anyhow::Result;
 
@@ -281,6 +282,24 @@ async fn test_maps_integration() -> Result<()> {
         let locations = location::get_range(alice, None, None, 0, 0).await?;
         assert_eq!(locations.len(), 2);
 
+        // Without location permission, the integration does not get locations
+        alice
+            .set_webxdc_permission(integration_id, WebxdcPermission::Location, false)
+            .await?;
+        let updates = alice
+            .get_webxdc_status_updates(integration_id, StatusUpdateSerial(0))
+            .await?;
+        assert_eq!(updates, "[]");
+        assert!(
+            alice
+                .send_webxdc_status_update(
+                    integration_id,
+                    r#"{"payload": {"action": "pos", "lat": 33.0, "lng": 34.0, "label": "poi #3"}}"#,
+                )
+                .await
+                .is_err()
+        );
+
         Ok(())
     }
 }
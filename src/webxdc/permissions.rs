@@ -0,0 +1,340 @@
+//! # Webxdc permissions.
+//!
+//! Webxdc apps declare the permissions they need in manifest.toml,
+//! eg. `permissions = ["realtime", "location"]`, unknown permissions are ignored.
+//! Requested permissions are granted per instance by the user,
+//! see [`Context::set_webxdc_permission`].
+//! The decisions are stored in the `webxdc_permissions` SQL table.
+//!
+//! Apps without `permissions` in the manifest keep the permissions they had implicitly before:
+//! `realtime` and `large-updates` are granted,
+//! `location` and `internet` are requested by map integrations
+//! and granted while the app is used as integration.
+//!
+//! `internet` is only granted to integrations
+//! and to encrypted instances in "Saved Messages".
+
+use std::collections::HashMap;
+
+use anyhow::{Result, ensure};
+use serde::{Deserialize, Serialize};
+use strum_macros::{Display, EnumString};
+
+use crate::context::Context;
+use crate::message::{Message, MsgId};
+use crate::param::Param;
+use crate::webxdc::WebxdcManifest;
+
+/// Permission a webxdc app can request in its manifest.
+#[derive(
+    Debug,
+    Display,
+    EnumString,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Hash,
+    PartialOrd,
+    Ord,
+    Serialize,
+    Deserialize,
+)]
+#[strum(serialize_all = "kebab_case")]
+#[serde(rename_all = "kebab-case")]
+pub enum WebxdcPermission {
+    /// Use realtime channels, see [`crate::peer_channels`].
+    Realtime,
+
+    /// Get the locations shared in the chat, used by map integrations.
+    Location,
+
+    /// Let the user pick contacts to share with the app.
+    ContactsPicker,
+
+    /// Access the internet.
+    Internet,
+
+    /// Send status updates larger than 100 KiB.
+    LargeUpdates,
+}
+
+/// Returns the permissions requested by the manifest.
+fn requested_permissions(msg: &Message, manifest: &WebxdcManifest) -> Vec<WebxdcPermission> {
+    let mut permissions = if let Some(names) = &manifest.permissions {
+        names.iter().filter_map(|name| name.parse().ok()).collect()
+    } else {
+        let mut permissions = vec![WebxdcPermission::Realtime, WebxdcPermission::LargeUpdates];
+        if manifest.request_integration.as_deref() == Some("map")
+            || msg.param.get_int(Param::WebxdcIntegration).is_some()
+        {
+            permissions.push(WebxdcPermission::Location);
+            permissions.push(WebxdcPermission::Internet);
+        }
+        permissions
+    };
+    permissions.sort();
+    permissions.dedup();
+    permissions
+}
+
+impl Context {
+    /// Grants or revokes a permission requested by a webxdc instance.
+    ///
+    /// `internet` can only be granted to encrypted instances in "Saved Messages".
+    pub async fn set_webxdc_permission(
+        &self,
+        instance_msg_id: MsgId,
+        permission: WebxdcPermission,
+        granted: bool,
+    ) -> Result<()> {
+        let instance = Message::load_from_db(self, instance_msg_id).await?;
+        let manifest = instance.get_webxdc_manifest(self).await?;
+        ensure!(
+            requested_permissions(&instance, &manifest).contains(&permission),
+            "Webxdc instance {instance_msg_id} does not request {permission}."
+        );
+        if permission == WebxdcPermission::Internet && granted {
+            ensure!(
+                instance.chat_id.is_self_talk(self).await? && instance.get_showpadlock(),
+                "Internet access can only be granted to encrypted apps in Saved Messages."
+            );
+        }
+
+        self.sql
+            .execute(
+                "INSERT OR REPLACE INTO webxdc_permissions (msg_id, permission, granted)
+                 VALUES (?, ?, ?)",
+                (instance_msg_id, permission.to_string(), granted),
+            )
+            .await?;
+        self.webxdc_permissions_cache
+            .write()
+            .remove(&instance_msg_id);
+        self.emit_msgs_changed(instance.chat_id, instance_msg_id);
+        Ok(())
+    }
+}
+
+impl Message {
+    /// Returns the permissions requested by the webxdc and the granted ones.
+    pub(crate) async fn get_webxdc_permissions(
+        &self,
+        context: &Context,
+        manifest: &WebxdcManifest,
+    ) -> Result<(Vec<WebxdcPermission>, Vec<WebxdcPermission>)> {
+        let requested = requested_permissions(self, manifest);
+        let stored: HashMap<WebxdcPermission, bool> = context
+            .sql
+            .query_map_vec(
+                "SELECT permission, granted FROM webxdc_permissions WHERE msg_id=?",
+                (self.id,),
+                |row| {
+                    let permission: String = row.get(0)?;
+                    let granted: bool = row.get(1)?;
+                    Ok(permission
+                        .parse()
+                        .ok()
+                        .map(|permission| (permission, granted)))
+                },
+            )
+            .await?
+            .into_iter()
+            .flatten()
+            .collect();
+        let is_legacy = manifest.permissions.is_none();
+        let is_integrated = self.is_set_as_webxdc_integration(context).await?;
+
+        let mut granted = Vec::new();
+        for permission in &requested {
+            let stored = stored.get(permission).copied();
+            let is_granted = match permission {
+                WebxdcPermission::Internet => {
+                    is_integrated
+                        || (stored == Some(true)
+                            && self.chat_id.is_self_talk(context).await?
+                            && self.get_showpadlock())
+                }
+                WebxdcPermission::Location => stored.unwrap_or(is_integrated),
+                _ => stored.unwrap_or(is_legacy),
+            };
+            if is_granted {
+                granted.push(*permission);
+            }
+        }
+        Ok((requested, granted))
+    }
+
+    /// Returns true if the webxdc was granted `permission`.
+    pub(crate) async fn has_webxdc_permission(
+        &self,
+        context: &Context,
+        permission: WebxdcPermission,
+    ) -> Result<bool> {
+        let file = self.param.get(Param::File).unwrap_or_default().to_string();
+        if let Some((cached_file, granted)) = context.webxdc_permissions_cache.read().get(&self.id)
+            && *cached_file == file
+        {
+            return Ok(granted.contains(&permission));
+        }
+        let manifest = self.get_webxdc_manifest(context).await?;
+        let (_, granted) = self.get_webxdc_permissions(context, &manifest).await?;
+        let has_permission = granted.contains(&permission);
+        context
+            .webxdc_permissions_cache
+            .write()
+            .insert(self.id, (file, granted));
+        Ok(has_permission)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chat::create_group;
+    use crate::test_utils::{TestContext, create_xdc, send_xdc};
+    use crate::webxdc::STATUS_UPDATE_SIZE_MAX;
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_declared_permissions() -> Result<()> {
+        let t = TestContext::new_alice().await;
+        let chat_id = create_group(&t, "chat").await?;
+        let instance_id = send_xdc(
+            &t,
+            chat_id,
+            r#"permissions = ["large-updates", "realtime", "internet", "teleport"]"#,
+        )
+        .await?;
+
+        let info = Message::load_from_db(&t, instance_id)
+            .await?
+            .get_webxdc_info(&t)
+            .await?;
+        assert_eq!(
+            info.permissions,
+            vec![
+                WebxdcPermission::Realtime,
+                WebxdcPermission::Internet,
+                WebxdcPermission::LargeUpdates
+            ]
+        );
+        assert!(info.granted_permissions.is_empty());
+        assert_eq!(info.send_update_max_size, STATUS_UPDATE_SIZE_MAX);
+
+        // Large updates need the permission.
+        let large_update = format!(r#"{{"payload": "{}"}}"#, "x".repeat(STATUS_UPDATE_SIZE_MAX));
+        assert!(
+            t.send_webxdc_status_update(instance_id, &large_update)
+                .await
+                .is_err()
+        );
+        t.set_webxdc_permission(instance_id, WebxdcPermission::LargeUpdates, true)
+            .await?;
+        t.send_webxdc_status_update(instance_id, &large_update)
+            .await?;
+
+        // Only requested permissions can be granted, internet not outside "Saved Messages".
+        assert!(
+            t.set_webxdc_permission(instance_id, WebxdcPermission::Location, true)
+                .await
+                .is_err()
+        );
+        assert!(
+            t.set_webxdc_permission(instance_id, WebxdcPermission::Internet, true)
+                .await
+                .is_err()
+        );
+
+        t.set_webxdc_permission(instance_id, WebxdcPermission::Realtime, true)
+            .await?;
+        let info = Message::load_from_db(&t, instance_id)
+            .await?
+            .get_webxdc_info(&t)
+            .await?;
+        assert_eq!(
+            info.granted_permissions,
+            vec![WebxdcPermission::Realtime, WebxdcPermission::LargeUpdates]
+        );
+        assert_eq!(info.internet_access, false);
+
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_internet_permission_in_self_chat() -> Result<()> {
+        let t = TestContext::new_alice().await;
+        let self_chat = t.get_self_chat().await;
+        let instance_id = send_xdc(&t, self_chat.id, r#"permissions = ["internet"]"#).await?;
+
+        let instance = Message::load_from_db(&t, instance_id).await?;
+        assert_eq!(instance.get_webxdc_info(&t).await?.internet_access, false);
+
+        t.set_webxdc_permission(instance_id, WebxdcPermission::Internet, true)
+            .await?;
+        let info = instance.get_webxdc_info(&t).await?;
+        assert_eq!(info.internet_access, true);
+        assert_eq!(info.granted_permissions, vec![WebxdcPermission::Internet]);
+
+        t.set_webxdc_permission(instance_id, WebxdcPermission::Internet, false)
+            .await?;
+        assert_eq!(instance.get_webxdc_info(&t).await?.internet_access, false);
+
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_legacy_permissions() -> Result<()> {
+        let t = TestContext::new_alice().await;
+        let chat_id = create_group(&t, "chat").await?;
+        let instance_id = send_xdc(&t, chat_id, r#"name = "legacy""#).await?;
+
+        let instance = Message::load_from_db(&t, instance_id).await?;
+        let info = instance.get_webxdc_info(&t).await?;
+        let implicit = vec![WebxdcPermission::Realtime, WebxdcPermission::LargeUpdates];
+        assert_eq!(info.permissions, implicit);
+        assert_eq!(info.granted_permissions, implicit);
+        assert_eq!(info.internet_access, false);
+
+        assert!(
+            instance
+                .has_webxdc_permission(&t, WebxdcPermission::Realtime)
+                .await?
+        );
+        t.set_webxdc_permission(instance_id, WebxdcPermission::Realtime, false)
+            .await?;
+        assert!(
+            !instance
+                .has_webxdc_permission(&t, WebxdcPermission::Realtime)
+                .await?
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_permissions_of_replaced_app() -> Result<()> {
+        let t = TestContext::new_alice().await;
+        let chat_id = create_group(&t, "chat").await?;
+        let instance_id = send_xdc(&t, chat_id, r#"name = "legacy""#).await?;
+        let instance = Message::load_from_db(&t, instance_id).await?;
+        assert!(
+            instance
+                .has_webxdc_permission(&t, WebxdcPermission::Realtime)
+                .await?
+        );
+
+        // The new version of the app declares that it needs no permissions.
+        let file = t.get_blobdir().join("new.xdc");
+        tokio::fs::write(&file, create_xdc("permissions = []").await?).await?;
+        t.replace_webxdc(instance_id, file.to_str().unwrap())
+            .await?;
+        let instance = Message::load_from_db(&t, instance_id).await?;
+        assert!(
+            !instance
+                .has_webxdc_permission(&t, WebxdcPermission::Realtime)
+                .await?
+        );
+
+        Ok(())
+    }
+}
@@ -457,6 +457,7 @@ async fn test_create_status_update_record() -> Result<()> {
                 summary: None,
                 uid: Some("iecie2Ze".to_string()),
                 notify: None,
+                snapshot: None,
             },
             1640178619,
             true,
@@ -483,6 +484,7 @@ async fn test_create_status_update_record() -> Result<()> {
                 summary: None,
                 uid: Some("iecie2Ze".to_string()),
                 notify: None,
+                snapshot: None,
             },
             1640178619,
             true,
@@ -520,6 +522,7 @@ async fn test_create_status_update_record() -> Result<()> {
                 summary: None,
                 uid: None,
                 notify: None,
+                snapshot: None,
             },
             1640178619,
             true,
@@ -541,6 +544,7 @@ async fn test_create_status_update_record() -> Result<()> {
             summary: None,
             uid: None,
             notify: None,
+            snapshot: None,
         },
         1640178619,
         true,
@@ -2241,3 +2245,281 @@ async fn test_webxdc_info_app_sender() -> Result<()> {
 
     Ok(())
 }
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_replace_webxdc() -> Result<()> {
+    let mut tcm = TestContextManager::new();
+    let alice = &tcm.alice().await;
+    let alice2 = &tcm.alice().await;
+    let bob = &tcm.bob().await;
+
+    let alice_chat_id = alice.create_group_with_members("Group", &[bob]).await;
+    let alice_instance = send_webxdc_instance(alice, alice_chat_id).await?;
+    let sent1 = alice.pop_sent_msg().await;
+    let bob_instance = bob.recv_msg(&sent1).await;
+    let alice2_instance = alice2.recv_msg(&sent1).await;
+    bob_instance.chat_id.accept(bob).await?;
+    let status =
+        helper_send_receive_status_update(bob, alice, &bob_instance, &alice_instance).await?;
+    assert_eq!(
+        status,
+        r#"[{"payload":7,"info":"i","summary":"s","serial":1,"max_serial":1}]"#
+    );
+
+    // Bob is not the app sender and cannot replace the app.
+    let file = bob.get_blobdir().join("new.xdc");
+    tokio::fs::write(
+        &file,
+        include_bytes!("../../test-data/webxdc/with-manifest-and-png-icon.xdc"),
+    )
+    .await?;
+    assert!(
+        bob.replace_webxdc(bob_instance.id, file.to_str().unwrap())
+            .await
+            .is_err()
+    );
+
+    // Replacements sent by others are ignored.
+    let mut msg = Message::new(Viewtype::Webxdc);
+    msg.set_file_and_deduplicate(bob, &file, None, None)?;
+    msg.hidden = true;
+    msg.param
+        .set(Param::WebxdcReplaceFor, &bob_instance.rfc724_mid);
+    send_msg(bob, bob_instance.chat_id, &mut msg).await?;
+    alice.recv_msg_trash(&bob.pop_sent_msg().await).await;
+    let info = Message::load_from_db(alice, alice_instance.id)
+        .await?
+        .get_webxdc_info(alice)
+        .await?;
+    assert_eq!(info.name, "minimal.xdc");
+
+    // Alice replaces the app, status updates are preserved.
+    let file = alice.get_blobdir().join("new.xdc");
+    tokio::fs::write(
+        &file,
+        include_bytes!("../../test-data/webxdc/with-manifest-and-png-icon.xdc"),
+    )
+    .await?;
+    alice
+        .replace_webxdc(alice_instance.id, file.to_str().unwrap())
+        .await?;
+    let sent2 = alice.pop_sent_msg().await;
+    bob.recv_msg_trash(&sent2).await;
+    alice2.recv_msg_trash(&sent2).await;
+    for (t, instance) in [
+        (alice, &alice_instance),
+        (bob, &bob_instance),
+        (alice2, &alice2_instance),
+    ] {
+        let event = t
+            .evtracker
+            .get_matching(|evt| matches!(evt, EventType::WebxdcInstanceUpdated { .. }))
+            .await;
+        assert_eq!(
+            event,
+            EventType::WebxdcInstanceUpdated {
+                msg_id: instance.id
+            }
+        );
+        let instance = Message::load_from_db(t, instance.id).await?;
+        assert_eq!(instance.get_webxdc_info(t).await?.name, "with some icon");
+        assert_eq!(instance.get_filename().unwrap(), "new.xdc");
+    }
+    assert_eq!(
+        bob.get_webxdc_status_updates(bob_instance.id, StatusUpdateSerial(0))
+            .await?,
+        r#"[{"payload":7,"info":"i","summary":"s","serial":1,"max_serial":1}]"#
+    );
+    assert_eq!(
+        alice
+            .get_webxdc_status_updates(alice_instance.id, StatusUpdateSerial(0))
+            .await?,
+        r#"[{"payload":7,"info":"i","summary":"s","serial":1,"max_serial":1}]"#
+    );
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_webxdc_snapshot_compaction() -> Result<()> {
+    let mut tcm = TestContextManager::new();
+    let alice = &tcm.alice().await;
+    let bob = &tcm.bob().await;
+
+    let alice_chat_id = alice.create_group_with_members("Group", &[bob]).await;
+    let alice_instance = send_webxdc_instance(alice, alice_chat_id).await?;
+    let bob_instance = bob.recv_msg(&alice.pop_sent_msg().await).await;
+
+    alice
+        .send_webxdc_status_update(alice_instance.id, r#"{"payload": 1}"#)
+        .await?;
+    alice
+        .send_webxdc_status_update(alice_instance.id, r#"{"payload": 2}"#)
+        .await?;
+    alice.flush_status_updates().await?;
+    bob.recv_msg_trash(&alice.pop_sent_msg().await).await;
+
+    alice
+        .send_webxdc_status_update(alice_instance.id, r#"{"payload": 3, "snapshot": true}"#)
+        .await?;
+    alice.flush_status_updates().await?;
+    bob.recv_msg_trash(&alice.pop_sent_msg().await).await;
+
+    // Updates superseded by the snapshot are deleted on both sides.
+    assert_eq!(
+        alice
+            .get_webxdc_status_updates(alice_instance.id, StatusUpdateSerial(0))
+            .await?,
+        r#"[{"payload":3,"snapshot":true,"serial":3,"max_serial":3}]"#
+    );
+    assert_eq!(
+        bob.get_webxdc_status_updates(bob_instance.id, StatusUpdateSerial(0))
+            .await?,
+        r#"[{"payload":3,"snapshot":true,"serial":3,"max_serial":3}]"#
+    );
+    let usage = crate::storage_usage::get_webxdc_storage_usage(bob, bob_instance.id).await?;
+    assert_eq!(usage.status_updates_count, 1);
+    assert!(usage.xdc_size > 0);
+
+    // Only the instance sender can send snapshots.
+    assert!(
+        bob.send_webxdc_status_update(bob_instance.id, r#"{"payload": 4, "snapshot": true}"#)
+            .await
+            .is_err()
+    );
+    let bob_id = alice.add_or_lookup_contact_id(bob).await;
+    alice
+        .receive_status_update(
+            bob_id,
+            &alice_instance,
+            tools::time(),
+            true,
+            r#"{"updates":[{"payload":4,"snapshot":true}]}"#,
+        )
+        .await?;
+    assert_eq!(
+        alice
+            .get_webxdc_status_updates(alice_instance.id, StatusUpdateSerial(0))
+            .await?,
+        r#"[{"payload":3,"snapshot":true,"serial":3,"max_serial":3}]"#
+    );
+
+    // Snapshots sent from another device of Bob are ignored as well.
+    bob.receive_status_update(
+        ContactId::SELF,
+        &bob_instance,
+        tools::time(),
+        true,
+        r#"{"updates":[{"payload":5,"snapshot":true}]}"#,
+    )
+    .await?;
+    assert_eq!(
+        bob.get_webxdc_status_updates(bob_instance.id, StatusUpdateSerial(0))
+            .await?,
+        r#"[{"payload":3,"snapshot":true,"serial":3,"max_serial":3}]"#
+    );
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_webxdc_status_updates_quota() -> Result<()> {
+    let t = TestContext::new_alice().await;
+    let chat_id = create_group(&t, "chat").await?;
+    let instance = send_webxdc_instance(&t, chat_id).await?;
+
+    let large_update = StatusUpdateItem {
+        payload: json!("x".repeat(STATUS_UPDATES_QUOTA)),
+        ..Default::default()
+    };
+    t.write_status_update_inner(&instance.id, &large_update, 1)
+        .await?
+        .unwrap();
+
+    // Quota is exceeded, only snapshots are accepted.
+    assert!(
+        t.send_webxdc_status_update(instance.id, r#"{"payload": 1}"#)
+            .await
+            .is_err()
+    );
+
+    // Received updates exceeding the quota are dropped.
+    t.evtracker.clear_events();
+    t.receive_status_update(
+        ContactId::SELF,
+        &instance,
+        tools::time(),
+        true,
+        r#"{"updates":[{"payload":1}]}"#,
+    )
+    .await?;
+    t.evtracker
+        .get_matching(|evt| matches!(evt, EventType::WebxdcStatusUpdatesDropped { .. }))
+        .await;
+    let usage = crate::storage_usage::get_webxdc_storage_usage(&t, instance.id).await?;
+    assert_eq!(usage.status_updates_count, 1);
+
+    // Snapshots must fit into the quota themselves.
+    let large_snapshot = format!(
+        r#"{{"payload": "{}", "snapshot": true}}"#,
+        "x".repeat(STATUS_UPDATES_QUOTA)
+    );
+    assert!(
+        t.send_webxdc_status_update(instance.id, &large_snapshot)
+            .await
+            .is_err()
+    );
+    t.send_webxdc_status_update(instance.id, r#"{"payload": 2, "snapshot": true}"#)
+        .await?;
+    t.send_webxdc_status_update(instance.id, r#"{"payload": 3}"#)
+        .await?;
+    let usage = crate::storage_usage::get_webxdc_storage_usage(&t, instance.id).await?;
+    assert_eq!(usage.status_updates_count, 2);
+    assert!(usage.status_updates_size < 1000);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_reset_webxdc_state() -> Result<()> {
+    let t = TestContext::new_alice().await;
+    let chat_id = create_group(&t, "chat").await?;
+    let instance = send_webxdc_instance(&t, chat_id).await?;
+    t.send_webxdc_status_update(
+        instance.id,
+        r#"{"payload": 1, "summary": "1 vote", "document": "poll"}"#,
+    )
+    .await?;
+    let info = Message::load_from_db(&t, instance.id)
+        .await?
+        .get_webxdc_info(&t)
+        .await?;
+    assert_eq!(info.summary, "1 vote");
+
+    t.evtracker.clear_events();
+    t.reset_webxdc_state(instance.id).await?;
+    t.evtracker
+        .get_matching(|evt| matches!(evt, EventType::WebxdcInstanceUpdated { .. }))
+        .await;
+    assert_eq!(
+        t.get_webxdc_status_updates(instance.id, StatusUpdateSerial(0))
+            .await?,
+        "[]"
+    );
+    let info = Message::load_from_db(&t, instance.id)
+        .await?
+        .get_webxdc_info(&t)
+        .await?;
+    assert_eq!(info.summary, "");
+    assert_eq!(info.document, "");
+
+    // The app can start over.
+    t.send_webxdc_status_update(instance.id, r#"{"payload": 2}"#)
+        .await?;
+    assert_eq!(
+        t.get_webxdc_status_updates(instance.id, StatusUpdateSerial(0))
+            .await?,
+        r#"[{"payload":2,"serial":2,"max_serial":2}]"#
+    );
+
+    Ok(())
+}
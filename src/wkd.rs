@@ -0,0 +1,269 @@
+//! # Web Key Directory (WKD) lookup.
+//!
+//! WKD allows to discover the OpenPGP certificate of a contact
+//! before the first message is exchanged,
+//! by fetching it from a well-known HTTPS location on the domain of the address.
+//!
+//! See <https://datatracker.ietf.org/doc/draft-koch-openpgp-webkey-service/>.
+
+use std::sync::LazyLock;
+
+use anyhow::{Context as _, Result, ensure};
+use data_encoding::{Encoding, Specification};
+use deltachat_contact_tools::{ContactAddress, EmailAddress, addr_cmp, sanitize_name};
+use percent_encoding::utf8_percent_encode;
+use sha1::{Digest, Sha1};
+
+use crate::config::Config;
+use crate::constants::NON_ALPHANUMERIC_WITHOUT_DOT;
+use crate::contact::{ContactId, Origin, import_certificate_contact};
+use crate::context::Context;
+use crate::key::{DcKey, SignedPublicKey};
+use crate::log::warn;
+use crate::net::read_url_blob;
+use crate::pgp::user_id_addresses;
+
+/// z-base-32 encoding used for the hashed local part,
+/// see <https://philzimmermann.com/docs/human-oriented-base-32-encoding.txt>.
+static ZBASE32: LazyLock<Encoding> = LazyLock::new(|| {
+    let mut spec = Specification::new();
+    spec.symbols.push_str("ybndrfg8ejkmcpqxot1uwisza345h769");
+    spec.encoding().expect("z-base-32 specification is valid")
+});
+
+/// Returns WKD URLs for the address in the order they should be tried.
+///
+/// The first URL uses the "advanced" method with `openpgpkey` subdomain,
+/// the second one uses the "direct" method.
+fn wkd_urls(addr: &EmailAddress) -> [String; 2] {
+    let domain = addr.domain.to_lowercase();
+    let hash = ZBASE32.encode(&Sha1::digest(addr.local.to_lowercase().as_bytes()));
+    let local = utf8_percent_encode(&addr.local, NON_ALPHANUMERIC_WITHOUT_DOT);
+    [
+        format!("https://openpgpkey.{domain}/.well-known/openpgpkey/{domain}/hu/{hash}?l={local}"),
+        format!("https://{domain}/.well-known/openpgpkey/hu/{hash}?l={local}"),
+    ]
+}
+
+/// Fetches the certificate from the URL
+/// and checks that it has a User ID with the given address.
+///
+/// Responses are stored in the HTTP cache.
+async fn fetch_certificate(context: &Context, url: &str, addr: &str) -> Result<SignedPublicKey> {
+    let response = read_url_blob(context, url).await?;
+    let public_key =
+        SignedPublicKey::from_slice(&response.blob).context("Cannot parse WKD certificate")?;
+    public_key
+        .verify_bindings()
+        .context("WKD certificate cannot be verified")?;
+    ensure!(
+        user_id_addresses(&public_key)
+            .iter()
+            .any(|user_id_addr| addr_cmp(user_id_addr, addr)),
+        "WKD certificate has no User ID for {addr}"
+    );
+    Ok(public_key)
+}
+
+/// Looks up the certificate for the address in the Web Key Directory of its domain.
+///
+/// Returns `None` if WKD lookup is disabled with [`Config::WkdLookup`]
+/// or no certificate is found.
+pub(crate) async fn lookup_certificate(
+    context: &Context,
+    addr: &ContactAddress,
+) -> Result<Option<SignedPublicKey>> {
+    if !context.get_config_bool(Config::WkdLookup).await? {
+        return Ok(None);
+    }
+    let email_addr = EmailAddress::new(addr)?;
+    let mut urls = wkd_urls(&email_addr).to_vec();
+    if context.get_config_bool(Config::TestHooks).await?
+        && let Some(url) = context.wkd_url_hook.lock().clone()
+    {
+        urls = vec![url];
+    }
+    for url in urls {
+        match fetch_certificate(context, &url, addr).await {
+            Ok(public_key) => {
+                info!(context, "Found WKD certificate for {addr}.");
+                return Ok(Some(public_key));
+            }
+            Err(err) => warn!(context, "WKD lookup at {url:?} failed: {err:#}."),
+        }
+    }
+    Ok(None)
+}
+
+/// Looks up the certificate for the address in the Web Key Directory of its domain.
+///
+/// If a certificate is found, it is stored as an unverified key
+/// and the ID of the key-contact with the given name is returned,
+/// so the first message to the address can already be encrypted.
+///
+/// The lookup needs network access,
+/// so [`Contact::create`](crate::contact::Contact::create) does not do it.
+/// UI may call this after creating a contact with the same name and address
+/// and use the key-contact if one is returned.
+/// The name of the key-contact is synchronized to other devices.
+///
+/// Returns `None` if WKD lookup is disabled with [`Config::WkdLookup`]
+/// or no certificate is found.
+pub async fn lookup_contact(
+    context: &Context,
+    name: &str,
+    addr: &str,
+) -> Result<Option<ContactId>> {
+    let contact_addr = ContactAddress::new(addr).context("Invalid address")?;
+    let Some(public_key) = lookup_certificate(context, &contact_addr).await? else {
+        return Ok(None);
+    };
+    let contact_id =
+        import_certificate_contact(context, &contact_addr, &public_key, Origin::ManuallyCreated)
+            .await?;
+    let name = sanitize_name(name);
+    if !name.is_empty() {
+        contact_id.set_name(context, &name).await?;
+    }
+    Ok(Some(contact_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::{AsyncReadExt as _, AsyncWriteExt as _};
+    use tokio::net::TcpListener;
+
+    use super::*;
+    use crate::chat::ChatId;
+    use crate::contact::Contact;
+    use crate::test_utils::{TestContext, bob_keypair, sync};
+
+    #[test]
+    fn test_wkd_urls() {
+        // Example from the specification.
+        let addr = EmailAddress::new("Joe.Doe@Example.ORG").unwrap();
+        assert_eq!(
+            wkd_urls(&addr),
+            [
+                "https://openpgpkey.example.org/.well-known/openpgpkey/example.org/hu/iy9q119eutrkn8s1mk4r39qejnbu3n5q?l=Joe.Doe",
+                "https://example.org/.well-known/openpgpkey/hu/iy9q119eutrkn8s1mk4r39qejnbu3n5q?l=Joe.Doe",
+            ]
+        );
+    }
+
+    /// Serves `body` to a single HTTP request on a local port and returns the URL.
+    async fn serve_once(body: Vec<u8>) -> Result<String> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let port = listener.local_addr()?.port();
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buf = [0u8; 4096];
+            let _ = stream.read(&mut buf).await.unwrap();
+            let header = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: application/octet-stream\r\nContent-Length: {}\r\n\r\n",
+                body.len()
+            );
+            stream.write_all(header.as_bytes()).await.unwrap();
+            stream.write_all(&body).await.unwrap();
+            stream.flush().await.unwrap();
+        });
+        Ok(format!(
+            "http://127.0.0.1:{port}/.well-known/openpgpkey/hu/test"
+        ))
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_fetch_certificate() -> Result<()> {
+        let t = &TestContext::new_alice().await;
+        let bob_public_key = bob_keypair().to_public_key();
+
+        let url = serve_once(bob_public_key.to_bytes()).await?;
+        let public_key = fetch_certificate(t, &url, "bob@example.net").await?;
+        assert_eq!(public_key.dc_fingerprint(), bob_public_key.dc_fingerprint());
+
+        // The response is cached, so the second request does not need the server.
+        let public_key = fetch_certificate(t, &url, "bob@example.net").await?;
+        assert_eq!(public_key.dc_fingerprint(), bob_public_key.dc_fingerprint());
+
+        // Certificate for another address is rejected.
+        assert!(
+            fetch_certificate(t, &url, "mallory@example.net")
+                .await
+                .is_err()
+        );
+
+        let url = serve_once(b"not a key".to_vec()).await?;
+        assert!(fetch_certificate(t, &url, "bob@example.net").await.is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_lookup_contact_disabled() -> Result<()> {
+        let t = &TestContext::new_alice().await;
+        assert!(!t.get_config_bool(Config::WkdLookup).await?);
+        assert_eq!(lookup_contact(t, "", "bob@example.net").await?, None);
+        assert_eq!(
+            Contact::lookup_id_by_addr(t, "bob@example.net", Origin::Unknown).await?,
+            None
+        );
+        Ok(())
+    }
+
+    /// Tests that contacts looked up after creating them by the user
+    /// get the certificate from WKD, so the first message to them is encrypted.
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_lookup_contact() -> Result<()> {
+        let alice0 = &TestContext::new_alice().await;
+        let alice1 = &TestContext::new_alice().await;
+        for a in [alice0, alice1] {
+            a.set_config_bool(Config::SyncMsgs, true).await?;
+        }
+        let bob_public_key = bob_keypair().to_public_key();
+        alice0.set_config_bool(Config::WkdLookup, true).await?;
+        alice0.set_config_bool(Config::TestHooks, true).await?;
+        *alice0.wkd_url_hook.lock() = Some(serve_once(bob_public_key.to_bytes()).await?);
+
+        // Creating the contact does not need network.
+        let contact_id = Contact::create(alice0, "Bob", "bob@example.net").await?;
+        assert!(
+            !Contact::get_by_id(alice0, contact_id)
+                .await?
+                .is_key_contact()
+        );
+
+        let contact_id = lookup_contact(alice0, "Bob", "bob@example.net")
+            .await?
+            .unwrap();
+        let contact = Contact::get_by_id(alice0, contact_id).await?;
+        assert!(contact.is_key_contact());
+        assert_eq!(contact.get_name(), "Bob");
+        assert_eq!(contact.fingerprint(), Some(bob_public_key.dc_fingerprint()));
+
+        let chat_id = ChatId::create_for_contact(alice0, contact_id).await?;
+        let sent = alice0.send_text(chat_id, "Hi Bob!").await;
+        assert!(sent.load_from_db().await.get_showpadlock());
+
+        // The name of the key-contact is synchronized.
+        sync(alice0, alice1).await;
+        let fingerprint = bob_public_key.dc_fingerprint().hex();
+        let a1_contact_id = alice1
+            .sql
+            .query_get_value::<ContactId>(
+                "SELECT id FROM contacts WHERE fingerprint=?",
+                (&fingerprint,),
+            )
+            .await?
+            .unwrap();
+        let a1_contact = Contact::get_by_id(alice1, a1_contact_id).await?;
+        assert_eq!(a1_contact.get_name(), "Bob");
+
+        // Without a certificate in WKD no key-contact is created.
+        *alice0.wkd_url_hook.lock() = Some(serve_once(b"not a key".to_vec()).await?);
+        assert_eq!(
+            lookup_contact(alice0, "Fiona", "fiona@example.net").await?,
+            None
+        );
+        Ok(())
+    }
+}